@@ -0,0 +1,56 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Pads the lines of `text` so every occurrence of `delimiter` lines up in
+/// columns, with one space on either side.
+pub fn align(text: &str, delimiter: &str) -> String {
+    let rows: Vec<Option<Vec<&str>>> = text
+        .lines()
+        .map(|line| {
+            line.contains(delimiter).then(|| {
+                line.split(delimiter)
+                    .enumerate()
+                    .map(|(i, field)| {
+                        if i == 0 {
+                            field.trim_end()
+                        } else {
+                            field.trim()
+                        }
+                    })
+                    .collect()
+            })
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = Vec::new();
+    for fields in rows.iter().flatten() {
+        for (i, field) in fields.iter().enumerate() {
+            if i == widths.len() {
+                widths.push(0);
+            }
+            widths[i] = widths[i].max(field.width());
+        }
+    }
+
+    text.lines()
+        .zip(&rows)
+        .map(|(line, fields)| match fields {
+            Some(fields) => {
+                let mut aligned = String::new();
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        aligned.push(' ');
+                        aligned.push_str(delimiter);
+                        aligned.push(' ');
+                    }
+                    aligned.push_str(field);
+                    if i + 1 < fields.len() {
+                        aligned.push_str(&" ".repeat(widths[i] - field.width()));
+                    }
+                }
+                aligned.trim_end().to_string()
+            }
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
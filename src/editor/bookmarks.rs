@@ -0,0 +1,139 @@
+use crate::{editor::signs::Sign, utils::config::get_config_dir};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const BOOKMARKS_FILE_NAME: &str = "bookmarks.toml";
+
+/// The sign column source bookmarks are placed under.
+pub const SIGN_SOURCE: &str = "bookmarks";
+
+pub fn sign() -> Sign {
+    Sign::new('*', "bookmark", 20)
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct BookmarksFile {
+    #[serde(default)]
+    projects: BTreeMap<String, BTreeMap<String, BTreeSet<usize>>>,
+}
+
+/// Bookmarked lines of the current project.
+#[derive(Default)]
+pub struct Bookmarks {
+    project: Option<PathBuf>,
+    // Lines counted from 0
+    files: BTreeMap<PathBuf, BTreeSet<usize>>,
+}
+
+impl Bookmarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the bookmarks saved for `project`.
+    pub fn load(project: &Path) -> Result<Self, String> {
+        let stored = read_file()?;
+        let files = stored
+            .projects
+            .get(&project.display().to_string())
+            .map(|files| {
+                files
+                    .iter()
+                    .map(|(file, lines)| {
+                        let lines = lines.iter().map(|line| line.saturating_sub(1)).collect();
+                        (PathBuf::from(file), lines)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            project: Some(project.to_path_buf()),
+            files,
+        })
+    }
+
+    pub fn lines(&self, file: &Path) -> Vec<usize> {
+        self.files
+            .get(file)
+            .map(|lines| lines.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Every bookmark as (file, line), sorted by file and line.
+    pub fn all(&self) -> Vec<(PathBuf, usize)> {
+        self.files
+            .iter()
+            .flat_map(|(file, lines)| lines.iter().map(|&line| (file.clone(), line)))
+            .collect()
+    }
+
+    /// Adds or removes the bookmark on `line`, returning whether it was
+    /// added.
+    pub fn toggle(&mut self, file: &Path, line: usize) -> bool {
+        let lines = self.files.entry(file.to_path_buf()).or_default();
+        let added = lines.insert(line);
+        if !added {
+            lines.remove(&line);
+            if lines.is_empty() {
+                self.files.remove(file);
+            }
+        }
+        added
+    }
+
+    /// Writes this project's bookmarks, keeping the other projects' as they
+    /// are in the file.
+    pub fn save(&self) -> Result<(), String> {
+        let Some(project) = &self.project else {
+            return Ok(());
+        };
+        let path = file_path().ok_or("No config directory for bookmarks")?;
+        let mut stored = read_file()?;
+        let files: BTreeMap<String, BTreeSet<usize>> = self
+            .files
+            .iter()
+            .map(|(file, lines)| {
+                let lines = lines.iter().map(|line| line + 1).collect();
+                (file.display().to_string(), lines)
+            })
+            .collect();
+        let key = project.display().to_string();
+        if files.is_empty() {
+            stored.projects.remove(&key);
+        } else {
+            stored.projects.insert(key, files);
+        }
+
+        let content =
+            toml::to_string(&stored).map_err(|e| format!("Failed to encode bookmarks: {}", e))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        fs::write(&path, content).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+    }
+}
+
+fn file_path() -> Option<PathBuf> {
+    get_config_dir().map(|dir| dir.join(BOOKMARKS_FILE_NAME))
+}
+
+fn read_file() -> Result<BookmarksFile, String> {
+    let Some(path) = file_path() else {
+        return Ok(BookmarksFile::default());
+    };
+    match fs::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content)
+            .map_err(|e| format!("Invalid bookmarks {}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BookmarksFile::default()),
+        Err(e) => Err(format!(
+            "Failed to read bookmarks {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
@@ -1,21 +1,382 @@
+use crate::editor::{
+    highlight::{self, HighlightCache, SharedHighlights},
+    history::{self, History},
+    line_slice::LineSliceCache,
+    options::LocalOptions,
+    signs::SignColumn,
+    syntax::Syntax,
+    syntax_tree::SyntaxTree,
+    undo::UndoStack,
+};
 use ropey::Rope;
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+/// Internal handle for a buffer, stable across renames and `:saveas`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BufferId(pub u64);
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum BufferName {
+    Named(PathBuf),
+    Unnamed(u64),
+    /// Fetched from an http(s) URL
+    Url(String),
+    /// A file as it was at a git commit
+    Revision(PathBuf, String),
+}
+
+impl BufferName {
+    pub fn path(&self) -> Option<&PathBuf> {
+        match self {
+            BufferName::Named(path) => Some(path),
+            BufferName::Unnamed(_) | BufferName::Url(_) | BufferName::Revision(..) => None,
+        }
+    }
+
+    fn syntax_path(&self) -> Option<&Path> {
+        match self {
+            BufferName::Named(path) | BufferName::Revision(path, _) => Some(path),
+            BufferName::Unnamed(_) => None,
+            BufferName::Url(url) => Some(Path::new(url.split(['?', '#']).next()?)),
+        }
+    }
+}
+
+impl Display for BufferName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BufferName::Named(path) => write!(f, "{}", path.display()),
+            BufferName::Unnamed(id) => write!(f, "[Scratch {}]", id),
+            BufferName::Url(url) => f.write_str(url),
+            BufferName::Revision(path, hash) => write!(f, "{}@{}", path.display(), hash),
+        }
+    }
+}
+
+/// A change to a buffer's content: `removed` chars at `start` replaced by
+/// `inserted` ones, spanning `removed_lines` and `inserted_lines` line
+/// breaks from `line` on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Edit {
+    pub start: usize,
+    pub removed: usize,
+    pub inserted: usize,
+    pub line: usize,
+    pub removed_lines: usize,
+    pub inserted_lines: usize,
+}
+
+impl Edit {
+    /// Where a char offset from before the edit is after it: text after the
+    /// edit moves with it, and offsets in the removed text go to its start.
+    pub fn map(&self, offset: usize) -> usize {
+        if offset < self.start {
+            offset
+        } else if offset >= self.start + self.removed {
+            offset - self.removed + self.inserted
+        } else {
+            self.start
+        }
+    }
+
+    /// Where a line from before the edit is after it.
+    pub fn map_line(&self, line: usize) -> usize {
+        if line <= self.line {
+            line
+        } else if line > self.line + self.removed_lines {
+            line - self.removed_lines + self.inserted_lines
+        } else {
+            self.line
+        }
+    }
+}
 
 pub struct Buffer {
+    pub name: BufferName,
     pub content: Rope,
-    pub cursor_pos: usize,
-    pub scroll_offset: (usize, usize),
+    /// Edits the windows onto the buffer have not caught up with yet
+    pub edits: Vec<Edit>,
+    // Edits the highlight cache has not been told about yet
+    unhighlighted: Vec<Edit>,
+    /// Where the cursor and view were when a window last left the buffer,
+    /// for the next window to show it
+    pub last_cursor: usize,
+    pub last_scroll: (usize, usize),
     pub is_modified: bool,
     pub selection_start: Option<usize>,
+    pub last_selection: Option<(usize, usize)>,
+    /// Whether the visual selection, or the last one, is a block of
+    /// columns (Ctrl-v) rather than a run of text
+    pub block_selection: bool,
+    pub local_directory: Option<PathBuf>,
+    pub history: History,
+    pub undo: UndoStack,
+    /// Bumped on every edit so caches derived from the content can tell
+    /// when they are stale
+    pub version: u64,
+    pub line_slices: RefCell<LineSliceCache>,
+    pub highlights: SharedHighlights,
+    // The content parsed with tree-sitter at a version, or None for a
+    // language without a grammar
+    parsed: RefCell<Option<(u64, Option<Rc<SyntaxTree>>)>>,
+    /// Set while the content has been dropped to save memory; the buffer
+    /// keeps its name and cursor and is read back from disk on next use
+    pub unloaded: bool,
+    /// When the buffer was last made current, for least-recently-used
+    /// unloading
+    pub last_used: u64,
+    /// Modification time of the file when it was last read or written, to
+    /// notice changes made outside the editor
+    pub disk_mtime: Option<SystemTime>,
+    pub signs: SignColumn,
+    /// Options set with `:setlocal`
+    pub local_options: LocalOptions,
+    /// Set for content that cannot be written back, such as a fetched URL
+    pub read_only: bool,
+    /// A `:calc` scratch buffer, where typing `=` at the end of a line
+    /// appends the value of the line
+    pub calc: bool,
 }
 
 impl Buffer {
-    pub fn new() -> Self {
+    pub fn new(name: BufferName) -> Self {
+        Self::with_content(name, Rope::new())
+    }
+
+    pub fn with_content(name: BufferName, content: Rope) -> Self {
+        let syntax = name.syntax_path().and_then(Syntax::for_path);
         Self {
-            content: Rope::new(),
-            cursor_pos: 0,
-            scroll_offset: (0, 0),
+            highlights: HighlightCache::new(syntax, &content),
+            name,
+            history: History::new(&content, 0),
+            undo: UndoStack::new(),
+            content,
+            edits: Vec::new(),
+            unhighlighted: Vec::new(),
+            last_cursor: 0,
+            last_scroll: (0, 0),
             is_modified: false,
             selection_start: None,
+            last_selection: None,
+            block_selection: false,
+            local_directory: None,
+            version: 0,
+            line_slices: RefCell::default(),
+            parsed: RefCell::default(),
+            unloaded: false,
+            last_used: 0,
+            disk_mtime: None,
+            signs: SignColumn::new(),
+            local_options: LocalOptions::new(),
+            read_only: false,
+            calc: false,
+        }
+    }
+
+    /// The content parsed with tree-sitter, when there is a grammar for the
+    /// file's language.
+    pub fn syntax_tree(&self) -> Option<Rc<SyntaxTree>> {
+        let mut parsed = self.parsed.borrow_mut();
+        if let Some((version, tree)) = &*parsed {
+            if *version == self.version {
+                return tree.clone();
+            }
+        }
+        let tree = self
+            .name
+            .syntax_path()
+            .and_then(|path| SyntaxTree::parse(path, &self.content.to_string()))
+            .map(Rc::new);
+        *parsed = Some((self.version, tree.clone()));
+        tree
+    }
+
+    fn file_mtime(&self) -> Option<SystemTime> {
+        fs::metadata(self.name.path()?).ok()?.modified().ok()
+    }
+
+    pub fn record_disk_mtime(&mut self) {
+        self.disk_mtime = self.file_mtime();
+    }
+
+    /// Whether the file was written by something else since the buffer last
+    /// read or wrote it.
+    pub fn changed_on_disk(&self) -> bool {
+        !self.unloaded
+            && self
+                .file_mtime()
+                .is_some_and(|mtime| Some(mtime) != self.disk_mtime)
+    }
+
+    /// Replaces a clean buffer's content with the file's, as an undoable
+    /// revision.
+    pub fn read_from_disk(&mut self) -> io::Result<()> {
+        let path = self
+            .name
+            .path()
+            .ok_or_else(|| io::Error::other("Buffer has no file to read"))?;
+        let edit = self.set_content(Rope::from_str(&fs::read_to_string(path)?));
+        self.selection_start = None;
+        self.mark_modified();
+        self.is_modified = false;
+        self.history.commit(&self.content, edit.start);
+        self.record_disk_mtime();
+        Ok(())
+    }
+
+    /// Replaces the chars in `range` with `text`.
+    pub fn replace(&mut self, range: Range<usize>, text: &str) {
+        let removed = self.content.slice(range.clone()).to_string();
+        let edit = self.splice(range, text);
+        self.undo.record(edit, removed, text.to_string());
+    }
+
+    /// Replaces the chars in `range` with `text` without recording the
+    /// change for undo.
+    pub fn splice(&mut self, range: Range<usize>, text: &str) -> Edit {
+        let line = self.content.char_to_line(range.start);
+        let removed_lines = self.content.char_to_line(range.end) - line;
+        self.content.remove(range.clone());
+        self.content.insert(range.start, text);
+        let inserted = text.chars().count();
+        let edit = Edit {
+            start: range.start,
+            removed: range.len(),
+            inserted,
+            line,
+            removed_lines,
+            inserted_lines: self.content.char_to_line(range.start + inserted) - line,
+        };
+        self.edits.push(edit);
+        self.unhighlighted.push(edit);
+        edit
+    }
+
+    pub fn insert(&mut self, at: usize, text: &str) {
+        self.replace(at..at, text);
+    }
+
+    pub fn insert_char(&mut self, at: usize, c: char) {
+        self.replace(at..at, c.encode_utf8(&mut [0; 4]));
+    }
+
+    pub fn remove(&mut self, range: Range<usize>) {
+        self.replace(range, "");
+    }
+
+    /// Replaces the whole content, recording the part that differs as the
+    /// edit, which it returns.
+    pub fn set_content(&mut self, content: Rope) -> Edit {
+        let start = history::first_difference(&self.content, &content);
+        let (old, new) = (self.content.len_chars(), content.len_chars());
+        let common = (self.content.chars_at(old).reversed())
+            .zip(content.chars_at(new).reversed())
+            .take(old.min(new) - start)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let line = self.content.char_to_line(start);
+        let edit = Edit {
+            start,
+            removed: old - common - start,
+            inserted: new - common - start,
+            line,
+            removed_lines: self.content.char_to_line(old - common) - line,
+            inserted_lines: content.char_to_line(new - common) - line,
+        };
+        let removed = self.content.slice(start..old - common).to_string();
+        let inserted = content.slice(start..new - common).to_string();
+        self.undo.record(edit, removed, inserted);
+        self.content = content;
+        self.edits.push(edit);
+        self.unhighlighted.push(edit);
+        edit
+    }
+
+    /// Marks the whole content as changed.
+    pub fn mark_modified(&mut self) {
+        self.is_modified = true;
+        self.version += 1;
+        self.unhighlighted.clear();
+        if let Ok(mut highlights) = self.highlights.lock() {
+            highlights.reset(&self.content, self.version);
+        }
+        highlight::request(&self.highlights);
+    }
+
+    /// Marks the lines the edits since the last call touched, so only they
+    /// need to be highlighted again.
+    pub fn mark_lines_modified(&mut self) {
+        self.is_modified = true;
+        self.version += 1;
+        let edits = std::mem::take(&mut self.unhighlighted);
+        if let Ok(mut highlights) = self.highlights.lock() {
+            highlights.edit(&self.content, self.version, &edits);
+        }
+        highlight::request(&self.highlights);
+    }
+
+    /// Only clean buffers backed by a file can be unloaded, since their
+    /// content can be read back.
+    pub fn can_unload(&self) -> bool {
+        !self.unloaded && !self.is_modified && self.name.path().is_some()
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        if self.unloaded {
+            0
+        } else {
+            self.content.len_bytes()
+        }
+    }
+
+    /// Drops the content and history, keeping the name and positions.
+    pub fn unload(&mut self) {
+        self.content = Rope::new();
+        self.history = History::new(&self.content, 0);
+        self.undo = UndoStack::new();
+        self.highlights = HighlightCache::new(None, &self.content);
+        self.line_slices = RefCell::default();
+        self.selection_start = None;
+        self.edits.clear();
+        self.unhighlighted.clear();
+        self.unloaded = true;
+    }
+
+    /// Reads an unloaded buffer back from disk.
+    pub fn reload(&mut self) -> io::Result<()> {
+        let path = self
+            .name
+            .path()
+            .ok_or_else(|| io::Error::other("Buffer has no file to reload from"))?;
+        let content = Rope::from_str(&fs::read_to_string(path)?);
+
+        let syntax = Syntax::for_path(path);
+        self.last_cursor = self.last_cursor.min(content.len_chars());
+        self.history = History::new(&content, self.last_cursor);
+        self.undo = UndoStack::new();
+        self.highlights = HighlightCache::new(syntax, &content);
+        self.unhighlighted.clear();
+        self.content = content;
+        self.version += 1;
+        self.unloaded = false;
+        self.record_disk_mtime();
+        Ok(())
+    }
+
+    pub fn set_name(&mut self, name: BufferName) {
+        let syntax = name.syntax_path().and_then(Syntax::for_path);
+        if let Ok(mut highlights) = self.highlights.lock() {
+            highlights.set_syntax(syntax);
         }
+        highlight::request(&self.highlights);
+        self.name = name;
     }
 }
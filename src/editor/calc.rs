@@ -0,0 +1,279 @@
+use std::f64::consts::{E, PI};
+
+/// Evaluates an arithmetic expression: numbers (`1_000`, `2.5e3`, `0x1f`),
+/// `+ - * / %`, `^` for powers, parentheses, the constants `pi` and `e` and
+/// functions such as `sqrt(2)`.
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err("Empty expression".to_string());
+    }
+    let value = parser.sum()?;
+    match parser.peek() {
+        None => Ok(value),
+        Some(token) => Err(format!("Unexpected {}", token)),
+    }
+}
+
+/// A result as it is inserted: whole numbers without a fraction, others
+/// rounded to 12 significant digits with trailing zeros dropped.
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let digits = 11 - value.abs().log10().floor().clamp(-100.0, 11.0) as i32;
+    let text = format!("{:.*}", digits.max(0) as usize, value);
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Name(name) => f.write_str(name),
+            Token::Operator(c) => write!(f, "'{}'", c),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_whitespace() {
+            index += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = index;
+            let hex = c == '0' && matches!(chars.get(index + 1), Some('x' | 'X'));
+            if hex {
+                index += 2;
+                while index < chars.len()
+                    && (chars[index].is_ascii_hexdigit() || chars[index] == '_')
+                {
+                    index += 1;
+                }
+            } else {
+                while index < chars.len()
+                    && (chars[index].is_ascii_digit() || matches!(chars[index], '.' | '_'))
+                {
+                    index += 1;
+                }
+                // An exponent, as in 2.5e3 or 1e-9
+                if matches!(chars.get(index), Some('e' | 'E'))
+                    && chars
+                        .get(index + 1)
+                        .is_some_and(|c| c.is_ascii_digit() || matches!(c, '+' | '-'))
+                {
+                    index += 2;
+                    while index < chars.len() && chars[index].is_ascii_digit() {
+                        index += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..index].iter().filter(|&&c| c != '_').collect();
+            let value = if hex {
+                i64::from_str_radix(&text[2..], 16)
+                    .map(|value| value as f64)
+                    .ok()
+            } else {
+                text.parse().ok()
+            };
+            tokens.push(Token::Number(
+                value.ok_or_else(|| format!("Invalid number {}", text))?,
+            ));
+        } else if c.is_alphabetic() {
+            let start = index;
+            while index < chars.len() && chars[index].is_alphanumeric() {
+                index += 1;
+            }
+            tokens.push(Token::Name(chars[start..index].iter().collect()));
+        } else if "+-*/%^()".contains(c) {
+            tokens.push(Token::Operator(c));
+            index += 1;
+        } else {
+            return Err(format!("Unexpected '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, operator: char) -> bool {
+        if self.peek() == Some(&Token::Operator(operator)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(base.powf(self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(value),
+            Some(Token::Operator('(')) => {
+                let value = self.sum()?;
+                if !self.eat(')') {
+                    return Err("Missing ')'".to_string());
+                }
+                Ok(value)
+            }
+            Some(Token::Name(name)) => match name.as_str() {
+                "pi" => Ok(PI),
+                "e" => Ok(E),
+                _ => {
+                    if !self.eat('(') {
+                        return Err(format!("Unknown name {}", name));
+                    }
+                    let argument = self.sum()?;
+                    if !self.eat(')') {
+                        return Err("Missing ')'".to_string());
+                    }
+                    apply(&name, argument)
+                }
+            },
+            Some(token) => Err(format!("Unexpected {}", token)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+
+fn apply(function: &str, argument: f64) -> Result<f64, String> {
+    Ok(match function {
+        "sqrt" => argument.sqrt(),
+        "abs" => argument.abs(),
+        "floor" => argument.floor(),
+        "ceil" => argument.ceil(),
+        "round" => argument.round(),
+        "ln" => argument.ln(),
+        "log" => argument.log10(),
+        "exp" => argument.exp(),
+        "sin" => argument.sin(),
+        "cos" => argument.cos(),
+        "tan" => argument.tan(),
+        _ => return Err(format!("Unknown function {}", function)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_precedence_and_grouping() {
+        assert_eq!(evaluate("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(evaluate("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(evaluate("7 % 4 - 10 / 4"), Ok(0.5));
+        assert_eq!(evaluate("-2^2"), Ok(-4.0));
+        assert_eq!(evaluate("2^3^2"), Ok(512.0));
+    }
+
+    #[test]
+    fn reads_numbers_constants_and_functions() {
+        assert_eq!(evaluate("0x1f + 1_000"), Ok(1031.0));
+        assert_eq!(evaluate("2.5e3"), Ok(2500.0));
+        assert_eq!(evaluate("sqrt(16) + abs(-1)"), Ok(5.0));
+        assert_eq!(evaluate("pi"), Ok(PI));
+    }
+
+    #[test]
+    fn reports_bad_expressions() {
+        assert!(evaluate("").is_err());
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("2 +").is_err());
+        assert!(evaluate("(1").is_err());
+        assert!(evaluate("nope(1)").is_err());
+    }
+
+    #[test]
+    fn formats_results_without_noise() {
+        assert_eq!(format_number(7.0), "7");
+        assert_eq!(format_number(2.5), "2.5");
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(1.0 / 3.0), "0.333333333333");
+    }
+}
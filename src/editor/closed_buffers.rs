@@ -0,0 +1,47 @@
+use std::{collections::VecDeque, path::PathBuf};
+
+// Closed buffers remembered for reopening
+const MAX_CLOSED_BUFFERS: usize = 20;
+
+/// A file buffer that was closed, with where its cursor was.
+#[derive(Clone, Debug)]
+pub struct ClosedBuffer {
+    pub path: PathBuf,
+    pub cursor_pos: usize,
+    /// The cursor's line, counted from 0, for listing
+    pub line: usize,
+    pub scroll_offset: (usize, usize),
+}
+
+/// The files closed with `:bd`, most recent first.
+#[derive(Default)]
+pub struct ClosedBuffers {
+    entries: VecDeque<ClosedBuffer>,
+}
+
+impl ClosedBuffers {
+    /// Records a closed buffer, forgetting an earlier close of the same
+    /// file and the oldest entry once the list is full.
+    pub fn push(&mut self, closed: ClosedBuffer) {
+        self.entries.retain(|entry| entry.path != closed.path);
+        self.entries.push_front(closed);
+        self.entries.truncate(MAX_CLOSED_BUFFERS);
+    }
+
+    /// Takes the entry for `path` out of the list, or the most recent one.
+    pub fn take(&mut self, path: Option<&PathBuf>) -> Option<ClosedBuffer> {
+        let index = match path {
+            Some(path) => self.entries.iter().position(|entry| &entry.path == path)?,
+            None => 0,
+        };
+        self.entries.remove(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ClosedBuffer> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,122 @@
+/// A color literal found in a line, as char offsets.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ColorSpan {
+    pub start: usize,
+    pub end: usize,
+    pub rgb: (u8, u8, u8),
+}
+
+/// Finds `#rgb`, `#rrggbb` (with optional alpha) and CSS `rgb()`, `rgba()`,
+/// `hsl()` and `hsla()` colors in `line`.
+pub fn find_colors(line: &str) -> Vec<ColorSpan> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut colors = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let boundary = i == 0 || !is_word_char(chars[i - 1]);
+        let found = match chars[i] {
+            '#' => hex_color(&chars, i),
+            'r' | 'h' if boundary => function_color(&chars, i),
+            _ => None,
+        };
+        match found {
+            Some(span) => {
+                i = span.end;
+                colors.push(span);
+            }
+            None => i += 1,
+        }
+    }
+    colors
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+fn hex_color(chars: &[char], start: usize) -> Option<ColorSpan> {
+    let digits = chars[start + 1..]
+        .iter()
+        .take_while(|c| c.is_ascii_hexdigit())
+        .count();
+    let end = start + 1 + digits;
+    if chars.get(end).is_some_and(|&c| is_word_char(c)) {
+        return None;
+    }
+
+    let hex: String = chars[start + 1..end].iter().collect();
+    let channel = |i: usize, len: usize| u8::from_str_radix(&hex[i..i + len], 16).ok();
+    let rgb = match digits {
+        3 | 4 => {
+            let expand = |i| channel(i, 1).map(|v| v * 17);
+            (expand(0)?, expand(1)?, expand(2)?)
+        }
+        6 | 8 => (channel(0, 2)?, channel(2, 2)?, channel(4, 2)?),
+        _ => return None,
+    };
+    Some(ColorSpan { start, end, rgb })
+}
+
+fn function_color(chars: &[char], start: usize) -> Option<ColorSpan> {
+    let rest: String = chars[start..].iter().take(5).collect();
+    let name_len = ["rgba(", "hsla(", "rgb(", "hsl("]
+        .iter()
+        .find(|name| rest.starts_with(*name))?
+        .len();
+    let close = chars[start..].iter().position(|&c| c == ')')? + start;
+    let args: String = chars[start + name_len..close].iter().collect();
+    let args: Vec<&str> = args
+        .split([',', ' ', '/'])
+        .filter(|arg| !arg.is_empty())
+        .collect();
+    if args.len() < 3 {
+        return None;
+    }
+
+    let rgb = if rest.starts_with("rgb") {
+        let channel = |arg: &str| match arg.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok().map(|p| p * 2.55),
+            None => arg.parse::<f32>().ok(),
+        };
+        (
+            to_u8(channel(args[0])?),
+            to_u8(channel(args[1])?),
+            to_u8(channel(args[2])?),
+        )
+    } else {
+        let hue = args[0].trim_end_matches("deg").parse::<f32>().ok()?;
+        let saturation = args[1].strip_suffix('%')?.parse::<f32>().ok()? / 100.0;
+        let lightness = args[2].strip_suffix('%')?.parse::<f32>().ok()? / 100.0;
+        hsl_to_rgb(hue, saturation, lightness)
+    };
+    Some(ColorSpan {
+        start,
+        end: close + 1,
+        rgb,
+    })
+}
+
+fn to_u8(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    (
+        to_u8((r + m) * 255.0),
+        to_u8((g + m) * 255.0),
+        to_u8((b + m) * 255.0),
+    )
+}
@@ -0,0 +1,193 @@
+// Unix timestamps in this range of seconds (1973 to 2286) are offered as
+// dates; the same range in milliseconds is recognised too.
+const TIMESTAMP_SECONDS: std::ops::Range<i64> = 100_000_000..10_000_000_000;
+
+/// Other ways of writing `token`: a number in another base, a unix
+/// timestamp as an ISO-8601 date, or a date as a unix timestamp.
+pub fn conversions(token: &str) -> Vec<(String, String)> {
+    let mut conversions = Vec::new();
+
+    if let Some((value, base)) = parse_integer(token) {
+        for (name, radix, text) in [
+            ("decimal", 10, value.to_string()),
+            ("hex", 16, format!("0x{:x}", value)),
+            ("binary", 2, format!("0b{:b}", value)),
+            ("octal", 8, format!("0o{:o}", value)),
+        ] {
+            if radix != base {
+                conversions.push((name.to_string(), text));
+            }
+        }
+
+        let value = value as i64;
+        if TIMESTAMP_SECONDS.contains(&value) {
+            conversions.push((
+                "unix seconds as UTC".to_string(),
+                format_iso8601(value, None),
+            ));
+        } else if TIMESTAMP_SECONDS.contains(&(value / 1000)) {
+            conversions.push((
+                "unix milliseconds as UTC".to_string(),
+                format_iso8601(value / 1000, Some(value % 1000)),
+            ));
+        }
+    }
+
+    if let Some(seconds) = parse_iso8601(token) {
+        conversions.push(("unix seconds".to_string(), seconds.to_string()));
+        conversions.push((
+            "unix milliseconds".to_string(),
+            (seconds * 1000).to_string(),
+        ));
+    }
+    conversions
+}
+
+fn parse_integer(token: &str) -> Option<(u64, u32)> {
+    let token = token.replace('_', "");
+    let lower = token.to_ascii_lowercase();
+    let (digits, base) = if let Some(hex) = lower.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        (binary, 2)
+    } else if let Some(octal) = lower.strip_prefix("0o") {
+        (octal, 8)
+    } else {
+        (lower.as_str(), 10)
+    };
+    u64::from_str_radix(digits, base)
+        .ok()
+        .map(|value| (value, base))
+}
+
+pub(crate) fn format_iso8601(seconds: i64, millis: Option<i64>) -> String {
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+    let fraction = millis.map_or(String::new(), |millis| format!(".{:03}", millis));
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        fraction
+    )
+}
+
+fn parse_iso8601(token: &str) -> Option<i64> {
+    let number = |text: &str| text.parse::<i64>().ok();
+    let (date, time) = token.split_once(['T', ' ']).unwrap_or((token, ""));
+
+    let mut parts = date.splitn(3, '-');
+    let year = number(parts.next().filter(|year| year.len() == 4)?)?;
+    let month = number(parts.next().filter(|month| month.len() == 2)?)?;
+    let day = number(parts.next().filter(|day| day.len() == 2)?)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (time, offset) = if let Some(time) = time.strip_suffix('Z') {
+        (time, 0)
+    } else if let Some(index) = time.rfind(['+', '-']) {
+        let (hours, minutes) = time[index + 1..].split_once(':')?;
+        let offset = number(hours)? * 3600 + number(minutes)? * 60;
+        let sign = if time.as_bytes()[index] == b'-' {
+            -1
+        } else {
+            1
+        };
+        (&time[..index], sign * offset)
+    } else {
+        (time, 0)
+    };
+
+    let mut seconds = 0;
+    if !time.is_empty() {
+        let mut fields = time.split(':');
+        let hours = number(fields.next()?)?;
+        let minutes = number(fields.next()?)?;
+        let secs = fields
+            .next()
+            .map_or(Some(0), |secs| number(secs.split('.').next()?))?;
+        seconds = hours * 3600 + minutes * 60 + secs;
+    }
+    Some(days_from_civil(year, month, day) * 86_400 + seconds - offset)
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar, after
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn converted(token: &str, description: &str) -> Option<String> {
+        conversions(token)
+            .into_iter()
+            .find(|(name, _)| name == description)
+            .map(|(_, text)| text)
+    }
+
+    #[test]
+    fn offers_the_other_bases() {
+        assert_eq!(converted("255", "hex").as_deref(), Some("0xff"));
+        assert_eq!(converted("255", "binary").as_deref(), Some("0b11111111"));
+        assert_eq!(converted("0xff", "decimal").as_deref(), Some("255"));
+        assert_eq!(converted("0b1_0000", "octal").as_deref(), Some("0o20"));
+        assert_eq!(converted("255", "decimal"), None);
+        assert!(conversions("hello").is_empty());
+    }
+
+    #[test]
+    fn converts_between_timestamps_and_dates() {
+        assert_eq!(
+            converted("1700000000", "unix seconds as UTC").as_deref(),
+            Some("2023-11-14T22:13:20Z")
+        );
+        assert_eq!(
+            converted("1700000000123", "unix milliseconds as UTC").as_deref(),
+            Some("2023-11-14T22:13:20.123Z")
+        );
+        assert_eq!(
+            converted("2023-11-14T22:13:20Z", "unix seconds").as_deref(),
+            Some("1700000000")
+        );
+        assert_eq!(
+            converted("2023-11-14T23:13:20+01:00", "unix seconds").as_deref(),
+            Some("1700000000")
+        );
+        assert_eq!(
+            converted("1970-01-02", "unix seconds").as_deref(),
+            Some("86400")
+        );
+        assert!(conversions("2023-13-01").is_empty());
+    }
+}
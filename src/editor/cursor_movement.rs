@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum CursorMovement {
     Left,
     Right,
@@ -5,4 +6,14 @@ pub enum CursorMovement {
     Down,
     LineStart,
     LineEnd,
+    /// `^`: the first non-blank character of the line
+    FirstNonBlank,
+    /// `g_`: the last non-blank character of the line
+    LastNonBlank,
+    /// Home: the first non-blank character, or column 0 when already there
+    SmartHome,
+    /// `)`: the start of the next sentence
+    NextSentence,
+    /// `(`: the start of the sentence, or of the one before
+    PreviousSentence,
 }
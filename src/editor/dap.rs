@@ -0,0 +1,450 @@
+use crate::{
+    editor::{hooks, lsp::read_message, signs::Sign},
+    utils::config::DebugConfig,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    process::{self, ChildStdin, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const BREAKPOINT_SOURCE: &str = "breakpoints";
+/// Marks the line the program stopped at
+pub const STOPPED_SOURCE: &str = "debug";
+
+// How long a request waits for the adapter before giving up
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Lines of program output kept for the console
+const CONSOLE_LINES: usize = 500;
+
+pub fn breakpoint_sign() -> Sign {
+    Sign::new('●', "breakpoint", 30)
+}
+
+pub fn stopped_sign() -> Sign {
+    Sign::new('▶', "stopped", 50)
+}
+
+/// What the adapter reported since the last `poll`.
+pub enum DebugEvent {
+    /// Ready for breakpoints, which end with `configuration_done`
+    Initialized,
+    Stopped {
+        thread_id: i64,
+        reason: String,
+    },
+    Continued,
+    /// Program output for the console
+    Output(String),
+    /// A request sent without waiting failed
+    Failed(String),
+    Terminated,
+}
+
+/// How to go on from where the program stopped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Step {
+    Continue,
+    Over,
+    Into,
+    Out,
+}
+
+impl Step {
+    fn command(self) -> &'static str {
+        match self {
+            Step::Continue => "continue",
+            Step::Over => "next",
+            Step::Into => "stepIn",
+            Step::Out => "stepOut",
+        }
+    }
+}
+
+pub struct StackFrame {
+    pub id: i64,
+    pub name: String,
+    pub path: Option<PathBuf>,
+    /// Counted from 0
+    pub line: usize,
+    /// Counted from 0
+    pub column: usize,
+}
+
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+}
+
+/// A debug adapter speaking DAP over its stdin and stdout.
+pub struct DebugAdapter {
+    pub name: String,
+    child: process::Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    next_seq: i64,
+    capabilities: Value,
+    // Requests not waited for, by sequence number, with their command
+    pending: HashMap<i64, String>,
+    // Events that arrived while waiting for an answer
+    events: Vec<DebugEvent>,
+}
+
+impl DebugAdapter {
+    /// Starts the adapter of `config` in `root` and asks it to launch or
+    /// attach to the program.
+    pub fn start(config: &DebugConfig, root: &Path) -> Result<Self, String> {
+        let mut child = hooks::shell_command(&config.adapter)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", config.adapter, e))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(format!("Failed to talk to {}", config.adapter));
+        };
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Some(message) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut adapter = Self {
+            name: config.name.clone(),
+            child,
+            stdin,
+            messages,
+            next_seq: 1,
+            capabilities: Value::Null,
+            pending: HashMap::new(),
+            events: Vec::new(),
+        };
+        adapter.capabilities = adapter.request(
+            "initialize",
+            json!({
+                "clientID": "pyne",
+                "clientName": "pyne",
+                "adapterID": config.name,
+                "linesStartAt1": true,
+                "columnsStartAt1": true,
+                "pathFormat": "path",
+                "supportsRunInTerminalRequest": false,
+            }),
+        )?;
+        let request = match config.request.as_str() {
+            "launch" | "attach" => config.request.as_str(),
+            other => return Err(format!("Unknown debug request: {}", other)),
+        };
+        // Adapters may answer only after `configurationDone`
+        adapter.send_request(request, Value::Object(config.arguments.clone()))?;
+        Ok(adapter)
+    }
+
+    /// Sends the breakpoints of a file, replacing the ones sent before.
+    pub fn set_breakpoints(&mut self, path: &Path, lines: &[usize]) -> Result<(), String> {
+        let breakpoints: Vec<Value> = lines
+            .iter()
+            .map(|line| json!({ "line": line + 1 }))
+            .collect();
+        self.send_request(
+            "setBreakpoints",
+            json!({
+                "source": { "path": path, "name": path.file_name().map(|name| name.to_string_lossy()) },
+                "breakpoints": breakpoints,
+            }),
+        )
+    }
+
+    /// Tells the adapter all breakpoints are set, if it waits for that.
+    pub fn configuration_done(&mut self) -> Result<(), String> {
+        if self.capabilities["supportsConfigurationDoneRequest"] == Value::Bool(true) {
+            self.send_request("configurationDone", json!({}))?;
+        }
+        Ok(())
+    }
+
+    pub fn step(&mut self, step: Step, thread_id: i64) -> Result<(), String> {
+        self.send_request(step.command(), json!({ "threadId": thread_id }))
+    }
+
+    /// Pauses the first thread, or all of them with adapters that stop the
+    /// whole program.
+    pub fn pause(&mut self) -> Result<(), String> {
+        let threads = self.request("threads", Value::Null)?;
+        let thread_id = threads["threads"][0]["id"]
+            .as_i64()
+            .ok_or_else(|| format!("{} has no threads to pause", self.name))?;
+        self.send_request("pause", json!({ "threadId": thread_id }))
+    }
+
+    /// The call stack of a stopped thread, innermost frame first.
+    pub fn stack_trace(&mut self, thread_id: i64) -> Result<Vec<StackFrame>, String> {
+        let body = self.request("stackTrace", json!({ "threadId": thread_id }))?;
+        Ok(body["stackFrames"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|frame| {
+                Some(StackFrame {
+                    id: frame["id"].as_i64()?,
+                    name: frame["name"].as_str().unwrap_or_default().to_string(),
+                    path: frame["source"]["path"].as_str().map(PathBuf::from),
+                    line: (frame["line"].as_u64()? as usize).saturating_sub(1),
+                    column: (frame["column"].as_u64().unwrap_or(1) as usize).saturating_sub(1),
+                })
+            })
+            .collect())
+    }
+
+    /// The variables of a frame's scopes, leaving out scopes the adapter
+    /// calls expensive such as globals.
+    pub fn variables(&mut self, frame_id: i64) -> Result<Vec<Variable>, String> {
+        let body = self.request("scopes", json!({ "frameId": frame_id }))?;
+        let mut variables = Vec::new();
+        for scope in body["scopes"].as_array().into_iter().flatten() {
+            if scope["expensive"] == Value::Bool(true) {
+                continue;
+            }
+            let Some(reference) = scope["variablesReference"].as_i64() else {
+                continue;
+            };
+            let body = self.request("variables", json!({ "variablesReference": reference }))?;
+            variables.extend(
+                body["variables"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|variable| {
+                        Some(Variable {
+                            name: variable["name"].as_str()?.to_string(),
+                            value: variable["value"].as_str().unwrap_or_default().to_string(),
+                        })
+                    }),
+            );
+        }
+        Ok(variables)
+    }
+
+    /// Evaluates an expression as typed into the debug console, in a
+    /// frame when the program is stopped.
+    pub fn evaluate(&mut self, expression: &str, frame_id: Option<i64>) -> Result<String, String> {
+        let body = self.request(
+            "evaluate",
+            json!({ "expression": expression, "frameId": frame_id, "context": "repl" }),
+        )?;
+        Ok(body["result"].as_str().unwrap_or_default().to_string())
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Result<Value, String> {
+        let seq = self.send_message(command, arguments)?;
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let message = match self.messages.recv_timeout(timeout) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(format!("{} did not answer {} in time", self.name, command))
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(format!("{} exited", self.name)),
+            };
+            if message["type"] == "response" && message["request_seq"] == json!(seq) {
+                if message["success"] != Value::Bool(true) {
+                    return Err(failure(&self.name, command, &message));
+                }
+                return Ok(message.get("body").cloned().unwrap_or(Value::Null));
+            }
+            self.receive(message)?;
+        }
+    }
+
+    fn send_request(&mut self, command: &str, arguments: Value) -> Result<(), String> {
+        let seq = self.send_message(command, arguments)?;
+        self.pending.insert(seq, command.to_string());
+        Ok(())
+    }
+
+    /// What the adapter sent since the last call, without waiting for more.
+    pub fn poll(&mut self) -> Result<Vec<DebugEvent>, String> {
+        loop {
+            match self.messages.try_recv() {
+                Ok(message) => self.receive(message)?,
+                Err(TryRecvError::Empty) => return Ok(std::mem::take(&mut self.events)),
+                Err(TryRecvError::Disconnected) => return Err(format!("{} exited", self.name)),
+            }
+        }
+    }
+
+    fn receive(&mut self, message: Value) -> Result<(), String> {
+        match message["type"].as_str() {
+            Some("event") => {
+                let body = &message["body"];
+                let event = match message["event"].as_str() {
+                    Some("initialized") => DebugEvent::Initialized,
+                    Some("stopped") => DebugEvent::Stopped {
+                        thread_id: body["threadId"].as_i64().unwrap_or_default(),
+                        reason: body["reason"].as_str().unwrap_or("paused").to_string(),
+                    },
+                    Some("continued") => DebugEvent::Continued,
+                    Some("output") if body["category"] != "telemetry" => {
+                        DebugEvent::Output(body["output"].as_str().unwrap_or_default().to_string())
+                    }
+                    Some("terminated") => DebugEvent::Terminated,
+                    _ => return Ok(()),
+                };
+                self.events.push(event);
+            }
+            Some("response") => {
+                let Some(command) = message["request_seq"]
+                    .as_i64()
+                    .and_then(|seq| self.pending.remove(&seq))
+                else {
+                    return Ok(());
+                };
+                if message["success"] != Value::Bool(true) {
+                    let text = failure(&self.name, &command, &message);
+                    self.events.push(DebugEvent::Failed(text));
+                }
+            }
+            // Such as `runInTerminal`, which we do not offer
+            Some("request") => {
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                self.send(json!({
+                    "seq": seq,
+                    "type": "response",
+                    "request_seq": message["seq"],
+                    "command": message["command"],
+                    "success": false,
+                    "message": "not supported",
+                }))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn send_message(&mut self, command: &str, arguments: Value) -> Result<i64, String> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.send(
+            json!({ "seq": seq, "type": "request", "command": command, "arguments": arguments }),
+        )?;
+        Ok(seq)
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), String> {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+            .and_then(|()| self.stdin.flush())
+            .map_err(|e| format!("Failed to write to {}: {}", self.name, e))
+    }
+}
+
+impl Drop for DebugAdapter {
+    fn drop(&mut self) {
+        let _ = self.send_message("disconnect", json!({ "terminateDebuggee": true }));
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn failure(name: &str, command: &str, response: &Value) -> String {
+    let text = response["body"]["error"]["format"]
+        .as_str()
+        .or_else(|| response["message"].as_str())
+        .unwrap_or("unknown error");
+    format!("{}: {} failed: {}", name, command, text)
+}
+
+/// What the debug panel shows: the stack and variables of the stopped
+/// thread and the console, which outlives the session.
+#[derive(Default)]
+pub struct DebugState {
+    /// The stopped thread, None while the program runs
+    pub thread_id: Option<i64>,
+    pub reason: String,
+    pub frames: Vec<StackFrame>,
+    /// Index of the frame whose variables are shown
+    pub frame: usize,
+    pub variables: Vec<Variable>,
+    pub console: String,
+}
+
+impl DebugState {
+    /// Adds output to the console, which may end partway through a line.
+    pub fn print(&mut self, output: &str) {
+        self.console.push_str(output);
+        let lines = self.console.matches('\n').count();
+        if lines > CONSOLE_LINES {
+            let cut = self
+                .console
+                .match_indices('\n')
+                .nth(lines - CONSOLE_LINES - 1)
+                .map_or(0, |(index, _)| index + 1);
+            self.console.drain(..cut);
+        }
+    }
+
+    /// Adds a line of our own to the console.
+    pub fn print_line(&mut self, text: &str) {
+        if !self.console.is_empty() && !self.console.ends_with('\n') {
+            self.console.push('\n');
+        }
+        self.print(&format!("{}\n", text));
+    }
+
+    pub fn selected_frame(&self) -> Option<&StackFrame> {
+        self.thread_id.and(self.frames.get(self.frame))
+    }
+
+    /// The panel's text, with the stack first so that line `n` from 1 is
+    /// frame `n - 1`.
+    pub fn render(&self, directory: &Path) -> String {
+        let mut text = match self.thread_id {
+            Some(thread_id) => format!("Stack of thread {} ({})\n", thread_id, self.reason),
+            None => "Stack (running)\n".to_string(),
+        };
+        if self.thread_id.is_some() {
+            for (index, frame) in self.frames.iter().enumerate() {
+                let location = frame
+                    .path
+                    .as_ref()
+                    .map(|path| {
+                        format!(
+                            "  {}:{}",
+                            path.strip_prefix(directory).unwrap_or(path).display(),
+                            frame.line + 1
+                        )
+                    })
+                    .unwrap_or_default();
+                let marker = if index == self.frame { '>' } else { ' ' };
+                text.push_str(&format!("{} {}{}\n", marker, frame.name, location));
+            }
+        }
+        text.push_str("\nVariables\n");
+        if self.thread_id.is_some() {
+            for variable in &self.variables {
+                text.push_str(&format!("  {} = {}\n", variable.name, variable.value));
+            }
+        }
+        text.push_str("\nConsole\n");
+        text.push_str(&self.console);
+        text
+    }
+
+    /// The frame listed on a line of the panel's text.
+    pub fn frame_at(&self, line: usize) -> Option<usize> {
+        let index = line.checked_sub(1)?;
+        (self.thread_id.is_some() && index < self.frames.len()).then_some(index)
+    }
+}
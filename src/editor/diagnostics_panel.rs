@@ -0,0 +1,173 @@
+use crate::editor::lsp::{Diagnostic, Position, Severity};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+// Characters of a message kept for its line in the panel
+const MESSAGE_CHARS: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DiagnosticSort {
+    /// Errors first, then by file and line
+    Severity,
+    /// By file and line
+    File,
+}
+
+/// What the panel shows of the diagnostics of every file.
+#[derive(Clone, Debug)]
+pub struct DiagnosticFilter {
+    /// Diagnostics less severe than this are left out
+    pub severity: Severity,
+    /// Only files whose path contains this
+    pub file: Option<String>,
+}
+
+impl Default for DiagnosticFilter {
+    fn default() -> Self {
+        Self {
+            severity: Severity::Hint,
+            file: None,
+        }
+    }
+}
+
+/// One diagnostic as a line of the panel.
+pub struct PanelEntry {
+    pub path: PathBuf,
+    pub start: Position,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// The bottom panel listing the diagnostics of all files, kept so that
+/// it can be refreshed as servers publish more.
+pub struct DiagnosticsPanel {
+    pub sort: DiagnosticSort,
+    pub filter: DiagnosticFilter,
+    /// What each line after the header jumps to
+    pub entries: Vec<PanelEntry>,
+}
+
+impl DiagnosticsPanel {
+    pub fn new(sort: DiagnosticSort, filter: DiagnosticFilter) -> Self {
+        Self {
+            sort,
+            filter,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Collects the diagnostics that pass the filter, in the panel's order.
+    pub fn refresh(&mut self, diagnostics: &HashMap<PathBuf, Vec<Diagnostic>>) {
+        let file = self.filter.file.as_deref();
+        let mut entries: Vec<PanelEntry> = diagnostics
+            .iter()
+            .filter(|(path, _)| file.is_none_or(|file| path.to_string_lossy().contains(file)))
+            .flat_map(|(path, diagnostics)| {
+                diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity <= self.filter.severity)
+                    .map(move |diagnostic| PanelEntry {
+                        path: path.clone(),
+                        start: diagnostic.start,
+                        severity: diagnostic.severity,
+                        message: diagnostic.message.clone(),
+                    })
+            })
+            .collect();
+        match self.sort {
+            DiagnosticSort::Severity => entries.sort_by(|a, b| {
+                (a.severity, &a.path, a.start).cmp(&(b.severity, &b.path, b.start))
+            }),
+            DiagnosticSort::File => entries.sort_by(|a, b| {
+                (&a.path, a.start, a.severity).cmp(&(&b.path, b.start, b.severity))
+            }),
+        }
+        self.entries = entries;
+    }
+
+    /// The panel's text: a header saying how it is sorted and filtered,
+    /// then a line per entry with paths relative to `directory`.
+    pub fn render(&self, directory: &Path) -> String {
+        let sort = match self.sort {
+            DiagnosticSort::Severity => "severity",
+            DiagnosticSort::File => "file",
+        };
+        let mut text = format!(
+            "{} diagnostic(s), {}{}, sorted by {}  [Enter jump, s sort, e/w/i/a filter, q close]\n",
+            self.entries.len(),
+            match self.filter.severity {
+                Severity::Hint => "all severities".to_string(),
+                severity => format!("{} and worse", severity.group()),
+            },
+            self.filter
+                .file
+                .as_ref()
+                .map(|file| format!(" in files matching {}", file))
+                .unwrap_or_default(),
+            sort,
+        );
+        for entry in &self.entries {
+            let message: String = entry
+                .message
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(MESSAGE_CHARS)
+                .collect();
+            text.push_str(&format!(
+                "{}:{}:{} {} {}\n",
+                entry
+                    .path
+                    .strip_prefix(directory)
+                    .unwrap_or(&entry.path)
+                    .display(),
+                entry.start.line + 1,
+                entry.start.character + 1,
+                entry.severity.sign().symbol,
+                message
+            ));
+        }
+        text
+    }
+
+    /// The entry on a line of the panel's text.
+    pub fn entry_at(&self, line: usize) -> Option<&PanelEntry> {
+        self.entries.get(line.checked_sub(1)?)
+    }
+}
+
+/// Parses the arguments of `:diagnostics`: a severity such as `warning`
+/// and any other word as the file filter.
+pub fn parse_filter(args: &str) -> DiagnosticFilter {
+    let mut filter = DiagnosticFilter::default();
+    for word in args.split_whitespace() {
+        match severity_from_name(word) {
+            Some(severity) => filter.severity = severity,
+            None => filter.file = Some(word.to_string()),
+        }
+    }
+    filter
+}
+
+fn severity_from_name(name: &str) -> Option<Severity> {
+    match name.to_lowercase().as_str() {
+        "e" | "error" | "errors" => Some(Severity::Error),
+        "w" | "warning" | "warnings" => Some(Severity::Warning),
+        "i" | "info" | "information" => Some(Severity::Information),
+        "h" | "a" | "hint" | "hints" | "all" => Some(Severity::Hint),
+        _ => None,
+    }
+}
+
+/// The number of diagnostics of each severity, most severe first.
+pub fn count_by_severity(diagnostics: &HashMap<PathBuf, Vec<Diagnostic>>) -> [usize; 4] {
+    let mut counts = [0; 4];
+    for diagnostic in diagnostics.values().flatten() {
+        counts[diagnostic.severity as usize] += 1;
+    }
+    counts
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Directories left out of the comparison
+const IGNORED_DIRS: &[&str] = &[".git"];
+
+/// Source of the signs marking the lines a file diff found
+pub const SIGN_SOURCE: &str = "diff";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FileChange {
+    /// Only in the second tree
+    Added,
+    /// Only in the first tree
+    Removed,
+    /// In both, with different content
+    Changed,
+}
+
+impl FileChange {
+    pub fn label(self) -> &'static str {
+        match self {
+            FileChange::Added => "added",
+            FileChange::Removed => "removed",
+            FileChange::Changed => "changed",
+        }
+    }
+}
+
+/// The files that differ between the trees under `a` and `b`, by path
+/// relative to them, sorted.
+pub fn compare_dirs(a: &Path, b: &Path) -> io::Result<Vec<(PathBuf, FileChange)>> {
+    let old = list_files(a)?;
+    let new = list_files(b)?;
+    let mut changes = Vec::new();
+    for relative in old.union(&new) {
+        let change = match (old.contains(relative), new.contains(relative)) {
+            (true, false) => FileChange::Removed,
+            (false, true) => FileChange::Added,
+            _ if same_content(&a.join(relative), &b.join(relative)) => continue,
+            _ => FileChange::Changed,
+        };
+        changes.push((relative.clone(), change));
+    }
+    Ok(changes)
+}
+
+// Symlinks are followed, so a link to a directory is listed by its files.
+// Entries that cannot be read, such as dangling links, are listed as files
+// for the comparison to report.
+fn list_files(root: &Path) -> io::Result<BTreeSet<PathBuf>> {
+    let mut files = BTreeSet::new();
+    let mut visited = HashSet::new();
+    let mut directories = vec![root.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        // A link back up the tree would otherwise be walked forever
+        if !visited.insert(fs::canonicalize(&directory)?) {
+            continue;
+        }
+        let entries = match fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(e) if directory == root => return Err(e),
+            Err(_) => {
+                if let Ok(relative) = directory.strip_prefix(root) {
+                    files.insert(relative.to_path_buf());
+                }
+                continue;
+            }
+        };
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            if fs::metadata(&path).is_ok_and(|metadata| metadata.is_dir()) {
+                if !IGNORED_DIRS.iter().any(|dir| entry.file_name() == *dir) {
+                    directories.push(path);
+                }
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                files.insert(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(files)
+}
+
+// Files that cannot be read count as differing
+fn same_content(a: &Path, b: &Path) -> bool {
+    let len = |path: &Path| fs::metadata(path).map(|metadata| metadata.len()).ok();
+    match (len(a), len(b)) {
+        (Some(a_len), Some(b_len)) if a_len == b_len => {}
+        _ => return false,
+    }
+    match (fs::read(a), fs::read(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+// Line comparisons beyond which the lines between the common start and
+// end are all taken as changed rather than matched up
+const MAX_DIFF_CELLS: usize = 16_000_000;
+
+/// The lines of `old` missing from `new` and the lines of `new` missing
+/// from `old`, counted from 0, through a longest common subsequence.
+pub fn line_diff(old: &str, new: &str) -> (Vec<usize>, Vec<usize>) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let (n, m) = (old_middle.len(), new_middle.len());
+    if n * m > MAX_DIFF_CELLS {
+        return (
+            (prefix..prefix + n).collect(),
+            (prefix..prefix + m).collect(),
+        );
+    }
+
+    // lengths[i][j]: longest common subsequence of old_middle[i..] and
+    // new_middle[j..]
+    let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if old_middle[i] == new_middle[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_middle[i] == new_middle[j] {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            removed.push(prefix + i);
+            i += 1;
+        } else {
+            added.push(prefix + j);
+            j += 1;
+        }
+    }
+    (removed, added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn follows_links_and_reports_dangling_ones_as_changed() {
+        let root = std::env::temp_dir().join(format!("pyne-dirdiff-{}", std::process::id()));
+        let (a, b) = (root.join("a"), root.join("b"));
+        fs::create_dir_all(root.join("shared")).unwrap();
+        fs::create_dir_all(b.join("lib")).unwrap();
+        fs::create_dir_all(&a).unwrap();
+        fs::write(root.join("shared/mod.rs"), "same").unwrap();
+        fs::write(b.join("lib/mod.rs"), "same").unwrap();
+        symlink(root.join("shared"), a.join("lib")).unwrap();
+        symlink(root.join("missing"), a.join("gone")).unwrap();
+        fs::write(b.join("gone"), "here").unwrap();
+
+        let changes = compare_dirs(&a, &b);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(
+            changes.unwrap(),
+            [(PathBuf::from("gone"), FileChange::Changed)]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Address {
+    CurrentLine,
+    LastLine,
+    Line(usize),
+    SelectionStart,
+    SelectionEnd,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LineAddress {
+    pub address: Address,
+    pub offset: isize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExRange {
+    WholeFile,
+    Single(LineAddress),
+    Span(LineAddress, LineAddress),
+}
+
+impl ExRange {
+    /// Splits a leading range such as `%`, `10,20`, `.,$-1` or `'<,'>` off a
+    /// command line, returning the range (if any) and the remaining command.
+    pub fn parse(input: &str) -> Result<(Option<ExRange>, &str), String> {
+        if let Some(rest) = input.strip_prefix('%') {
+            return Ok((Some(ExRange::WholeFile), rest.trim_start()));
+        }
+
+        let (start, rest) = match Self::parse_address(input)? {
+            Some(parsed) => parsed,
+            None => return Ok((None, input)),
+        };
+
+        if let Some(after_comma) = rest.strip_prefix(',') {
+            match Self::parse_address(after_comma)? {
+                Some((end, rest)) => Ok((Some(ExRange::Span(start, end)), rest.trim_start())),
+                None => Err(format!("Missing end of range in: {}", input)),
+            }
+        } else {
+            Ok((Some(ExRange::Single(start)), rest.trim_start()))
+        }
+    }
+
+    /// Resolves the range to zero-based, inclusive line indices.
+    pub fn resolve(
+        &self,
+        current_line: usize,
+        last_line: usize,
+        selection: Option<(usize, usize)>,
+    ) -> Result<(usize, usize), String> {
+        let (start, end) = match self {
+            ExRange::WholeFile => return Ok((0, last_line)),
+            ExRange::Single(address) => {
+                let line = address.resolve(current_line, last_line, selection)?;
+                (line, line)
+            }
+            ExRange::Span(start, end) => (
+                start.resolve(current_line, last_line, selection)?,
+                end.resolve(current_line, last_line, selection)?,
+            ),
+        };
+
+        if start > end {
+            Err("Backwards range given".to_string())
+        } else {
+            Ok((start, end))
+        }
+    }
+
+    fn parse_address(input: &str) -> Result<Option<(LineAddress, &str)>, String> {
+        let (address, rest) = if let Some(rest) = input.strip_prefix('.') {
+            (Address::CurrentLine, rest)
+        } else if let Some(rest) = input.strip_prefix('$') {
+            (Address::LastLine, rest)
+        } else if let Some(rest) = input.strip_prefix("'<") {
+            (Address::SelectionStart, rest)
+        } else if let Some(rest) = input.strip_prefix("'>") {
+            (Address::SelectionEnd, rest)
+        } else if input.starts_with(|c: char| c.is_ascii_digit()) {
+            let (digits, rest) = Self::split_digits(input);
+            let line = digits
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid line number: {}", digits))?;
+            (Address::Line(line), rest)
+        } else if input.starts_with(['+', '-']) {
+            (Address::CurrentLine, input)
+        } else {
+            return Ok(None);
+        };
+
+        let mut offset: isize = 0;
+        let mut rest = rest;
+        while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+            let (digits, remaining) = Self::split_digits(&rest[1..]);
+            let amount = if digits.is_empty() {
+                1
+            } else {
+                digits
+                    .parse::<isize>()
+                    .map_err(|_| format!("Invalid line offset: {}", digits))?
+            };
+            offset += if sign == '+' { amount } else { -amount };
+            rest = remaining;
+        }
+
+        Ok(Some((LineAddress { address, offset }, rest)))
+    }
+
+    fn split_digits(input: &str) -> (&str, &str) {
+        let end = input
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(input.len());
+        input.split_at(end)
+    }
+}
+
+impl LineAddress {
+    fn resolve(
+        &self,
+        current_line: usize,
+        last_line: usize,
+        selection: Option<(usize, usize)>,
+    ) -> Result<usize, String> {
+        let base = match self.address {
+            Address::CurrentLine => current_line,
+            Address::LastLine => last_line,
+            // Line numbers are one-based on the command line
+            Address::Line(line) => line.saturating_sub(1),
+            Address::SelectionStart => selection.ok_or("No visual selection")?.0,
+            Address::SelectionEnd => selection.ok_or("No visual selection")?.1,
+        };
+
+        let line = base as isize + self.offset;
+        if line < 0 || line as usize > last_line {
+            Err(format!("Line out of range: {}", line + 1))
+        } else {
+            Ok(line as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(input: &str) -> Result<(usize, usize), String> {
+        let (range, _) = ExRange::parse(input)?;
+        range.unwrap().resolve(4, 9, Some((2, 6)))
+    }
+
+    #[test]
+    fn parses_a_range_off_the_command() {
+        let (range, rest) = ExRange::parse("10,20d").unwrap();
+        assert!(matches!(range, Some(ExRange::Span(_, _))));
+        assert_eq!(rest, "d");
+        assert_eq!(ExRange::parse("%s/a/b/").unwrap().1, "s/a/b/");
+        assert_eq!(ExRange::parse("w").unwrap(), (None, "w"));
+        assert!(ExRange::parse("3,").is_err());
+    }
+
+    #[test]
+    fn resolves_to_zero_based_lines() {
+        assert_eq!(resolve("%"), Ok((0, 9)));
+        assert_eq!(resolve("2,3"), Ok((1, 2)));
+        assert_eq!(resolve(".,$-1"), Ok((4, 8)));
+        assert_eq!(resolve("+2"), Ok((6, 6)));
+        assert_eq!(resolve("'<,'>"), Ok((2, 6)));
+        assert_eq!(resolve(".--"), Ok((2, 2)));
+    }
+
+    #[test]
+    fn rejects_backwards_and_out_of_range_lines() {
+        assert!(resolve("5,2").is_err());
+        assert!(resolve("$+1").is_err());
+        let (range, _) = ExRange::parse("'<").unwrap();
+        assert!(range.unwrap().resolve(0, 9, None).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::editor::syntax::{HighlightKind, LineState, Syntax};
+use ropey::Rope;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExportFormat {
+    /// A standalone HTML page
+    Html,
+    /// Text with ANSI color escapes, for `cat` or `less -R`
+    Ansi,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "html" => Some(ExportFormat::Html),
+            "ansi" => Some(ExportFormat::Ansi),
+            _ => None,
+        }
+    }
+}
+
+fn colors(kind: HighlightKind) -> (&'static str, u8) {
+    match kind {
+        HighlightKind::Keyword => ("#c678dd", 35),
+        HighlightKind::String => ("#98c379", 32),
+        HighlightKind::Comment => ("#7f848e", 90),
+        HighlightKind::Number => ("#e5c07b", 33),
+    }
+}
+
+/// Renders `content` with syntax highlighting in the given format.
+pub fn export(
+    content: &Rope,
+    syntax: Option<&Syntax>,
+    title: &str,
+    format: ExportFormat,
+) -> String {
+    let mut out = String::new();
+    if format == ExportFormat::Html {
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             </head>\n<body style=\"background:#1e1e1e;color:#d4d4d4\">\n<pre>",
+            escape_html(title)
+        );
+    }
+
+    let mut state = LineState::Normal;
+    for line in content.lines() {
+        let line = line.to_string();
+        let text = line.trim_end_matches(['\n', '\r']);
+        let chars: Vec<char> = text.chars().collect();
+        let spans = match syntax {
+            Some(syntax) => {
+                let (spans, end_state) = syntax.highlight_line(text, state);
+                state = end_state;
+                spans
+            }
+            None => Vec::new(),
+        };
+
+        let mut position = 0;
+        for span in spans {
+            push_text(&mut out, &chars[position..span.start], format);
+            let (html, sgr) = colors(span.kind);
+            match format {
+                ExportFormat::Html => {
+                    let _ = write!(out, "<span style=\"color:{}\">", html);
+                }
+                ExportFormat::Ansi => {
+                    let _ = write!(out, "\x1b[{}m", sgr);
+                }
+            }
+            push_text(&mut out, &chars[span.start..span.end], format);
+            out.push_str(match format {
+                ExportFormat::Html => "</span>",
+                ExportFormat::Ansi => "\x1b[0m",
+            });
+            position = span.end;
+        }
+        push_text(&mut out, &chars[position..], format);
+        if line.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+
+    if format == ExportFormat::Html {
+        out.push_str("</pre>\n</body>\n</html>\n");
+    }
+    out
+}
+
+fn push_text(out: &mut String, chars: &[char], format: ExportFormat) {
+    let text: String = chars.iter().collect();
+    match format {
+        ExportFormat::Html => out.push_str(&escape_html(&text)),
+        ExportFormat::Ansi => out.push_str(&text),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,134 @@
+use crate::utils::progress::{self, Progress};
+use std::{
+    io::{self, Read},
+    process::{self, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Whether `target` names a resource to fetch rather than a file.
+pub fn is_url(target: &str) -> bool {
+    target.starts_with("http://") || target.starts_with("https://")
+}
+
+/// A download running in the background through `curl`, with its progress
+/// in the status line.
+pub struct Fetch {
+    pub url: String,
+    result: Arc<Mutex<Option<io::Result<String>>>>,
+    progress: usize,
+}
+
+impl Fetch {
+    pub fn spawn(url: &str) -> io::Result<Self> {
+        let mut curl = process::Command::new("curl");
+        curl.args(["--fail", "--silent", "--show-error", "--location"])
+            .args(["--proto", "=http,https", "--"])
+            .arg(url);
+        Self::spawn_with(url, curl)
+    }
+
+    // Runs `downloader`, which writes the resource to its stdout
+    fn spawn_with(url: &str, mut downloader: process::Command) -> io::Result<Self> {
+        let mut child = downloader
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| io::Error::other(format!("Failed to run curl: {}", e)))?;
+
+        let result = Arc::new(Mutex::new(None));
+        let shared = Arc::clone(&result);
+        let title = format!("Fetching {}", url);
+        let progress = Progress::start(title.clone());
+        let progress_id = progress.id();
+        thread::spawn(move || {
+            let (mut stdout, mut stderr) = (child.stdout.take(), child.stderr.take());
+            let child = Arc::new(Mutex::new(child));
+            // A stalled download never returns from its read, so cancelling
+            // kills curl rather than waiting for the loop below to notice
+            let cancelled = Arc::clone(&child);
+            progress.on_cancel(move || {
+                if let Ok(mut child) = cancelled.lock() {
+                    let _ = child.kill();
+                }
+            });
+            let mut body = Vec::new();
+            let mut chunk = [0; 64 * 1024];
+            let mut read = || -> io::Result<()> {
+                let Some(stdout) = stdout.as_mut() else {
+                    return Ok(());
+                };
+                loop {
+                    let count = stdout.read(&mut chunk)?;
+                    if progress.is_cancelled() {
+                        return Err(io::Error::other("Cancelled"));
+                    }
+                    if count == 0 {
+                        return Ok(());
+                    }
+                    body.extend_from_slice(&chunk[..count]);
+                    progress.set_title(format!("{} ({} KB)", title, body.len() / 1024));
+                }
+            };
+            let outcome = read().and_then(|()| {
+                let mut errors = String::new();
+                if let Some(stderr) = stderr.as_mut() {
+                    stderr.read_to_string(&mut errors)?;
+                }
+                let status = child
+                    .lock()
+                    .map_err(|_| io::Error::other("curl is gone"))?
+                    .wait()?;
+                if !status.success() {
+                    return Err(io::Error::other(errors.trim().to_string()));
+                }
+                String::from_utf8(body).map_err(|_| io::Error::other("Not a text resource"))
+            });
+            if let Ok(mut result) = shared.lock() {
+                *result = Some(outcome);
+            }
+        });
+
+        Ok(Self {
+            url: url.to_string(),
+            result,
+            progress: progress_id,
+        })
+    }
+
+    /// Stops the download, which then fails with "Cancelled".
+    pub fn cancel(&self) {
+        progress::cancel(self.progress);
+    }
+
+    /// The fetched text or why it failed, once the download is over.
+    pub fn take_result(&self) -> Option<io::Result<String>> {
+        self.result.lock().ok()?.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn cancelling_stops_a_stalled_download() {
+        // Never writes anything, like a server that does not answer
+        let mut stalled = process::Command::new("sleep");
+        stalled.arg("60");
+        let fetch = Fetch::spawn_with("http://example.com/", stalled).unwrap();
+
+        let started = Instant::now();
+        let result = loop {
+            fetch.cancel();
+            if let Some(result) = fetch.take_result() {
+                break result;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert!(result.is_err());
+    }
+}
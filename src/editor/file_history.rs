@@ -0,0 +1,36 @@
+use crate::editor::git::Commit;
+use std::path::{Path, PathBuf};
+
+/// The panel listing the commits that changed a file, opened by
+/// `:git log %`.
+pub struct FileHistory {
+    pub path: PathBuf,
+    pub commits: Vec<Commit>,
+}
+
+impl FileHistory {
+    /// The panel's text: a header naming the file relative to `directory`,
+    /// then a line per commit.
+    pub fn render(&self, directory: &Path) -> String {
+        let mut text = format!(
+            "{} commit(s) changing {}  [Enter open, d diff with working copy, q close]\n",
+            self.commits.len(),
+            self.path
+                .strip_prefix(directory)
+                .unwrap_or(&self.path)
+                .display(),
+        );
+        for commit in &self.commits {
+            text.push_str(&format!(
+                "{} {} {}: {}\n",
+                commit.hash, commit.date, commit.author, commit.subject
+            ));
+        }
+        text
+    }
+
+    /// The commit on a line of the panel's text.
+    pub fn commit_at(&self, line: usize) -> Option<&Commit> {
+        self.commits.get(line.checked_sub(1)?)
+    }
+}
@@ -0,0 +1,171 @@
+use crate::utils::progress::Progress;
+use notify::{event::ModifyKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+// Directories the index never descends into
+const IGNORED_DIRS: &[&str] = &[".git", "target", "node_modules"];
+
+#[derive(Default)]
+struct IndexState {
+    // Relative to the root
+    files: BTreeSet<PathBuf>,
+    // Whether the first walk of the tree has finished
+    ready: bool,
+}
+
+/// The files under a project directory, listed on a background thread and
+/// then kept current from filesystem events, so file pickers need not walk
+/// the tree each time they open.
+#[derive(Clone)]
+pub struct FileIndex {
+    root: PathBuf,
+    state: Arc<Mutex<IndexState>>,
+    // None when the platform could not watch the tree; the index then
+    // only knows what the first walk found
+    _watcher: Option<Arc<RecommendedWatcher>>,
+}
+
+type Excluded = Arc<[String]>;
+
+impl FileIndex {
+    /// Starts indexing `root`, skipping directories named in `exclude`.
+    pub fn spawn(root: &Path, exclude: &[String]) -> Self {
+        let exclude: Excluded = exclude.into();
+        let state = Arc::new(Mutex::new(IndexState::default()));
+        // Watch before walking so no change falls in between
+        let watcher = watch(root, Arc::clone(&state), Arc::clone(&exclude)).map(Arc::new);
+
+        let walked = Arc::clone(&state);
+        let directory = root.to_path_buf();
+        thread::spawn(move || {
+            let progress = Progress::start("Indexing files");
+            let mut files = Vec::new();
+            walk(
+                &directory,
+                &directory,
+                &exclude,
+                &mut files,
+                Some(&progress),
+            );
+            if let Ok(mut state) = walked.lock() {
+                state.files.extend(files);
+                state.ready = true;
+            }
+        });
+
+        Self {
+            root: root.to_path_buf(),
+            state,
+            _watcher: watcher,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The indexed files relative to the root, sorted, and whether the
+    /// first walk has finished so the list is complete.
+    pub fn files(&self) -> (Vec<PathBuf>, bool) {
+        match self.state.lock() {
+            Ok(state) => (state.files.iter().cloned().collect(), state.ready),
+            Err(_) => (Vec::new(), false),
+        }
+    }
+}
+
+fn is_ignored(relative: &Path, exclude: &[String]) -> bool {
+    relative.components().any(|component| {
+        let name = component.as_os_str();
+        IGNORED_DIRS.iter().any(|dir| name == *dir)
+            || exclude.iter().any(|dir| name == dir.as_str())
+    })
+}
+
+fn walk(
+    root: &Path,
+    directory: &Path,
+    exclude: &[String],
+    files: &mut Vec<PathBuf>,
+    progress: Option<&Progress>,
+) {
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        if progress.is_some_and(Progress::is_cancelled) {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            if is_ignored(relative, exclude) {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                directories.push(path);
+            } else if path.is_file() {
+                files.push(relative.to_path_buf());
+            }
+        }
+        if let Some(progress) = progress {
+            progress.set_count(files.len());
+        }
+    }
+}
+
+fn watch(
+    root: &Path,
+    state: Arc<Mutex<IndexState>>,
+    exclude: Excluded,
+) -> Option<RecommendedWatcher> {
+    let watched = root.to_path_buf();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        for path in &event.paths {
+            let Ok(relative) = path.strip_prefix(&watched) else {
+                continue;
+            };
+            if is_ignored(relative, &exclude) {
+                continue;
+            }
+            // Look at what is on disk now rather than at the kind of event,
+            // as renames arrive in different forms on each platform
+            if path.is_file() {
+                state.files.insert(relative.to_path_buf());
+            } else if path.is_dir() {
+                // A directory created or moved in brings its files along;
+                // other events on directories change nothing listed
+                let arrived = matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+                );
+                if !arrived || relative.as_os_str().is_empty() {
+                    continue;
+                }
+                let mut files = Vec::new();
+                walk(&watched, path, &exclude, &mut files, None);
+                state.files.extend(files);
+            } else {
+                state.files.retain(|file| !file.starts_with(relative));
+            }
+        }
+    })
+    .ok()?;
+    watcher.watch(root, RecursiveMode::Recursive).ok()?;
+    Some(watcher)
+}
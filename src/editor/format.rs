@@ -0,0 +1,69 @@
+use crate::editor::{
+    hooks,
+    move_lines::{indent_width, shift_indent},
+};
+use std::{io::Write, path::Path, process::Stdio, thread};
+
+/// Pipes `text` through the shell `command` run in `directory`, returning
+/// what it printed.
+pub fn run_formatter(command: &str, text: &str, directory: &Path) -> Result<String, String> {
+    let mut child = hooks::shell_command(command)
+        .current_dir(directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", command, e))?;
+
+    // Written from a thread so a formatter that prints as it reads cannot
+    // fill its output pipe while we are still writing
+    let writer = child.stdin.take().map(|mut stdin| {
+        let text = text.to_string();
+        thread::spawn(move || stdin.write_all(text.as_bytes()))
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run {}: {}", command, e))?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map_or_else(|| output.status.to_string(), str::to_string);
+        return Err(format!("`{}` failed: {}", command, reason));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("`{}` printed invalid UTF-8", command))
+}
+
+/// `formatted` shifted back to the indentation of `original`, for a
+/// formatter that prints the lines of a nested block from the first column.
+pub fn keep_indent(original: &str, formatted: &str, shiftwidth: usize, expandtab: bool) -> String {
+    let least_indent = |text: &str| {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| indent_width(line, shiftwidth))
+            .min()
+    };
+    let (Some(before), Some(after)) = (least_indent(original), least_indent(formatted)) else {
+        return formatted.to_string();
+    };
+    if before == after {
+        return formatted.to_string();
+    }
+    let lines: Vec<String> = formatted.lines().map(String::from).collect();
+    let mut shifted = shift_indent(
+        &lines,
+        before as isize - after as isize,
+        shiftwidth,
+        expandtab,
+    )
+    .join("\n");
+    if formatted.ends_with('\n') {
+        shifted.push('\n');
+    }
+    shifted
+}
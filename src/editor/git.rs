@@ -0,0 +1,137 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+fn git_dir(directory: &Path) -> Option<PathBuf> {
+    let dot_git = directory
+        .ancestors()
+        .map(|ancestor| ancestor.join(".git"))
+        .find(|dot_git| dot_git.exists())?;
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let gitdir = fs::read_to_string(&dot_git).ok()?;
+    let gitdir = Path::new(gitdir.strip_prefix("gitdir:")?.trim());
+    Some(dot_git.parent()?.join(gitdir))
+}
+
+/// The branch checked out in the repository `directory` is in, or the
+/// start of the commit when the head is detached.
+pub fn current_branch(directory: &Path) -> Option<String> {
+    let head = fs::read_to_string(git_dir(directory)?.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// The local branches of the repository `directory` is in.
+pub fn branches(directory: &Path) -> Result<Vec<String>, String> {
+    let output = git(directory, &["branch", "--format=%(refname:short)"])?;
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+/// Checks out `branch`, failing with git's message when it refuses, as it
+/// does over uncommitted changes the branch would overwrite.
+pub fn checkout(directory: &Path, branch: &str) -> Result<(), String> {
+    git(directory, &["checkout", branch]).map(|_| ())
+}
+
+/// The stashes of the repository `directory` is in, newest first, as their
+/// names such as `stash@{0}` and their messages.
+pub fn stashes(directory: &Path) -> Result<Vec<(String, String)>, String> {
+    let output = git(directory, &["stash", "list", "--format=%gd%x09%s"])?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, message)| (name.to_string(), message.to_string()))
+        .collect())
+}
+
+/// Applies `stash` to the working tree, keeping it on the stash list.
+pub fn apply_stash(directory: &Path, stash: &str) -> Result<(), String> {
+    git(directory, &["stash", "apply", stash]).map(|_| ())
+}
+
+/// A commit that changed a file, as `:git log %` lists it.
+pub struct Commit {
+    pub hash: String,
+    pub date: String,
+    pub author: String,
+    pub subject: String,
+    /// The file's path from the top of the repository at this commit,
+    /// which differs before a rename
+    pub path: String,
+}
+
+/// The commits that changed `file`, newest first, following it across
+/// renames.
+pub fn log(file: &Path) -> Result<Vec<Commit>, String> {
+    let directory = file.parent().unwrap_or(Path::new("."));
+    let output = git(
+        directory,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "log",
+            "--follow",
+            "--name-only",
+            "--date=short",
+            "--format=%x00%h%x09%ad%x09%an%x09%s",
+            "--",
+            &file.to_string_lossy(),
+        ],
+    )?;
+    let mut commits: Vec<Commit> = Vec::new();
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix('\0') {
+            let mut fields = header.splitn(4, '\t').map(str::to_string);
+            commits.push(Commit {
+                hash: fields.next().unwrap_or_default(),
+                date: fields.next().unwrap_or_default(),
+                author: fields.next().unwrap_or_default(),
+                subject: fields.next().unwrap_or_default(),
+                path: String::new(),
+            });
+        } else if let Some(commit) = commits.last_mut().filter(|_| !line.is_empty()) {
+            commit.path = line.to_string();
+        }
+    }
+    // Merges list no files
+    commits.retain(|commit| !commit.path.is_empty());
+    Ok(commits)
+}
+
+/// The content at commit `hash` of `path`, relative to the top of the
+/// repository `directory` is in.
+pub fn show(directory: &Path, hash: &str, path: &str) -> Result<String, String> {
+    git(directory, &["show", &format!("{}:{}", hash, path)])
+}
+
+fn git(directory: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // Merge conflicts are reported on stdout
+        let message = stderr
+            .lines()
+            .next()
+            .or_else(|| stdout.lines().find(|line| line.starts_with("CONFLICT")))
+            .unwrap_or("git failed");
+        return Err(message.to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,219 @@
+use crate::editor::{
+    buffer::Edit,
+    syntax::{HighlightSpan, LineState, Syntax},
+};
+use once_cell::sync::Lazy;
+use ropey::Rope;
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+// Lines highlighted per batch before the worker checks whether the buffer
+// changed underneath it.
+const BATCH_LINES: usize = 256;
+
+#[derive(Clone)]
+struct LineHighlight {
+    spans: Vec<HighlightSpan>,
+    end_state: LineState,
+}
+
+/// Per-line highlight cache of one buffer, shared with the highlight worker.
+pub struct HighlightCache {
+    syntax: Option<&'static Syntax>,
+    content: Rope,
+    version: u64,
+    lines: Vec<Option<LineHighlight>>,
+    dirty: Option<(usize, usize)>,
+}
+
+pub type SharedHighlights = Arc<Mutex<HighlightCache>>;
+
+impl HighlightCache {
+    pub fn new(syntax: Option<&'static Syntax>, content: &Rope) -> SharedHighlights {
+        let mut cache = Self {
+            syntax,
+            content: Rope::new(),
+            version: 0,
+            lines: Vec::new(),
+            dirty: None,
+        };
+        cache.reset(content, 0);
+        let cache = Arc::new(Mutex::new(cache));
+        request(&cache);
+        cache
+    }
+
+    pub fn set_syntax(&mut self, syntax: Option<&'static Syntax>) {
+        if self.syntax.map(|s| s.name) != syntax.map(|s| s.name) {
+            self.syntax = syntax;
+            let content = self.content.clone();
+            self.reset(&content, self.version);
+        }
+    }
+
+    /// Invalidates every line, e.g. after the whole content was replaced.
+    pub fn reset(&mut self, content: &Rope, version: u64) {
+        self.content = content.clone();
+        self.version = version;
+        self.lines.resize(content.len_lines(), None);
+        self.dirty = Some((0, content.len_lines()));
+    }
+
+    /// Records edits made since the last call, in the order they were made.
+    pub fn edit(&mut self, content: &Rope, version: u64, edits: &[Edit]) {
+        for edit in edits {
+            let line = edit.line.min(self.lines.len());
+            let removed = (edit.removed_lines + 1).min(self.lines.len() - line);
+            let inserted = edit.inserted_lines + 1;
+            self.lines
+                .splice(line..line + removed, std::iter::repeat_n(None, inserted));
+
+            let delta = inserted as isize - removed as isize;
+            let edited_end = line + inserted;
+            self.dirty = Some(match self.dirty {
+                Some((start, end)) => {
+                    let end = if end > line {
+                        (end as isize + delta).max(line as isize) as usize
+                    } else {
+                        end
+                    };
+                    (start.min(line), end.max(edited_end))
+                }
+                None => (line, edited_end),
+            });
+        }
+        self.lines.resize(content.len_lines(), None);
+        if let Some((start, end)) = self.dirty {
+            self.dirty = Some((start.min(self.lines.len()), end.min(self.lines.len())));
+        }
+        self.content = content.clone();
+        self.version = version;
+    }
+
+    /// Spans for `line`, possibly stale while the worker catches up.
+    pub fn line_spans(&self, line: usize) -> &[HighlightSpan] {
+        match self.lines.get(line) {
+            Some(Some(highlight)) => &highlight.spans,
+            _ => &[],
+        }
+    }
+}
+
+static WORKER: Lazy<Mutex<Sender<SharedHighlights>>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || run_worker(receiver));
+    Mutex::new(sender)
+});
+
+/// Asks the worker to bring a cache up to date.
+pub fn request(cache: &SharedHighlights) {
+    if let Ok(sender) = WORKER.lock() {
+        let _ = sender.send(Arc::clone(cache));
+    }
+}
+
+fn run_worker(receiver: Receiver<SharedHighlights>) {
+    while let Ok(cache) = receiver.recv() {
+        // Coalesce requests queued while the last batch was running
+        let mut pending = vec![cache];
+        for cache in receiver.try_iter() {
+            if !pending.iter().any(|queued| Arc::ptr_eq(queued, &cache)) {
+                pending.push(cache);
+            }
+        }
+        for cache in pending {
+            while highlight_batch(&cache) {}
+        }
+    }
+}
+
+fn highlight_batch(cache: &SharedHighlights) -> bool {
+    let (syntax, content, version, start, mut state) = {
+        let Ok(cache) = cache.lock() else {
+            return false;
+        };
+        let (Some(syntax), Some((start, _))) = (cache.syntax, cache.dirty) else {
+            return false;
+        };
+        let state = start
+            .checked_sub(1)
+            .and_then(|previous| cache.lines.get(previous).cloned().flatten())
+            .map(|line| line.end_state)
+            .unwrap_or_default();
+        (syntax, cache.content.clone(), cache.version, start, state)
+    };
+
+    let end = (start + BATCH_LINES).min(content.len_lines());
+    let mut results = Vec::with_capacity(end - start);
+    for line_idx in start..end {
+        let line = content.line(line_idx).to_string();
+        let text = line.trim_end_matches(['\n', '\r']);
+        let (spans, end_state) = syntax.highlight_line(text, state);
+        results.push(LineHighlight { spans, end_state });
+        state = end_state;
+    }
+
+    let Ok(mut cache) = cache.lock() else {
+        return false;
+    };
+    if cache.version != version {
+        // Edited while highlighting; the edit queued a fresh request
+        return false;
+    }
+
+    let dirty_end = cache.dirty.map_or(0, |(_, end)| end);
+    for (offset, result) in results.into_iter().enumerate() {
+        let line_idx = start + offset;
+        let previous = cache.lines[line_idx].replace(result);
+        let converged = line_idx + 1 >= dirty_end
+            && previous.is_some_and(|previous| {
+                Some(previous.end_state) == cache.lines[line_idx].as_ref().map(|l| l.end_state)
+            });
+        if converged {
+            cache.dirty = None;
+            return false;
+        }
+    }
+
+    if end >= content.len_lines() {
+        cache.dirty = None;
+        false
+    } else {
+        cache.dirty = Some((end, dirty_end.max(end)));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_on_several_lines_invalidate_each_of_them() {
+        let mut cache = HighlightCache {
+            syntax: None,
+            content: Rope::from("ab\ncd\nef\ngh\n"),
+            version: 0,
+            lines: vec![None; 5],
+            dirty: None,
+        };
+        // Deleting a column from lines 0 to 2, bottom up, as a block does
+        let edit = |line| Edit {
+            start: line * 3,
+            removed: 1,
+            inserted: 0,
+            line,
+            removed_lines: 0,
+            inserted_lines: 0,
+        };
+        let content = Rope::from("b\nd\nf\ngh\n");
+        cache.edit(&content, 1, &[edit(2), edit(1), edit(0)]);
+        assert_eq!(cache.dirty, Some((0, 3)));
+        assert_eq!(cache.lines.len(), 5);
+    }
+}
@@ -0,0 +1,166 @@
+use ropey::Rope;
+use std::time::{Duration, SystemTime};
+
+pub struct Revision {
+    pub content: Rope,
+    pub cursor_pos: usize,
+    pub time: SystemTime,
+}
+
+/// Timestamped snapshots of a buffer for `:earlier`/`:later`.
+pub struct History {
+    revisions: Vec<Revision>,
+    current: usize,
+}
+
+pub enum TimeTravel {
+    Steps(usize),
+    Duration(Duration),
+}
+
+impl History {
+    pub fn new(content: &Rope, cursor_pos: usize) -> Self {
+        Self {
+            revisions: vec![Revision {
+                content: content.clone(),
+                cursor_pos,
+                time: SystemTime::now(),
+            }],
+            current: 0,
+        }
+    }
+
+    /// Records the content as a new revision if it differs from the current one.
+    pub fn commit(&mut self, content: &Rope, cursor_pos: usize) -> bool {
+        if self.revisions[self.current].content == *content {
+            return false;
+        }
+
+        self.revisions.push(Revision {
+            content: content.clone(),
+            cursor_pos,
+            time: SystemTime::now(),
+        });
+        self.current = self.revisions.len() - 1;
+        true
+    }
+
+    pub fn current(&self) -> &Revision {
+        &self.revisions[self.current]
+    }
+
+    pub fn earlier(&mut self, travel: &TimeTravel) -> &Revision {
+        self.current = match travel {
+            TimeTravel::Steps(steps) => self.current.saturating_sub(*steps),
+            TimeTravel::Duration(duration) => {
+                let target = self.revisions[self.current]
+                    .time
+                    .checked_sub(*duration)
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                // The newest revision that already existed at the target time
+                self.revisions[..=self.current]
+                    .iter()
+                    .rposition(|revision| revision.time <= target)
+                    .unwrap_or(0)
+            }
+        };
+        &self.revisions[self.current]
+    }
+
+    pub fn later(&mut self, travel: &TimeTravel) -> &Revision {
+        let last = self.revisions.len() - 1;
+        self.current = match travel {
+            TimeTravel::Steps(steps) => (self.current + steps).min(last),
+            TimeTravel::Duration(duration) => {
+                let time = self.revisions[self.current].time;
+                match time.checked_add(*duration) {
+                    Some(target) => self
+                        .revisions
+                        .iter()
+                        .rposition(|revision| revision.time <= target)
+                        .unwrap_or(last)
+                        .max(self.current),
+                    None => last,
+                }
+            }
+        };
+        &self.revisions[self.current]
+    }
+}
+
+/// The char offset of the first difference between two texts.
+pub fn first_difference(a: &Rope, b: &Rope) -> usize {
+    a.chars()
+        .zip(b.chars())
+        .position(|(a, b)| a != b)
+        .unwrap_or(a.len_chars().min(b.len_chars()))
+}
+
+impl TimeTravel {
+    /// Parses the argument of `:earlier`/`:later`: a step count (`3`) or a
+    /// duration with an `s`, `m`, `h` or `d` suffix (`30s`, `5m`).
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(TimeTravel::Steps(1));
+        }
+
+        let (digits, unit) = input.split_at(
+            input
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(input.len()),
+        );
+        let amount: u64 = digits
+            .parse()
+            .map_err(|_| format!("Invalid count: {}", input))?;
+
+        let unit_seconds = match unit {
+            "" => return Ok(TimeTravel::Steps(amount as usize)),
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 60 * 60 * 24,
+            _ => return Err(format!("Invalid time unit: {}", unit)),
+        };
+        let seconds = amount
+            .checked_mul(unit_seconds)
+            .ok_or_else(|| "Duration too large".to_string())?;
+        Ok(TimeTravel::Duration(Duration::from_secs(seconds)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_difference_finds_the_first_changed_char() {
+        let diff = |a: &str, b: &str| first_difference(&Rope::from(a), &Rope::from(b));
+        assert_eq!(diff("hello", "help"), 3);
+        assert_eq!(diff("abc", "abc"), 3);
+        assert_eq!(diff("ab", "abcd"), 2);
+        assert_eq!(diff("", "x"), 0);
+    }
+
+    #[test]
+    fn parses_steps_and_durations() {
+        assert!(matches!(TimeTravel::parse(""), Ok(TimeTravel::Steps(1))));
+        assert!(matches!(TimeTravel::parse("3"), Ok(TimeTravel::Steps(3))));
+        assert!(matches!(
+            TimeTravel::parse("5m"),
+            Ok(TimeTravel::Duration(duration)) if duration == Duration::from_secs(300)
+        ));
+        assert!(TimeTravel::parse("5y").is_err());
+        assert!(TimeTravel::parse("m").is_err());
+        assert!(TimeTravel::parse("18446744073709551615d").is_err());
+    }
+
+    #[test]
+    fn later_past_the_end_of_time_goes_to_the_newest_revision() {
+        let mut history = History::new(&Rope::from("a"), 0);
+        history.commit(&Rope::from("ab"), 1);
+        history.earlier(&TimeTravel::Steps(1));
+        let revision = history.later(&TimeTravel::Duration(Duration::MAX));
+        assert_eq!(revision.content.to_string(), "ab");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    editor::Editor,
+    utils::error_handler::{add_message, set_error},
+};
+use glob::Pattern;
+use std::{fmt::Display, path::Path, process::Command, str::FromStr};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum HookEvent {
+    BufReadPost,
+    BufWritePre,
+    BufWritePost,
+    ModeChanged,
+    CursorHold,
+    FocusGained,
+    FocusLost,
+}
+
+#[derive(Clone)]
+pub enum HookAction {
+    /// Runs synchronously while the event fires, so `BufWritePre` actions
+    /// take effect before the file is written.
+    Builtin(fn(&mut Editor)),
+    /// An ex command queued for the command bar to run after the event.
+    Command(String),
+    /// A shell command run synchronously; `{file}` expands to the buffer path.
+    Shell(String),
+}
+
+pub struct Hook {
+    pub event: HookEvent,
+    pub pattern: Pattern,
+    pub action: HookAction,
+}
+
+pub struct HookRegistry {
+    hooks: Vec<Hook>,
+}
+
+impl HookRegistry {
+    pub fn new() -> Self {
+        Self { hooks: Vec::new() }
+    }
+
+    pub fn add(&mut self, hook: Hook) {
+        self.hooks.push(hook);
+    }
+
+    pub fn clear(&mut self) {
+        self.hooks.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Returns the actions registered for `event` whose pattern matches the
+    /// buffer path (either the full path or just the file name).
+    pub fn matching(&self, event: HookEvent, path: Option<&Path>) -> Vec<HookAction> {
+        self.hooks
+            .iter()
+            .filter(|hook| hook.event == event)
+            .filter(|hook| match path {
+                Some(path) => {
+                    hook.pattern.matches_path(path)
+                        || path
+                            .file_name()
+                            .is_some_and(|name| hook.pattern.matches_path(Path::new(name)))
+                }
+                None => hook.pattern.as_str() == "*",
+            })
+            .map(|hook| hook.action.clone())
+            .collect()
+    }
+}
+
+impl Default for HookRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Looks up an action that can be attached by name, e.g.
+/// `:autocmd BufWritePre *.rs strip_whitespace`.
+pub fn builtin_action(name: &str) -> Option<fn(&mut Editor)> {
+    match name {
+        "strip_whitespace" => Some(|editor| editor.strip_trailing_whitespace()),
+        _ => None,
+    }
+}
+
+/// Runs a shell hook, recording its output in the message history and
+/// reporting a non-zero exit status in the message line.
+pub fn run_shell_hook(event: HookEvent, command: &str, path: Option<&Path>) {
+    let command = match path {
+        Some(path) => command.replace("{file}", &shell_quote(path)),
+        None => command.to_string(),
+    };
+
+    let output = shell_command(&command).output();
+
+    match output {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            for line in stdout.lines().chain(stderr.lines()) {
+                add_message(format!("[{}] {}", event, line));
+            }
+
+            if !output.status.success() {
+                let reason = stderr.lines().next().unwrap_or_default();
+                set_error(format!(
+                    "{} hook `{}` failed ({}): {} (see :messages)",
+                    event, command, output.status, reason
+                ));
+            }
+        }
+        Err(e) => set_error(format!("{} hook `{}` could not run: {}", event, command, e)),
+    }
+}
+
+/// A command running `command` through `sh -c`, or `cmd /C` on Windows.
+pub fn shell_command(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = Command::new(shell);
+    shell.arg(flag).arg(command);
+    shell
+}
+
+/// Quotes a path for interpolation into a `sh -c` command line.
+pub fn shell_quote(path: &Path) -> String {
+    let path = path.display().to_string();
+    if cfg!(windows) {
+        format!("\"{}\"", path)
+    } else {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    }
+}
+
+impl FromStr for HookEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bufreadpost" | "bufread" => Ok(HookEvent::BufReadPost),
+            "bufwritepre" | "bufwrite" => Ok(HookEvent::BufWritePre),
+            "bufwritepost" => Ok(HookEvent::BufWritePost),
+            "modechanged" => Ok(HookEvent::ModeChanged),
+            "cursorhold" => Ok(HookEvent::CursorHold),
+            "focusgained" => Ok(HookEvent::FocusGained),
+            "focuslost" => Ok(HookEvent::FocusLost),
+            _ => Err(format!("Unknown event: {}", s)),
+        }
+    }
+}
+
+impl Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookEvent::BufReadPost => f.write_str("BufReadPost"),
+            HookEvent::BufWritePre => f.write_str("BufWritePre"),
+            HookEvent::BufWritePost => f.write_str("BufWritePost"),
+            HookEvent::ModeChanged => f.write_str("ModeChanged"),
+            HookEvent::CursorHold => f.write_str("CursorHold"),
+            HookEvent::FocusGained => f.write_str("FocusGained"),
+            HookEvent::FocusLost => f.write_str("FocusLost"),
+        }
+    }
+}
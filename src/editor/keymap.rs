@@ -0,0 +1,515 @@
+use crate::{
+    editor::{
+        cursor_movement::CursorMovement,
+        dap::Step,
+        mode::Mode,
+        motion::{CharFind, Operator, TextObject},
+        window::SplitDirection,
+        Editor,
+    },
+    utils::error_handler::{clear_error, set_error},
+};
+use std::ops::BitOr;
+
+/// A key press as the editor sees it, whatever frontend it came from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: Modifiers) -> Self {
+        Self { code, modifiers }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyCode {
+    Char(char),
+    F(u8),
+    Enter,
+    Esc,
+    Backspace,
+    Tab,
+    BackTab,
+    Delete,
+    Insert,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    /// A key the editor has no use for
+    Other,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Self = Self {
+        shift: false,
+        control: false,
+        alt: false,
+    };
+    pub const SHIFT: Self = Self {
+        shift: true,
+        ..Self::NONE
+    };
+    pub const CONTROL: Self = Self {
+        control: true,
+        ..Self::NONE
+    };
+    pub const ALT: Self = Self {
+        alt: true,
+        ..Self::NONE
+    };
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self {
+            shift: self.shift || other.shift,
+            control: self.control || other.control,
+            alt: self.alt || other.alt,
+        }
+    }
+}
+
+/// What a key asks of the frontend, for the parts of the UI the editor
+/// does not own.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum KeyAction {
+    /// Open the command line with this input
+    CommandLine(String),
+    /// Prompt for a search pattern, forward for `/`
+    Search(bool),
+    /// Prompt for an expression to insert, for Ctrl-r =
+    Expression,
+    FileExplorer,
+}
+
+pub(crate) enum KeyPrefix {
+    // Ctrl-w
+    Window,
+    // g
+    G,
+    // m
+    Mark,
+    // f, t, F or T, waiting for the character to find
+    Find(char),
+    // d or c, waiting for a motion
+    Operator(Operator),
+    // d or c followed by f, t, F or T
+    OperatorFind(Operator, char),
+    // Ctrl-r in insert mode, waiting for the register to insert
+    Register,
+    // ] or [
+    Bracket(char),
+    // i or a after an operator or in visual mode, waiting for the object,
+    // with whether it was i
+    TextObject(Option<Operator>, bool),
+    // gq, waiting for q or a paragraph to reflow
+    Reflow,
+    // gq followed by i or a, waiting for p
+    ReflowObject,
+}
+
+/// Runs the command a key completes in the editor's mode.
+pub fn handle_key(editor: &mut Editor, key: Key) -> Option<KeyAction> {
+    if let Some(pending) = editor.pending_keys.take() {
+        return handle_prefixed_key(editor, pending, key);
+    }
+    // Each command outside insert mode is an undo step of its own
+    if editor.get_mode() != Mode::Insert {
+        editor.close_undo_step();
+    }
+    if let Some(started) = start_prefix(editor, key) {
+        editor.pending_keys = Some(started);
+        return None;
+    }
+    clear_error();
+    if !editor.is_terminal_window() && handle_line_keys(editor, key) {
+        return None;
+    }
+    match editor.get_mode() {
+        Mode::Normal => handle_normal_mode(editor, key),
+        Mode::Visual => handle_visual_mode(editor, key),
+        Mode::Insert => {
+            handle_insert_mode(editor, key);
+            None
+        }
+        Mode::Terminal => None,
+    }
+}
+
+fn start_prefix(editor: &Editor, key: Key) -> Option<KeyPrefix> {
+    let mode = editor.get_mode();
+    if key == Key::new(KeyCode::Char('w'), Modifiers::CONTROL) {
+        return (mode == Mode::Normal).then_some(KeyPrefix::Window);
+    }
+    if key == Key::new(KeyCode::Char('r'), Modifiers::CONTROL) {
+        return (mode == Mode::Insert).then_some(KeyPrefix::Register);
+    }
+    if !matches!(mode, Mode::Normal | Mode::Visual)
+        || editor.is_terminal_window()
+        || key.modifiers.control
+        || key.modifiers.alt
+    {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char('g') => Some(KeyPrefix::G),
+        KeyCode::Char('m') if mode == Mode::Normal => Some(KeyPrefix::Mark),
+        KeyCode::Char(c @ (']' | '[')) if mode == Mode::Normal => Some(KeyPrefix::Bracket(c)),
+        KeyCode::Char(c @ ('f' | 't' | 'F' | 'T')) => Some(KeyPrefix::Find(c)),
+        KeyCode::Char(c @ ('i' | 'a')) if mode == Mode::Visual => {
+            Some(KeyPrefix::TextObject(None, c == 'i'))
+        }
+        // Visual mode applies d and c to the selection instead
+        KeyCode::Char(c) if mode == Mode::Normal => Operator::from_key(c).map(KeyPrefix::Operator),
+        _ => None,
+    }
+}
+
+fn handle_prefixed_key(editor: &mut Editor, pending: KeyPrefix, key: Key) -> Option<KeyAction> {
+    match (pending, key.code) {
+        (KeyPrefix::Window, _) => handle_window_command(editor, key),
+        (KeyPrefix::Register, KeyCode::Char('=')) => return Some(KeyAction::Expression),
+        (KeyPrefix::Register, KeyCode::Char(register)) => {
+            set_error(format!("No register {}; only = is supported", register));
+        }
+        (KeyPrefix::G, KeyCode::Char('_')) => editor.move_cursor(CursorMovement::LastNonBlank),
+        (KeyPrefix::G, KeyCode::Char('q')) if editor.get_mode() == Mode::Visual => {
+            if let Some(lines) = editor.selected_lines() {
+                editor.exit_visual_mode();
+                editor.reflow_lines(lines);
+            }
+        }
+        (KeyPrefix::G, KeyCode::Char('q')) => editor.pending_keys = Some(KeyPrefix::Reflow),
+        // gqq reflows the cursor's line, gqip and gqap its paragraph
+        (KeyPrefix::Reflow, KeyCode::Char('q')) => {
+            if let Some(lines) = editor.selected_lines() {
+                editor.reflow_lines(lines);
+            }
+        }
+        (KeyPrefix::Reflow, KeyCode::Char('i' | 'a')) => {
+            editor.pending_keys = Some(KeyPrefix::ReflowObject);
+        }
+        (KeyPrefix::ReflowObject, KeyCode::Char('p')) => {
+            if let Some(lines) = editor.paragraph_around_cursor() {
+                editor.reflow_lines(lines);
+            }
+        }
+        (KeyPrefix::Mark, KeyCode::Char('b')) => editor.toggle_bookmark_at_cursor(),
+        // ]p pastes below the cursor's line, [p, [P and ]P above it
+        (KeyPrefix::Bracket(bracket), KeyCode::Char(c @ ('p' | 'P'))) => {
+            editor.paste_lines_from_clipboard(bracket == ']' && c == 'p');
+        }
+        (KeyPrefix::Bracket(bracket), KeyCode::Char('t')) => editor.jump_to_todo(bracket == ']'),
+        (KeyPrefix::Find(motion), KeyCode::Char(target)) => {
+            if let Some(find) = CharFind::new(motion, target) {
+                editor.find_char(find);
+            }
+        }
+        (KeyPrefix::Operator(operator), KeyCode::Char(c @ ('f' | 't' | 'F' | 'T'))) => {
+            editor.pending_keys = Some(KeyPrefix::OperatorFind(operator, c));
+        }
+        (KeyPrefix::Operator(operator), KeyCode::Char(c @ (';' | ','))) => {
+            editor.apply_operator(operator, true, |editor| editor.repeat_find(c == ','));
+        }
+        (KeyPrefix::Operator(operator), KeyCode::Char(c @ ('i' | 'a'))) => {
+            editor.pending_keys = Some(KeyPrefix::TextObject(Some(operator), c == 'i'));
+        }
+        (KeyPrefix::Operator(operator), KeyCode::Char('%')) => {
+            editor.apply_operator(operator, true, |editor| editor.jump_to_matching_bracket());
+        }
+        (KeyPrefix::Operator(operator), KeyCode::Char(c @ ('(' | ')'))) => {
+            editor.apply_operator(operator, false, |editor| {
+                editor.move_cursor(sentence_movement(c));
+                true
+            });
+        }
+        (KeyPrefix::TextObject(operator, inner), KeyCode::Char(c)) => {
+            if let Some(object) = TextObject::from_key(c) {
+                match operator {
+                    Some(operator) => editor.apply_operator_to_object(operator, object, inner),
+                    None => editor.select_text_object(object, inner),
+                }
+            }
+        }
+        (KeyPrefix::Operator(operator), KeyCode::Char('^' | '_')) => {
+            editor.apply_operator(operator, false, |editor| {
+                editor.move_cursor(CursorMovement::FirstNonBlank);
+                true
+            });
+        }
+        (KeyPrefix::OperatorFind(operator, motion), KeyCode::Char(target)) => {
+            if let Some(find) = CharFind::new(motion, target) {
+                editor.apply_operator(operator, true, |editor| editor.find_char(find));
+            }
+        }
+        _ => {}
+    }
+    None
+}
+
+fn sentence_movement(key: char) -> CursorMovement {
+    match key {
+        ')' => CursorMovement::NextSentence,
+        _ => CursorMovement::PreviousSentence,
+    }
+}
+
+fn handle_window_command(editor: &mut Editor, key: Key) {
+    clear_error();
+    match key.code {
+        KeyCode::Char('w') => editor.cycle_window(true),
+        KeyCode::Char('W') => editor.cycle_window(false),
+        KeyCode::Char('s') => {
+            editor.split_window(SplitDirection::Horizontal);
+        }
+        KeyCode::Char('v') => {
+            editor.split_window(SplitDirection::Vertical);
+        }
+        KeyCode::Char('c') if !editor.close_window() => {
+            set_error("Cannot close the last window.".to_string());
+        }
+        KeyCode::Char('o') => editor.close_other_windows(),
+        KeyCode::Char('=') => editor.equalize_windows(),
+        KeyCode::Char('+') => editor.resize_window(SplitDirection::Horizontal, 1),
+        KeyCode::Char('-') => editor.resize_window(SplitDirection::Horizontal, -1),
+        KeyCode::Char('>') => editor.resize_window(SplitDirection::Vertical, 1),
+        KeyCode::Char('<') => editor.resize_window(SplitDirection::Vertical, -1),
+        _ => {}
+    }
+}
+
+fn handle_line_keys(editor: &mut Editor, key: Key) -> bool {
+    let down = match key.code {
+        KeyCode::Up => false,
+        KeyCode::Down => true,
+        _ => return false,
+    };
+    let Some(lines) = editor.selected_lines() else {
+        return false;
+    };
+    if key.modifiers == Modifiers::ALT {
+        editor.move_lines(lines, down);
+    } else if key.modifiers == Modifiers::ALT | Modifiers::SHIFT {
+        editor.duplicate_lines(lines, down);
+    } else {
+        return false;
+    }
+    true
+}
+
+fn handle_motion(editor: &mut Editor, key: Key) -> bool {
+    match (key.modifiers, key.code) {
+        (Modifiers::NONE, KeyCode::Left) => editor.move_cursor(CursorMovement::Left),
+        (Modifiers::NONE, KeyCode::Right) => editor.move_cursor(CursorMovement::Right),
+        (Modifiers::NONE, KeyCode::Up) => editor.move_cursor(CursorMovement::Up),
+        (Modifiers::NONE, KeyCode::Down) => editor.move_cursor(CursorMovement::Down),
+        (Modifiers::NONE, KeyCode::Home) => editor.move_cursor(CursorMovement::SmartHome),
+        (Modifiers::NONE, KeyCode::End) => editor.move_cursor(CursorMovement::LineEnd),
+        (_, KeyCode::Char('^' | '_')) => editor.move_cursor(CursorMovement::FirstNonBlank),
+        (_, KeyCode::Char(c @ (';' | ','))) => {
+            editor.repeat_find(c == ',');
+        }
+        (_, KeyCode::Char('%')) => {
+            editor.jump_to_matching_bracket();
+        }
+        (_, KeyCode::Char(c @ ('(' | ')'))) => editor.move_cursor(sentence_movement(c)),
+        (_, KeyCode::PageUp | KeyCode::PageDown) => {
+            editor.scroll_page(key.code == KeyCode::PageDown)
+        }
+        (Modifiers::CONTROL, KeyCode::Char(c @ ('e' | 'y'))) => {
+            editor.scroll_lines(if c == 'e' { 1 } else { -1 })
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn handle_normal_mode(editor: &mut Editor, key: Key) -> Option<KeyAction> {
+    if handle_motion(editor, key) {
+        return None;
+    }
+    match (key.modifiers, key.code) {
+        (Modifiers::NONE, KeyCode::Char('v')) => editor.enter_visual_mode(),
+        (Modifiers::CONTROL, KeyCode::Char('v')) => editor.enter_visual_block_mode(),
+        (Modifiers::NONE, KeyCode::F(5)) => editor.debug_step(Step::Continue),
+        (Modifiers::SHIFT, KeyCode::F(5)) => editor.stop_debugging(),
+        (Modifiers::NONE, KeyCode::F(6)) => editor.pause_debugging(),
+        (Modifiers::NONE, KeyCode::F(9)) => editor.toggle_breakpoint_at_cursor(),
+        (Modifiers::NONE, KeyCode::F(10)) => editor.debug_step(Step::Over),
+        (Modifiers::NONE, KeyCode::F(11)) => editor.debug_step(Step::Into),
+        (Modifiers::SHIFT, KeyCode::F(11)) => editor.debug_step(Step::Out),
+        (Modifiers::NONE, KeyCode::Char(':')) => {
+            return Some(KeyAction::CommandLine(String::new()))
+        }
+        (Modifiers::NONE, KeyCode::Char('i')) => editor.set_mode(Mode::Insert),
+        (_, KeyCode::Char('A')) => {
+            editor.move_cursor(CursorMovement::LineEnd);
+            editor.set_mode(Mode::Insert);
+        }
+        (Modifiers::NONE, KeyCode::Char('-')) => return Some(KeyAction::FileExplorer),
+        (Modifiers::SHIFT, KeyCode::Char('D')) => editor.toggle_debug_info(),
+        (_, KeyCode::Char(c @ ('/' | '?'))) => return Some(KeyAction::Search(c == '/')),
+        (_, KeyCode::Char(c @ ('n' | 'N'))) => editor.search_next(c == 'N'),
+        (_, KeyCode::Char(c @ ('*' | '#'))) => editor.search_word_under_cursor(c == '*'),
+        (Modifiers::NONE, KeyCode::Char('p')) => editor.paste_from_clipboard(),
+        (Modifiers::NONE, KeyCode::Char('u')) => editor.undo(),
+        (Modifiers::CONTROL, KeyCode::Char('r')) => editor.redo(),
+        (modifiers, KeyCode::Char('t' | 'T'))
+            if modifiers == Modifiers::CONTROL | Modifiers::SHIFT =>
+        {
+            editor.reopen_closed_buffer(None);
+        }
+        _ => {}
+    }
+    None
+}
+
+fn handle_visual_mode(editor: &mut Editor, key: Key) -> Option<KeyAction> {
+    if handle_motion(editor, key) {
+        return None;
+    }
+    match (key.modifiers, key.code) {
+        (Modifiers::NONE, KeyCode::Esc) => editor.exit_visual_mode(),
+        (Modifiers::NONE, KeyCode::Char(':')) => {
+            editor.exit_visual_mode();
+            return Some(KeyAction::CommandLine("'<,'>".to_string()));
+        }
+        (Modifiers::NONE, KeyCode::Char('d')) => {
+            editor.delete_selection();
+            editor.exit_visual_mode();
+        }
+        (Modifiers::NONE, KeyCode::Char('=')) => {
+            if let Some(lines) = editor.selected_lines() {
+                editor.exit_visual_mode();
+                if let Err(e) = editor.format_lines(lines) {
+                    set_error(e);
+                }
+            }
+        }
+        (Modifiers::NONE, KeyCode::Char('y')) => {
+            if let Some(selected_text) = editor.copy_selection() {
+                match editor.copy_to_clipboard(&selected_text) {
+                    Ok(()) => set_error("Copied to clipboard successfully.".to_string()),
+                    Err(e) => set_error(format!("Failed to copy: {}", e)),
+                }
+            }
+            editor.exit_visual_mode();
+        }
+        _ => {}
+    }
+    None
+}
+
+fn handle_insert_mode(editor: &mut Editor, key: Key) {
+    match key.code {
+        KeyCode::Char(c) => editor.type_char(c),
+        KeyCode::Backspace => editor.delete(),
+        // Enter continues a markdown list
+        KeyCode::Enter if !editor.continue_list() => editor.insert_new_line(),
+        KeyCode::Left => editor.move_cursor(CursorMovement::Left),
+        KeyCode::Right => editor.move_cursor(CursorMovement::Right),
+        KeyCode::Up => editor.move_cursor(CursorMovement::Up),
+        KeyCode::Down => editor.move_cursor(CursorMovement::Down),
+        KeyCode::Home => editor.move_cursor(CursorMovement::SmartHome),
+        KeyCode::End => editor.move_cursor(CursorMovement::LineEnd),
+        KeyCode::PageUp => editor.scroll_page(false),
+        KeyCode::PageDown => editor.scroll_page(true),
+        // Tab moves between cells inside a markdown table
+        KeyCode::Tab if !editor.table_next_cell(true) => editor.insert_tab(),
+        KeyCode::BackTab => {
+            editor.table_next_cell(false);
+        }
+        KeyCode::Esc => editor.set_mode(Mode::Normal),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(editor: &mut Editor, keys: &str) -> Option<KeyAction> {
+        keys.chars()
+            .map(|c| handle_key(editor, Key::new(KeyCode::Char(c), Modifiers::NONE)))
+            .last()
+            .flatten()
+    }
+
+    #[test]
+    fn operators_wait_for_their_motion() {
+        let mut editor = Editor::new();
+        editor.new_scratch_buffer_with_content("abc def\n");
+        press(&mut editor, "df");
+        assert!(editor.has_pending_keys());
+        press(&mut editor, "c");
+        assert!(!editor.has_pending_keys());
+        let content = editor.get_current_buffer().unwrap().content.to_string();
+        assert_eq!(content, " def\n");
+    }
+
+    #[test]
+    fn undo_takes_back_an_insert_session_at_once_with_its_cursor() {
+        let mut editor = Editor::new();
+        editor.new_scratch_buffer_with_content("abc def\n");
+        let content = |editor: &Editor| editor.get_content().unwrap().to_string();
+        let cursor = |editor: &Editor| editor.current_view().unwrap().1.cursor_pos;
+
+        editor.current_view_mut().unwrap().1.cursor_pos = 4;
+        press(&mut editor, "iXY");
+        handle_key(&mut editor, Key::new(KeyCode::Esc, Modifiers::NONE));
+        let after_insert = cursor(&editor);
+        press(&mut editor, "dfe");
+        assert_eq!(content(&editor), "abc XYf\n");
+
+        press(&mut editor, "u");
+        assert_eq!(content(&editor), "abc XYdef\n");
+        assert_eq!(cursor(&editor), after_insert);
+        press(&mut editor, "u");
+        assert_eq!(content(&editor), "abc def\n");
+        assert_eq!(cursor(&editor), 4);
+
+        handle_key(
+            &mut editor,
+            Key::new(KeyCode::Char('r'), Modifiers::CONTROL),
+        );
+        assert_eq!(content(&editor), "abc XYdef\n");
+        assert_eq!(cursor(&editor), after_insert);
+    }
+
+    #[test]
+    fn keys_for_the_frontend_come_back_as_actions() {
+        let mut editor = Editor::new();
+        editor.new_scratch_buffer_with_content("abc\n");
+        assert_eq!(
+            press(&mut editor, ":"),
+            Some(KeyAction::CommandLine(String::new()))
+        );
+        assert_eq!(
+            press(&mut editor, "v:"),
+            Some(KeyAction::CommandLine("'<,'>".to_string()))
+        );
+        assert_eq!(press(&mut editor, "?"), Some(KeyAction::Search(false)));
+
+        press(&mut editor, "i");
+        let register = Key::new(KeyCode::Char('r'), Modifiers::CONTROL);
+        assert_eq!(handle_key(&mut editor, register), None);
+        assert_eq!(press(&mut editor, "="), Some(KeyAction::Expression));
+    }
+}
@@ -0,0 +1,61 @@
+use ropey::RopeSlice;
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthChar;
+
+/// Remembers where the visible part of each line ends, so redrawing a frame
+/// does not rescan long lines that have not changed.
+#[derive(Default)]
+pub struct LineSliceCache {
+    key: (u64, usize, usize),
+    ends: HashMap<usize, usize>,
+}
+
+impl LineSliceCache {
+    /// Returns the visible text of `line` starting at char `start` and at
+    /// most `width` columns wide, without the line break.
+    pub fn visible_text(
+        &mut self,
+        key: (u64, usize, usize),
+        line_idx: usize,
+        line: RopeSlice,
+        start: usize,
+        width: usize,
+    ) -> String {
+        if self.key != key {
+            self.key = key;
+            self.ends.clear();
+        }
+
+        let len = text_len(line);
+        let start = start.min(len);
+        let end = *self
+            .ends
+            .entry(line_idx)
+            .or_insert_with(|| visible_end(line.slice(start..len), width) + start);
+        line.slice(start..end).to_string()
+    }
+}
+
+pub(crate) fn text_len(line: RopeSlice) -> usize {
+    let len = line.len_chars();
+    if len > 0 && line.char(len - 1) == '\n' {
+        len - 1
+    } else {
+        len
+    }
+}
+
+fn visible_end(text: RopeSlice, width: usize) -> usize {
+    let mut columns = 0;
+    let mut count = 0;
+    for chunk in text.chunks() {
+        for c in chunk.chars() {
+            columns += c.width().unwrap_or(1);
+            if columns > width {
+                return count;
+            }
+            count += 1;
+        }
+    }
+    count
+}
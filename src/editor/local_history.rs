@@ -0,0 +1,85 @@
+use crate::{
+    editor::convert::format_iso8601,
+    utils::config::{digest, get_config_dir},
+};
+use std::{
+    cmp::Reverse,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A copy of a file as it was saved at one time.
+pub struct Snapshot {
+    pub path: PathBuf,
+    /// When it was saved, in milliseconds since the unix epoch
+    pub saved: u64,
+}
+
+impl Snapshot {
+    /// When the snapshot was saved, in UTC.
+    pub fn time(&self) -> String {
+        format_iso8601((self.saved / 1000) as i64, None)
+    }
+
+    pub fn read(&self) -> io::Result<String> {
+        fs::read_to_string(&self.path)
+    }
+}
+
+fn history_dir(file: &Path) -> Option<PathBuf> {
+    let name = file.file_name()?.to_string_lossy().into_owned();
+    let digest = digest(&file.to_string_lossy());
+    Some(
+        get_config_dir()?
+            .join("history")
+            .join(format!("{}-{}", name, &digest[..8])),
+    )
+}
+
+/// The snapshots of `file`, newest first.
+pub fn snapshots(file: &Path) -> Vec<Snapshot> {
+    let Some(Ok(entries)) = history_dir(file).map(fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let saved = entry.file_name().to_str()?.parse().ok()?;
+            Some(Snapshot {
+                path: entry.path(),
+                saved,
+            })
+        })
+        .collect();
+    snapshots.sort_by_key(|snapshot| Reverse(snapshot.saved));
+    snapshots
+}
+
+/// Keeps `content` as the newest snapshot of `file` unless it matches the
+/// last one, then drops the oldest beyond `keep`.
+pub fn record(file: &Path, content: &str, keep: usize) -> io::Result<()> {
+    let dir = history_dir(file).ok_or_else(|| io::Error::other("No config directory"))?;
+    let existing = snapshots(file);
+    if existing
+        .first()
+        .is_some_and(|latest| latest.read().is_ok_and(|text| text == content))
+    {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dir)?;
+    let saved = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    // Keep two saves within a millisecond apart
+    let saved = existing
+        .first()
+        .map_or(saved, |latest| saved.max(latest.saved + 1));
+    fs::write(dir.join(saved.to_string()), content)?;
+
+    for old in existing.iter().skip(keep.saturating_sub(1)) {
+        fs::remove_file(&old.path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,579 @@
+use crate::editor::{buffer::Buffer, hooks, signs::Sign};
+use ropey::Rope;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{self, ChildStdin, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const SIGN_SOURCE: &str = "lsp";
+
+// How long a request waits for the server before giving up
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A language server speaking LSP over its stdin and stdout.
+pub struct LanguageServer {
+    pub command: String,
+    child: process::Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    next_id: u64,
+    capabilities: Value,
+    // Files opened in the server, with the last version sent and the
+    // buffer version it was sent from
+    documents: HashMap<PathBuf, (i64, u64)>,
+    // Edits the server asked to apply with `workspace/applyEdit`
+    applied_edits: Vec<Value>,
+    // Diagnostics published since `take_diagnostics`, by file
+    diagnostics: HashMap<PathBuf, Vec<Diagnostic>>,
+}
+
+impl LanguageServer {
+    /// Starts `command` through the shell and initializes it for the
+    /// project at `root`.
+    pub fn start(command: &str, root: &Path) -> Result<Self, String> {
+        let mut child = hooks::shell_command(command)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", command, e))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(format!("Failed to talk to {}", command));
+        };
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Some(message) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut server = Self {
+            command: command.to_string(),
+            child,
+            stdin,
+            messages,
+            next_id: 1,
+            capabilities: Value::Null,
+            documents: HashMap::new(),
+            applied_edits: Vec::new(),
+            diagnostics: HashMap::new(),
+        };
+        let result = server.request(
+            "initialize",
+            json!({
+                "processId": process::id(),
+                "rootUri": path_to_uri(root),
+                "workspaceFolders": [{ "uri": path_to_uri(root), "name": root.display().to_string() }],
+                "capabilities": {
+                    "workspace": {
+                        "applyEdit": true,
+                        "workspaceEdit": { "documentChanges": true },
+                        "configuration": true,
+                    },
+                    "textDocument": {
+                        "synchronization": { "didSave": false },
+                        "rename": { "prepareSupport": false },
+                        "codeAction": {
+                            "codeActionLiteralSupport": {
+                                "codeActionKind": {
+                                    "valueSet": [
+                                        "", "quickfix", "refactor", "refactor.extract",
+                                        "refactor.inline", "refactor.rewrite", "source",
+                                        "source.organizeImports",
+                                    ],
+                                },
+                            },
+                            "resolveSupport": { "properties": ["edit"] },
+                        },
+                    },
+                },
+            }),
+        )?;
+        server.capabilities = result["capabilities"].clone();
+        server.notify("initialized", json!({}))?;
+        Ok(server)
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Whether the server announced `capability`, such as `renameProvider`.
+    pub fn supports(&self, capability: &str) -> bool {
+        !matches!(
+            self.capabilities.get(capability),
+            None | Some(Value::Null) | Some(Value::Bool(false))
+        )
+    }
+
+    /// Whether code actions without an edit can be asked for one.
+    pub fn resolves_code_actions(&self) -> bool {
+        self.capabilities["codeActionProvider"]["resolveProvider"] == Value::Bool(true)
+    }
+
+    /// Sends the content of `path` if the server has not seen this
+    /// `version` of it.
+    pub fn sync(&mut self, path: &Path, content: &Rope, version: u64) -> Result<(), String> {
+        let uri = path_to_uri(path);
+        match self.documents.get(path).copied() {
+            Some((_, synced)) if synced == version => Ok(()),
+            Some((sent, _)) => {
+                self.documents
+                    .insert(path.to_path_buf(), (sent + 1, version));
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": { "uri": uri, "version": sent + 1 },
+                        "contentChanges": [{ "text": content.to_string() }],
+                    }),
+                )
+            }
+            None => {
+                self.documents.insert(path.to_path_buf(), (1, version));
+                let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {
+                            "uri": uri,
+                            "languageId": language_id(extension),
+                            "version": 1,
+                            "text": content.to_string(),
+                        },
+                    }),
+                )
+            }
+        }
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<(), String> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Sends a request and waits for its result, answering what the server
+    /// asks in the meantime.
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let message = match self.messages.recv_timeout(timeout) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(format!(
+                        "{} did not answer {} in time; it may still be indexing",
+                        self.command, method
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(format!("{} exited", self.command))
+                }
+            };
+            match (message.get("id"), message.get("method")) {
+                (Some(answered), None) if *answered == json!(id) => {
+                    if let Some(error) = message.get("error") {
+                        let text = error["message"].as_str().unwrap_or("unknown error");
+                        return Err(format!("{}: {}", self.command, text));
+                    }
+                    return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+                }
+                _ => self.receive(message)?,
+            }
+        }
+    }
+
+    /// Handles what the server sent without being asked so far, without
+    /// waiting for more.
+    pub fn poll(&mut self) -> Result<(), String> {
+        loop {
+            match self.messages.try_recv() {
+                Ok(message) => self.receive(message)?,
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => return Err(format!("{} exited", self.command)),
+            }
+        }
+    }
+
+    fn receive(&mut self, message: Value) -> Result<(), String> {
+        let Some(method) = message["method"].as_str() else {
+            // The answer to a request that stopped waiting for it
+            return Ok(());
+        };
+        if let Some(id) = message.get("id") {
+            let result = self.answer(method, &message["params"]);
+            let id = id.clone();
+            return self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+        }
+        if method == "textDocument/publishDiagnostics" {
+            let params = &message["params"];
+            if let Some(path) = params["uri"].as_str().and_then(uri_to_path) {
+                self.diagnostics
+                    .insert(path, Diagnostic::parse_all(&params["diagnostics"]));
+            }
+        }
+        Ok(())
+    }
+
+    fn answer(&mut self, method: &str, params: &Value) -> Value {
+        match method {
+            "workspace/applyEdit" => {
+                self.applied_edits.push(params["edit"].clone());
+                json!({ "applied": true })
+            }
+            // No settings of our own; the server uses its defaults
+            "workspace/configuration" => {
+                let items = params["items"].as_array().map_or(0, Vec::len);
+                Value::Array(vec![Value::Null; items])
+            }
+            _ => Value::Null,
+        }
+    }
+
+    /// The diagnostics published since the last call, replacing all of a
+    /// file's earlier ones.
+    pub fn take_diagnostics(&mut self) -> HashMap<PathBuf, Vec<Diagnostic>> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    /// The edits the server asked to apply while answering requests.
+    pub fn take_applied_edits(&mut self) -> Vec<Value> {
+        std::mem::take(&mut self.applied_edits)
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), String> {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+            .and_then(|()| self.stdin.flush())
+            .map_err(|e| format!("Failed to write to {}: {}", self.command, e))
+    }
+}
+
+impl Drop for LanguageServer {
+    fn drop(&mut self) {
+        let _ = self.notify("exit", Value::Null);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Reads one message framed with a `Content-Length` header, or None once
+/// the server's output ends.
+pub fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    loop {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+        let Some(length) = length else {
+            continue;
+        };
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).ok()?;
+        if let Ok(message) = serde_json::from_slice(&body) {
+            return Some(message);
+        }
+    }
+}
+
+/// A code action or command offered by the server, as `:codeaction` lists
+/// them.
+pub struct CodeAction {
+    pub title: String,
+    pub action: Value,
+}
+
+impl CodeAction {
+    /// The actions of a `textDocument/codeAction` result, leaving out the
+    /// disabled ones.
+    pub fn parse_all(result: &Value) -> Vec<Self> {
+        result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|action| action.get("disabled").is_none())
+            .filter_map(|action| {
+                Some(Self {
+                    title: action["title"].as_str()?.to_string(),
+                    action: action.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn kind(&self) -> &str {
+        self.action["kind"].as_str().unwrap_or("")
+    }
+
+    /// The command to execute, for a bare command or a code action that
+    /// has one.
+    pub fn command(&self) -> Option<&Value> {
+        match &self.action["command"] {
+            // A bare command has the name itself in `command`
+            Value::String(_) => Some(&self.action),
+            Value::Object(_) => Some(&self.action["command"]),
+            _ => None,
+        }
+    }
+}
+
+/// A place in a document as LSP counts it: lines from 0 and columns in
+/// UTF-16 code units.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    pub fn from_char(content: &Rope, pos: usize) -> Self {
+        let line = content.char_to_line(pos);
+        let line_start = content.line_to_char(line);
+        Self {
+            line,
+            character: content.char_to_utf16_cu(pos) - content.char_to_utf16_cu(line_start),
+        }
+    }
+
+    /// The char at this position, clamped to the line and the content.
+    pub fn to_char(self, content: &Rope) -> usize {
+        if self.line >= content.len_lines() {
+            return content.len_chars();
+        }
+        let line = content.line(self.line);
+        let line_length = line.len_utf16_cu();
+        content.line_to_char(self.line) + line.utf16_cu_to_char(self.character.min(line_length))
+    }
+
+    pub fn to_json(self) -> Value {
+        json!({ "line": self.line, "character": self.character })
+    }
+
+    fn parse(value: &Value) -> Option<Self> {
+        Some(Self {
+            line: value["line"].as_u64()? as usize,
+            character: value["character"].as_u64()? as usize,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    /// The sign group, and so the color, for diagnostics of this severity.
+    pub fn group(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "info",
+            Severity::Hint => "hint",
+        }
+    }
+
+    /// The sign for a line with diagnostics of this severity, placed over
+    /// bookmarks for errors and warnings.
+    pub fn sign(self) -> Sign {
+        match self {
+            Severity::Error => Sign::new('E', self.group(), 40),
+            Severity::Warning => Sign::new('W', self.group(), 30),
+            Severity::Information => Sign::new('I', self.group(), 15),
+            Severity::Hint => Sign::new('H', self.group(), 10),
+        }
+    }
+}
+
+/// A problem the server found in a file.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub start: Position,
+    pub end: Position,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn parse_all(diagnostics: &Value) -> Vec<Self> {
+        let mut parsed: Vec<Self> = diagnostics
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|diagnostic| {
+                Some(Self {
+                    start: Position::parse(&diagnostic["range"]["start"])?,
+                    end: Position::parse(&diagnostic["range"]["end"])?,
+                    // Servers that leave it out mostly report errors
+                    severity: match diagnostic["severity"].as_u64() {
+                        Some(2) => Severity::Warning,
+                        Some(3) => Severity::Information,
+                        Some(4) => Severity::Hint,
+                        _ => Severity::Error,
+                    },
+                    message: diagnostic["message"].as_str()?.to_string(),
+                })
+            })
+            .collect();
+        parsed.sort_by_key(|diagnostic| {
+            (diagnostic.start.line, diagnostic.severity, diagnostic.start)
+        });
+        parsed
+    }
+}
+
+pub struct TextEdit {
+    pub start: Position,
+    pub end: Position,
+    pub new_text: String,
+}
+
+/// The text edits of a workspace edit for one file.
+pub struct FileEdit {
+    pub path: PathBuf,
+    pub edits: Vec<TextEdit>,
+}
+
+/// The edits of a `TextEdit[]` result, such as formatting returns.
+pub fn parse_text_edits(edits: &Value) -> Vec<TextEdit> {
+    edits
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|edit| {
+            Some(TextEdit {
+                start: Position::parse(&edit["range"]["start"])?,
+                end: Position::parse(&edit["range"]["end"])?,
+                new_text: edit["newText"].as_str()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Applies `edits`, sorted by where they start, to a buffer.
+pub fn apply_text_edits(buffer: &mut Buffer, edits: &[TextEdit]) {
+    // From the end, so the positions of earlier edits still hold
+    for edit in edits.iter().rev() {
+        let start = edit.start.to_char(&buffer.content);
+        let end = edit.end.to_char(&buffer.content).max(start);
+        buffer.replace(start..end, &edit.new_text);
+    }
+}
+
+/// The text edits of a workspace edit by file, and a description of each
+/// file operation (create, rename, delete) in it, which are not applied.
+pub fn parse_workspace_edit(edit: &Value) -> (Vec<FileEdit>, Vec<String>) {
+    let mut files: Vec<FileEdit> = Vec::new();
+    let mut skipped = Vec::new();
+    let mut add = |uri: &Value, edits: &Value| {
+        let Some(path) = uri.as_str().and_then(uri_to_path) else {
+            return;
+        };
+        let edits = parse_text_edits(edits);
+        match files.iter_mut().find(|file| file.path == path) {
+            Some(file) => file.edits.extend(edits),
+            None => files.push(FileEdit { path, edits }),
+        }
+    };
+
+    if let Some(changes) = edit["documentChanges"].as_array() {
+        for change in changes {
+            match change["kind"].as_str() {
+                Some(kind) => {
+                    let target = change["uri"].as_str().or(change["newUri"].as_str());
+                    skipped.push(format!("{} {}", kind, target.unwrap_or("?")));
+                }
+                None => add(&change["textDocument"]["uri"], &change["edits"]),
+            }
+        }
+    } else if let Some(changes) = edit["changes"].as_object() {
+        for (uri, edits) in changes {
+            add(&Value::String(uri.clone()), edits);
+        }
+    }
+    files.retain(|file| !file.edits.is_empty());
+    (files, skipped)
+}
+
+/// The LSP language identifier for files with `extension`.
+pub fn language_id(extension: &str) -> &str {
+    match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "jsx" => "javascriptreact",
+        "h" => "c",
+        "cc" | "cxx" | "hpp" => "cpp",
+        "md" => "markdown",
+        "sh" => "shellscript",
+        "yml" => "yaml",
+        extension => extension,
+    }
+}
+
+/// A `file://` URI for `path`, percent-encoding what a URI cannot hold.
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut chars = path.bytes();
+    while let Some(byte) = chars.next() {
+        if byte == b'%' {
+            let hex = [chars.next()?, chars.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // file:///C:/... on Windows
+    let path = match path.strip_prefix('/') {
+        Some(rest) if cfg!(windows) && rest.get(1..2) == Some(":") => rest.to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
@@ -0,0 +1,146 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Marker {
+    /// `-`, `*` or `+`
+    Bullet(char),
+    /// `1.` or `1)`
+    Number(u64, char),
+}
+
+/// A line starting a list item, such as `  - [ ] buy milk` or `3. call`.
+#[derive(Debug)]
+pub struct ListItem<'a> {
+    pub indent: &'a str,
+    pub marker: Marker,
+    // Byte offset just past the marker
+    marker_end: usize,
+    /// Byte offset of a `[ ]` or `[x]` checkbox, and whether it is checked
+    pub checkbox: Option<(usize, bool)>,
+    /// Byte offset where the item's text starts
+    pub text_start: usize,
+}
+
+impl<'a> ListItem<'a> {
+    pub fn parse(line: &'a str) -> Option<Self> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let rest = line.trim_start();
+        let indent = &line[..line.len() - rest.len()];
+        let (marker, marker_length) = match rest.chars().next()? {
+            c @ ('-' | '*' | '+') => (Marker::Bullet(c), 1),
+            _ => {
+                let digits = rest.chars().take_while(char::is_ascii_digit).count();
+                // Longer numbers are not list items in CommonMark
+                if digits == 0 || digits > 9 {
+                    return None;
+                }
+                let delimiter = rest[digits..]
+                    .chars()
+                    .next()
+                    .filter(|c| matches!(c, '.' | ')'))?;
+                (
+                    Marker::Number(rest[..digits].parse().ok()?, delimiter),
+                    digits + 1,
+                )
+            }
+        };
+        let marker_end = indent.len() + marker_length;
+        // A marker is followed by a space or ends the line, unlike `---`
+        // or `**bold**`
+        let after = &line[marker_end..];
+        let text = match after.strip_prefix([' ', '\t']) {
+            Some(text) => text,
+            None if after.is_empty() => after,
+            None => return None,
+        };
+        let checked = match text.get(..3) {
+            Some("[ ]") => Some(false),
+            Some("[x]" | "[X]") => Some(true),
+            _ => None,
+        };
+        let checkbox = checked.map(|checked| (line.len() - text.len(), checked));
+        let text = match checkbox {
+            Some(_) => text[3..].strip_prefix(' ').unwrap_or(&text[3..]),
+            None => text,
+        };
+        Some(Self {
+            indent,
+            marker,
+            marker_end,
+            checkbox,
+            text_start: line.len() - text.len(),
+        })
+    }
+
+    /// How the next item of the same list starts: the same bullet or the
+    /// next number, with an unchecked box if this item has a box.
+    pub fn continuation(&self) -> String {
+        let marker = match self.marker {
+            Marker::Bullet(c) => c.to_string(),
+            Marker::Number(number, delimiter) => format!("{}{}", number + 1, delimiter),
+        };
+        let checkbox = if self.checkbox.is_some() { "[ ] " } else { "" };
+        format!("{}{} {}", self.indent, marker, checkbox)
+    }
+}
+
+/// `line` with its checkbox checked or unchecked, or given an unchecked
+/// one if the item has none.
+pub fn toggle_checkbox(line: &str) -> Option<String> {
+    let item = ListItem::parse(line)?;
+    Some(match item.checkbox {
+        Some((start, checked)) => {
+            let checkbox = if checked { "[ ]" } else { "[x]" };
+            format!("{}{}{}", &line[..start], checkbox, &line[start + 3..])
+        }
+        None => {
+            let start = item.text_start.max(item.marker_end);
+            let space = if start == item.marker_end { " " } else { "" };
+            format!("{}{}[ ] {}", &line[..start], space, &line[start..])
+        }
+    })
+}
+
+/// Numbers the ordered items of `lines` one after another at each level of
+/// nesting, starting from each list's first number.
+pub fn renumber(lines: &[&str]) -> Vec<String> {
+    // The indent width of each open ordered list and its next number
+    let mut lists: Vec<(usize, u64)> = Vec::new();
+    lines
+        .iter()
+        .map(|line| {
+            let Some(item) = ListItem::parse(line) else {
+                // Indented lines continue an item, others end the lists
+                if !line.starts_with([' ', '\t']) {
+                    lists.clear();
+                }
+                return line.to_string();
+            };
+            let width = item.indent.len();
+            match item.marker {
+                Marker::Bullet(_) => {
+                    lists.retain(|(level, _)| *level < width);
+                    line.to_string()
+                }
+                Marker::Number(first, delimiter) => {
+                    lists.retain(|(level, _)| *level <= width);
+                    let number = match lists.last_mut() {
+                        Some((level, next)) if *level == width => {
+                            *next += 1;
+                            *next - 1
+                        }
+                        _ => {
+                            lists.push((width, first + 1));
+                            first
+                        }
+                    };
+                    format!(
+                        "{}{}{}{}",
+                        item.indent,
+                        number,
+                        delimiter,
+                        &line[item.marker_end..]
+                    )
+                }
+            }
+        })
+        .collect()
+}
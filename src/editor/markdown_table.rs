@@ -0,0 +1,229 @@
+use unicode_width::UnicodeWidthStr;
+
+const MIN_WIDTH: usize = 3;
+
+pub fn is_table_line(line: &str) -> bool {
+    line.trim_start().starts_with('|')
+}
+
+enum Row {
+    Cells(Vec<String>),
+    // The `|---|:---:|` row under the header, keeping each column's
+    // alignment colons
+    Separator(Vec<(bool, bool)>),
+}
+
+/// A markdown table parsed from its lines, which can be edited and written
+/// back with every pipe lined up.
+pub struct Table {
+    indent: String,
+    rows: Vec<Row>,
+}
+
+impl Table {
+    pub fn parse(lines: &[&str]) -> Self {
+        let indent = lines
+            .first()
+            .map(|line| line[..line.len() - line.trim_start().len()].to_string())
+            .unwrap_or_default();
+
+        let rows = lines
+            .iter()
+            .map(|line| {
+                let line = line.trim();
+                let line = line.strip_prefix('|').unwrap_or(line);
+                let line = line.strip_suffix('|').unwrap_or(line);
+                let cells: Vec<String> = line
+                    .split('|')
+                    .map(|cell| cell.trim().to_string())
+                    .collect();
+
+                let is_separator = cells.iter().all(|cell| {
+                    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+                    !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+                });
+                if is_separator {
+                    Row::Separator(
+                        cells
+                            .iter()
+                            .map(|cell| (cell.starts_with(':'), cell.ends_with(':')))
+                            .collect(),
+                    )
+                } else {
+                    Row::Cells(cells)
+                }
+            })
+            .collect();
+
+        Self { indent, rows }
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|row| match row {
+                Row::Cells(cells) => cells.len(),
+                Row::Separator(columns) => columns.len(),
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn is_separator(&self, row: usize) -> bool {
+        matches!(self.rows.get(row), Some(Row::Separator(_)))
+    }
+
+    /// Inserts an empty row below `row`, or below the separator when `row`
+    /// is the header.
+    pub fn insert_row(&mut self, row: usize) -> usize {
+        let mut index = (row + 1).min(self.rows.len());
+        if self.is_separator(index) {
+            index += 1;
+        }
+        let columns = self.column_count();
+        self.rows
+            .insert(index, Row::Cells(vec![String::new(); columns]));
+        index
+    }
+
+    /// Inserts an empty column to the right of `column`.
+    pub fn insert_column(&mut self, column: usize) {
+        self.pad_columns();
+        let index = (column + 1).min(self.column_count());
+        for row in &mut self.rows {
+            match row {
+                Row::Cells(cells) => cells.insert(index, String::new()),
+                Row::Separator(columns) => columns.insert(index, (false, false)),
+            }
+        }
+    }
+
+    fn pad_columns(&mut self) {
+        let columns = self.column_count();
+        for row in &mut self.rows {
+            match row {
+                Row::Cells(cells) => cells.resize(columns, String::new()),
+                Row::Separator(alignments) => alignments.resize(columns, (false, false)),
+            }
+        }
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        let mut widths = vec![MIN_WIDTH; self.column_count()];
+        for row in &self.rows {
+            if let Row::Cells(cells) = row {
+                for (width, cell) in widths.iter_mut().zip(cells) {
+                    *width = (*width).max(cell.width());
+                }
+            }
+        }
+        widths
+    }
+
+    /// The table's lines with padded cells and aligned pipes.
+    pub fn render(&mut self) -> Vec<String> {
+        self.pad_columns();
+        let widths = self.widths();
+        self.rows
+            .iter()
+            .map(|row| {
+                let cells: Vec<String> = match row {
+                    Row::Cells(cells) => cells
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, width)| {
+                            format!("{}{}", cell, " ".repeat(width - cell.width()))
+                        })
+                        .collect(),
+                    Row::Separator(alignments) => alignments
+                        .iter()
+                        .zip(&widths)
+                        .map(|(&(left, right), &width)| {
+                            let dashes = width - left as usize - right as usize;
+                            format!(
+                                "{}{}{}",
+                                if left { ":" } else { "" },
+                                "-".repeat(dashes),
+                                if right { ":" } else { "" }
+                            )
+                        })
+                        .collect(),
+                };
+                format!("{}| {} |", self.indent, cells.join(" | "))
+            })
+            .collect()
+    }
+
+    /// Char offset of the start of cell `column` in a line written by
+    /// `render`.
+    pub fn cell_offset(&self, line: &str, column: usize) -> usize {
+        let mut pipes = 0;
+        for (offset, c) in line.chars().enumerate() {
+            if c == '|' {
+                if pipes == column {
+                    return offset + 2;
+                }
+                pipes += 1;
+            }
+        }
+        line.chars().count()
+    }
+}
+
+/// Index of the cell containing char `column` of a table line.
+pub fn cell_at(line: &str, column: usize) -> usize {
+    line.chars()
+        .take(column)
+        .filter(|&c| c == '|')
+        .count()
+        .saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_with_aligned_pipes_and_kept_alignment() {
+        let mut table = Table::parse(&["|a|long header|", "|:-|-:|", "|x|y|"]);
+        assert_eq!(
+            table.render(),
+            [
+                "| a   | long header |",
+                "| :-- | ----------: |",
+                "| x   | y           |",
+            ]
+        );
+    }
+
+    #[test]
+    fn pads_short_rows_and_keeps_the_indent() {
+        let mut table = Table::parse(&["  |a|", "  |b|c|"]);
+        assert_eq!(table.render(), ["  | a   |     |", "  | b   | c   |"]);
+    }
+
+    #[test]
+    fn inserts_rows_below_the_separator_and_columns_to_the_right() {
+        let mut table = Table::parse(&["|a|b|", "|-|-|", "|c|d|"]);
+        assert_eq!(table.insert_row(0), 2);
+        assert_eq!(table.row_count(), 4);
+        table.insert_column(0);
+        assert_eq!(table.column_count(), 3);
+        assert_eq!(table.render()[0], "| a   |     | b   |");
+    }
+
+    #[test]
+    fn finds_cells_by_column() {
+        let line = "| a   | b   |";
+        assert_eq!(cell_at(line, 2), 0);
+        assert_eq!(cell_at(line, 8), 1);
+        let table = Table::parse(&[line]);
+        assert_eq!(table.cell_offset(line, 1), 8);
+        assert!(is_table_line("  | a |"));
+        assert!(!is_table_line("a | b"));
+    }
+}
@@ -0,0 +1,35 @@
+use crate::editor::{lsp::CodeAction, BufferId};
+use std::path::PathBuf;
+
+/// A list of choices for the frontend to show at the cursor.
+pub struct Menu {
+    pub title: String,
+    pub items: Vec<MenuItem>,
+    pub action: MenuAction,
+}
+
+/// What choosing an item does.
+pub enum MenuAction {
+    /// Replace chars `start..end` of the current buffer with the item's value
+    Replace(usize, usize),
+    /// Open the file and go to the line at the item's index
+    Jump(Vec<(PathBuf, usize)>),
+    /// Compare the pair of files at the item's index side by side, or open
+    /// the one that exists
+    Diff(Vec<(Option<PathBuf>, Option<PathBuf>)>),
+    /// Reopen the closed file at the item's index where its cursor was
+    Reopen(Vec<PathBuf>),
+    /// Run the language server's code action at the item's index
+    CodeAction(Vec<CodeAction>),
+    /// Show the buffer at the item's index in the current window
+    Buffer(Vec<BufferId>),
+    /// Check out the git branch named by the item's value
+    Checkout,
+    /// Apply the git stash named by the item's value
+    ApplyStash,
+}
+
+pub struct MenuItem {
+    pub label: String,
+    pub value: String,
+}
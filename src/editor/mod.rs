@@ -1,22 +1,221 @@
 use crate::{
-    editor::buffer::Buffer, editor::cursor_movement::CursorMovement, editor::mode::Mode,
-    utils::error_handler::set_error,
+    editor::bookmarks::Bookmarks,
+    editor::buffer::Buffer,
+    editor::cursor_movement::CursorMovement,
+    editor::dap::{DebugAdapter, DebugEvent, DebugState, Step},
+    editor::diagnostics_panel::{DiagnosticSort, DiagnosticsPanel},
+    editor::ex_range::ExRange,
+    editor::export::ExportFormat,
+    editor::fetch::Fetch,
+    editor::file_history::FileHistory,
+    editor::file_index::FileIndex,
+    editor::history::TimeTravel,
+    editor::hooks::{Hook, HookAction, HookEvent, HookRegistry},
+    editor::lsp::{CodeAction, Diagnostic, LanguageServer, Position, Severity},
+    editor::markdown_list::ListItem,
+    editor::markdown_table::{cell_at, is_table_line, Table},
+    editor::menu::{Menu, MenuAction, MenuItem},
+    editor::mode::Mode,
+    editor::motion::{CharFind, Operator, TextObject},
+    editor::move_lines::{expected_indent, indent_width, shift_indent},
+    editor::options::Options,
+    editor::pairs::Pair,
+    editor::paste::Invisibles,
+    editor::prose::Row,
+    editor::search::{is_word_char, Search, Substitution},
+    editor::signs::Sign,
+    editor::syntax::{HighlightSpan, Syntax},
+    editor::tasks::{RunningTask, TaskStatus},
+    editor::window::{Area, LayoutNode, Split, SplitDirection, WindowId},
+    editor::{
+        closed_buffers::{ClosedBuffer, ClosedBuffers},
+        dir_diff::FileChange,
+        notes::{Notes, PassphraseRequest},
+        tags::Tag,
+    },
+    utils::{
+        config::{Config, ProjectFile, SaveHook},
+        error_handler::set_error,
+    },
 };
 use clipboard::{ClipboardContext, ClipboardProvider};
+use glob::Pattern;
 use ropey::Rope;
-use std::{collections::HashMap, env, error::Error, fs, io, path::PathBuf};
+use serde_json::json;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    env,
+    error::Error,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::SystemTime,
+};
 
+pub mod align;
+pub mod bookmarks;
 mod buffer;
+pub mod calc;
+pub mod closed_buffers;
+pub mod color_swatch;
+pub mod convert;
 pub mod cursor_movement;
+pub mod dap;
+pub mod diagnostics_panel;
+pub mod dir_diff;
+pub mod ex_range;
+pub mod export;
+pub mod fetch;
+pub mod file_history;
+pub mod file_index;
+pub mod format;
+pub mod git;
+pub mod highlight;
+pub mod history;
+pub mod hooks;
+pub mod keymap;
+pub mod line_slice;
+pub mod local_history;
+pub mod lsp;
+pub mod markdown_list;
+pub mod markdown_table;
+pub mod menu;
 pub mod mode;
+pub mod motion;
+pub mod move_lines;
+pub mod notes;
+pub mod options;
+pub mod pairs;
+pub mod paste;
+pub mod prose;
+pub mod reformat;
+pub mod search;
+pub mod signs;
+pub mod syntax;
+pub mod syntax_tree;
+pub mod tags;
+pub mod tasks;
+#[cfg(feature = "terminal")]
+pub mod terminal;
+pub mod todo;
+pub mod undo;
+pub mod window;
+
+pub use buffer::{BufferId, BufferName};
+
+/// A write that failed with permission denied, awaiting the user's choice
+/// between saving elsewhere and retrying with elevated privileges.
+pub struct WritePrompt {
+    pub path: PathBuf,
+    pub rename: bool,
+}
+
+impl WritePrompt {
+    pub fn message(&self) -> String {
+        format!(
+            "Permission denied: {}. [s]ave as, [r]etry with privileges, [c]ancel",
+            self.path.display()
+        )
+    }
+}
+
+/// Counts shown in the debug overlay.
+pub struct BufferStats {
+    pub buffers: usize,
+    pub loaded: usize,
+    /// Lines across all loaded buffers
+    pub lines: usize,
+    /// Content bytes across all loaded buffers
+    pub bytes: usize,
+    /// Edit version of the current buffer
+    pub version: u64,
+}
 
 pub struct Editor {
     mode: Mode,
-    viewport: (usize, usize),
     show_debug_info: bool,
-    buffers: HashMap<PathBuf, Buffer>,
-    current_buffer: Option<PathBuf>,
+    // Zen mode hides the gutter and status line and centers the text
+    zen: bool,
+    buffers: HashMap<BufferId, Buffer>,
+    windows: HashMap<WindowId, Split>,
+    // Tasks started with `:task`, each with the buffer showing its output
+    tasks: Vec<(RunningTask, BufferId, TaskStatus)>,
+    #[cfg(feature = "terminal")]
+    terminals: HashMap<WindowId, terminal::TerminalPane>,
+    layout: LayoutNode,
+    // The area the frontend last laid the windows out in
+    layout_area: Area,
+    current_window: WindowId,
+    next_window_id: u64,
+    next_buffer_id: u64,
+    next_scratch_id: u64,
+    buffer_clock: u64,
     starting_directory: Option<PathBuf>,
+    working_directory: PathBuf,
+    options: Options,
+    config: Config,
+    write_prompt: Option<WritePrompt>,
+    hooks: HookRegistry,
+    pending_commands: Vec<String>,
+    // Keys queued by `:normal` for the frontend to run, with the lines to
+    // run them on
+    pending_normal: Vec<(Option<(usize, usize)>, String)>,
+    // The start of a multi-key command, waiting for the rest of it
+    pending_keys: Option<keymap::KeyPrefix>,
+    // A menu waiting for the frontend to show it
+    menu: Option<Menu>,
+    // A paste too large to insert before the frontend confirms it
+    paste_preview: Option<String>,
+    // Set by `:qa` with unsaved changes until the frontend shows the triage
+    quit_triage: bool,
+    // Set by `:reveal` until the frontend opens the explorer
+    reveal: bool,
+    // The query `:emoji` opens the character picker with, until the
+    // frontend opens it
+    char_picker: Option<String>,
+    // Set by an error or a motion that could not move, until the frontend
+    // rings the bell
+    bell: bool,
+    // Files under the working directory, for the file picker
+    file_index: Option<FileIndex>,
+    // A `.pyne.toml` found but not yet trusted, until the user answers
+    project_prompt: Option<ProjectFile>,
+    // Root of the project whose `.pyne.toml` was applied
+    project_root: Option<PathBuf>,
+    // Directories the project config keeps out of the file index
+    project_exclude: Vec<String>,
+    // Notes buffers opened with `:note`, saved whenever they close
+    notes: Vec<Notes>,
+    // Set until the frontend asks the user for a passphrase
+    passphrase_request: Option<PassphraseRequest>,
+    // URLs being fetched for `:e`
+    fetches: Vec<Fetch>,
+    // Language servers started for `:rename` and `:codeaction`, by command
+    language_servers: HashMap<String, LanguageServer>,
+    // Servers that failed to start or exited, which are not started again
+    // for diagnostics, only when a command needs them
+    failed_language_servers: HashSet<String>,
+    // The diagnostics servers published for each file, open or not
+    diagnostics: HashMap<PathBuf, Vec<Diagnostic>>,
+    // The `:diagnostics` panel, its buffer and the window it jumps in
+    diagnostics_panel: Option<(DiagnosticsPanel, BufferId, WindowId)>,
+    // The `:git log %` panel, its buffer and the window it opens
+    // revisions in
+    file_history: Option<(FileHistory, BufferId, WindowId)>,
+    // The adapter of the running `:debug` session
+    debugger: Option<DebugAdapter>,
+    debug_state: DebugState,
+    // The buffer showing `debug_state`
+    debug_panel: Option<BufferId>,
+    bookmarks: Bookmarks,
+    // Files closed with `:bd`, for reopening
+    closed_buffers: ClosedBuffers,
+    // The last `f`/`t`/`F`/`T`, repeated by `;` and `,`
+    last_find: Option<CharFind>,
+    last_search: Option<Search>,
+    firing_hooks: bool,
     clipboard: Option<ClipboardContext>,
 }
 
@@ -24,11 +223,55 @@ impl Editor {
     pub fn new() -> Self {
         Self {
             mode: Mode::Normal,
-            viewport: (80, 24),
             show_debug_info: false,
+            zen: false,
             buffers: HashMap::new(),
-            current_buffer: None,
+            windows: HashMap::from([(WindowId(1), Split::new())]),
+            tasks: Vec::new(),
+            #[cfg(feature = "terminal")]
+            terminals: HashMap::new(),
+            layout: LayoutNode::Window(WindowId(1)),
+            layout_area: Area::new(0, 0, 80, 24),
+            current_window: WindowId(1),
+            next_window_id: 2,
+            next_buffer_id: 1,
+            next_scratch_id: 1,
+            buffer_clock: 0,
             starting_directory: None,
+            working_directory: env::current_dir().unwrap_or_default(),
+            options: Options::new(),
+            config: Config::default(),
+            write_prompt: None,
+            hooks: HookRegistry::new(),
+            pending_commands: Vec::new(),
+            pending_normal: Vec::new(),
+            pending_keys: None,
+            menu: None,
+            paste_preview: None,
+            quit_triage: false,
+            reveal: false,
+            char_picker: None,
+            bell: false,
+            file_index: None,
+            project_prompt: None,
+            project_root: None,
+            project_exclude: Vec::new(),
+            notes: Vec::new(),
+            passphrase_request: None,
+            fetches: Vec::new(),
+            language_servers: HashMap::new(),
+            failed_language_servers: HashSet::new(),
+            diagnostics: HashMap::new(),
+            diagnostics_panel: None,
+            file_history: None,
+            debugger: None,
+            debug_state: DebugState::default(),
+            debug_panel: None,
+            bookmarks: Bookmarks::new(),
+            closed_buffers: ClosedBuffers::default(),
+            last_find: None,
+            last_search: None,
+            firing_hooks: false,
             clipboard: ClipboardContext::new().ok(),
         }
     }
@@ -42,408 +285,5590 @@ impl Editor {
         }
     }
 
+    fn read_clipboard(&mut self) -> Option<String> {
+        let contents = match &mut self.clipboard {
+            Some(clipboard) => clipboard.get_contents(),
+            None => Err("Clipboard not available".into()),
+        };
+        contents
+            .map_err(|e| set_error(format!("Failed to read clipboard: {}", e)))
+            .ok()
+    }
+
+    /// Pastes the system clipboard at the cursor.
+    pub fn paste_from_clipboard(&mut self) {
+        let Some(text) = self.read_clipboard() else {
+            return;
+        };
+        if self.options.pasteindent && text.ends_with('\n') {
+            self.paste_lines(&text, true);
+        } else {
+            self.paste(&text);
+        }
+    }
+
+    /// Pastes the system clipboard as lines below or above the cursor's
+    /// line, for `]p` and `[p`.
+    pub fn paste_lines_from_clipboard(&mut self, below: bool) {
+        if let Some(text) = self.read_clipboard() {
+            self.paste_lines(&text, below);
+        }
+    }
+
+    /// Inserts `text` as whole lines below the cursor's line, or above it,
+    /// shifted so the first non-blank line is indented like the cursor's
+    /// line and the rest keep their indentation relative to it.
+    pub fn paste_lines(&mut self, text: &str, below: bool) {
+        if text.is_empty() || self.refuse_read_only() {
+            return;
+        }
+        let options = self.buffer_options();
+        let Some((buffer, window)) = self.current_view() else {
+            return;
+        };
+        let text = paste::normalize_line_endings(text);
+        let lines: Vec<String> = text
+            .strip_suffix('\n')
+            .unwrap_or(&text)
+            .split('\n')
+            .map(String::from)
+            .collect();
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let target = indent_width(&buffer.content.line(line).to_string(), options.shiftwidth);
+        let shift = lines
+            .iter()
+            .find(|line| !line.trim().is_empty())
+            .map_or(0, |first| {
+                target as isize - indent_width(first, options.shiftwidth) as isize
+            });
+        let mut text =
+            shift_indent(&lines, shift, options.shiftwidth, options.expandtab).join("\n");
+
+        self.commit_revision();
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let at = if below {
+            let next = buffer.content.line_to_char(line + 1);
+            // The last line may have no line break to paste after
+            if next == 0 || buffer.content.char(next - 1) != '\n' {
+                text.insert(0, '\n');
+                window.cursor_pos = next + 1;
+            } else {
+                text.push('\n');
+                window.cursor_pos = next;
+            }
+            next
+        } else {
+            text.push('\n');
+            window.cursor_pos = buffer.content.line_to_char(line);
+            window.cursor_pos
+        };
+        buffer.insert(at, &text);
+        window.cursor_pos = Self::first_non_blank(buffer, window);
+        buffer.mark_lines_modified();
+        self.commit_revision();
+        self.scroll();
+    }
+
+    /// Inserts pasted text at the cursor, unless it is longer than
+    /// `pastepreview` characters; then it waits in `take_paste_preview`
+    /// for the frontend to confirm it.
+    pub fn paste(&mut self, text: &str) {
+        let text = paste::normalize_line_endings(text);
+        let limit = self.options.pastepreview;
+        if limit > 0 && text.chars().count() > limit {
+            self.paste_preview = Some(text);
+        } else {
+            self.insert_paste(&text);
+        }
+    }
+
+    pub fn take_paste_preview(&mut self) -> Option<String> {
+        self.paste_preview.take()
+    }
+
+    /// Asks the frontend to go through the modified buffers before quitting.
+    pub fn request_quit_triage(&mut self) {
+        self.quit_triage = true;
+    }
+
+    pub fn take_quit_triage(&mut self) -> bool {
+        std::mem::take(&mut self.quit_triage)
+    }
+
+    /// Asks the frontend to open the file explorer on the current file.
+    pub fn request_reveal(&mut self) {
+        self.reveal = true;
+    }
+
+    pub fn take_reveal(&mut self) -> bool {
+        std::mem::take(&mut self.reveal)
+    }
+
+    /// Asks the frontend to open the character picker, searching `query`.
+    pub fn request_char_picker(&mut self, query: &str) {
+        self.char_picker = Some(query.to_string());
+    }
+
+    pub fn take_char_picker(&mut self) -> Option<String> {
+        self.char_picker.take()
+    }
+
+    /// How a paste preview shows invisible characters, from `pastechars`.
+    pub fn paste_invisibles(&self) -> Invisibles {
+        Invisibles::parse(&self.options.pastechars)
+    }
+
+    /// Inserts `text` at the cursor as a change of its own in the undo
+    /// history.
+    pub fn insert_paste(&mut self, text: &str) {
+        if text.is_empty() || self.refuse_read_only() {
+            return;
+        }
+        self.commit_revision();
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer.insert(window.cursor_pos, text);
+            window.cursor_pos += text.chars().count();
+            buffer.mark_lines_modified();
+        }
+        self.commit_revision();
+        self.scroll();
+    }
+
     pub fn enter_visual_mode(&mut self) {
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            buffer.selection_start = Some(buffer.cursor_pos);
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer.selection_start = Some(window.cursor_pos);
+            buffer.block_selection = false;
             self.set_mode(Mode::Visual);
         }
     }
 
-    pub fn exit_visual_mode(&mut self) {
+    /// Starts a visual selection of the block of columns between the
+    /// cursor and where it was.
+    pub fn enter_visual_block_mode(&mut self) {
+        self.enter_visual_mode();
         if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer.block_selection = true;
+        }
+    }
+
+    fn block(content: &Rope, a: usize, b: usize) -> (RangeInclusive<usize>, Range<usize>) {
+        let (line_a, line_b) = (content.char_to_line(a), content.char_to_line(b));
+        let column_a = a - content.line_to_char(line_a);
+        let column_b = b - content.line_to_char(line_b);
+        (
+            line_a.min(line_b)..=line_a.max(line_b),
+            column_a.min(column_b)..column_a.max(column_b) + 1,
+        )
+    }
+
+    fn block_row(content: &Rope, line: usize, columns: &Range<usize>) -> Range<usize> {
+        let start = content.line_to_char(line);
+        let len = line_slice::text_len(content.line(line));
+        start + columns.start.min(len)..start + columns.end.min(len)
+    }
+
+    /// Selects the word under the cursor in visual mode, or the run of
+    /// spaces or punctuation it is on, as a double click does.
+    pub fn select_word(&mut self) {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let class = |c: char| {
+            if c.is_alphanumeric() || c == '_' {
+                1
+            } else if c == ' ' || c == '\t' {
+                2
+            } else if c == '\n' || c == '\r' {
+                3
+            } else {
+                4
+            }
+        };
+        let content = &buffer.content;
+        let cursor = window.cursor_pos;
+        let Some(kind) = (cursor < content.len_chars()).then(|| class(content.char(cursor))) else {
+            return;
+        };
+        if kind == 3 {
+            return;
+        }
+        let start = (0..cursor)
+            .rev()
+            .find(|&i| class(content.char(i)) != kind)
+            .map_or(0, |i| i + 1);
+        let end = (cursor..content.len_chars())
+            .find(|&i| class(content.char(i)) != kind)
+            .unwrap_or(content.len_chars());
+
+        buffer.selection_start = Some(start);
+        buffer.block_selection = false;
+        window.cursor_pos = end;
+        self.set_mode(Mode::Visual);
+    }
+
+    /// Selects the cursor's line, including its line break, in visual mode.
+    pub fn select_line(&mut self) {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        buffer.selection_start = Some(buffer.content.line_to_char(line));
+        window.cursor_pos = buffer.content.line_to_char(line + 1);
+        buffer.block_selection = false;
+        self.set_mode(Mode::Visual);
+    }
+
+    pub fn exit_visual_mode(&mut self) {
+        if let Some((buffer, window)) = self.current_view_mut() {
+            if let Some(start) = buffer.selection_start {
+                let end = window.cursor_pos;
+                buffer.last_selection = Some((start.min(end), start.max(end)));
+            }
             buffer.selection_start = None;
             self.set_mode(Mode::Normal);
         }
     }
 
     pub fn get_selection(&self) -> Option<(usize, usize)> {
-        self.get_current_buffer().and_then(|buffer| {
+        self.current_view().and_then(|(buffer, window)| {
             buffer.selection_start.map(|start| {
-                let end = buffer.cursor_pos;
+                let end = window.cursor_pos;
                 (start.min(end), start.max(end))
             })
         })
     }
 
     pub fn delete_selection(&mut self) {
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            if let Some(selection_start) = buffer.selection_start {
-                let start = selection_start.min(buffer.cursor_pos);
-                let end = selection_start.max(buffer.cursor_pos);
-                buffer.content.remove(start..end);
-                buffer.cursor_pos = start;
-                buffer.is_modified = true;
+        if self.refuse_read_only() {
+            return;
+        }
+        if let Some((buffer, window)) = self.current_view_mut() {
+            if let Some(selection_start) = buffer.selection_start.filter(|_| buffer.block_selection)
+            {
+                let (lines, columns) =
+                    Self::block(&buffer.content, selection_start, window.cursor_pos);
+                for line in lines.clone().rev() {
+                    let row = Self::block_row(&buffer.content, line, &columns);
+                    buffer.remove(row);
+                }
+                window.cursor_pos =
+                    Self::block_row(&buffer.content, *lines.start(), &columns).start;
+                buffer.mark_lines_modified();
+                buffer.selection_start = None;
+            } else if let Some(selection_start) = buffer.selection_start {
+                let start = selection_start.min(window.cursor_pos);
+                let end = selection_start.max(window.cursor_pos);
+                buffer.remove(start..end);
+                window.cursor_pos = start;
+                buffer.mark_lines_modified();
                 buffer.selection_start = None;
             }
         }
+        self.commit_revision();
         self.set_mode(Mode::Normal);
     }
 
     pub fn copy_selection(&self) -> Option<String> {
+        let (buffer, window) = self.current_view()?;
+        if let Some(start) = buffer.selection_start.filter(|_| buffer.block_selection) {
+            let (lines, columns) = Self::block(&buffer.content, start, window.cursor_pos);
+            let rows: Vec<String> = lines
+                .map(|line| {
+                    let row = Self::block_row(&buffer.content, line, &columns);
+                    buffer.content.slice(row).to_string()
+                })
+                .collect();
+            return Some(rows.join("\n"));
+        }
         self.get_current_buffer().and_then(|buffer| {
             self.get_selection()
                 .map(|(start, end)| buffer.content.slice(start..end).to_string())
         })
     }
 
+    /// Writes the current buffer to `path`, refusing to overwrite a
+    /// read-only file or its own file when something else changed it since
+    /// it was read.
     pub fn save_file(&mut self, path: &PathBuf) -> io::Result<()> {
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            let content = buffer.content.to_string();
-            fs::write(path, content)?;
-            buffer.is_modified = false;
-
-            // Update the current buffer path if it's a new file
-            if self.current_buffer.is_none() || self.current_buffer.as_ref().unwrap() != path {
-                self.current_buffer = Some(path.clone());
-            }
-
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "No active buffer to save",
-            ))
+        let resolved_path = self.resolve_path(path);
+        if fs::metadata(&resolved_path).is_ok_and(|metadata| metadata.permissions().readonly()) {
+            return Err(io::Error::other("The file is read-only; :w! overwrites it"));
         }
+        if self.get_current_buffer().is_some_and(|buffer| {
+            buffer.name.path() == Some(&resolved_path) && buffer.changed_on_disk()
+        }) {
+            return Err(io::Error::other(
+                "The file changed on disk since it was read; :w! overwrites it",
+            ));
+        }
+        self.force_save_file(path)
     }
 
-    pub fn handle_set_command(&mut self, option: &str) {
-        set_error(format!(
-            "Setting option: {}. (Not actually implemented)",
-            option
-        ));
-    }
-
-    pub fn get_starting_directory(&self) -> Option<&PathBuf> {
-        self.starting_directory.as_ref()
-    }
-
-    pub fn set_starting_directory(&mut self, path: PathBuf) {
-        self.starting_directory = Some(path);
+    /// Writes the current buffer to `path` even if the file is read-only or
+    /// changed on disk.
+    pub fn force_save_file(&mut self, path: &PathBuf) -> io::Result<()> {
+        let resolved_path = self.resolve_path(path);
+        let rename = self.is_scratch_buffer();
+        // A read-only file is made writable for the write and read-only again
+        // after. When that is not allowed, the write fails and offers to
+        // retry with privileges.
+        let read_only = fs::metadata(&resolved_path)
+            .map(|metadata| metadata.permissions())
+            .ok()
+            .filter(|permissions| permissions.readonly())
+            .filter(|permissions| {
+                fs::set_permissions(&resolved_path, Self::writable(permissions.clone())).is_ok()
+            });
+        let result = self.write_current_buffer(resolved_path.clone(), rename, false);
+        if let Some(permissions) = read_only {
+            fs::set_permissions(&resolved_path, permissions)?;
+        }
+        result
     }
 
-    pub fn get_current_file_path(&self) -> Option<PathBuf> {
-        self.current_buffer.clone()
+    fn writable(mut permissions: fs::Permissions) -> fs::Permissions {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            permissions.set_mode(permissions.mode() | 0o200);
+        }
+        #[cfg(not(unix))]
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        permissions
     }
 
-    pub fn get_content(&self) -> Option<Rope> {
-        self.current_buffer
-            .as_ref()
-            .and_then(|path| self.buffers.get(path))
-            .map(|buffer| buffer.content.clone())
+    /// Writes the current buffer to `path` and renames the buffer to it.
+    pub fn save_file_as(&mut self, path: &PathBuf) -> io::Result<()> {
+        let resolved_path = self.resolve_path(path);
+        self.write_current_buffer(resolved_path, true, false)
     }
 
-    pub fn set_mode(&mut self, mode: Mode) {
-        self.mode = mode;
+    /// Retries the write that failed with permission denied through the
+    /// configured privilege-escalation command (`sudo tee` by default).
+    pub fn retry_write_with_privileges(&mut self) -> io::Result<()> {
+        let prompt = self
+            .write_prompt
+            .take()
+            .ok_or_else(|| io::Error::other("No failed write to retry"))?;
+        self.write_current_buffer(prompt.path, prompt.rename, true)
     }
 
-    pub fn get_mode(&self) -> Mode {
-        self.mode.clone()
+    pub fn get_write_prompt(&self) -> Option<&WritePrompt> {
+        self.write_prompt.as_ref()
     }
 
-    pub fn get_viewport(&self) -> (usize, usize) {
-        self.viewport
+    pub fn dismiss_write_prompt(&mut self) {
+        self.write_prompt = None;
     }
 
-    pub fn set_viewport(&mut self, viewport: (usize, usize)) {
-        self.viewport = viewport;
-        self.scroll();
+    fn write_current_buffer(
+        &mut self,
+        path: PathBuf,
+        rename: bool,
+        privileged: bool,
+    ) -> io::Result<()> {
+        let current_id = self
+            .get_current_buffer_id()
+            .ok_or_else(|| io::Error::other("No active buffer to save"))?;
+        self.write_buffer(current_id, path, rename, privileged)
     }
 
-    pub fn get_scroll_offset(&self) -> Option<(usize, usize)> {
-        self.current_buffer
-            .as_ref()
-            .and_then(|path| self.buffers.get(path))
-            .map(|buffer| buffer.scroll_offset)
+    /// Writes buffer `id` to its own file, as the quit triage does for each
+    /// modified buffer.
+    pub fn save_buffer(&mut self, id: BufferId) -> io::Result<()> {
+        let buffer = self
+            .buffers
+            .get(&id)
+            .ok_or_else(|| io::Error::other("No such buffer"))?;
+        let path = buffer
+            .name
+            .path()
+            .cloned()
+            .ok_or_else(|| io::Error::other("No file name; use :saveas first"))?;
+        if buffer.changed_on_disk() {
+            return Err(io::Error::other(
+                "The file changed on disk since it was read",
+            ));
+        }
+        let prompt = self.write_prompt.take();
+        let result = self.write_buffer(id, path, false, false);
+        // A privileged retry only applies to the current buffer
+        self.write_prompt = prompt;
+        result
     }
 
-    pub fn scroll(&mut self) {
-        const VERTICAL_PADDING: usize = 6;
-        const HORIZONTAL_PADDING: usize = 6;
-
-        let cursor_position = self.get_cursor_screen_position();
-        let (viewport_width, viewport_height) = self.viewport;
+    fn write_buffer(
+        &mut self,
+        current_id: BufferId,
+        path: PathBuf,
+        rename: bool,
+        privileged: bool,
+    ) -> io::Result<()> {
+        if rename {
+            self.ensure_not_loaded_elsewhere(current_id, &path)?;
+        }
 
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            let cursor_line = buffer.content.char_to_line(buffer.cursor_pos);
-            let (scroll_x, scroll_y) = buffer.scroll_offset;
+        self.fire_hook_for_path(HookEvent::BufWritePre, Some(path.clone()));
 
-            // Vertical scrolling
-            if cursor_line < scroll_y + VERTICAL_PADDING {
-                buffer.scroll_offset.1 = cursor_line.saturating_sub(VERTICAL_PADDING);
-            } else if cursor_line >= scroll_y + viewport_height - VERTICAL_PADDING {
-                buffer.scroll_offset.1 =
-                    cursor_line.saturating_sub(viewport_height - VERTICAL_PADDING - 1);
+        let content = self
+            .buffers
+            .get(&current_id)
+            .map(|buffer| buffer.content.to_string())
+            .ok_or_else(|| io::Error::other("No active buffer to save"))?;
+        let passphrase = self
+            .notes
+            .iter()
+            .find(|notes| notes.buffer == current_id && notes.path == path)
+            .and_then(|notes| notes.passphrase.as_deref());
+        let result = if let Some(passphrase) = passphrase {
+            notes::encrypt(&content, passphrase).and_then(|data| fs::write(&path, data))
+        } else if privileged {
+            self.write_with_privileges(&path, &content)
+        } else {
+            fs::write(&path, &content)
+        };
+        if let Err(e) = result {
+            if e.kind() == io::ErrorKind::PermissionDenied && !privileged {
+                self.write_prompt = Some(WritePrompt { path, rename });
             }
-
-            // Horizontal scrolling
-            if let Some((_, cursor_column)) = cursor_position {
-                if cursor_column < scroll_x + HORIZONTAL_PADDING {
-                    buffer.scroll_offset.0 = cursor_column.saturating_sub(HORIZONTAL_PADDING);
-                } else if cursor_column >= scroll_x + viewport_width - HORIZONTAL_PADDING {
-                    buffer.scroll_offset.0 =
-                        cursor_column.saturating_sub(viewport_width - HORIZONTAL_PADDING - 1);
-                }
+            return Err(e);
+        }
+        // Notes stay out of the local history, which is not encrypted
+        let keep = self.options.localhistory;
+        if keep > 0 && !self.is_notes_buffer(current_id) {
+            if let Err(e) = local_history::record(&path, &content, keep) {
+                set_error(format!("Saved, but failed to keep local history: {}", e));
             }
         }
-    }
 
-    pub fn insert_str(&mut self, s: String) {
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            buffer.content.insert(buffer.cursor_pos, &s);
-            buffer.cursor_pos += s.len();
-            buffer.is_modified = true;
-            self.scroll();
-        }
-    }
+        let Some(buffer) = self.buffers.get_mut(&current_id) else {
+            return Ok(());
+        };
 
-    pub fn insert(&mut self, char: char) {
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            buffer.content.insert_char(buffer.cursor_pos, char);
-            buffer.cursor_pos += 1;
-            buffer.is_modified = true;
-            self.scroll();
+        if rename || buffer.name.path() == Some(&path) {
+            buffer.is_modified = false;
+        }
+        if rename {
+            buffer.set_name(BufferName::Named(path.clone()));
         }
+        if buffer.name.path() == Some(&path) {
+            buffer.record_disk_mtime();
+        }
+        if rename {
+            self.apply_autochdir();
+        }
+
+        self.fire_hook_for_path(HookEvent::BufWritePost, Some(path));
+        Ok(())
     }
 
-    pub fn insert_new_line(&mut self) {
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            buffer.content.insert_char(buffer.cursor_pos, '\n');
-            buffer.cursor_pos += 1;
-            buffer.is_modified = true;
-            self.scroll();
+    fn write_with_privileges(&self, path: &Path, content: &str) -> io::Result<()> {
+        let command = self
+            .config
+            .privilege_command
+            .replace("{file}", &hooks::shell_quote(path));
+        let mut child = hooks::shell_command(&command)
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(content.as_bytes())?;
         }
-    }
 
-    pub fn delete(&mut self) {
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            if buffer.cursor_pos > 0 {
-                buffer
-                    .content
-                    .remove(buffer.cursor_pos - 1..buffer.cursor_pos);
-                buffer.cursor_pos -= 1;
-                buffer.is_modified = true;
-                self.scroll();
-            }
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "`{}` failed ({})",
+                command, status
+            )))
         }
     }
 
-    pub fn move_cursor(&mut self, direction: CursorMovement) {
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            match direction {
-                CursorMovement::Left => Self::move_cursor_left(buffer),
-                CursorMovement::Right => Self::move_cursor_right(buffer),
-                CursorMovement::Up => Self::move_cursor_up(buffer),
-                CursorMovement::Down => Self::move_cursor_down(buffer),
-                CursorMovement::LineStart => Self::move_cursor_line_start(buffer),
-                CursorMovement::LineEnd => Self::move_cursor_line_end(buffer),
-            }
-            self.scroll();
+    fn ensure_not_loaded_elsewhere(&self, id: BufferId, path: &Path) -> io::Result<()> {
+        match self.find_buffer_by_path(path) {
+            Some(other) if other != id => Err(io::Error::other(format!(
+                "File is loaded in another buffer: {}",
+                path.display()
+            ))),
+            _ => Ok(()),
         }
     }
 
-    pub fn get_visible_content(&self) -> Option<String> {
-        self.get_current_buffer().map(|buffer| {
-            let (scroll_x, scroll_y) = buffer.scroll_offset;
-            let (viewport_width, viewport_height) = self.viewport;
-            let max_line_idx = buffer.content.len_lines().min(scroll_y + viewport_height);
-            (scroll_y..max_line_idx)
-                .map(|line_idx| {
-                    let line = buffer.content.line(line_idx);
-                    let line_length = line.len_chars();
-                    let visible_start = scroll_x.min(line_length.saturating_sub(1));
-                    let visible_end = (scroll_x + viewport_width).min(line_length);
-
-                    let visible_line: String = if line_length > 0 {
-                        line.chars()
-                            .skip(visible_start)
-                            .take(viewport_width)
-                            .collect()
-                    } else {
-                        String::new()
-                    };
+    pub fn resolve_range(&self, range: &ExRange) -> Result<(usize, usize), String> {
+        let (buffer, window) = self.current_view().ok_or("No active buffer")?;
+        let current_line = buffer.content.char_to_line(window.cursor_pos);
+        let selection = buffer.last_selection.map(|(start, end)| {
+            (
+                buffer.content.char_to_line(start),
+                buffer.content.char_to_line(end),
+            )
+        });
+        range.resolve(current_line, Self::last_line_index(buffer), selection)
+    }
 
-                    let has_newline = line.chars().skip(visible_end).any(|c| c == '\n');
+    pub fn write_range(
+        &self,
+        path: &PathBuf,
+        (start_line, end_line): (usize, usize),
+        append: bool,
+    ) -> io::Result<usize> {
+        let buffer = self
+            .get_current_buffer()
+            .ok_or_else(|| io::Error::other("No active buffer to write"))?;
+        let start = buffer.content.line_to_char(start_line);
+        let end = buffer.content.line_to_char(end_line + 1);
+        let text = buffer.content.slice(start..end).to_string();
 
-                    if line_idx == buffer.content.char_to_line(buffer.cursor_pos) {
-                        if has_newline {
-                            format!("{}\n", visible_line)
-                        } else {
-                            visible_line
-                        }
-                    } else if scroll_x >= line_length.saturating_sub(1) {
-                        String::from("\n")
-                    } else if has_newline {
-                        format!("{}\n", visible_line)
-                    } else {
-                        format!("{}\n", visible_line.trim_end())
-                    }
-                })
-                .collect()
-        })
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(self.resolve_path(path))?;
+        file.write_all(text.as_bytes())?;
+        Ok(end_line - start_line + 1)
     }
 
-    pub fn new_scratch_buffer(&mut self) -> Result<(), Box<dyn Error>> {
-        // Use the starting directory if it is set
-        let config_dir = self
-            .starting_directory
-            .clone()
-            .unwrap_or_else(|| Self::get_config_dir().unwrap_or_else(|| PathBuf::from("config")));
+    /// Text of lines `start..=end`, including the last line's break.
+    pub fn get_lines(&self, (start_line, end_line): (usize, usize)) -> Option<String> {
+        let buffer = self.get_current_buffer()?;
+        let start = buffer.content.line_to_char(start_line);
+        let end = buffer.content.line_to_char(end_line + 1);
+        Some(buffer.content.slice(start..end).to_string())
+    }
 
-        // Ensure the configuration directory exists
-        if !config_dir.exists() {
-            if let Err(e) = fs::create_dir_all(&config_dir) {
-                set_error(format!(
-                    "Failed to create config directory: {}",
-                    config_dir.display()
-                ));
-                return Err(Box::new(e));
-            }
+    /// The run of adjacent lines around the cursor that match `predicate`.
+    pub fn block_around_cursor(&self, predicate: impl Fn(&str) -> bool) -> Option<(usize, usize)> {
+        let (buffer, window) = self.current_view()?;
+        let contains = |line: usize| predicate(&buffer.content.line(line).to_string());
+        let cursor_line = buffer.content.char_to_line(window.cursor_pos);
+        if !contains(cursor_line) {
+            return None;
         }
 
-        let path = config_dir.join(format!("scratch_{}.txt", uuid::Uuid::new_v4()));
+        let last_line = Self::last_line_index(buffer);
+        let start = (0..cursor_line)
+            .rev()
+            .find(|&line| !contains(line))
+            .map_or(0, |line| line + 1);
+        let end = (cursor_line + 1..=last_line)
+            .find(|&line| !contains(line))
+            .map_or(last_line, |line| line - 1);
+        Some((start, end))
+    }
 
-        let buffer = Buffer::new();
-        self.buffers.insert(path.clone(), buffer);
-        self.current_buffer = Some(path);
+    /// The word under the cursor as (start, end, text), taking in the
+    /// punctuation of numbers and dates such as `0x1f` or `2024-01-31T12:00Z`.
+    pub fn token_under_cursor(&self) -> Option<(usize, usize, String)> {
+        let (buffer, window) = self.current_view()?;
+        let is_token = |c: char| c.is_alphanumeric() || "_-+:.".contains(c);
+        let content = &buffer.content;
+        let cursor = window.cursor_pos.min(content.len_chars());
 
-        Ok(())
+        let start = (0..cursor)
+            .rev()
+            .find(|&i| !is_token(content.char(i)))
+            .map_or(0, |i| i + 1);
+        let end = (cursor..content.len_chars())
+            .find(|&i| !is_token(content.char(i)))
+            .unwrap_or(content.len_chars());
+        (start < end).then(|| (start, end, content.slice(start..end).to_string()))
     }
 
-    pub fn is_scratch_buffer(&self) -> bool {
-        self.current_buffer
-            .as_ref()
-            .map(|path| {
-                path.file_name()
-                    .unwrap_or_default()
-                    .to_str()
-                    .unwrap_or("")
-                    .starts_with("scratch_")
-            })
-            .unwrap_or(false)
-    }
-
-    fn get_config_dir() -> Option<PathBuf> {
-        if let Ok(home) = env::var("HOME") {
-            let mut config_path = PathBuf::from(home);
-            config_path.push(".config/pyne");
-            Some(config_path)
-        } else if let Ok(appdata) = env::var("APPDATA") {
-            let mut config_path = PathBuf::from(appdata);
-            config_path.push("pyne");
-            Some(config_path)
-        } else {
-            None
+    /// Offers other bases for the number under the cursor, or conversions
+    /// between unix timestamps and ISO-8601 dates, in a menu.
+    pub fn show_conversions(&mut self) {
+        let Some((start, end, token)) = self.token_under_cursor() else {
+            set_error("No number or date under the cursor".to_string());
+            return;
+        };
+        let items: Vec<MenuItem> = convert::conversions(&token)
+            .into_iter()
+            .map(|(label, value)| MenuItem { label, value })
+            .collect();
+        if items.is_empty() {
+            set_error(format!("No conversions for {}", token));
+            return;
         }
+        self.menu = Some(Menu {
+            title: format!("Convert {}", token),
+            items,
+            action: MenuAction::Replace(start, end),
+        });
     }
 
-    pub fn get_cursor_screen_position(&self) -> Option<(usize, usize)> {
-        self.get_current_buffer().map(|buffer| {
-            let line = buffer.content.char_to_line(buffer.cursor_pos);
-            let line_start = buffer.content.line_to_char(line);
-            let column = buffer.cursor_pos - line_start;
-            (line, column)
-        })
+    pub fn take_menu(&mut self) -> Option<Menu> {
+        self.menu.take()
     }
 
-    pub fn open_file(&mut self, path: &PathBuf) -> io::Result<()> {
-        let resolved_path = if path.is_relative() {
-            self.starting_directory
-                .as_ref()
-                .map(|dir| dir.join(path))
-                .unwrap_or_else(|| path.clone())
-        } else {
-            path.clone()
+    /// Applies the chosen item of a menu taken with `take_menu`.
+    pub fn choose_menu_item(&mut self, menu: &Menu, index: usize) {
+        let Some(item) = menu.items.get(index) else {
+            return;
+        };
+        let (start, end) = match &menu.action {
+            MenuAction::Replace(start, end) => (*start, *end),
+            MenuAction::Jump(locations) => {
+                let (path, line) = &locations[index];
+                match self.open_file(path) {
+                    Ok(()) => self.go_to_line(*line),
+                    Err(e) => set_error(format!("Failed to open {}: {}", path.display(), e)),
+                }
+                return;
+            }
+            MenuAction::Reopen(paths) => {
+                self.reopen_closed_buffer(Some(&paths[index]));
+                return;
+            }
+            MenuAction::Buffer(ids) => {
+                if self.buffers.contains_key(&ids[index]) {
+                    self.set_current_buffer(ids[index]);
+                    self.scroll();
+                }
+                return;
+            }
+            MenuAction::Checkout => {
+                self.checkout_branch(&item.value);
+                return;
+            }
+            MenuAction::ApplyStash => {
+                self.apply_stash(&item.value);
+                return;
+            }
+            MenuAction::CodeAction(actions) => {
+                if let Err(e) = self.run_code_action(&actions[index]) {
+                    set_error(e);
+                }
+                return;
+            }
+            MenuAction::Diff(pairs) => {
+                let result = match &pairs[index] {
+                    (Some(old), Some(new)) => self.diff_files(old, new),
+                    (Some(path), None) | (None, Some(path)) => self.open_file(path),
+                    (None, None) => Ok(()),
+                };
+                if let Err(e) = result {
+                    set_error(format!("Failed to open {}: {}", item.value, e));
+                }
+                return;
+            }
         };
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        if end > buffer.content.len_chars() {
+            return;
+        }
+        buffer.remove(start..end);
+        buffer.insert(start, &item.value);
+        window.cursor_pos = start;
+        buffer.mark_lines_modified();
+        self.commit_revision();
+    }
 
-        let content = fs::read_to_string(&resolved_path)?;
-        let buffer = Buffer {
-            content: Rope::from_str(&content),
-            cursor_pos: 0,
-            scroll_offset: (0, 0),
-            is_modified: false,
-            selection_start: Some(0),
-        };
-        self.buffers.insert(resolved_path.clone(), buffer);
-        self.current_buffer = Some(resolved_path);
-        Ok(())
+    fn edit_table(
+        &mut self,
+        edit: impl FnOnce(&mut Table, usize, usize) -> (usize, usize),
+    ) -> bool {
+        let Some(lines) = self.block_around_cursor(is_table_line) else {
+            return false;
+        };
+        let (Some((buffer, window)), Some(text)) = (self.current_view(), self.get_lines(lines))
+        else {
+            return false;
+        };
+        let cursor_line = buffer.content.char_to_line(window.cursor_pos);
+        let column = window.cursor_pos - buffer.content.line_to_char(cursor_line);
+
+        let rows: Vec<&str> = text.lines().collect();
+        let row = cursor_line - lines.0;
+        let mut table = Table::parse(&rows);
+        let cell = cell_at(rows[row], column).min(table.column_count().saturating_sub(1));
+        let (row, cell) = edit(&mut table, row, cell);
+        let rendered = table.render();
+        self.replace_lines(lines, &rendered.join("\n"));
+
+        if let Some((buffer, window)) = self.current_view_mut() {
+            let line_start = buffer.content.line_to_char(lines.0 + row);
+            window.cursor_pos = line_start + table.cell_offset(&rendered[row], cell);
+        }
+        true
     }
 
-    pub fn toggle_debug_info(&mut self) {
-        self.show_debug_info = !self.show_debug_info;
+    pub fn format_table(&mut self) -> bool {
+        self.edit_table(|_, row, cell| (row, cell))
     }
 
-    pub fn has_unsaved_changes(&self) -> bool {
-        self.buffers.values().any(|buffer| buffer.is_modified)
+    pub fn table_insert_row(&mut self) -> bool {
+        self.edit_table(|table, row, cell| (table.insert_row(row), cell))
     }
 
-    pub fn get_unsaved_buffers(&self) -> Vec<PathBuf> {
-        self.buffers
+    pub fn table_insert_column(&mut self) -> bool {
+        self.edit_table(|table, row, cell| {
+            table.insert_column(cell);
+            (row, cell + 1)
+        })
+    }
+
+    /// Moves to the next or previous cell, skipping the separator row.
+    pub fn table_next_cell(&mut self, forward: bool) -> bool {
+        self.edit_table(|table, row, cell| {
+            let columns = table.column_count();
+            if forward {
+                if cell + 1 < columns {
+                    return (row, cell + 1);
+                }
+                let mut next = row + 1;
+                if table.is_separator(next) {
+                    next += 1;
+                }
+                if next >= table.row_count() {
+                    next = table.insert_row(row);
+                }
+                (next, 0)
+            } else if cell > 0 {
+                (row, cell - 1)
+            } else {
+                let previous = (0..row).rev().find(|&row| !table.is_separator(row));
+                match previous {
+                    Some(previous) => (previous, columns.saturating_sub(1)),
+                    None => (row, 0),
+                }
+            }
+        })
+    }
+
+    fn edit_list_lines(
+        &mut self,
+        (first, last): (usize, usize),
+        edit: impl Fn(&[&str]) -> Vec<Option<String>>,
+    ) -> bool {
+        let (Some((buffer, window)), Some(text)) =
+            (self.current_view(), self.get_lines((first, last)))
+        else {
+            return false;
+        };
+        let cursor_line = buffer.content.char_to_line(window.cursor_pos);
+        let column = window.cursor_pos - buffer.content.line_to_char(cursor_line);
+
+        let lines: Vec<&str> = text.lines().collect();
+        let edited = edit(&lines);
+        if edited.iter().all(Option::is_none) {
+            return false;
+        }
+        let rewritten: Vec<String> = lines
             .iter()
-            .filter(|(_, buffer)| buffer.is_modified)
-            .map(|(path, _)| path.clone())
-            .collect()
+            .zip(edited)
+            .map(|(line, edited)| edited.unwrap_or_else(|| line.to_string()))
+            .collect();
+        self.replace_lines((first, last), &rewritten.join("\n"));
+
+        if let Some((buffer, window)) = self.current_view_mut() {
+            let line_start = buffer.content.line_to_char(cursor_line);
+            let length = buffer.content.line(cursor_line).len_chars();
+            window.cursor_pos = line_start + column.min(length.saturating_sub(1));
+        }
+        true
     }
 
-    pub fn get_current_buffer(&self) -> Option<&Buffer> {
-        self.current_buffer
-            .as_ref()
-            .and_then(|path| self.buffers.get(path))
+    /// Checks or unchecks the `- [ ]` checkbox of each list item in
+    /// `lines`, adding one to items without.
+    pub fn toggle_checkboxes(&mut self, lines: (usize, usize)) -> bool {
+        self.edit_list_lines(lines, |lines| {
+            lines
+                .iter()
+                .map(|line| markdown_list::toggle_checkbox(line))
+                .collect()
+        })
     }
 
-    fn get_current_buffer_mut(&mut self) -> Option<&mut Buffer> {
-        self.current_buffer
-            .as_ref()
-            .and_then(|path| self.buffers.get_mut(path))
+    /// Numbers the ordered list items in `lines` one after another.
+    pub fn renumber_list(&mut self, lines: (usize, usize)) -> bool {
+        self.edit_list_lines(lines, |lines| {
+            lines
+                .iter()
+                .zip(markdown_list::renumber(lines))
+                .map(|(line, renumbered)| (*line != renumbered).then_some(renumbered))
+                .collect()
+        })
     }
 
-    fn move_cursor_left(buffer: &mut Buffer) {
-        if buffer.cursor_pos > 0 {
-            buffer.cursor_pos -= 1;
-        }
+    /// The lines around the cursor up to the nearest blank lines.
+    pub fn list_around_cursor(&self) -> Option<(usize, usize)> {
+        self.paragraph_around_cursor()
     }
 
-    fn move_cursor_right(buffer: &mut Buffer) {
-        if buffer.cursor_pos < buffer.content.len_chars() {
-            buffer.cursor_pos += 1;
-        }
+    /// The lines around the cursor up to the nearest blank lines, as `ip`
+    /// selects them.
+    pub fn paragraph_around_cursor(&self) -> Option<(usize, usize)> {
+        self.block_around_cursor(|line| !line.trim().is_empty())
     }
 
-    fn move_cursor_up(buffer: &mut Buffer) {
-        if let Some(prev_line) = buffer
-            .content
-            .char_to_line(buffer.cursor_pos)
-            .checked_sub(1)
-        {
-            let cur_line_start = buffer
-                .content
-                .line_to_char(buffer.content.char_to_line(buffer.cursor_pos));
-            let prev_line_start = buffer.content.line_to_char(prev_line);
-            let cur_col = buffer.cursor_pos - cur_line_start;
-            let prev_line_len = buffer.content.line(prev_line).len_chars();
-            buffer.cursor_pos = prev_line_start + cur_col.min(prev_line_len);
+    /// Re-wraps `lines` to `textwidth`, keeping comment leaders and list
+    /// item indentation.
+    pub fn reflow_lines(&mut self, lines: (usize, usize)) -> bool {
+        if self.refuse_read_only() {
+            return false;
+        }
+        let Some(text) = self.get_lines(lines) else {
+            return false;
+        };
+        let width = self.buffer_options().textwidth;
+        let reflowed = prose::reflow(&text.lines().collect::<Vec<&str>>(), width).join("\n");
+        if reflowed == text.trim_end_matches('\n') {
+            return false;
         }
+        self.replace_lines(lines, &reflowed);
+        self.scroll();
+        true
     }
 
-    fn move_cursor_down(buffer: &mut Buffer) {
-        let next_line = buffer.content.char_to_line(buffer.cursor_pos) + 1;
-        if next_line < buffer.content.len_lines() {
-            let cur_line_start = buffer
-                .content
-                .line_to_char(buffer.content.char_to_line(buffer.cursor_pos));
-            let next_line_start = buffer.content.line_to_char(next_line);
-            let cur_col = buffer.cursor_pos - cur_line_start;
-            let next_line_len = buffer.content.line(next_line).len_chars();
-            buffer.cursor_pos = next_line_start + cur_col.min(next_line_len);
+    fn auto_wrap(&mut self) {
+        let options = self.buffer_options();
+        if !options.autowrap {
+            return;
+        }
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let text = buffer.content.line(line).to_string();
+        let Some((spaces, prefix)) =
+            prose::wrap_point(text.trim_end_matches(['\n', '\r']), options.textwidth)
+        else {
+            return;
+        };
+        let line_start = buffer.content.line_to_char(line);
+        let start = line_start + text[..spaces.start].chars().count();
+        let end = line_start + text[..spaces.end].chars().count();
+        let break_text = format!("\n{}", prefix);
+        buffer.remove(start..end);
+        buffer.insert(start, &break_text);
+        if window.cursor_pos >= end {
+            window.cursor_pos = window.cursor_pos - (end - start) + break_text.chars().count();
         }
+        buffer.mark_lines_modified();
+        self.scroll();
     }
 
-    fn move_cursor_line_start(buffer: &mut Buffer) {
-        let line = buffer.content.char_to_line(buffer.cursor_pos);
-        buffer.cursor_pos = buffer.content.line_to_char(line);
+    fn is_markdown_buffer(&self) -> bool {
+        self.get_current_buffer()
+            .and_then(|buffer| buffer.name.path())
+            .and_then(|path| path.extension())
+            .is_some_and(|extension| {
+                ["md", "markdown", "org"].contains(&&*extension.to_string_lossy())
+            })
     }
 
-    fn move_cursor_line_end(buffer: &mut Buffer) {
-        let line = buffer.content.char_to_line(buffer.cursor_pos);
-        buffer.cursor_pos = buffer.content.line_to_char(line + 1).saturating_sub(1);
+    /// Enter on a list item of a markdown buffer starts the next item, or
+    /// ends the list when the item is still empty.
+    pub fn continue_list(&mut self) -> bool {
+        if !self.is_markdown_buffer() {
+            return false;
+        }
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return false;
+        };
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let text = buffer.content.line(line).to_string();
+        let Some(item) = ListItem::parse(&text) else {
+            return false;
+        };
+        let line_start = buffer.content.line_to_char(line);
+        let text_start = line_start + text[..item.text_start].chars().count();
+        if window.cursor_pos < text_start {
+            return false;
+        }
+
+        if text[item.text_start..].trim().is_empty() {
+            let line_end = line_start + text.trim_end_matches(['\n', '\r']).chars().count();
+            buffer.remove(line_start..line_end);
+            window.cursor_pos = line_start;
+        } else {
+            let continuation = format!("\n{}", item.continuation());
+            buffer.insert(window.cursor_pos, &continuation);
+            window.cursor_pos += continuation.chars().count();
+        }
+        buffer.mark_lines_modified();
+        self.scroll();
+        true
+    }
+
+    /// Replaces lines `start..=end` with `text` as a single revision,
+    /// keeping the line break after the last line.
+    pub fn replace_lines(&mut self, (start_line, end_line): (usize, usize), text: &str) {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let start = buffer.content.line_to_char(start_line);
+        let end = buffer.content.line_to_char(end_line + 1);
+        let mut text = text.to_string();
+        if buffer.content.slice(start..end).chars().last() == Some('\n') && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        if buffer.content.slice(start..end) == text.as_str() {
+            return;
+        }
+
+        buffer.remove(start..end);
+        buffer.insert(start, &text);
+        window.cursor_pos = start;
+        buffer.selection_start = None;
+        buffer.mark_modified();
+        self.commit_revision();
+    }
+
+    fn last_line_index(buffer: &Buffer) -> usize {
+        // Ropey reports an extra empty line after a trailing newline
+        let len_lines = buffer.content.len_lines();
+        if len_lines > 1 && buffer.content.line(len_lines - 1).len_chars() == 0 {
+            len_lines - 2
+        } else {
+            len_lines - 1
+        }
+    }
+
+    /// The lines the visual selection covers, or the cursor's line.
+    pub fn selected_lines(&self) -> Option<(usize, usize)> {
+        let (buffer, window) = self.current_view()?;
+        let (start, end) = self
+            .get_selection()
+            .unwrap_or((window.cursor_pos, window.cursor_pos));
+        let first = buffer.content.char_to_line(start);
+        let mut last = buffer.content.char_to_line(end);
+        if last > first && buffer.content.line_to_char(last) == end {
+            last -= 1;
+        }
+        let last_line = Self::last_line_index(buffer);
+        Some((first.min(last_line), last.min(last_line)))
+    }
+
+    /// Moves lines `first..=last` one line down or up, past their
+    /// neighbour.
+    pub fn move_lines(&mut self, (first, last): (usize, usize), down: bool) {
+        if self.refuse_read_only() {
+            return;
+        }
+        let options = self.buffer_options();
+        let (shiftwidth, expandtab) = (options.shiftwidth, options.expandtab);
+        let Some((buffer, window)) = self.current_view() else {
+            return;
+        };
+        let last_line = Self::last_line_index(buffer);
+        if first > last || (down && last >= last_line) || (!down && first == 0) {
+            self.ring_bell();
+            return;
+        }
+        let neighbour = if down { last + 1 } else { first - 1 };
+
+        let text_of = |line: usize| {
+            let mut text = buffer.content.line(line).to_string();
+            if text.ends_with('\n') {
+                text.pop();
+            }
+            text
+        };
+        let is_blank = |text: &String| text.trim().is_empty();
+        let non_blank_above =
+            |line: usize| (0..line).rev().map(text_of).find(|text| !is_blank(text));
+        let block: Vec<String> = (first..=last).map(text_of).collect();
+        let neighbour_text = text_of(neighbour);
+
+        let shift = match block.iter().find(|text| !is_blank(text)) {
+            Some(first_text) => {
+                let expected = |above: Option<String>| {
+                    above.map_or(0, |above| expected_indent(&above, first_text, shiftwidth))
+                        as isize
+                };
+                let old_above = non_blank_above(first);
+                let new_above = if down {
+                    Some(neighbour_text.clone())
+                        .filter(|text| !is_blank(text))
+                        .or_else(|| old_above.clone())
+                } else {
+                    non_blank_above(neighbour)
+                };
+                expected(new_above) - expected(old_above)
+            }
+            None => 0,
+        };
+        let moved = shift_indent(&block, shift, shiftwidth, expandtab);
+
+        // Where the cursor and selection end up, as lines and columns
+        let new_first = if down { first + 1 } else { first - 1 };
+        let new_neighbour = if down { first } else { last };
+        let selection_end = buffer
+            .selection_start
+            .map(|start| start.max(window.cursor_pos));
+        let leading = |text: &String| text.len() - text.trim_start_matches([' ', '\t']).len();
+        let locate = |pos: usize| {
+            let line = buffer.content.char_to_line(pos);
+            let column = pos - buffer.content.line_to_char(line);
+            if (first..=last).contains(&line) {
+                let index = line - first;
+                let change = leading(&moved[index]) as isize - leading(&block[index]) as isize;
+                (
+                    new_first + index,
+                    (column as isize + change).max(0) as usize,
+                )
+            } else if line == neighbour && column == 0 && Some(pos) == selection_end {
+                // A selection that stops at the start of the next line
+                (new_first + block.len(), 0)
+            } else if line == neighbour {
+                (new_neighbour, column)
+            } else {
+                (line, column)
+            }
+        };
+        let cursor = locate(window.cursor_pos);
+        let selection = buffer.selection_start.map(locate);
+
+        let region_start = buffer.content.line_to_char(first.min(neighbour));
+        let region_end = buffer.content.line_to_char(last.max(neighbour) + 1);
+        let trailing_newline = buffer.content.char(region_end - 1) == '\n';
+        let mut lines = moved;
+        if down {
+            lines.insert(0, neighbour_text);
+        } else {
+            lines.push(neighbour_text);
+        }
+        let mut text = lines.join("\n");
+        if trailing_newline {
+            text.push('\n');
+        }
+
+        self.commit_revision();
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        buffer.remove(region_start..region_end);
+        buffer.insert(region_start, &text);
+        let position = |content: &Rope, (line, column): (usize, usize)| {
+            let line = line.min(content.len_lines() - 1);
+            let length = content
+                .line(line)
+                .chars()
+                .take_while(|&c| c != '\n')
+                .count();
+            content.line_to_char(line) + column.min(length)
+        };
+        window.cursor_pos = position(&buffer.content, cursor);
+        buffer.selection_start = selection.map(|selection| position(&buffer.content, selection));
+        buffer.mark_modified();
+        self.commit_revision();
+        self.scroll();
+    }
+
+    /// Copies lines `first..=last` below themselves, or above with `down`
+    /// false.
+    pub fn duplicate_lines(&mut self, (first, last): (usize, usize), down: bool) {
+        if self.refuse_read_only() || first > last {
+            return;
+        }
+        self.commit_revision();
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let start = buffer.content.line_to_char(first);
+        let end = buffer.content.line_to_char(last + 1);
+        let mut text = buffer.content.slice(start..end).to_string();
+        // The last line of the buffer may have no line break to copy
+        let (at, text) = match (down, text.ends_with('\n')) {
+            (true, true) => (end, text),
+            (true, false) => (end, format!("\n{}", text)),
+            (false, _) => {
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                (start, text)
+            }
+        };
+        let length = text.chars().count();
+        buffer.insert(at, &text);
+        let moves = |pos: usize| if down { pos >= start } else { pos > end };
+        for pos in [
+            Some(&mut window.cursor_pos),
+            buffer.selection_start.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if moves(*pos) {
+                *pos += length;
+            }
+        }
+        buffer.mark_lines_modified();
+        self.commit_revision();
+        self.scroll();
+    }
+
+    pub fn handle_set_command(&mut self, option: &str) {
+        if let Some(value) = self.set_window_option(option.trim()) {
+            set_error(format!("Set {}", value));
+            return;
+        }
+
+        match self.options.set(option) {
+            Ok(value) => {
+                set_error(format!("Set {}", value));
+                self.apply_autochdir();
+                self.enforce_buffer_budget();
+            }
+            Err(e) => set_error(e),
+        }
+    }
+
+    /// Like `handle_set_command`, but options that can be local to a buffer
+    /// only change for the current one.
+    pub fn handle_setlocal_command(&mut self, option: &str) {
+        if let Some(value) = self.set_window_option(option.trim()) {
+            set_error(format!("Set {}", value));
+            return;
+        }
+        let Some(buffer) = self
+            .get_current_buffer_id()
+            .and_then(|id| self.buffers.get_mut(&id))
+        else {
+            set_error("No buffer to set a local option for".to_string());
+            return;
+        };
+        match self.options.set_local(&mut buffer.local_options, option) {
+            Ok(value) => set_error(format!("Set {} locally", value)),
+            Err(e) => set_error(e),
+        }
+    }
+
+    fn set_window_option(&mut self, option: &str) -> Option<String> {
+        let window = self.windows.get_mut(&self.current_window)?;
+        window.scrollbind = match option {
+            "scrollbind" | "scb" => true,
+            "noscrollbind" | "noscb" => false,
+            "scrollbind!" | "scb!" => !window.scrollbind,
+            _ => return None,
+        };
+        Some(format!(
+            "{}scrollbind",
+            if window.scrollbind { "" } else { "no" }
+        ))
+    }
+
+    /// Splits the current window, showing the current buffer in the new
+    /// window, which becomes the focused one.
+    pub fn split_window(&mut self, direction: SplitDirection) -> WindowId {
+        let id = WindowId(self.next_window_id);
+        self.next_window_id += 1;
+
+        self.sync_windows();
+        let mut window = Split::with_buffer(self.get_current_buffer_id());
+        if let Some(current) = self.windows.get(&self.current_window) {
+            window.cursor_pos = current.cursor_pos;
+            window.scroll_offset = current.scroll_offset;
+            window.viewport = current.viewport;
+        }
+        self.windows.insert(id, window);
+        self.layout.split(self.current_window, id, direction);
+        self.current_window = id;
+        id
+    }
+
+    fn switch_window(&mut self, id: WindowId) {
+        if id != self.current_window {
+            self.sync_windows();
+            self.current_window = id;
+        }
+    }
+
+    fn sync_windows(&mut self) {
+        let mut moved = Vec::new();
+        for (id, buffer) in &mut self.buffers {
+            if buffer.edits.is_empty() {
+                continue;
+            }
+            let edits = std::mem::take(&mut buffer.edits);
+            let len = buffer.content.len_chars();
+            let map = |offset: usize| edits.iter().fold(offset, |offset, edit| edit.map(offset));
+            for (window_id, window) in &mut self.windows {
+                if window.buffer != Some(*id) {
+                    continue;
+                }
+                if *window_id != self.current_window {
+                    window.cursor_pos = map(window.cursor_pos);
+                    window.scroll_offset.1 = edits
+                        .iter()
+                        .fold(window.scroll_offset.1, |line, edit| edit.map_line(line));
+                    moved.push(*window_id);
+                }
+                window.cursor_pos = window.cursor_pos.min(len);
+            }
+            buffer.last_cursor = map(buffer.last_cursor).min(len);
+        }
+        for id in moved {
+            self.scroll_to_cursor(id);
+        }
+    }
+
+    /// Closes the current window, returning false if it is the last one.
+    pub fn close_window(&mut self) -> bool {
+        if self.windows.len() == 1 {
+            return false;
+        }
+
+        self.sync_windows();
+        self.layout.remove(self.current_window);
+        let closed = self.windows.remove(&self.current_window);
+        if let Some(window) = &closed {
+            if let Some(buffer) = window.buffer.and_then(|id| self.buffers.get_mut(&id)) {
+                buffer.last_cursor = window.cursor_pos;
+                buffer.last_scroll = window.scroll_offset;
+            }
+        }
+        // Notes save when the last window showing them closes
+        let notes = closed
+            .and_then(|window| window.buffer)
+            .filter(|id| self.is_notes_buffer(*id))
+            .filter(|id| {
+                self.buffers
+                    .get(id)
+                    .is_some_and(|buffer| buffer.is_modified)
+            })
+            .filter(|id| {
+                !self
+                    .windows
+                    .values()
+                    .any(|window| window.buffer == Some(*id))
+            });
+        if let Some(id) = notes {
+            if let Err(e) = self.save_buffer(id) {
+                set_error(format!("Failed to save notes: {}", e));
+            }
+        }
+        #[cfg(feature = "terminal")]
+        self.terminals.remove(&self.current_window);
+        self.current_window = self.layout.windows()[0];
+        true
+    }
+
+    /// Closes the current buffer, showing the most recently used other
+    /// buffer in its place in every window.
+    pub fn close_buffer(&mut self, force: bool) {
+        let Some(id) = self.get_current_buffer_id() else {
+            return;
+        };
+        if self.tasks.iter().any(|(_, buffer, _)| *buffer == id) {
+            set_error("Task output buffers cannot be closed".to_string());
+            return;
+        }
+        let notes = self.notes.iter().position(|notes| notes.buffer == id);
+        let Some(buffer) = self.buffers.get(&id) else {
+            return;
+        };
+        if buffer.is_modified {
+            if notes.is_some() {
+                if let Err(e) = self.save_buffer(id) {
+                    set_error(format!("Failed to save notes: {}", e));
+                    return;
+                }
+            } else if !force {
+                set_error(format!(
+                    "{} has unsaved changes; use :bd! to close it anyway",
+                    buffer.name
+                ));
+                return;
+            }
+        }
+
+        self.sync_windows();
+        let (cursor_pos, scroll_offset) = self
+            .windows
+            .get(&self.current_window)
+            .map(|window| (window.cursor_pos, window.scroll_offset))
+            .unwrap_or_default();
+        let Some(buffer) = self.buffers.remove(&id) else {
+            return;
+        };
+        match notes {
+            Some(index) => {
+                self.notes.remove(index);
+            }
+            None => {
+                if let Some(path) = buffer.name.path() {
+                    self.closed_buffers.push(ClosedBuffer {
+                        path: path.clone(),
+                        cursor_pos,
+                        line: buffer.content.char_to_line(cursor_pos),
+                        scroll_offset,
+                    });
+                }
+            }
+        }
+
+        let replacement = self
+            .buffers
+            .iter()
+            .max_by_key(|(_, buffer)| buffer.last_used)
+            .map(|(id, _)| *id);
+        let replacement = replacement.unwrap_or_else(|| {
+            let name = BufferName::Unnamed(self.next_scratch_id);
+            self.next_scratch_id += 1;
+            self.add_buffer(Buffer::with_content(name, Rope::new()))
+        });
+        let (cursor_pos, scroll_offset) = self
+            .buffers
+            .get(&replacement)
+            .map(|buffer| (buffer.last_cursor, buffer.last_scroll))
+            .unwrap_or_default();
+        for window in self.windows.values_mut() {
+            if window.buffer == Some(id) {
+                window.buffer = Some(replacement);
+                window.cursor_pos = cursor_pos;
+                window.scroll_offset = scroll_offset;
+                window.desired_column = None;
+            }
+        }
+        self.set_current_buffer(replacement);
+        set_error(format!("Closed {}", buffer.name));
+    }
+
+    /// Opens a file closed with `:bd` again with its cursor where it was:
+    /// the one at `path`, or else the most recently closed.
+    pub fn reopen_closed_buffer(&mut self, path: Option<&PathBuf>) {
+        let Some(closed) = self.closed_buffers.take(path) else {
+            set_error("No closed buffers to reopen".to_string());
+            return;
+        };
+        if let Err(e) = self.open_file(&closed.path) {
+            set_error(format!("Failed to reopen {}: {}", closed.path.display(), e));
+            return;
+        }
+        if let Some((buffer, window)) = self.current_view_mut() {
+            // The file may have shrunk since
+            window.cursor_pos = closed.cursor_pos.min(buffer.content.len_chars());
+            window.scroll_offset = closed.scroll_offset;
+        }
+        self.scroll();
+    }
+
+    /// Handles `:history`, listing the saved snapshots of the current file
+    /// in a menu that compares the chosen one with the file, and
+    /// `:history restore <n>`, which puts snapshot n back in the buffer.
+    pub fn local_history(&mut self, args: &str) {
+        let Some(path) = self
+            .get_current_buffer()
+            .and_then(|buffer| buffer.name.path())
+            .cloned()
+        else {
+            set_error("The current buffer has no file".to_string());
+            return;
+        };
+        let snapshots = local_history::snapshots(&path);
+        if snapshots.is_empty() {
+            set_error(format!("No local history for {}", path.display()));
+            return;
+        }
+
+        let args: Vec<&str> = args.split_whitespace().collect();
+        match args[..] {
+            [] => {
+                let items = snapshots
+                    .iter()
+                    .enumerate()
+                    .map(|(index, snapshot)| MenuItem {
+                        label: format!("{:>3}  {}", index + 1, snapshot.time()),
+                        value: snapshot.path.display().to_string(),
+                    })
+                    .collect();
+                let pairs = snapshots
+                    .into_iter()
+                    .map(|snapshot| (Some(snapshot.path), Some(path.clone())))
+                    .collect();
+                self.menu = Some(Menu {
+                    title: "Local history (compare with the saved file)".to_string(),
+                    items,
+                    action: MenuAction::Diff(pairs),
+                });
+            }
+            ["restore", number] => {
+                let Some(snapshot) = number
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|number| snapshots.get(number.checked_sub(1)?))
+                else {
+                    set_error(format!(
+                        "No snapshot {}; :history lists 1 to {}",
+                        number,
+                        snapshots.len()
+                    ));
+                    return;
+                };
+                let text = match snapshot.read() {
+                    Ok(text) => text,
+                    Err(e) => {
+                        set_error(format!("Failed to read snapshot: {}", e));
+                        return;
+                    }
+                };
+                let Some(buffer) = self.get_current_buffer() else {
+                    return;
+                };
+                let last_line = buffer.content.len_lines().saturating_sub(1);
+                // Keep the restore apart from earlier edits so u undoes it
+                self.commit_revision();
+                self.replace_lines((0, last_line), &text);
+                set_error(format!(
+                    "Restored the version saved at {}; :w to keep it",
+                    snapshot.time()
+                ));
+            }
+            _ => set_error("Usage: :history [restore <n>]".to_string()),
+        }
+    }
+
+    /// Handles `:closed`, listing recently closed files in a menu.
+    pub fn list_closed_buffers(&mut self) {
+        if self.closed_buffers.is_empty() {
+            set_error("No closed buffers".to_string());
+            return;
+        }
+        let directory = self.get_working_directory().to_path_buf();
+        let items = self
+            .closed_buffers
+            .iter()
+            .map(|closed| MenuItem {
+                label: format!(
+                    "{}:{}",
+                    closed
+                        .path
+                        .strip_prefix(&directory)
+                        .unwrap_or(&closed.path)
+                        .display(),
+                    closed.line + 1
+                ),
+                value: closed.path.display().to_string(),
+            })
+            .collect();
+        let paths = self
+            .closed_buffers
+            .iter()
+            .map(|closed| closed.path.clone())
+            .collect();
+        self.menu = Some(Menu {
+            title: "Closed buffers".to_string(),
+            items,
+            action: MenuAction::Reopen(paths),
+        });
+    }
+
+    /// Handles `:buffers`, listing the open buffers in a menu, most recently
+    /// used first.
+    pub fn list_buffers(&mut self) {
+        let mut ids: Vec<BufferId> = self.buffers.keys().copied().collect();
+        ids.sort_by_key(|id| std::cmp::Reverse(self.buffers[id].last_used));
+        let directory = self.get_working_directory().to_path_buf();
+        let items = ids
+            .iter()
+            .map(|id| {
+                let buffer = &self.buffers[id];
+                let name = match buffer.name.path() {
+                    Some(path) => path
+                        .strip_prefix(&directory)
+                        .unwrap_or(path)
+                        .display()
+                        .to_string(),
+                    None => buffer.name.to_string(),
+                };
+                let modified = if buffer.is_modified { " [+]" } else { "" };
+                MenuItem {
+                    label: format!("{}{}", name, modified),
+                    value: name,
+                }
+            })
+            .collect();
+        self.menu = Some(Menu {
+            title: "Buffers".to_string(),
+            items,
+            action: MenuAction::Buffer(ids),
+        });
+    }
+
+    /// The git branch checked out in the working directory, if it is in a
+    /// repository.
+    pub fn git_branch(&self) -> Option<String> {
+        git::current_branch(self.get_working_directory())
+    }
+
+    /// Handles `:git branch`, listing the local git branches in a menu to
+    /// check one out.
+    pub fn list_branches(&mut self) {
+        let branches = match git::branches(self.get_working_directory()) {
+            Ok(branches) => branches,
+            Err(e) => {
+                set_error(e);
+                return;
+            }
+        };
+        let current = self.git_branch();
+        let items = branches
+            .into_iter()
+            .map(|branch| MenuItem {
+                label: match current.as_ref() == Some(&branch) {
+                    true => format!("{} (current)", branch),
+                    false => branch.clone(),
+                },
+                value: branch,
+            })
+            .collect();
+        self.menu = Some(Menu {
+            title: self.git_menu_title("Branches"),
+            items,
+            action: MenuAction::Checkout,
+        });
+    }
+
+    /// Handles `:git stash`, listing the stashes in a menu to apply one.
+    pub fn list_stashes(&mut self) {
+        let stashes = match git::stashes(self.get_working_directory()) {
+            Ok(stashes) => stashes,
+            Err(e) => {
+                set_error(e);
+                return;
+            }
+        };
+        if stashes.is_empty() {
+            set_error("No stashes".to_string());
+            return;
+        }
+        let items = stashes
+            .into_iter()
+            .map(|(name, message)| MenuItem {
+                label: format!("{} {}", name, message),
+                value: name,
+            })
+            .collect();
+        self.menu = Some(Menu {
+            title: self.git_menu_title("Stashes"),
+            items,
+            action: MenuAction::ApplyStash,
+        });
+    }
+
+    fn git_menu_title(&self, title: &str) -> String {
+        match self.get_unsaved_buffers().len() {
+            0 => title.to_string(),
+            unsaved => format!("{} ({} unsaved buffer(s) keep their edits)", title, unsaved),
+        }
+    }
+
+    /// Checks out a git branch, then reloads the open files it changed.
+    pub fn checkout_branch(&mut self, branch: &str) {
+        let result = git::checkout(self.get_working_directory(), branch);
+        self.after_git(result.map(|()| format!("Switched to {}", branch)));
+    }
+
+    /// Applies a git stash, then reloads the open files it changed.
+    pub fn apply_stash(&mut self, stash: &str) {
+        let result = git::apply_stash(self.get_working_directory(), stash);
+        self.after_git(result.map(|()| format!("Applied {}", stash)));
+    }
+
+    fn after_git(&mut self, result: Result<String, String>) {
+        match result {
+            Ok(message) => {
+                set_error(message);
+                // Reports the files reloaded in place of the message above
+                self.check_external_changes();
+            }
+            Err(e) => {
+                self.check_external_changes();
+                set_error(e);
+            }
+        }
+    }
+
+    pub fn cycle_window(&mut self, forward: bool) {
+        let windows = self.layout.windows();
+        let Some(index) = windows.iter().position(|id| *id == self.current_window) else {
+            return;
+        };
+        let next = if forward {
+            (index + 1) % windows.len()
+        } else {
+            (index + windows.len() - 1) % windows.len()
+        };
+        self.switch_window(windows[next]);
+    }
+
+    pub fn focus_window(&mut self, id: WindowId) {
+        if self.windows.contains_key(&id) {
+            self.switch_window(id);
+        }
+    }
+
+    /// Opens a shell in a new window below the current one and starts
+    /// sending keys to it.
+    #[cfg(feature = "terminal")]
+    pub fn open_terminal(&mut self) {
+        let (width, height) = self.viewport();
+        let directory = self.get_working_directory().to_path_buf();
+        let pane = match terminal::TerminalPane::spawn(
+            &directory,
+            (height / 2).max(1) as u16,
+            width.max(1) as u16,
+        ) {
+            Ok(pane) => pane,
+            Err(e) => {
+                set_error(format!("Failed to start terminal: {}", e));
+                return;
+            }
+        };
+
+        let id = WindowId(self.next_window_id);
+        self.next_window_id += 1;
+        let mut window = Split::new();
+        window.viewport = self.viewport();
+        self.windows.insert(id, window);
+        self.layout
+            .split(self.current_window, id, SplitDirection::Horizontal);
+        self.switch_window(id);
+        self.terminals.insert(id, pane);
+        self.set_mode(Mode::Terminal);
+    }
+
+    #[cfg(not(feature = "terminal"))]
+    pub fn open_terminal(&mut self) {
+        set_error("pyne was built without terminal support".to_string());
+    }
+
+    #[cfg(feature = "terminal")]
+    pub fn get_current_terminal_mut(&mut self) -> Option<&mut terminal::TerminalPane> {
+        self.get_terminal_mut(self.current_window)
+    }
+
+    #[cfg(feature = "terminal")]
+    pub fn get_terminal(&self, id: WindowId) -> Option<&terminal::TerminalPane> {
+        self.terminals.get(&id)
+    }
+
+    #[cfg(feature = "terminal")]
+    pub fn get_terminal_mut(&mut self, id: WindowId) -> Option<&mut terminal::TerminalPane> {
+        self.terminals.get_mut(&id)
+    }
+
+    #[cfg_attr(not(feature = "terminal"), allow(unused_variables))]
+    pub fn is_terminal(&self, id: WindowId) -> bool {
+        #[cfg(feature = "terminal")]
+        return self.terminals.contains_key(&id);
+        #[cfg(not(feature = "terminal"))]
+        false
+    }
+
+    pub fn is_terminal_window(&self) -> bool {
+        self.is_terminal(self.current_window)
+    }
+
+    pub fn has_terminals(&self) -> bool {
+        #[cfg(feature = "terminal")]
+        return !self.terminals.is_empty();
+        #[cfg(not(feature = "terminal"))]
+        false
+    }
+
+    /// Returns whether any terminal printed output since the last call.
+    pub fn poll_terminals(&mut self) -> bool {
+        #[cfg(feature = "terminal")]
+        {
+            // Every pane's flag is cleared, not just up to the first hit
+            let output = self
+                .terminals
+                .values()
+                .filter(|pane| pane.take_output())
+                .count()
+                > 0;
+            if self.mode == Mode::Terminal
+                && self
+                    .terminals
+                    .get_mut(&self.current_window)
+                    .is_none_or(|pane| pane.has_exited())
+            {
+                self.set_mode(Mode::Normal);
+            }
+            output
+        }
+        #[cfg(not(feature = "terminal"))]
+        false
+    }
+
+    pub fn get_current_window(&self) -> WindowId {
+        self.current_window
+    }
+
+    pub fn window_count(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn window_rects(&self, area: Area) -> Vec<(WindowId, Area)> {
+        self.layout.rects(area)
+    }
+
+    /// Records the area windows are drawn in, which resizing by cells
+    /// works against.
+    pub fn set_layout_area(&mut self, area: Area) {
+        self.layout_area = area;
+    }
+
+    /// Makes the current window `delta` lines taller (`Horizontal`) or
+    /// columns wider (`Vertical`), or smaller for a negative `delta`.
+    pub fn resize_window(&mut self, direction: SplitDirection, delta: i32) {
+        if !self
+            .layout
+            .resize(self.current_window, direction, delta, self.layout_area)
+        {
+            set_error(
+                match direction {
+                    SplitDirection::Horizontal => "No window above or below to resize against",
+                    SplitDirection::Vertical => "No window beside this one to resize against",
+                }
+                .to_string(),
+            );
+        }
+    }
+
+    pub fn equalize_windows(&mut self) {
+        self.layout.equalize();
+    }
+
+    /// Closes every window but the current one.
+    pub fn close_other_windows(&mut self) {
+        let current = self.current_window;
+        self.windows.retain(|id, _| *id == current);
+        #[cfg(feature = "terminal")]
+        self.terminals.retain(|id, _| *id == current);
+        self.layout = LayoutNode::Window(current);
+    }
+
+    /// Returns the directory relative paths are resolved against: the
+    /// current buffer's local directory if one was set with `:lcd`,
+    /// otherwise the global working directory.
+    pub fn get_working_directory(&self) -> &Path {
+        self.get_current_buffer()
+            .and_then(|buffer| buffer.local_directory.as_deref())
+            .unwrap_or(&self.working_directory)
+    }
+
+    pub fn change_directory(&mut self, path: &str, local: bool) -> io::Result<PathBuf> {
+        let target = if path.is_empty() {
+            env::var("HOME")
+                .map(PathBuf::from)
+                .map_err(|_| io::Error::other("HOME is not set"))?
+        } else {
+            self.resolve_path(&PathBuf::from(path))
+        };
+
+        let directory = fs::canonicalize(&target)?;
+        if !directory.is_dir() {
+            return Err(io::Error::other(format!(
+                "Not a directory: {}",
+                directory.display()
+            )));
+        }
+
+        if local {
+            if let Some(buffer) = self.get_current_buffer_mut() {
+                buffer.local_directory = Some(directory.clone());
+            }
+        } else {
+            env::set_current_dir(&directory)?;
+            self.working_directory = directory.clone();
+            self.load_project_config();
+        }
+        Ok(directory)
+    }
+
+    fn apply_autochdir(&mut self) {
+        if !self.options.autochdir {
+            return;
+        }
+
+        let parent = self
+            .get_current_file_path()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .filter(|parent| parent.is_dir());
+        if let Some(parent) = parent {
+            if env::set_current_dir(&parent).is_ok() {
+                self.working_directory = parent;
+            }
+        }
+    }
+
+    /// The index of the files under the working directory, started on
+    /// first use and again whenever the working directory changes.
+    pub fn file_index(&mut self) -> FileIndex {
+        match &self.file_index {
+            Some(index) if index.root() == self.working_directory => index.clone(),
+            _ => {
+                let index = FileIndex::spawn(&self.working_directory, &self.project_exclude);
+                self.file_index = Some(index.clone());
+                index
+            }
+        }
+    }
+
+    pub fn get_starting_directory(&self) -> Option<&PathBuf> {
+        self.starting_directory.as_ref()
+    }
+
+    pub fn set_starting_directory(&mut self, path: PathBuf) {
+        self.starting_directory = Some(path);
+    }
+
+    /// Writes the current buffer, highlighted, to `path` as HTML or ANSI text.
+    pub fn export_buffer(&self, format: ExportFormat, path: &Path) -> io::Result<()> {
+        let buffer = self
+            .get_current_buffer()
+            .ok_or_else(|| io::Error::other("No active buffer"))?;
+        let syntax = buffer.name.path().and_then(|path| Syntax::for_path(path));
+        let title = buffer.name.to_string();
+        fs::write(
+            path,
+            export::export(&buffer.content, syntax, &title, format),
+        )
+    }
+
+    pub fn get_current_file_path(&self) -> Option<PathBuf> {
+        self.get_current_buffer()
+            .and_then(|buffer| buffer.name.path())
+            .cloned()
+    }
+
+    /// Number of lines in the buffer a window shows.
+    pub fn window_line_count(&self, id: WindowId) -> usize {
+        self.windows
+            .get(&id)
+            .and_then(|window| window.buffer)
+            .and_then(|buffer| self.buffers.get(&buffer))
+            .map_or(0, |buffer| buffer.content.len_lines())
+    }
+
+    pub fn get_current_buffer_id(&self) -> Option<BufferId> {
+        self.windows
+            .get(&self.current_window)
+            .and_then(|window| window.buffer)
+    }
+
+    fn set_current_buffer(&mut self, id: BufferId) {
+        self.buffer_clock += 1;
+        if let Some(buffer) = self.buffers.get_mut(&id) {
+            buffer.last_used = self.buffer_clock;
+            if buffer.unloaded {
+                if let Err(e) = buffer.reload() {
+                    set_error(format!("Failed to reload {}: {}", buffer.name, e));
+                }
+            }
+        }
+
+        self.sync_windows();
+        if let Some(window) = self.windows.get_mut(&self.current_window) {
+            if window.buffer != Some(id) {
+                // The buffer left keeps the window's place in it for next time
+                if let Some(left) = window.buffer.and_then(|left| self.buffers.get_mut(&left)) {
+                    left.last_cursor = window.cursor_pos;
+                    left.last_scroll = window.scroll_offset;
+                }
+                if let Some(buffer) = self.buffers.get(&id) {
+                    window.cursor_pos = buffer.last_cursor.min(buffer.content.len_chars());
+                    window.scroll_offset = buffer.last_scroll;
+                }
+                window.desired_column = None;
+                window.buffer = Some(id);
+            }
+        }
+        self.enforce_buffer_budget();
+    }
+
+    fn enforce_buffer_budget(&mut self) {
+        let budget = self.options.bufferbudget.saturating_mul(1024 * 1024);
+        if budget == 0 {
+            return;
+        }
+
+        let mut usage: usize = self.buffers.values().map(Buffer::memory_usage).sum();
+        let visible: Vec<BufferId> = self.windows.values().filter_map(|w| w.buffer).collect();
+        let mut candidates: Vec<(u64, BufferId)> = self
+            .buffers
+            .iter()
+            // Encrypted notes cannot be read back as they are on disk
+            .filter(|(id, buffer)| {
+                buffer.can_unload() && !visible.contains(id) && !self.is_notes_buffer(**id)
+            })
+            .map(|(id, buffer)| (buffer.last_used, *id))
+            .collect();
+        candidates.sort_by_key(|(last_used, _)| *last_used);
+
+        for (_, id) in candidates {
+            if usage <= budget {
+                break;
+            }
+            if let Some(buffer) = self.buffers.get_mut(&id) {
+                usage -= buffer.memory_usage();
+                buffer.unload();
+            }
+        }
+    }
+
+    pub fn get_buffer(&self, id: BufferId) -> Option<&Buffer> {
+        self.buffers.get(&id)
+    }
+
+    pub fn find_buffer_by_path(&self, path: &Path) -> Option<BufferId> {
+        self.buffers
+            .iter()
+            .find(|(_, buffer)| buffer.name.path().map(PathBuf::as_path) == Some(path))
+            .map(|(id, _)| *id)
+    }
+
+    pub fn get_content(&self) -> Option<Rope> {
+        self.get_current_buffer()
+            .map(|buffer| buffer.content.clone())
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        if mode == Mode::Insert && self.refuse_read_only() {
+            return;
+        }
+        // An insert session becomes a single revision once it ends
+        if self.mode == Mode::Insert && mode != Mode::Insert {
+            self.commit_revision();
+        }
+
+        let changed = self.mode != mode;
+        self.mode = mode;
+        if changed {
+            self.fire_hook(HookEvent::ModeChanged);
+        }
+    }
+
+    pub fn get_options(&self) -> &Options {
+        &self.options
+    }
+
+    /// The options in effect for the current buffer: the global ones with
+    /// its `:setlocal` values in their place.
+    pub fn buffer_options(&self) -> Options {
+        self.window_options(self.current_window)
+    }
+
+    fn window_options(&self, id: WindowId) -> Options {
+        match self.window_view(id) {
+            Some((buffer, _)) => self.options.with_local(&buffer.local_options),
+            None => self.options.clone(),
+        }
+    }
+
+    pub fn add_hook(&mut self, event: HookEvent, pattern: &str, action: HookAction) {
+        match Pattern::new(pattern) {
+            Ok(pattern) => self.hooks.add(Hook {
+                event,
+                pattern,
+                action,
+            }),
+            Err(e) => set_error(format!("Invalid pattern {}: {}", pattern, e)),
+        }
+    }
+
+    /// Registers the hooks declared in the user's config file and keeps the
+    /// remaining settings for later use.
+    pub fn apply_config(&mut self, config: Config) {
+        self.add_save_hooks(&config.save_hooks);
+        self.config = config;
+    }
+
+    // Shared by the user and project configs so both register them alike
+    fn add_save_hooks(&mut self, save_hooks: &[SaveHook]) {
+        for hook in save_hooks {
+            if let Some(command) = &hook.before {
+                let action = HookAction::Shell(command.clone());
+                self.add_hook(HookEvent::BufWritePre, &hook.pattern, action);
+            }
+            if let Some(command) = &hook.after {
+                let action = HookAction::Shell(command.clone());
+                self.add_hook(HookEvent::BufWritePost, &hook.pattern, action);
+            }
+        }
+    }
+
+    /// Looks for a `.pyne.toml` from the working directory up and applies
+    /// it if the user trusted it, otherwise asks first.
+    pub fn load_project_config(&mut self) {
+        let project = match ProjectFile::find(&self.working_directory) {
+            Some(Ok(project)) => project,
+            Some(Err(e)) => return set_error(e),
+            None => return,
+        };
+        if self.project_root.as_deref() == Some(project.root()) {
+            return;
+        }
+        if project.is_trusted() {
+            self.apply_project_config(project);
+        } else {
+            self.project_prompt = Some(project);
+            if let Some(message) = self.project_prompt_message() {
+                set_error(message);
+            }
+        }
+    }
+
+    pub fn project_prompt_message(&self) -> Option<String> {
+        self.project_prompt.as_ref().map(|project| {
+            format!(
+                "Untrusted project config {} can run commands. [t]rust and load, [i]gnore",
+                project.path.display()
+            )
+        })
+    }
+
+    /// Trusts the prompted project config, so it also loads without asking
+    /// next time, and applies it.
+    pub fn trust_project(&mut self) -> io::Result<()> {
+        let project = self
+            .project_prompt
+            .take()
+            .ok_or_else(|| io::Error::other("No project config to trust"))?;
+        project.trust()?;
+        self.apply_project_config(project);
+        Ok(())
+    }
+
+    pub fn dismiss_project_prompt(&mut self) {
+        self.project_prompt = None;
+    }
+
+    fn apply_project_config(&mut self, project: ProjectFile) {
+        let root = project.root().to_path_buf();
+        let config = project.config;
+        for option in &config.set {
+            if let Err(e) = self.options.set(option) {
+                set_error(format!("{}: {}", project.path.display(), e));
+            }
+        }
+        self.apply_autochdir();
+        self.add_save_hooks(&config.save_hooks);
+        for task in config.tasks {
+            self.config
+                .tasks
+                .retain(|existing| existing.name != task.name);
+            self.config.tasks.push(task);
+        }
+        for debug in config.debug {
+            self.config
+                .debug
+                .retain(|existing| existing.name != debug.name);
+            self.config.debug.push(debug);
+        }
+        self.config.formatters.extend(config.formatters);
+        if config.exclude != self.project_exclude {
+            self.project_exclude = config.exclude;
+            // Restarts with the new exclusions when next asked for
+            self.file_index = None;
+        }
+        self.project_root = Some(root);
+    }
+
+    /// Shows the notes for the current project, opening them first.
+    pub fn open_notes(&mut self) -> io::Result<()> {
+        let project = self
+            .project_root
+            .clone()
+            .unwrap_or_else(|| self.working_directory.clone());
+        let path =
+            notes::notes_path(&project).ok_or_else(|| io::Error::other("No config directory"))?;
+        if let Some(notes) = self.notes.iter().find(|notes| notes.path == path) {
+            let id = notes.buffer;
+            self.set_current_buffer(id);
+            return Ok(());
+        }
+
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                fs::create_dir_all(path.parent().unwrap_or(Path::new("")))?;
+                Vec::new()
+            }
+            Err(e) => return Err(e),
+        };
+        if notes::is_encrypted(&data) {
+            self.passphrase_request = Some(PassphraseRequest::Unlock(path));
+            return Ok(());
+        }
+        let text = String::from_utf8(data).map_err(|_| io::Error::other("Notes are not UTF-8"))?;
+        self.add_notes_buffer(path, &text, None);
+        Ok(())
+    }
+
+    /// Asks for a passphrase to keep the current notes encrypted with.
+    pub fn encrypt_notes(&mut self) -> io::Result<()> {
+        self.current_notes()?;
+        self.passphrase_request = Some(PassphraseRequest::Encrypt);
+        Ok(())
+    }
+
+    /// Writes the current notes back as plain text.
+    pub fn decrypt_notes(&mut self) -> io::Result<()> {
+        let index = self.current_notes()?;
+        let notes = &mut self.notes[index];
+        if notes.passphrase.take().is_none() {
+            return Err(io::Error::other("The notes are not encrypted"));
+        }
+        let id = notes.buffer;
+        self.save_buffer(id)
+    }
+
+    pub fn take_passphrase_request(&mut self) -> Option<PassphraseRequest> {
+        self.passphrase_request.take()
+    }
+
+    /// Continues what asked for the passphrase.
+    pub fn submit_passphrase(
+        &mut self,
+        request: PassphraseRequest,
+        passphrase: String,
+    ) -> io::Result<()> {
+        match request {
+            PassphraseRequest::Unlock(path) => {
+                let text = notes::decrypt(&fs::read(&path)?, &passphrase)?;
+                self.add_notes_buffer(path, &text, Some(passphrase));
+                Ok(())
+            }
+            PassphraseRequest::Encrypt => {
+                let index = self.current_notes()?;
+                self.notes[index].passphrase = Some(passphrase);
+                let id = self.notes[index].buffer;
+                self.save_buffer(id)
+            }
+        }
+    }
+
+    /// Saves the modified notes, as when quitting.
+    pub fn save_notes(&mut self) -> io::Result<()> {
+        let modified: Vec<BufferId> = self
+            .notes
+            .iter()
+            .map(|notes| notes.buffer)
+            .filter(|id| {
+                self.buffers
+                    .get(id)
+                    .is_some_and(|buffer| buffer.is_modified)
+            })
+            .collect();
+        for id in modified {
+            self.save_buffer(id)?;
+        }
+        Ok(())
+    }
+
+    fn is_notes_buffer(&self, id: BufferId) -> bool {
+        self.notes.iter().any(|notes| notes.buffer == id)
+    }
+
+    fn current_notes(&self) -> io::Result<usize> {
+        let current = self.get_current_buffer_id();
+        self.notes
+            .iter()
+            .position(|notes| Some(notes.buffer) == current)
+            .ok_or_else(|| io::Error::other("Not in a notes buffer; open one with :note"))
+    }
+
+    fn add_notes_buffer(&mut self, path: PathBuf, text: &str, passphrase: Option<String>) {
+        let mut buffer =
+            Buffer::with_content(BufferName::Named(path.clone()), Rope::from_str(text));
+        buffer.record_disk_mtime();
+        let id = self.add_buffer(buffer);
+        self.notes.push(Notes {
+            buffer: id,
+            path,
+            passphrase,
+        });
+        self.set_current_buffer(id);
+    }
+
+    /// The color the config gives a sign group, if any.
+    pub fn sign_color(&self, group: &str) -> Option<&str> {
+        self.config.sign_colors.get(group).map(String::as_str)
+    }
+
+    pub fn place_sign(&mut self, source: &str, line: usize, sign: Sign) {
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer.signs.place(source, line, sign);
+        }
+    }
+
+    pub fn unplace_sign(&mut self, source: &str, line: usize) {
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer.signs.unplace(source, line);
+        }
+    }
+
+    pub fn clear_signs(&mut self, source: &str) {
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer.signs.clear(source);
+        }
+    }
+
+    /// Runs the `signclick` action for a click on the sign column at a row
+    /// of the current window's view, toggling a bookmark or breakpoint.
+    pub fn click_sign(&mut self, row: usize) {
+        let Some((buffer, window)) = self.current_view() else {
+            return;
+        };
+        let line = match self.get_wrapped_rows(self.current_window) {
+            Some(rows) => match rows.get(row) {
+                Some(row) => row.line,
+                None => return,
+            },
+            None => window.scroll_offset.1 + row,
+        };
+        if line >= buffer.content.len_lines() {
+            return;
+        }
+        match self.options.signclick.as_str() {
+            "bookmark" => self.toggle_bookmark(line),
+            "breakpoint" => self.toggle_breakpoint(line),
+            "none" => {}
+            other => set_error(format!("Unknown signclick action: {}", other)),
+        }
+    }
+
+    /// Loads the bookmarks saved for the working directory and shows them
+    /// in the open buffers.
+    pub fn load_bookmarks(&mut self) {
+        match Bookmarks::load(&self.working_directory) {
+            Ok(bookmarks) => self.bookmarks = bookmarks,
+            Err(e) => set_error(e),
+        }
+        let ids: Vec<BufferId> = self.buffers.keys().copied().collect();
+        for id in ids {
+            self.show_bookmarks(id);
+        }
+    }
+
+    fn show_bookmarks(&mut self, id: BufferId) {
+        let Some(buffer) = self.buffers.get_mut(&id) else {
+            return;
+        };
+        let Some(path) = buffer.name.path() else {
+            return;
+        };
+        buffer.signs.clear(bookmarks::SIGN_SOURCE);
+        for line in self.bookmarks.lines(path) {
+            buffer
+                .signs
+                .place(bookmarks::SIGN_SOURCE, line, bookmarks::sign());
+        }
+    }
+
+    /// Adds or removes a bookmark on a line of the current buffer and saves
+    /// the project's bookmarks.
+    pub fn toggle_bookmark(&mut self, line: usize) {
+        let Some(buffer) = self.get_current_buffer() else {
+            return;
+        };
+        let Some(path) = buffer.name.path().cloned() else {
+            set_error("Save the buffer to a file to bookmark it".to_string());
+            return;
+        };
+        self.bookmarks.toggle(&path, line);
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer
+                .signs
+                .toggle(bookmarks::SIGN_SOURCE, line, bookmarks::sign());
+        }
+        if let Err(e) = self.bookmarks.save() {
+            set_error(e);
+        }
+    }
+
+    /// Toggles a bookmark on the cursor line.
+    pub fn toggle_bookmark_at_cursor(&mut self) {
+        if let Some(line) = self.get_cursor_screen_position().map(|(line, _)| line) {
+            self.toggle_bookmark(line);
+        }
+    }
+
+    /// The words `todokeywords` highlights and `:todo` lists.
+    pub fn todo_keywords(&self) -> Vec<String> {
+        todo::parse_keywords(&self.options.todokeywords)
+    }
+
+    fn language_server(&mut self) -> Result<(PathBuf, &mut LanguageServer), String> {
+        let path = self
+            .get_current_buffer()
+            .and_then(|buffer| buffer.name.path().cloned())
+            .ok_or_else(|| "The buffer has no file for a language server".to_string())?;
+        let command = self.language_server_command(&path).ok_or_else(|| {
+            format!(
+                "No language server for .{} files; add one under [language_servers] in the config",
+                path.extension().unwrap_or_default().to_string_lossy()
+            )
+        })?;
+        let root = self.language_server_root();
+
+        // Start it again if it exited
+        if let Some(server) = self.language_servers.get_mut(&command) {
+            if !server.is_running() {
+                self.language_servers.remove(&command);
+            }
+        }
+        if let Entry::Vacant(entry) = self.language_servers.entry(command.clone()) {
+            let server = LanguageServer::start(entry.key(), &root)?;
+            entry.insert(server);
+            self.failed_language_servers.remove(&command);
+        }
+        self.sync_language_servers()?;
+        let server = self
+            .language_servers
+            .get_mut(&command)
+            .ok_or_else(|| format!("{} is not running", command))?;
+        Ok((path, server))
+    }
+
+    /// Formats `lines` with the formatter configured for the file's
+    /// extension, or else the language server's range formatting.
+    pub fn format_lines(&mut self, (first, last): (usize, usize)) -> Result<(), String> {
+        if self.refuse_read_only() {
+            return Ok(());
+        }
+        let path = self.get_current_file_path();
+        let extension = path
+            .as_ref()
+            .and_then(|path| path.extension()?.to_str())
+            .unwrap_or("");
+        let formatter = self.config.formatters.get(extension).cloned();
+        if formatter.is_none()
+            && path
+                .as_ref()
+                .and_then(|path| self.language_server_command(path))
+                .is_none()
+        {
+            return Err(format!(
+                "No formatter for .{} files; add one under [formatters] in the config",
+                extension
+            ));
+        }
+        let (cursor_line, cursor_column, line_count) = {
+            let (buffer, window) = self
+                .current_view()
+                .ok_or_else(|| "No active buffer".to_string())?;
+            let line = buffer.content.char_to_line(window.cursor_pos);
+            let column = window.cursor_pos - buffer.content.line_to_char(line);
+            (line, column, buffer.content.len_lines())
+        };
+        let options = self.buffer_options();
+        self.commit_revision();
+
+        if let Some(command) = formatter {
+            let command = match &path {
+                Some(path) => command.replace("{file}", &hooks::shell_quote(path)),
+                None => command,
+            };
+            let text = self
+                .get_lines((first, last))
+                .ok_or_else(|| "No active buffer".to_string())?;
+            let formatted = format::run_formatter(&command, &text, &self.working_directory)?;
+            let formatted =
+                format::keep_indent(&text, &formatted, options.shiftwidth, options.expandtab);
+            self.replace_lines((first, last), &formatted);
+        } else {
+            let (start, end, whole) = {
+                let buffer = self
+                    .get_current_buffer()
+                    .ok_or_else(|| "No active buffer".to_string())?;
+                let end = if last + 1 < buffer.content.len_lines() {
+                    Position {
+                        line: last + 1,
+                        character: 0,
+                    }
+                } else {
+                    Position::from_char(&buffer.content, buffer.content.len_chars())
+                };
+                let whole = first == 0 && last >= Self::last_line_index(buffer);
+                (
+                    Position {
+                        line: first,
+                        character: 0,
+                    },
+                    end,
+                    whole,
+                )
+            };
+            let (path, server) = self.language_server()?;
+            let mut params = json!({
+                "textDocument": { "uri": lsp::path_to_uri(&path) },
+                "options": { "tabSize": options.shiftwidth, "insertSpaces": options.expandtab },
+            });
+            let result = if whole && server.supports("documentFormattingProvider") {
+                server.request("textDocument/formatting", params)?
+            } else if server.supports("documentRangeFormattingProvider") {
+                params["range"] = json!({ "start": start.to_json(), "end": end.to_json() });
+                server.request("textDocument/rangeFormatting", params)?
+            } else {
+                return Err(format!("{} cannot format a range", server.command));
+            };
+            let mut edits = lsp::parse_text_edits(&result);
+            edits.sort_by_key(|edit| edit.start);
+            if let Some(buffer) = self.get_current_buffer_mut() {
+                if !edits.is_empty() {
+                    lsp::apply_text_edits(buffer, &edits);
+                    buffer.mark_modified();
+                }
+            }
+        }
+
+        // Back to the cursor's line, following the lines the formatting
+        // added or removed when it was below them
+        if let Some((buffer, window)) = self.current_view_mut() {
+            let added = buffer.content.len_lines() as isize - line_count as isize;
+            let line = if cursor_line < first {
+                cursor_line
+            } else if cursor_line > last {
+                (cursor_line as isize + added).max(0) as usize
+            } else {
+                cursor_line.min((last as isize + added).max(first as isize) as usize)
+            };
+            let line = line.min(Self::last_line_index(buffer));
+            let length = buffer
+                .content
+                .line(line)
+                .to_string()
+                .trim_end_matches(['\n', '\r'])
+                .chars()
+                .count();
+            window.cursor_pos = buffer.content.line_to_char(line) + cursor_column.min(length);
+            buffer.selection_start = None;
+        }
+        self.commit_revision();
+        self.scroll();
+        Ok(())
+    }
+
+    fn language_server_command(&self, path: &Path) -> Option<String> {
+        let extension = path.extension()?.to_str()?;
+        self.config.language_servers.get(extension).cloned()
+    }
+
+    fn language_server_root(&self) -> PathBuf {
+        self.project_root
+            .clone()
+            .unwrap_or_else(|| self.working_directory.clone())
+    }
+
+    fn sync_language_servers(&mut self) -> Result<(), String> {
+        for buffer in self.buffers.values() {
+            // An unloaded buffer is clean, so the server reads it from disk
+            let Some(file) = buffer.name.path().filter(|_| !buffer.unloaded) else {
+                continue;
+            };
+            let server = self
+                .language_server_command(file)
+                .and_then(|command| self.language_servers.get_mut(&command));
+            if let Some(server) = server {
+                server.sync(file, &buffer.content, buffer.version)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_language_servers(&self) -> bool {
+        !self.language_servers.is_empty()
+    }
+
+    /// Starts the language server for the current file if one is configured
+    /// and not yet running, keeps the servers up to date with the buffers
+    /// and shows the diagnostics they published as signs.
+    pub fn poll_language_servers(&mut self) -> bool {
+        let command = self
+            .get_current_file_path()
+            .and_then(|path| self.language_server_command(&path));
+        if let Some(command) = command {
+            if !self.language_servers.contains_key(&command)
+                && !self.failed_language_servers.contains(&command)
+            {
+                match LanguageServer::start(&command, &self.language_server_root()) {
+                    Ok(server) => {
+                        self.language_servers.insert(command, server);
+                    }
+                    Err(_) => {
+                        self.failed_language_servers.insert(command);
+                    }
+                }
+            }
+        }
+        // A server that stopped reading is dropped below
+        let _ = self.sync_language_servers();
+
+        let mut published = HashMap::new();
+        let mut exited = Vec::new();
+        self.language_servers.retain(|command, server| {
+            let running = server.poll().is_ok();
+            published.extend(server.take_diagnostics());
+            if !running {
+                exited.push(command.clone());
+            }
+            running
+        });
+        self.failed_language_servers.extend(exited);
+
+        let arrived = !published.is_empty();
+        for (path, diagnostics) in published {
+            let id = self.find_buffer_by_path(&path);
+            if diagnostics.is_empty() {
+                self.diagnostics.remove(&path);
+            } else {
+                self.diagnostics.insert(path, diagnostics);
+            }
+            if let Some(id) = id {
+                self.show_diagnostics(id);
+            }
+        }
+        if arrived {
+            self.update_diagnostics_panel();
+        }
+        arrived
+    }
+
+    fn show_diagnostics(&mut self, id: BufferId) {
+        let Some(buffer) = self.buffers.get_mut(&id) else {
+            return;
+        };
+        buffer.signs.clear(lsp::SIGN_SOURCE);
+        let Some(diagnostics) = buffer
+            .name
+            .path()
+            .and_then(|path| self.diagnostics.get(path))
+        else {
+            return;
+        };
+        // The most severe diagnostic of a line comes first
+        let mut previous_line = None;
+        for diagnostic in diagnostics {
+            if previous_line != Some(diagnostic.start.line) {
+                let sign = diagnostic.severity.sign();
+                buffer
+                    .signs
+                    .place(lsp::SIGN_SOURCE, diagnostic.start.line, sign);
+                previous_line = Some(diagnostic.start.line);
+            }
+        }
+    }
+
+    /// The number of diagnostics of each severity in all files, errors
+    /// first.
+    pub fn diagnostic_counts(&self) -> [usize; 4] {
+        diagnostics_panel::count_by_severity(&self.diagnostics)
+    }
+
+    /// Opens the panel listing the diagnostics of every file in a window
+    /// below the current one, or focuses and refilters the open panel.
+    pub fn show_diagnostics_panel(&mut self, args: &str) {
+        let filter = diagnostics_panel::parse_filter(args);
+        let existing = self
+            .diagnostics_panel
+            .take()
+            .filter(|(_, id, _)| self.buffers.contains_key(id));
+        let (panel, id, origin) = match existing {
+            Some((mut panel, id, origin)) => {
+                panel.filter = filter;
+                (panel, id, Some(origin))
+            }
+            None => {
+                let name = BufferName::Unnamed(self.next_scratch_id);
+                self.next_scratch_id += 1;
+                let mut buffer = Buffer::new(name);
+                buffer.read_only = true;
+                let panel = DiagnosticsPanel::new(DiagnosticSort::Severity, filter);
+                (panel, self.add_buffer(buffer), None)
+            }
+        };
+        // Reopening it from the panel keeps jumping where it did
+        let origin = match origin {
+            Some(origin) if self.get_current_buffer_id() == Some(id) => origin,
+            _ => self.current_window,
+        };
+        self.diagnostics_panel = Some((panel, id, origin));
+        self.show_panel(id);
+        self.update_diagnostics_panel();
+    }
+
+    fn show_panel(&mut self, id: BufferId) {
+        let shown = self
+            .layout
+            .windows()
+            .into_iter()
+            .find(|window| self.windows.get(window).and_then(|split| split.buffer) == Some(id));
+        match shown {
+            Some(window) => self.switch_window(window),
+            None => {
+                self.split_window(SplitDirection::Horizontal);
+                self.set_current_buffer(id);
+            }
+        }
+    }
+
+    fn leave_panel(&mut self, id: BufferId, origin: WindowId) {
+        let shows_panel = |window: &WindowId| {
+            self.windows
+                .get(window)
+                .is_some_and(|split| split.buffer == Some(id))
+        };
+        let target = Some(origin)
+            .filter(|origin| self.windows.contains_key(origin) && !shows_panel(origin))
+            .or_else(|| {
+                self.layout
+                    .windows()
+                    .into_iter()
+                    .find(|window| !shows_panel(window))
+            });
+        if let Some(window) = target {
+            self.switch_window(window);
+        }
+    }
+
+    fn update_diagnostics_panel(&mut self) {
+        let directory = self.get_working_directory().to_path_buf();
+        let Some((panel, id, _)) = &mut self.diagnostics_panel else {
+            return;
+        };
+        let Some(buffer) = self.buffers.get_mut(id) else {
+            self.diagnostics_panel = None;
+            return;
+        };
+        panel.refresh(&self.diagnostics);
+        buffer.set_content(Rope::from_str(&panel.render(&directory)));
+        buffer.mark_modified();
+        buffer.is_modified = false;
+        self.sync_windows();
+    }
+
+    pub fn is_diagnostics_panel(&self) -> bool {
+        self.diagnostics_panel
+            .as_ref()
+            .is_some_and(|(_, id, _)| self.get_current_buffer_id() == Some(*id))
+    }
+
+    /// Switches the diagnostics panel between sorting by severity and by
+    /// file.
+    pub fn toggle_diagnostics_sort(&mut self) {
+        if let Some((panel, _, _)) = &mut self.diagnostics_panel {
+            panel.sort = match panel.sort {
+                DiagnosticSort::Severity => DiagnosticSort::File,
+                DiagnosticSort::File => DiagnosticSort::Severity,
+            };
+            self.update_diagnostics_panel();
+        }
+    }
+
+    /// Shows only diagnostics as severe as `severity` in the panel.
+    pub fn filter_diagnostics_panel(&mut self, severity: Severity) {
+        if let Some((panel, _, _)) = &mut self.diagnostics_panel {
+            panel.filter.severity = severity;
+            self.update_diagnostics_panel();
+        }
+    }
+
+    /// Opens the diagnostic on the cursor's line of the panel in the
+    /// window the panel was opened from.
+    pub fn jump_to_diagnostic(&mut self) {
+        let Some((panel, id, origin)) = &self.diagnostics_panel else {
+            return;
+        };
+        let Some((line, _)) = self.get_cursor_screen_position() else {
+            return;
+        };
+        let Some(entry) = panel.entry_at(line) else {
+            return;
+        };
+        let (path, start, id, origin) = (entry.path.clone(), entry.start, *id, *origin);
+        self.leave_panel(id, origin);
+        if let Err(e) = self.open_file(&path) {
+            set_error(format!("Failed to open {}: {}", path.display(), e));
+            return;
+        }
+        if let Some((buffer, window)) = self.current_view_mut() {
+            window.cursor_pos = start.to_char(&buffer.content);
+        }
+        self.scroll();
+    }
+
+    /// Handles `:git log %`, opening a panel below the current window that
+    /// lists the commits that changed the current file.
+    pub fn show_file_history(&mut self) {
+        let Some(path) = self
+            .get_current_buffer()
+            .and_then(|buffer| buffer.name.path().cloned())
+        else {
+            set_error("The buffer has no file".to_string());
+            return;
+        };
+        let commits = match git::log(&path) {
+            Ok(commits) if commits.is_empty() => {
+                set_error(format!("No commits change {}", path.display()));
+                return;
+            }
+            Ok(commits) => commits,
+            Err(e) => {
+                set_error(e);
+                return;
+            }
+        };
+        let history = FileHistory { path, commits };
+        let existing = self
+            .file_history
+            .take()
+            .map(|(_, id, _)| id)
+            .filter(|id| self.buffers.contains_key(id));
+        let id = existing.unwrap_or_else(|| {
+            let name = BufferName::Unnamed(self.next_scratch_id);
+            self.next_scratch_id += 1;
+            let mut buffer = Buffer::new(name);
+            buffer.read_only = true;
+            self.add_buffer(buffer)
+        });
+        let origin = self.current_window;
+        let directory = self.get_working_directory().to_path_buf();
+        if let Some(buffer) = self.buffers.get_mut(&id) {
+            buffer.set_content(Rope::from_str(&history.render(&directory)));
+            buffer.mark_modified();
+            buffer.is_modified = false;
+        }
+        self.file_history = Some((history, id, origin));
+        self.show_panel(id);
+        if let Some((_, window)) = self.current_view_mut() {
+            window.cursor_pos = 0;
+        }
+        self.scroll();
+    }
+
+    pub fn is_file_history_panel(&self) -> bool {
+        self.file_history
+            .as_ref()
+            .is_some_and(|(_, id, _)| self.get_current_buffer_id() == Some(*id))
+    }
+
+    /// Opens the file as it was at the commit on the cursor's line of the
+    /// `:git log %` panel in a read-only buffer, in the window the panel was
+    /// opened from.
+    pub fn open_file_revision(&mut self, diff: bool) {
+        let Some((history, id, origin)) = &self.file_history else {
+            return;
+        };
+        let Some((line, _)) = self.get_cursor_screen_position() else {
+            return;
+        };
+        let Some(commit) = history.commit_at(line) else {
+            return;
+        };
+        let path = history.path.clone();
+        let hash = commit.hash.clone();
+        let directory = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let text = match git::show(&directory, &hash, &commit.path) {
+            Ok(text) => text,
+            Err(e) => {
+                set_error(e);
+                return;
+            }
+        };
+        let (id, origin) = (*id, *origin);
+        self.leave_panel(id, origin);
+
+        let name = BufferName::Revision(path.clone(), hash.clone());
+        let existing = self
+            .buffers
+            .iter()
+            .find(|(_, buffer)| buffer.name == name)
+            .map(|(id, _)| *id);
+        let revision = existing.unwrap_or_else(|| {
+            let mut buffer = Buffer::with_content(name, Rope::from_str(&text));
+            buffer.read_only = true;
+            self.add_buffer(buffer)
+        });
+        self.set_current_buffer(revision);
+        if !diff {
+            set_error(format!(
+                "{} at {} (read-only); d on its commit diffs it with the working copy",
+                path.display(),
+                hash
+            ));
+            return;
+        }
+        let result = fs::read_to_string(&path).and_then(|working| {
+            let (removed, added) = dir_diff::line_diff(&text, &working);
+            self.show_diff(&removed, &added, &path)
+        });
+        if let Err(e) = result {
+            set_error(format!("Failed to open {}: {}", path.display(), e));
+        }
+    }
+
+    pub fn is_debugging(&self) -> bool {
+        self.debugger.is_some()
+    }
+
+    /// Starts the `[[debug]]` setup called `name`, or the first one, and
+    /// shows the debug panel below the current window.
+    pub fn start_debugging(&mut self, name: &str) {
+        if self.debugger.is_some() {
+            set_error("Already debugging; :debugstop ends it".to_string());
+            return;
+        }
+        let config = if name.is_empty() {
+            self.config.debug.first()
+        } else {
+            self.config.debug.iter().find(|config| config.name == name)
+        };
+        let Some(config) = config else {
+            let names: Vec<&str> = self
+                .config
+                .debug
+                .iter()
+                .map(|config| config.name.as_str())
+                .collect();
+            set_error(if names.is_empty() {
+                "No debug setups; add [[debug]] to config.toml".to_string()
+            } else {
+                format!(
+                    "Unknown debug setup: {}. Setups: {}",
+                    name,
+                    names.join(", ")
+                )
+            });
+            return;
+        };
+        let directory = self.get_working_directory().to_path_buf();
+        match DebugAdapter::start(config, &directory) {
+            Ok(adapter) => {
+                self.debug_state = DebugState::default();
+                self.debug_state
+                    .print_line(&format!("Started {}", adapter.name));
+                self.debugger = Some(adapter);
+                self.show_debug_panel();
+            }
+            Err(e) => set_error(e),
+        }
+    }
+
+    /// Ends the debug session, stopping the program.
+    pub fn stop_debugging(&mut self) {
+        if self.debugger.is_none() {
+            set_error("Not debugging".to_string());
+            return;
+        }
+        self.end_debugging("Stopped");
+    }
+
+    fn end_debugging(&mut self, message: &str) {
+        self.debugger = None;
+        self.debug_state.thread_id = None;
+        self.debug_state.print_line(message);
+        for buffer in self.buffers.values_mut() {
+            buffer.signs.clear(dap::STOPPED_SOURCE);
+        }
+        self.update_debug_panel();
+    }
+
+    /// Continues or steps the stopped program.
+    pub fn debug_step(&mut self, step: Step) {
+        let Some(adapter) = &mut self.debugger else {
+            if step == Step::Continue {
+                self.start_debugging("");
+            } else {
+                set_error("Not debugging".to_string());
+            }
+            return;
+        };
+        let Some(thread_id) = self.debug_state.thread_id else {
+            set_error("The program is running".to_string());
+            return;
+        };
+        if let Err(e) = adapter.step(step, thread_id) {
+            set_error(e);
+            return;
+        }
+        self.resumed();
+    }
+
+    pub fn pause_debugging(&mut self) {
+        let Some(adapter) = &mut self.debugger else {
+            set_error("Not debugging".to_string());
+            return;
+        };
+        if let Err(e) = adapter.pause() {
+            set_error(e);
+        }
+    }
+
+    fn resumed(&mut self) {
+        self.debug_state.thread_id = None;
+        for buffer in self.buffers.values_mut() {
+            buffer.signs.clear(dap::STOPPED_SOURCE);
+        }
+        self.update_debug_panel();
+    }
+
+    /// Adds or removes a breakpoint on a line of the current buffer,
+    /// updating the running session's.
+    pub fn toggle_breakpoint(&mut self, line: usize) {
+        let Some(buffer) = self.get_current_buffer_mut() else {
+            return;
+        };
+        buffer
+            .signs
+            .toggle(dap::BREAKPOINT_SOURCE, line, dap::breakpoint_sign());
+        if let Some(id) = self.get_current_buffer_id() {
+            self.send_breakpoints(id);
+        }
+    }
+
+    pub fn toggle_breakpoint_at_cursor(&mut self) {
+        if let Some(line) = self.get_cursor_screen_position().map(|(line, _)| line) {
+            self.toggle_breakpoint(line);
+        }
+    }
+
+    fn send_breakpoints(&mut self, id: BufferId) {
+        let (Some(adapter), Some(buffer)) = (&mut self.debugger, self.buffers.get(&id)) else {
+            return;
+        };
+        let Some(path) = buffer.name.path() else {
+            return;
+        };
+        let lines = buffer.signs.lines(dap::BREAKPOINT_SOURCE);
+        if let Err(e) = adapter.set_breakpoints(path, &lines) {
+            set_error(e);
+        }
+    }
+
+    /// Handles what the debug adapter reported: output goes to the console
+    /// and a stop shows the stack and jumps to where it stopped.
+    pub fn poll_debugger(&mut self) -> bool {
+        let Some(adapter) = &mut self.debugger else {
+            return false;
+        };
+        let events = match adapter.poll() {
+            Ok(events) => events,
+            Err(e) => {
+                self.end_debugging(&e);
+                return true;
+            }
+        };
+        if events.is_empty() {
+            return false;
+        }
+        for event in events {
+            match event {
+                DebugEvent::Initialized => {
+                    let ids: Vec<BufferId> = self.buffers.keys().copied().collect();
+                    for id in ids {
+                        if self.buffers.get(&id).is_some_and(|buffer| {
+                            !buffer.signs.lines(dap::BREAKPOINT_SOURCE).is_empty()
+                        }) {
+                            self.send_breakpoints(id);
+                        }
+                    }
+                    if let Some(Err(e)) =
+                        self.debugger.as_mut().map(DebugAdapter::configuration_done)
+                    {
+                        set_error(e);
+                    }
+                }
+                DebugEvent::Stopped { thread_id, reason } => {
+                    let Some(adapter) = &mut self.debugger else {
+                        break;
+                    };
+                    match adapter.stack_trace(thread_id) {
+                        Ok(frames) => {
+                            self.debug_state.thread_id = Some(thread_id);
+                            self.debug_state.reason = reason;
+                            self.debug_state.frames = frames;
+                            self.select_frame(0);
+                        }
+                        Err(e) => self.debug_state.print_line(&e),
+                    }
+                }
+                DebugEvent::Continued => self.resumed(),
+                DebugEvent::Output(output) => self.debug_state.print(&output),
+                DebugEvent::Failed(e) => {
+                    self.debug_state.print_line(&e);
+                    set_error(e);
+                }
+                DebugEvent::Terminated => {
+                    self.end_debugging("The program ended");
+                    break;
+                }
+            }
+        }
+        self.update_debug_panel();
+        true
+    }
+
+    /// Shows the variables of a frame of the stopped thread and its line
+    /// in a window other than the debug panel.
+    pub fn select_frame(&mut self, index: usize) {
+        let Some(adapter) = &mut self.debugger else {
+            return;
+        };
+        let Some(frame) = self.debug_state.frames.get(index) else {
+            return;
+        };
+        self.debug_state.frame = index;
+        self.debug_state.variables = adapter.variables(frame.id).unwrap_or_else(|e| {
+            set_error(e);
+            Vec::new()
+        });
+        let location = frame
+            .path
+            .clone()
+            .map(|path| (path, frame.line, frame.column));
+        self.update_debug_panel();
+
+        for buffer in self.buffers.values_mut() {
+            buffer.signs.clear(dap::STOPPED_SOURCE);
+        }
+        let Some((path, line, column)) = location else {
+            return;
+        };
+        if self.is_debug_panel() {
+            let panel = self.debug_panel;
+            let other =
+                self.layout.windows().into_iter().find(|window| {
+                    self.windows.get(window).and_then(|split| split.buffer) != panel
+                });
+            if let Some(window) = other {
+                self.switch_window(window);
+            }
+        }
+        if let Err(e) = self.open_file(&path) {
+            set_error(format!("Failed to open {}: {}", path.display(), e));
+            return;
+        }
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer
+                .signs
+                .place(dap::STOPPED_SOURCE, line, dap::stopped_sign());
+            let line = line.min(buffer.content.len_lines().saturating_sub(1));
+            let start = buffer.content.line_to_char(line);
+            let length = buffer.content.line(line).len_chars();
+            window.cursor_pos = start + column.min(length.saturating_sub(1));
+        }
+        self.scroll();
+    }
+
+    /// Selects the frame on the cursor's line of the debug panel.
+    pub fn select_frame_at_cursor(&mut self) {
+        let line = self.get_cursor_screen_position().map(|(line, _)| line);
+        if let Some(index) = line.and_then(|line| self.debug_state.frame_at(line)) {
+            self.select_frame(index);
+        }
+    }
+
+    /// Evaluates an expression in the selected frame, printing it and its
+    /// value to the debug console.
+    pub fn evaluate_in_debugger(&mut self, expression: &str) {
+        let Some(adapter) = &mut self.debugger else {
+            set_error("Not debugging".to_string());
+            return;
+        };
+        let frame_id = self.debug_state.selected_frame().map(|frame| frame.id);
+        let result = adapter.evaluate(expression, frame_id).unwrap_or_else(|e| e);
+        self.debug_state.print_line(&format!("> {}", expression));
+        self.debug_state.print_line(&result);
+        self.update_debug_panel();
+    }
+
+    /// Shows the debug panel in a window below the current one without
+    /// taking focus, unless a window shows it already.
+    pub fn show_debug_panel(&mut self) {
+        let id = match self.debug_panel.filter(|id| self.buffers.contains_key(id)) {
+            Some(id) => id,
+            None => {
+                let name = BufferName::Unnamed(self.next_scratch_id);
+                self.next_scratch_id += 1;
+                let mut buffer = Buffer::new(name);
+                buffer.read_only = true;
+                self.add_buffer(buffer)
+            }
+        };
+        self.debug_panel = Some(id);
+        if !self
+            .windows
+            .values()
+            .any(|window| window.buffer == Some(id))
+        {
+            let current = self.current_window;
+            self.split_window(SplitDirection::Horizontal);
+            self.set_current_buffer(id);
+            self.switch_window(current);
+        }
+        self.update_debug_panel();
+    }
+
+    fn update_debug_panel(&mut self) {
+        let directory = self.get_working_directory().to_path_buf();
+        let Some(buffer) = self.debug_panel.and_then(|id| self.buffers.get_mut(&id)) else {
+            return;
+        };
+        buffer.set_content(Rope::from_str(&self.debug_state.render(&directory)));
+        buffer.mark_modified();
+        buffer.is_modified = false;
+        self.sync_windows();
+    }
+
+    pub fn is_debug_panel(&self) -> bool {
+        self.debug_panel.is_some() && self.get_current_buffer_id() == self.debug_panel
+    }
+
+    /// The first diagnostic of each line returned by `get_visible_content`,
+    /// with the `virtualtext` option.
+    pub fn get_visible_diagnostics(&self, id: WindowId) -> Vec<Option<&Diagnostic>> {
+        let Some((buffer, window)) = self.window_view(id) else {
+            return Vec::new();
+        };
+        if !self.window_options(id).virtualtext {
+            return Vec::new();
+        }
+        let Some(diagnostics) = buffer
+            .name
+            .path()
+            .and_then(|path| self.diagnostics.get(path))
+        else {
+            return Vec::new();
+        };
+        let first_on = |line: usize| {
+            let first = diagnostics.partition_point(|diagnostic| diagnostic.start.line < line);
+            diagnostics
+                .get(first)
+                .filter(|diagnostic| diagnostic.start.line == line)
+        };
+        // A wrapped line shows its diagnostic after its last row
+        if let Some(rows) = self.get_wrapped_rows(id) {
+            return rows
+                .iter()
+                .map(|row| first_on(row.line).filter(|_| row.last))
+                .collect();
+        }
+        Self::visible_lines(buffer, window).map(first_on).collect()
+    }
+
+    fn cursor_lsp_position(&self) -> Result<Position, String> {
+        self.current_view()
+            .map(|(buffer, window)| Position::from_char(&buffer.content, window.cursor_pos))
+            .ok_or_else(|| "No active buffer".to_string())
+    }
+
+    /// Renames the symbol under the cursor everywhere the language server
+    /// finds it, as `:rename` does.
+    pub fn rename_symbol(&mut self, new_name: &str) -> Result<(), String> {
+        if self.refuse_read_only() {
+            return Ok(());
+        }
+        let position = self.cursor_lsp_position()?;
+        let (path, server) = self.language_server()?;
+        if !server.supports("renameProvider") {
+            return Err(format!("{} cannot rename", server.command));
+        }
+        let edit = server.request(
+            "textDocument/rename",
+            json!({
+                "textDocument": { "uri": lsp::path_to_uri(&path) },
+                "position": position.to_json(),
+                "newName": new_name,
+            }),
+        )?;
+        if edit.is_null() {
+            return Err("Nothing to rename at the cursor".to_string());
+        }
+        self.apply_workspace_edit(&edit)
+    }
+
+    /// Asks the language server for code actions on the cursor's line and
+    /// offers them in a menu, as `:codeaction` does.
+    pub fn show_code_actions(&mut self) -> Result<(), String> {
+        let (start, end) = {
+            let (buffer, window) = self
+                .current_view()
+                .ok_or_else(|| "No active buffer".to_string())?;
+            let line = buffer.content.char_to_line(window.cursor_pos);
+            let text = buffer.content.line(line).to_string();
+            let length = text.trim_end_matches(['\n', '\r']).chars().count();
+            let end = buffer.content.line_to_char(line) + length;
+            (
+                Position { line, character: 0 },
+                Position::from_char(&buffer.content, end),
+            )
+        };
+        let (path, server) = self.language_server()?;
+        if !server.supports("codeActionProvider") {
+            return Err(format!("{} offers no code actions", server.command));
+        }
+        let result = server.request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": lsp::path_to_uri(&path) },
+                "range": { "start": start.to_json(), "end": end.to_json() },
+                "context": { "diagnostics": [], "triggerKind": 1 },
+            }),
+        )?;
+        let actions = CodeAction::parse_all(&result);
+        if actions.is_empty() {
+            return Err("No code actions on this line".to_string());
+        }
+        self.menu = Some(Menu {
+            title: format!("Code actions ({})", actions.len()),
+            items: actions
+                .iter()
+                .map(|action| MenuItem {
+                    label: action.title.clone(),
+                    value: action.kind().to_string(),
+                })
+                .collect(),
+            action: MenuAction::CodeAction(actions),
+        });
+        Ok(())
+    }
+
+    fn run_code_action(&mut self, action: &CodeAction) -> Result<(), String> {
+        let (_, server) = self.language_server()?;
+        let mut resolved = action.action.clone();
+        if resolved.get("edit").is_none()
+            && action.command().is_none()
+            && server.resolves_code_actions()
+        {
+            resolved = server.request("codeAction/resolve", resolved)?;
+        }
+        let resolved = CodeAction {
+            title: action.title.clone(),
+            action: resolved,
+        };
+
+        if let Some(edit) = resolved.action.get("edit") {
+            self.apply_workspace_edit(edit)?;
+        }
+        let Some(command) = resolved.command() else {
+            return Ok(());
+        };
+        let mut params = json!({ "command": command["command"] });
+        if let Some(arguments) = command.get("arguments") {
+            params["arguments"] = arguments.clone();
+        }
+        let (_, server) = self.language_server()?;
+        server.request("workspace/executeCommand", params)?;
+        let edits = server.take_applied_edits();
+        if edits.is_empty() && resolved.action.get("edit").is_none() {
+            set_error(format!("Ran {}", action.title));
+        }
+        for edit in edits {
+            self.apply_workspace_edit(&edit)?;
+        }
+        Ok(())
+    }
+
+    fn apply_workspace_edit(&mut self, edit: &serde_json::Value) -> Result<(), String> {
+        let (files, skipped) = lsp::parse_workspace_edit(edit);
+        let mut changes: Vec<(PathBuf, usize, String)> = Vec::new();
+        let mut counts = Vec::new();
+        for mut file in files {
+            let id = match self.find_buffer_by_path(&file.path) {
+                Some(id) => id,
+                None => {
+                    let content = fs::read_to_string(&file.path)
+                        .map_err(|e| format!("Failed to read {}: {}", file.path.display(), e))?;
+                    let name = BufferName::Named(file.path.clone());
+                    let mut buffer = Buffer::with_content(name, Rope::from_str(&content));
+                    buffer.record_disk_mtime();
+                    self.add_buffer(buffer)
+                }
+            };
+            let Some(buffer) = self.buffers.get_mut(&id) else {
+                continue;
+            };
+            if buffer.unloaded {
+                buffer
+                    .reload()
+                    .map_err(|e| format!("Failed to reload {}: {}", buffer.name, e))?;
+            }
+            let current = (self.windows.get_mut(&self.current_window))
+                .filter(|window| window.buffer == Some(id));
+            let cursor = current
+                .as_ref()
+                .map_or(buffer.last_cursor, |window| window.cursor_pos);
+            buffer.history.commit(&buffer.content, cursor);
+            buffer.undo.close(cursor);
+
+            // Where each edit lands once the ones above it have changed
+            // the line count
+            file.edits.sort_by_key(|edit| edit.start);
+            let mut shift = 0isize;
+            let mut lines = Vec::new();
+            for edit in &file.edits {
+                lines.push((edit.start.line as isize + shift).max(0) as usize);
+                shift += edit.new_text.matches('\n').count() as isize
+                    - (edit.end.line - edit.start.line.min(edit.end.line)) as isize;
+            }
+
+            let first_edit = buffer.edits.len();
+            lsp::apply_text_edits(buffer, &file.edits);
+            // The current window follows the edits here, the others when
+            // they catch up
+            let cursor = buffer.edits[first_edit..]
+                .iter()
+                .fold(cursor, |cursor, edit| edit.map(cursor));
+            if let Some(window) = current {
+                window.cursor_pos = cursor;
+            }
+            buffer.mark_modified();
+            buffer.history.commit(&buffer.content, cursor);
+            buffer.undo.close(cursor);
+
+            for line in lines {
+                let text = match buffer.content.get_line(line) {
+                    Some(text) => text.to_string().trim().to_string(),
+                    None => String::new(),
+                };
+                changes.push((file.path.clone(), line, text));
+            }
+            counts.push((file.path, file.edits.len()));
+        }
+        self.scroll();
+
+        let describe = |path: &Path| {
+            path.strip_prefix(&self.working_directory)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        let mut message = if changes.is_empty() {
+            "The edit changes nothing".to_string()
+        } else {
+            format!(
+                "Changed {} place(s) in {} file(s): {}",
+                changes.len(),
+                counts.len(),
+                counts
+                    .iter()
+                    .map(|(path, count)| format!("{} ({})", describe(path), count))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        if !skipped.is_empty() {
+            message.push_str(&format!("; not applied: {}", skipped.join(", ")));
+        }
+        set_error(message);
+
+        // Edits across files are listed to go through them
+        if counts.len() > 1 {
+            self.menu = Some(Menu {
+                title: format!("Changed ({})", changes.len()),
+                items: changes
+                    .iter()
+                    .map(|(path, line, text)| MenuItem {
+                        label: format!("{}:{}", describe(path), line + 1),
+                        value: text.clone(),
+                    })
+                    .collect(),
+                action: MenuAction::Jump(
+                    changes
+                        .into_iter()
+                        .map(|(path, line, _)| (path, line))
+                        .collect(),
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Lists the lines of the project's files that have a `todokeywords`
+    /// word, or only `keyword` when one is given, in a menu that jumps to
+    /// the chosen one.
+    pub fn list_todos(&mut self, keyword: &str) {
+        let keywords = match keyword {
+            "" => self.todo_keywords(),
+            keyword => vec![keyword.to_string()],
+        };
+        if keywords.is_empty() {
+            set_error("No keywords to look for; :set todokeywords=TODO,FIXME".to_string());
+            return;
+        }
+        let index = self.file_index();
+        let (files, complete) = index.files();
+        let items: Vec<todo::TodoItem> = files
+            .iter()
+            .map(|file| index.root().join(file))
+            .flat_map(|path| {
+                match self
+                    .find_buffer_by_path(&path)
+                    .and_then(|id| self.buffers.get(&id))
+                {
+                    Some(buffer) => todo::scan_text(&path, &buffer.content.to_string(), &keywords),
+                    None => todo::scan_file(&path, &keywords),
+                }
+            })
+            .collect();
+        if items.is_empty() {
+            set_error(if complete {
+                format!("No {} in the project", keywords.join(", "))
+            } else {
+                "Nothing found yet; the project is still being indexed".to_string()
+            });
+            return;
+        }
+        if !complete {
+            set_error("The project is still being indexed; the list may be incomplete".to_string());
+        }
+
+        let directory = index.root();
+        let menu_items = items
+            .iter()
+            .map(|item| MenuItem {
+                label: format!(
+                    "{}:{}",
+                    item.path
+                        .strip_prefix(directory)
+                        .unwrap_or(&item.path)
+                        .display(),
+                    item.line + 1
+                ),
+                value: item.text.clone(),
+            })
+            .collect();
+        self.menu = Some(Menu {
+            title: format!("{} ({})", keywords.join(", "), items.len()),
+            items: menu_items,
+            action: MenuAction::Jump(
+                items
+                    .into_iter()
+                    .map(|item| (item.path, item.line))
+                    .collect(),
+            ),
+        });
+    }
+
+    /// Moves to the next `todokeywords` word after the cursor, or the
+    /// previous one before it, wrapping around the buffer.
+    pub fn jump_to_todo(&mut self, forward: bool) {
+        let keywords = self.todo_keywords();
+        let Some((buffer, window)) = self.current_view() else {
+            return;
+        };
+        let cursor = window.cursor_pos;
+        let mut found: Vec<usize> = Vec::new();
+        for (index, line) in buffer.content.lines().enumerate() {
+            let start = buffer.content.line_to_char(index);
+            let text = line.to_string();
+            found.extend(
+                todo::find_keywords(&text, &keywords)
+                    .into_iter()
+                    .map(|span| start + span.start),
+            );
+        }
+        let target = if forward {
+            found.iter().find(|&&pos| pos > cursor).or(found.first())
+        } else {
+            found
+                .iter()
+                .rev()
+                .find(|&&pos| pos < cursor)
+                .or(found.last())
+        };
+        let Some(&target) = target else {
+            set_error(format!("No {} in this buffer", keywords.join(", ")));
+            self.ring_bell();
+            return;
+        };
+        if let Some((_, window)) = self.current_view_mut() {
+            window.cursor_pos = target;
+        }
+        self.scroll();
+    }
+
+    /// Lists the project's bookmarks in a menu that jumps to the chosen one.
+    pub fn show_bookmarks_menu(&mut self) {
+        let locations = self.bookmarks.all();
+        if locations.is_empty() {
+            set_error("No bookmarks in this project".to_string());
+            return;
+        }
+        let directory = self.get_working_directory().to_path_buf();
+        let items = locations
+            .iter()
+            .map(|(path, line)| {
+                let text = self
+                    .find_buffer_by_path(path)
+                    .and_then(|id| self.buffers.get(&id))
+                    .filter(|buffer| *line < buffer.content.len_lines())
+                    .map(|buffer| buffer.content.line(*line).to_string())
+                    .unwrap_or_default();
+                MenuItem {
+                    label: format!(
+                        "{}:{}",
+                        path.strip_prefix(&directory).unwrap_or(path).display(),
+                        line + 1
+                    ),
+                    value: text.trim().to_string(),
+                }
+            })
+            .collect();
+        self.menu = Some(Menu {
+            title: "Bookmarks".to_string(),
+            items,
+            action: MenuAction::Jump(locations),
+        });
+    }
+
+    /// Handles `:dirdiff <a> <b>`, listing the files that differ between
+    /// the two trees in a menu.
+    pub fn dir_diff(&mut self, args: &str) {
+        let [old, new] = args.split_whitespace().collect::<Vec<_>>()[..] else {
+            set_error("Use :dirdiff <dir> <dir>".to_string());
+            return;
+        };
+        let old = self.resolve_path(&PathBuf::from(old));
+        let new = self.resolve_path(&PathBuf::from(new));
+        let changes = match dir_diff::compare_dirs(&old, &new) {
+            Ok(changes) => changes,
+            Err(e) => {
+                set_error(format!("Failed to compare directories: {}", e));
+                return;
+            }
+        };
+        if changes.is_empty() {
+            set_error("The directories have the same files".to_string());
+            return;
+        }
+
+        let items = changes
+            .iter()
+            .map(|(relative, change)| MenuItem {
+                label: change.label().to_string(),
+                value: relative.display().to_string(),
+            })
+            .collect();
+        let pairs = changes
+            .iter()
+            .map(|(relative, change)| {
+                let old = (*change != FileChange::Added).then(|| old.join(relative));
+                let new = (*change != FileChange::Removed).then(|| new.join(relative));
+                (old, new)
+            })
+            .collect();
+        self.menu = Some(Menu {
+            title: format!("{} files differ", changes.len()),
+            items,
+            action: MenuAction::Diff(pairs),
+        });
+    }
+
+    /// Shows `old` and `new` in windows side by side that scroll together,
+    /// with signs on the lines only one of them has.
+    pub fn diff_files(&mut self, old: &Path, new: &Path) -> io::Result<()> {
+        let (removed, added) =
+            dir_diff::line_diff(&fs::read_to_string(old)?, &fs::read_to_string(new)?);
+
+        self.open_file(&old.to_path_buf())?;
+        self.show_diff(&removed, &added, new)
+    }
+
+    fn show_diff(&mut self, removed: &[usize], added: &[usize], new: &Path) -> io::Result<()> {
+        self.mark_diff_lines(removed, Sign::new('-', "git_removed", 10));
+        let old_window = self.current_window;
+        self.split_window(SplitDirection::Vertical);
+        self.open_file(&new.to_path_buf())?;
+        self.mark_diff_lines(added, Sign::new('+', "git_added", 10));
+
+        for id in [old_window, self.current_window] {
+            if let Some(window) = self.windows.get_mut(&id) {
+                window.scrollbind = true;
+            }
+        }
+        set_error(format!(
+            "{} lines removed, {} added",
+            removed.len(),
+            added.len()
+        ));
+        Ok(())
+    }
+
+    fn mark_diff_lines(&mut self, lines: &[usize], sign: Sign) {
+        self.clear_signs(dir_diff::SIGN_SOURCE);
+        for &line in lines {
+            self.place_sign(dir_diff::SIGN_SOURCE, line, sign.clone());
+        }
+    }
+
+    /// The sign shown beside each line returned by `get_visible_content`.
+    pub fn get_visible_signs(&self, id: WindowId) -> Vec<Option<Sign>> {
+        let Some((buffer, window)) = self.window_view(id) else {
+            return Vec::new();
+        };
+        // A wrapped line shows its sign beside its first row
+        if let Some(rows) = self.get_wrapped_rows(id) {
+            return rows
+                .iter()
+                .map(|row| {
+                    buffer
+                        .signs
+                        .at(row.line)
+                        .filter(|_| row.start == 0)
+                        .cloned()
+                })
+                .collect();
+        }
+        Self::visible_lines(buffer, window)
+            .map(|line| buffer.signs.at(line).cloned())
+            .collect()
+    }
+
+    pub fn task_names(&self) -> Vec<&str> {
+        self.config
+            .tasks
+            .iter()
+            .map(|task| task.name.as_str())
+            .collect()
+    }
+
+    /// Starts a task from the config, streaming its output into a buffer
+    /// shown in a window below the current one.
+    pub fn run_task(&mut self, name: &str) {
+        let Some(task) = self.config.tasks.iter().find(|task| task.name == name) else {
+            set_error(format!(
+                "Unknown task: {}. Tasks: {}",
+                name,
+                self.task_names().join(", ")
+            ));
+            return;
+        };
+        let previous = self.tasks.iter().position(|(task, _, _)| task.name == name);
+        if let Some(index) = previous {
+            if self.tasks[index].2 == TaskStatus::Running {
+                set_error(format!("Task {} is already running", name));
+                return;
+            }
+        }
+
+        let directory = self.get_working_directory().to_path_buf();
+        let running = match RunningTask::spawn(name, &task.command, &directory) {
+            Ok(running) => running,
+            Err(e) => {
+                set_error(format!("Failed to start task {}: {}", name, e));
+                return;
+            }
+        };
+
+        let buffer_id = match previous.map(|index| self.tasks.remove(index)) {
+            Some((_, buffer_id, _)) if self.buffers.contains_key(&buffer_id) => {
+                if let Some(buffer) = self.buffers.get_mut(&buffer_id) {
+                    buffer.set_content(Rope::new());
+                    buffer.mark_modified();
+                    buffer.is_modified = false;
+                }
+                buffer_id
+            }
+            _ => {
+                let name = BufferName::Unnamed(self.next_scratch_id);
+                self.next_scratch_id += 1;
+                self.add_buffer(Buffer::new(name))
+            }
+        };
+        self.tasks.push((running, buffer_id, TaskStatus::Running));
+
+        // Show the output without taking focus from the current window
+        if !self
+            .windows
+            .values()
+            .any(|window| window.buffer == Some(buffer_id))
+        {
+            let current = self.current_window;
+            self.split_window(SplitDirection::Horizontal);
+            self.set_current_buffer(buffer_id);
+            self.switch_window(current);
+        }
+    }
+
+    pub fn has_running_tasks(&self) -> bool {
+        self.tasks
+            .iter()
+            .any(|(_, _, status)| *status == TaskStatus::Running)
+    }
+
+    /// Starts fetching `url` into a read-only buffer, or shows the buffer
+    /// it was fetched into before.
+    pub fn open_url(&mut self, url: &str) -> io::Result<()> {
+        let existing = self
+            .buffers
+            .iter()
+            .find(|(_, buffer)| matches!(&buffer.name, BufferName::Url(name) if name == url))
+            .map(|(id, _)| *id);
+        if let Some(id) = existing {
+            self.set_current_buffer(id);
+            return Ok(());
+        }
+        if self.fetches.iter().any(|fetch| fetch.url == url) {
+            return Err(io::Error::other("Already fetching it"));
+        }
+        self.fetches.push(Fetch::spawn(url)?);
+        set_error(format!("Fetching {}", url));
+        Ok(())
+    }
+
+    /// Opens the URLs that finished downloading, returning whether any did.
+    pub fn poll_fetches(&mut self) -> bool {
+        let mut finished = Vec::new();
+        self.fetches.retain(|fetch| match fetch.take_result() {
+            Some(result) => {
+                finished.push((fetch.url.clone(), result));
+                false
+            }
+            None => true,
+        });
+        for (url, result) in &finished {
+            match result {
+                Ok(text) => {
+                    let name = BufferName::Url(url.clone());
+                    let mut buffer = Buffer::with_content(name, Rope::from_str(text));
+                    buffer.read_only = true;
+                    let id = self.add_buffer(buffer);
+                    self.set_current_buffer(id);
+                    set_error(format!("Fetched {}", url));
+                }
+                Err(e) => set_error(format!("Failed to fetch {}: {}", url, e)),
+            }
+        }
+        !finished.is_empty()
+    }
+
+    fn refuse_read_only(&self) -> bool {
+        let read_only = self
+            .get_current_buffer()
+            .is_some_and(|buffer| buffer.read_only);
+        if read_only {
+            set_error("The buffer is read-only".to_string());
+        }
+        read_only
+    }
+
+    /// Copies new task output into the task buffers, returning whether
+    /// anything changed.
+    pub fn poll_tasks(&mut self) -> bool {
+        self.sync_windows();
+        let mut changed = false;
+        let mut following = Vec::new();
+        for (task, buffer_id, status) in &mut self.tasks {
+            let (output, new_status) = task.take_output();
+            changed |= !output.is_empty() || new_status != *status;
+            *status = new_status;
+
+            let Some(buffer) = self.buffers.get_mut(buffer_id) else {
+                continue;
+            };
+            if output.is_empty() || buffer.unloaded {
+                continue;
+            }
+            let last_line = buffer.content.len_lines() - 1;
+            following.extend(
+                self.windows
+                    .iter()
+                    .filter(|(_, window)| window.buffer == Some(*buffer_id))
+                    .filter(|(_, window)| {
+                        buffer.content.char_to_line(window.cursor_pos) == last_line
+                    })
+                    .map(|(id, _)| *id),
+            );
+            let end = buffer.content.len_chars();
+            buffer.insert(end, &output);
+            buffer.mark_lines_modified();
+            // Output is not an edit the user needs to save
+            buffer.is_modified = false;
+        }
+        self.sync_windows();
+        for id in following {
+            if let Some((buffer, window)) = self.window_view_mut(id) {
+                let last_line = buffer.content.len_lines() - 1;
+                window.cursor_pos = buffer.content.line_to_char(last_line);
+                self.scroll_to_cursor(id);
+            }
+        }
+        changed
+    }
+
+    /// Kills the running tasks, returning their names.
+    pub fn kill_tasks(&mut self) -> Vec<String> {
+        let mut killed = Vec::new();
+        for (task, _, status) in &self.tasks {
+            if *status != TaskStatus::Running {
+                continue;
+            }
+            match task.kill() {
+                Ok(()) => killed.push(task.name.clone()),
+                Err(e) => set_error(format!("Failed to stop {}: {}", task.name, e)),
+            }
+        }
+        killed
+    }
+
+    /// Status of the most recently started task, for the statusline.
+    pub fn task_status(&self) -> Option<String> {
+        let (task, _, status) = self.tasks.last()?;
+        Some(match status {
+            TaskStatus::Running => format!("{}: running", task.name),
+            TaskStatus::Exited(Some(code)) => format!("{}: exit {}", task.name, code),
+            TaskStatus::Exited(None) => format!("{}: killed", task.name),
+        })
+    }
+
+    /// Handles `:autocmd <event> <pattern> <action>`, where the action is a
+    /// builtin hook name, an ex command prefixed with `:` or a shell command
+    /// prefixed with `!`.
+    pub fn handle_autocmd_command(&mut self, args: &str) {
+        let mut parts = args.trim().splitn(3, char::is_whitespace);
+        let (Some(event), Some(pattern), Some(action)) = (parts.next(), parts.next(), parts.next())
+        else {
+            set_error("Use :autocmd <event> <pattern> <action>".to_string());
+            return;
+        };
+
+        let event = match event.parse::<HookEvent>() {
+            Ok(event) => event,
+            Err(e) => {
+                set_error(e);
+                return;
+            }
+        };
+
+        let action = action.trim();
+        let action = if let Some(command) = action.strip_prefix(':') {
+            HookAction::Command(command.to_string())
+        } else if let Some(command) = action.strip_prefix('!') {
+            HookAction::Shell(command.to_string())
+        } else if let Some(builtin) = hooks::builtin_action(action) {
+            HookAction::Builtin(builtin)
+        } else {
+            set_error(format!("Unknown hook action: {}", action));
+            return;
+        };
+
+        self.add_hook(event, pattern, action);
+        set_error(format!("Added {} hook for {}", event, pattern));
+    }
+
+    pub fn clear_hooks(&mut self) {
+        self.hooks.clear();
+    }
+
+    pub fn hook_count(&self) -> usize {
+        self.hooks.len()
+    }
+
+    pub fn fire_hook(&mut self, event: HookEvent) {
+        let path = self.get_current_file_path();
+        self.fire_hook_for_path(event, path);
+    }
+
+    fn fire_hook_for_path(&mut self, event: HookEvent, path: Option<PathBuf>) {
+        // Events raised by a hook's own actions are not re-dispatched
+        if self.firing_hooks || self.hooks.is_empty() {
+            return;
+        }
+
+        self.firing_hooks = true;
+        for action in self.hooks.matching(event, path.as_deref()) {
+            match action {
+                HookAction::Builtin(action) => action(self),
+                HookAction::Command(command) => self.pending_commands.push(command),
+                HookAction::Shell(command) => {
+                    hooks::run_shell_hook(event, &command, path.as_deref())
+                }
+            }
+        }
+        self.firing_hooks = false;
+    }
+
+    /// Drains ex commands queued by hooks so the command bar can run them.
+    pub fn take_pending_commands(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.pending_commands)
+    }
+
+    /// Queues `:normal` keys, written as in a `-s` key script, to be run on
+    /// each of `lines` or where the cursor is.
+    pub fn queue_normal(&mut self, lines: Option<(usize, usize)>, keys: &str) {
+        self.pending_normal.push((lines, keys.to_string()));
+    }
+
+    pub fn has_pending_keys(&self) -> bool {
+        self.pending_keys.is_some()
+    }
+
+    pub fn take_pending_normal(&mut self) -> Vec<(Option<(usize, usize)>, String)> {
+        std::mem::take(&mut self.pending_normal)
+    }
+
+    pub fn strip_trailing_whitespace(&mut self) {
+        if let Some((buffer, window)) = self.current_view_mut() {
+            let original = buffer.content.to_string();
+            let stripped: String = original
+                .split_inclusive('\n')
+                .map(|line| {
+                    let (text, newline) = match line.strip_suffix('\n') {
+                        Some(text) => (text, "\n"),
+                        None => (line, ""),
+                    };
+                    format!("{}{}", text.trim_end_matches([' ', '\t', '\r']), newline)
+                })
+                .collect();
+
+            if stripped != original {
+                let cursor_line = buffer.content.char_to_line(window.cursor_pos);
+                let cursor_column = window.cursor_pos - buffer.content.line_to_char(cursor_line);
+
+                buffer.set_content(Rope::from_str(&stripped));
+                let line_start = buffer.content.line_to_char(cursor_line);
+                let line_len = buffer.content.line(cursor_line).len_chars();
+                window.cursor_pos = line_start + cursor_column.min(line_len);
+                buffer.mark_modified();
+            }
+        }
+        self.commit_revision();
+    }
+
+    /// Snapshots the current buffer into its history if it has changed, and
+    /// ends its undo step.
+    pub fn commit_revision(&mut self) {
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer.history.commit(&buffer.content, window.cursor_pos);
+            buffer.undo.close(window.cursor_pos);
+        }
+    }
+
+    /// Ends the current buffer's undo step, so the next change starts one.
+    pub fn close_undo_step(&mut self) {
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer.undo.close(window.cursor_pos);
+        }
+    }
+
+    /// Restores the buffer to how it looked at an earlier or later point,
+    /// returning when that revision was recorded.
+    pub fn time_travel(&mut self, travel: &TimeTravel, forward: bool) -> Option<SystemTime> {
+        self.commit_revision();
+
+        let buffer = self.get_current_buffer_mut()?;
+        let revision = if forward {
+            buffer.history.later(travel)
+        } else {
+            buffer.history.earlier(travel)
+        };
+        let (content, cursor_pos, time) =
+            (revision.content.clone(), revision.cursor_pos, revision.time);
+        self.restore_revision(content, cursor_pos);
+        Some(time)
+    }
+
+    /// Handles `u`, undoing the last change (a whole insert session counts
+    /// as one) and leaving the cursor where it was.
+    pub fn undo(&mut self) {
+        self.step_history(false);
+    }
+
+    /// Handles `Ctrl-r`, redoing the change undone last.
+    pub fn redo(&mut self) {
+        self.step_history(true);
+    }
+
+    fn step_history(&mut self, forward: bool) {
+        self.commit_revision();
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let step = if forward {
+            buffer.undo.redo()
+        } else {
+            buffer.undo.undo()
+        };
+        let Some((replacements, cursor_pos)) = step else {
+            set_error(match forward {
+                true => "Already at newest change".to_string(),
+                false => "Already at oldest change".to_string(),
+            });
+            return;
+        };
+        for (start, len, text) in replacements {
+            buffer.splice(start..start + len, &text);
+        }
+        buffer.mark_modified();
+        window.cursor_pos = cursor_pos.min(buffer.content.len_chars());
+        buffer.selection_start = None;
+        self.scroll();
+    }
+
+    fn restore_revision(&mut self, content: Rope, cursor_pos: usize) {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        if buffer.content != content {
+            buffer.set_content(content);
+            buffer.mark_modified();
+        }
+        window.cursor_pos = cursor_pos.min(buffer.content.len_chars());
+        buffer.undo.close(window.cursor_pos);
+        buffer.selection_start = None;
+        self.scroll();
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        self.mode.clone()
+    }
+
+    /// The size of a window's text area when the frontend last laid it out.
+    pub fn get_viewport(&self, window: WindowId) -> (usize, usize) {
+        self.windows
+            .get(&window)
+            .map_or((80, 24), |window| window.viewport)
+    }
+
+    fn viewport(&self) -> (usize, usize) {
+        self.get_viewport(self.current_window)
+    }
+
+    /// Sets the size of a window's text area and scrolls the window to keep
+    /// its cursor in view.
+    pub fn set_viewport(&mut self, id: WindowId, viewport: (usize, usize)) {
+        self.sync_windows();
+        if let Some(window) = self.windows.get_mut(&id) {
+            window.viewport = viewport;
+        }
+        #[cfg(feature = "terminal")]
+        if let Some(pane) = self.terminals.get_mut(&id) {
+            pane.resize(viewport.1 as u16, viewport.0 as u16);
+        }
+        // Resizing is not a scroll, so bound windows are left alone
+        self.scroll_to_cursor(id);
+    }
+
+    pub fn get_scroll_offset(&self, window: WindowId) -> Option<(usize, usize)> {
+        self.window_view(window)
+            .map(|(_, window)| window.scroll_offset)
+    }
+
+    /// Scrolls the current window to keep the cursor in view, scrolling
+    /// every other scrollbound window by the same number of lines.
+    pub fn scroll(&mut self) {
+        self.sync_windows();
+        let id = self.current_window;
+        let before = self.get_scroll_offset(id);
+        self.scroll_to_cursor(id);
+
+        if let (Some((_, before)), Some((_, after))) = (before, self.get_scroll_offset(id)) {
+            if before != after {
+                self.sync_scrollbind(id, after as isize - before as isize);
+            }
+        }
+    }
+
+    /// Scrolls a window without moving its cursor, as the mouse wheel does.
+    pub fn scroll_view(&mut self, id: WindowId, lines: isize, columns: isize) {
+        let wrapped = self.wrap_width(id).is_some();
+        let Some((buffer, window)) = self.window_view_mut(id) else {
+            return;
+        };
+        let (scroll_x, scroll_y) = window.scroll_offset;
+        let last_line = buffer.content.len_lines().saturating_sub(1);
+        let new_y = scroll_y.saturating_add_signed(lines).min(last_line);
+        window.scroll_offset = (scroll_x.saturating_add_signed(columns), new_y);
+        // A wrapped view has nothing to scroll sideways and starts whole lines
+        if wrapped {
+            window.scroll_offset.0 = 0;
+        }
+        self.sync_scrollbind(id, new_y as isize - scroll_y as isize);
+    }
+
+    fn sync_scrollbind(&mut self, from: WindowId, delta: isize) {
+        let scrollbind = self
+            .windows
+            .get(&from)
+            .is_some_and(|window| window.scrollbind);
+        if !scrollbind {
+            return;
+        }
+
+        let targets: Vec<WindowId> = self
+            .windows
+            .iter()
+            .filter(|(id, window)| **id != from && window.scrollbind)
+            .map(|(id, _)| *id)
+            .collect();
+        let scrolloff = self.options.scrolloff;
+        for id in targets {
+            if let Some((buffer, window)) = self.window_view_mut(id) {
+                let padding = Self::scroll_padding(scrolloff, window.viewport.1);
+                let last_line = Self::last_line_index(buffer);
+                window.scroll_offset.1 =
+                    (window.scroll_offset.1 as isize + delta).clamp(0, last_line as isize) as usize;
+                Self::keep_cursor_in_view(buffer, window, padding);
+            }
+        }
+    }
+
+    fn keep_cursor_in_view(buffer: &Buffer, window: &mut Split, padding: usize) {
+        let scroll_y = window.scroll_offset.1;
+        let top = if scroll_y == 0 { 0 } else { scroll_y + padding };
+        let bottom = (scroll_y + window.viewport.1)
+            .saturating_sub(padding + 1)
+            .max(top);
+        let cursor_line = buffer.content.char_to_line(window.cursor_pos);
+        let line = cursor_line
+            .clamp(top, bottom)
+            .min(Self::last_line_index(buffer));
+        if line != cursor_line {
+            Self::move_cursor_to_line(buffer, window, line);
+        }
+    }
+
+    /// Scrolls the current window by `lines` without moving the cursor,
+    /// unless it would leave the view (Ctrl-e and Ctrl-y).
+    pub fn scroll_lines(&mut self, lines: isize) {
+        let padding = Self::scroll_padding(self.options.scrolloff, self.viewport().1);
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let before = window.scroll_offset.1;
+        let last_line = Self::last_line_index(buffer);
+        window.scroll_offset.1 = before.saturating_add_signed(lines).min(last_line);
+        Self::keep_cursor_in_view(buffer, window, padding);
+        let after = window.scroll_offset.1;
+        self.sync_scrollbind(self.current_window, after as isize - before as isize);
+    }
+
+    /// Scrolls a page, keeping two lines of the last one in view, and takes
+    /// the cursor along (PageUp and PageDown).
+    pub fn scroll_page(&mut self, down: bool) {
+        let page = self.viewport().1.saturating_sub(2).max(1) as isize;
+        let before = self.get_scroll_offset(self.current_window);
+        self.scroll_lines(if down { page } else { -page });
+        if self.get_scroll_offset(self.current_window) != before {
+            return;
+        }
+        if let Some((buffer, window)) = self.current_view_mut() {
+            let line = if down {
+                Self::last_line_index(buffer)
+            } else {
+                0
+            };
+            Self::move_cursor_to_line(buffer, window, line);
+            self.scroll();
+        }
+    }
+
+    fn scroll_padding(offset: usize, size: usize) -> usize {
+        offset.min(size.saturating_sub(1) / 2)
+    }
+
+    fn scroll_to_keep(scroll: &mut usize, cursor: usize, padding: usize, size: usize) {
+        if cursor < *scroll + padding {
+            *scroll = cursor.saturating_sub(padding);
+        } else if cursor + padding >= *scroll + size {
+            *scroll = (cursor + padding + 1).saturating_sub(size);
+        }
+    }
+
+    fn scroll_to_cursor(&mut self, id: WindowId) {
+        let (viewport_width, viewport_height) = self.get_viewport(id);
+        // Typewriter scrolling keeps the cursor line centered
+        let scrolloff = match self.window_options(id).typewriter {
+            true => usize::MAX,
+            false => self.options.scrolloff,
+        };
+        let vertical_padding = Self::scroll_padding(scrolloff, viewport_height);
+        if let Some(width) = self.wrap_width(id) {
+            self.scroll_wrapped_to_cursor(id, width, vertical_padding);
+            return;
+        }
+        let horizontal_padding = Self::scroll_padding(self.options.sidescrolloff, viewport_width);
+
+        if let Some((buffer, window)) = self.window_view_mut(id) {
+            let (cursor_line, cursor_column) = Self::line_and_column(buffer, window.cursor_pos);
+            Self::scroll_to_keep(
+                &mut window.scroll_offset.1,
+                cursor_line,
+                vertical_padding,
+                viewport_height,
+            );
+            Self::scroll_to_keep(
+                &mut window.scroll_offset.0,
+                cursor_column,
+                horizontal_padding,
+                viewport_width,
+            );
+        }
+    }
+
+    fn scroll_wrapped_to_cursor(&mut self, id: WindowId, width: usize, padding: usize) {
+        let Some((buffer, window)) = self.window_view_mut(id) else {
+            return;
+        };
+        let height = window.viewport.1;
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let rows = Self::line_rows(buffer, line, width);
+        let column = window.cursor_pos - buffer.content.line_to_char(line);
+        let row = rows
+            .iter()
+            .position(|row| column < row.end || row.last)
+            .unwrap_or_default();
+
+        // Rows from the top of the view to the cursor, counted no further
+        // than the view reaches
+        let (skip, top) = window.scroll_offset;
+        let above = if line < top {
+            None
+        } else if line == top {
+            row.checked_sub(skip)
+        } else if line > top + height {
+            Some(height)
+        } else {
+            let top_rows = Self::line_rows(buffer, top, width).len();
+            let mut count = top_rows.saturating_sub(skip);
+            for between in top + 1..line {
+                if count >= height {
+                    break;
+                }
+                count += Self::line_rows(buffer, between, width).len();
+            }
+            Some(count + row)
+        };
+        let back = match above {
+            None => padding,
+            Some(above) if above < padding => padding,
+            Some(above) if above + padding >= height => height.saturating_sub(padding + 1),
+            Some(_) => return,
+        };
+
+        // Walks `back` rows up from the cursor's row to the new top
+        let (mut line, mut row, mut back) = (line, row, back);
+        while back > row {
+            if line == 0 {
+                (row, back) = (0, 0);
+                break;
+            }
+            back -= row + 1;
+            line -= 1;
+            row = Self::line_rows(buffer, line, width).len() - 1;
+        }
+        window.scroll_offset = (row - back, line);
+    }
+
+    /// Inserts `shiftwidth` spaces, or a tab character with `noexpandtab`.
+    pub fn insert_tab(&mut self) {
+        let options = self.buffer_options();
+        if options.expandtab {
+            self.insert_str(" ".repeat(options.shiftwidth));
+        } else {
+            self.insert('\t');
+        }
+    }
+
+    pub fn insert_str(&mut self, s: String) {
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer.insert(window.cursor_pos, &s);
+            window.cursor_pos += s.len();
+            buffer.mark_lines_modified();
+            self.scroll();
+        }
+    }
+
+    pub fn insert(&mut self, char: char) {
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer.insert_char(window.cursor_pos, char);
+            window.cursor_pos += 1;
+            buffer.mark_lines_modified();
+            let calc = char == '=' && buffer.calc;
+            self.scroll();
+            if calc {
+                self.append_calc_result();
+            } else if !char.is_whitespace() {
+                self.auto_wrap();
+            }
+        }
+    }
+
+    /// Opens a scratch buffer for `:calc`.
+    pub fn open_calc_buffer(&mut self) {
+        self.new_scratch_buffer_with_content("");
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer.calc = true;
+        }
+        set_error("Type an expression and = to see its value, as in 2^10 =".to_string());
+    }
+
+    fn append_calc_result(&mut self) {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let text = buffer.content.line(line).to_string();
+        let text = text.trim_end_matches(['\n', '\r']);
+        let line_end = buffer.content.line_to_char(line) + text.chars().count();
+        if window.cursor_pos != line_end {
+            return;
+        }
+        let Some(expression) = text.strip_suffix('=') else {
+            return;
+        };
+        if let Ok(value) = calc::evaluate(expression) {
+            self.insert_str(format!(" {}", calc::format_number(value)));
+        }
+    }
+
+    /// Inserts the value of `expression` at the cursor, for the `=`
+    /// register.
+    pub fn insert_expression(&mut self, expression: &str) {
+        match calc::evaluate(expression) {
+            Ok(value) => self.insert_str(calc::format_number(value)),
+            Err(e) => {
+                set_error(e);
+                self.ring_bell();
+            }
+        }
+    }
+
+    pub fn insert_new_line(&mut self) {
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer.insert_char(window.cursor_pos, '\n');
+            window.cursor_pos += 1;
+            buffer.mark_lines_modified();
+            self.scroll();
+        }
+    }
+
+    /// Inserts a typed `c`, closing brackets and quotes when the
+    /// `autopairs` option is on; typing the closing half just before itself
+    /// steps over it.
+    pub fn type_char(&mut self, c: char) {
+        let pairs = self.autopairs();
+        let pairs_with = pairs
+            .iter()
+            .any(|pair| pair.open.ends_with(c) || pair.close.starts_with(c));
+        if !self.buffer_options().autopairs || !pairs_with {
+            self.insert(c);
+            return;
+        }
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let pos = window.cursor_pos;
+        let reach = pairs
+            .iter()
+            .map(|pair| pair.open.chars().count())
+            .max()
+            .unwrap_or(1);
+        let (before, _) = pairs::text_around(&buffer.content, pos, reach);
+        let next = buffer.content.get_char(pos);
+        let byte = buffer.content.char_to_byte(pos);
+        let tree = buffer.syntax_tree();
+        let in_literal = tree.as_ref().and_then(|tree| tree.in_literal(byte));
+
+        let step_over = next == Some(c)
+            && match pairs::closed_by(&pairs, c) {
+                Some(pair) if pair.is_quote() => in_literal != Some(false),
+                Some(_) => tree.as_ref().and_then(|tree| tree.is_bracket(byte)) != Some(false),
+                None => false,
+            };
+        if step_over {
+            window.cursor_pos += 1;
+            self.scroll();
+            return;
+        }
+
+        let closing =
+            pairs::pair_for(&pairs, c, &before, next).filter(|_| in_literal != Some(true));
+        self.insert(c);
+        if let (Some(closing), Some((buffer, window))) = (closing, self.current_view_mut()) {
+            buffer.insert(window.cursor_pos, closing);
+            buffer.mark_lines_modified();
+        }
+    }
+
+    fn autopairs(&self) -> Vec<Pair> {
+        let path = self.get_current_file_path();
+        let extension = path.as_ref().and_then(|path| path.extension()?.to_str());
+        let configured = extension
+            .and_then(|extension| self.config.autopairs.get(extension))
+            .or_else(|| self.config.autopairs.get("*"));
+        match configured {
+            Some(pairs) => pairs.iter().filter_map(|pair| Pair::parse(pair)).collect(),
+            None => pairs::default_pairs(extension),
+        }
+    }
+
+    pub fn delete(&mut self) {
+        // Backspace between an empty pair in insert mode removes both halves
+        let remove_pair = self.mode == Mode::Insert && self.buffer_options().autopairs;
+        let pairs = if remove_pair {
+            self.autopairs()
+        } else {
+            Vec::new()
+        };
+        if let Some((buffer, window)) = self.current_view_mut() {
+            if window.cursor_pos > 0 {
+                let reach = pairs
+                    .iter()
+                    .map(|pair| pair.open.chars().count().max(pair.close.chars().count()))
+                    .max()
+                    .unwrap_or(0);
+                let (before, after) = pairs::text_around(&buffer.content, window.cursor_pos, reach);
+                let end = window.cursor_pos + pairs::empty_pair_length(&pairs, &before, &after);
+                buffer.remove(window.cursor_pos - 1..end);
+                window.cursor_pos -= 1;
+                buffer.mark_lines_modified();
+                self.scroll();
+            }
+        }
+    }
+
+    /// Moves to the bracket matching the first one at or after the cursor
+    /// on its line, as `%` does.
+    pub fn jump_to_matching_bracket(&mut self) -> bool {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return false;
+        };
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let line_end = buffer.content.line_to_char(line) + buffer.content.line(line).len_chars();
+        let tree = buffer.syntax_tree();
+        let content = &buffer.content;
+        let found = (window.cursor_pos..line_end).find_map(|pos| {
+            let byte = content.char_to_byte(pos);
+            match tree
+                .as_ref()
+                .and_then(|tree| Some((tree, tree.is_bracket(byte)?)))
+            {
+                Some((tree, true)) => tree
+                    .matching_bracket(byte)
+                    .map(|other| content.byte_to_char(other))
+                    .or_else(|| pairs::matching_bracket(content, pos)),
+                Some((_, false)) => None,
+                None => pairs::matching_bracket(content, pos),
+            }
+        });
+        match found {
+            Some(pos) => {
+                window.cursor_pos = pos;
+                self.scroll();
+                true
+            }
+            None => {
+                self.ring_bell();
+                false
+            }
+        }
+    }
+
+    fn text_object_range(&self, object: TextObject, inner: bool) -> Result<Range<usize>, String> {
+        let (buffer, window) = self
+            .current_view()
+            .ok_or_else(|| "No active buffer".to_string())?;
+        let pos = window.cursor_pos;
+        let byte = buffer.content.char_to_byte(pos);
+        let tree = buffer.syntax_tree();
+        let text = || buffer.content.to_string();
+        let bytes = match object {
+            TextObject::Function => {
+                let tree = tree.ok_or_else(|| {
+                    "No grammar for this file's language to find functions with".to_string()
+                })?;
+                tree.function_range(&text(), byte, inner)
+                    .ok_or_else(|| "No function around the cursor".to_string())?
+            }
+            TextObject::Argument => {
+                match tree.and_then(|tree| tree.argument_range(&text(), byte, inner)) {
+                    Some(bytes) => bytes,
+                    None => {
+                        return pairs::argument_range(&buffer.content, pos, inner)
+                            .ok_or_else(|| "No argument around the cursor".to_string())
+                    }
+                }
+            }
+        };
+        Ok(buffer.content.byte_to_char(bytes.start)..buffer.content.byte_to_char(bytes.end))
+    }
+
+    /// Selects `object` around the cursor in visual mode, as `vif` or `vaa`
+    /// do.
+    pub fn select_text_object(&mut self, object: TextObject, inner: bool) {
+        let range = match self.text_object_range(object, inner) {
+            Ok(range) => range,
+            Err(e) => {
+                set_error(e);
+                self.ring_bell();
+                return;
+            }
+        };
+        if let Some((buffer, window)) = self.current_view_mut() {
+            buffer.selection_start = Some(range.start);
+            window.cursor_pos = range.end;
+        }
+        self.set_mode(Mode::Visual);
+        self.scroll();
+    }
+
+    /// Applies `operator` to `object` around the cursor, as `dif` or `caa`
+    /// do.
+    pub fn apply_operator_to_object(
+        &mut self,
+        operator: Operator,
+        object: TextObject,
+        inner: bool,
+    ) {
+        if self.refuse_read_only() {
+            return;
+        }
+        match self.text_object_range(object, inner) {
+            Ok(range) => self.remove_for_operator(operator, range),
+            Err(e) => {
+                set_error(e);
+                self.ring_bell();
+            }
+        }
+    }
+
+    pub fn move_cursor(&mut self, direction: CursorMovement) {
+        let before = self.current_view().map(|(_, window)| window.cursor_pos);
+        let wrap_width = self.wrap_width(self.current_window);
+        if let Some((buffer, window)) = self.current_view_mut() {
+            match direction {
+                CursorMovement::Left => Self::move_cursor_left(window),
+                CursorMovement::Right => Self::move_cursor_right(buffer, window),
+                // Soft wrapped lines are moved through a row at a time
+                CursorMovement::Up | CursorMovement::Down => match wrap_width {
+                    Some(width) => Self::move_cursor_by_row(
+                        buffer,
+                        window,
+                        direction == CursorMovement::Down,
+                        width,
+                    ),
+                    None => Self::move_cursor_vertically(
+                        buffer,
+                        window,
+                        direction == CursorMovement::Down,
+                    ),
+                },
+                CursorMovement::LineStart => Self::move_cursor_line_start(buffer, window),
+                CursorMovement::LineEnd => Self::move_cursor_line_end(buffer, window),
+                CursorMovement::FirstNonBlank => {
+                    window.cursor_pos = Self::first_non_blank(buffer, window);
+                }
+                CursorMovement::LastNonBlank => Self::move_cursor_last_non_blank(buffer, window),
+                CursorMovement::SmartHome => {
+                    let first_non_blank = Self::first_non_blank(buffer, window);
+                    if window.cursor_pos == first_non_blank {
+                        Self::move_cursor_line_start(buffer, window);
+                    } else {
+                        window.cursor_pos = first_non_blank;
+                    }
+                }
+                CursorMovement::NextSentence => {
+                    window.cursor_pos =
+                        prose::next_sentence_start(&buffer.content, window.cursor_pos);
+                }
+                CursorMovement::PreviousSentence => {
+                    window.cursor_pos =
+                        prose::previous_sentence_start(&buffer.content, window.cursor_pos);
+                }
+            }
+            // Stepping past the start or end of the buffer or a line
+            let stuck = matches!(
+                direction,
+                CursorMovement::Left
+                    | CursorMovement::Right
+                    | CursorMovement::Up
+                    | CursorMovement::Down
+                    | CursorMovement::NextSentence
+                    | CursorMovement::PreviousSentence
+            ) && before == Some(window.cursor_pos);
+            self.scroll();
+            if stuck {
+                self.ring_bell();
+            }
+        }
+    }
+
+    /// Asks the frontend to signal a failure as the `bell` option says.
+    pub fn ring_bell(&mut self) {
+        self.bell = true;
+    }
+
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// Moves to the character found by `find` on the cursor's line and
+    /// remembers it for `;`.
+    pub fn find_char(&mut self, find: CharFind) -> bool {
+        self.last_find = Some(find);
+        let found = self.move_to_char(find, false);
+        if !found {
+            self.ring_bell();
+        }
+        found
+    }
+
+    /// Repeats the last character find, in the other direction for `,`.
+    pub fn repeat_find(&mut self, reverse: bool) -> bool {
+        let found = match self.last_find {
+            Some(find) if reverse => self.move_to_char(find.reversed(), true),
+            Some(find) => self.move_to_char(find, true),
+            None => false,
+        };
+        if !found {
+            self.ring_bell();
+        }
+        found
+    }
+
+    fn move_to_char(&mut self, find: CharFind, repeat: bool) -> bool {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return false;
+        };
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let start = buffer.content.line_to_char(line);
+        let chars: Vec<char> = buffer
+            .content
+            .line(line)
+            .chars()
+            .take_while(|&c| c != '\n' && c != '\r')
+            .collect();
+        match find.column(&chars, window.cursor_pos - start, repeat) {
+            Some(column) => {
+                window.cursor_pos = start + column;
+                self.scroll();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Deletes from the cursor to where `motion` moves it, as `d` or `c`
+    /// followed by a motion.
+    pub fn apply_operator(
+        &mut self,
+        operator: Operator,
+        inclusive: bool,
+        motion: impl FnOnce(&mut Self) -> bool,
+    ) {
+        if self.refuse_read_only() {
+            return;
+        }
+        let Some(start) = self.current_view().map(|(_, window)| window.cursor_pos) else {
+            return;
+        };
+        if !motion(self) {
+            return;
+        }
+        let Some((buffer, window)) = self.current_view() else {
+            return;
+        };
+        let target = window.cursor_pos;
+        let range = if target > start {
+            start..(target + inclusive as usize).min(buffer.content.len_chars())
+        } else {
+            target..start
+        };
+        self.remove_for_operator(operator, range);
+    }
+
+    fn remove_for_operator(&mut self, operator: Operator, range: Range<usize>) {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        window.cursor_pos = range.start;
+        if !range.is_empty() {
+            buffer.remove(range);
+            buffer.mark_lines_modified();
+        }
+
+        match operator {
+            Operator::Delete => self.commit_revision(),
+            // The deletion joins the insert session's revision
+            Operator::Change => self.set_mode(Mode::Insert),
+        }
+    }
+
+    /// The column a window's lines wrap at in prose mode, or None when they
+    /// are not wrapped.
+    pub fn wrap_width(&self, id: WindowId) -> Option<usize> {
+        let options = self.window_options(id);
+        if !options.prose {
+            return None;
+        }
+        let viewport_width = self.get_viewport(id).0;
+        let width = match options.wrapwidth {
+            0 => viewport_width,
+            width => width.min(viewport_width),
+        };
+        Some(width.max(1))
+    }
+
+    fn line_rows(buffer: &Buffer, line: usize, width: usize) -> Vec<Row> {
+        let text = buffer.content.line(line).to_string();
+        prose::line_rows(line, text.trim_end_matches(['\n', '\r']), width)
+    }
+
+    /// The rows a window shows when its lines are soft wrapped, starting at
+    /// the row of the top line held in the horizontal scroll offset.
+    pub fn get_wrapped_rows(&self, id: WindowId) -> Option<Vec<Row>> {
+        let width = self.wrap_width(id)?;
+        let (buffer, window) = self.window_view(id)?;
+        let (skip, top) = window.scroll_offset;
+        let height = window.viewport.1;
+        let mut rows = Vec::with_capacity(height);
+        for line in top..buffer.content.len_lines() {
+            let line_rows = Self::line_rows(buffer, line, width);
+            let skip = if line == top {
+                skip.min(line_rows.len() - 1)
+            } else {
+                0
+            };
+            rows.extend(line_rows.into_iter().skip(skip).take(height - rows.len()));
+            if rows.len() == height {
+                break;
+            }
+        }
+        Some(rows)
+    }
+
+    fn visible_lines(buffer: &Buffer, window: &Split) -> Range<usize> {
+        let scroll_y = window.scroll_offset.1;
+        let end = buffer.content.len_lines().min(scroll_y + window.viewport.1);
+        scroll_y.min(end)..end
+    }
+
+    fn visible_rows(&self, id: WindowId) -> Vec<(usize, usize)> {
+        let Some((buffer, window)) = self.window_view(id) else {
+            return Vec::new();
+        };
+        if let Some(rows) = self.get_wrapped_rows(id) {
+            return rows.iter().map(|row| (row.line, row.start)).collect();
+        }
+        Self::visible_lines(buffer, window)
+            .map(|line| (line, window.scroll_offset.0))
+            .collect()
+    }
+
+    /// The selected chars of each row returned by `get_visible_content`,
+    /// counted from the start of the row.
+    pub fn get_visible_selections(&self, id: WindowId) -> Vec<Option<Range<usize>>> {
+        let rows = self.visible_rows(id);
+        let Some((buffer, window, anchor)) = self
+            .current_view()
+            .filter(|_| id == self.current_window)
+            .and_then(|(buffer, window)| Some((buffer, window, buffer.selection_start?)))
+        else {
+            return vec![None; rows.len()];
+        };
+        let cursor = window.cursor_pos;
+        let block = Self::block(&buffer.content, anchor, cursor);
+        rows.into_iter()
+            .map(|(line, column)| {
+                let (start, end) = if buffer.block_selection {
+                    let (lines, columns) = &block;
+                    if !lines.contains(&line) {
+                        return None;
+                    }
+                    let row = Self::block_row(&buffer.content, line, columns);
+                    (row.start, row.end)
+                } else {
+                    (anchor.min(cursor), anchor.max(cursor))
+                };
+                let row_start = buffer.content.line_to_char(line) + column;
+                (end > row_start).then(|| start.saturating_sub(row_start)..end - row_start)
+            })
+            .collect()
+    }
+
+    /// The text a window shows, one row per line.
+    pub fn get_visible_content(&self, id: WindowId) -> Option<String> {
+        let (buffer, window) = self.window_view(id)?;
+        if let Some(rows) = self.get_wrapped_rows(id) {
+            return Some(
+                rows.iter()
+                    .map(|row| {
+                        let start = buffer.content.line_to_char(row.line);
+                        buffer
+                            .content
+                            .slice(start + row.start..start + row.end)
+                            .to_string()
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
+        }
+        let scroll_x = window.scroll_offset.0;
+        let viewport_width = window.viewport.0;
+
+        // Only the visible window of each line is materialized, so a
+        // single huge line costs no more than a short one
+        let mut cache = buffer.line_slices.borrow_mut();
+        let key = (buffer.version, scroll_x, viewport_width);
+        Some(
+            Self::visible_lines(buffer, window)
+                .map(|line_idx| {
+                    let line = buffer.content.line(line_idx);
+                    cache.visible_text(key, line_idx, line, scroll_x, viewport_width)
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )
+    }
+
+    /// Whether the `colorswatch` option covers the file extension of the
+    /// buffer a window shows.
+    pub fn color_swatches_enabled(&self, id: WindowId) -> bool {
+        let Some((buffer, _)) = self.window_view(id) else {
+            return false;
+        };
+        let filetypes = self.window_options(id).colorswatch;
+        if filetypes == "*" {
+            return true;
+        }
+        let extension = buffer
+            .name
+            .path()
+            .and_then(|path| Some(path.extension()?.to_str()?.to_string()));
+        extension.is_some_and(|extension| {
+            filetypes
+                .split(',')
+                .any(|filetype| filetype.trim() == extension)
+        })
+    }
+
+    /// Highlight spans of the lines returned by `get_visible_content`, with
+    /// columns relative to the horizontal scroll offset.
+    pub fn get_visible_highlights(&self, id: WindowId) -> Vec<Vec<HighlightSpan>> {
+        let Some((buffer, window)) = self.window_view(id) else {
+            return Vec::new();
+        };
+        let Ok(highlights) = buffer.highlights.lock() else {
+            return Vec::new();
+        };
+
+        if let Some(rows) = self.get_wrapped_rows(id) {
+            return rows
+                .iter()
+                .map(|row| {
+                    highlights
+                        .line_spans(row.line)
+                        .iter()
+                        .filter(|span| span.end > row.start && span.start < row.end)
+                        .map(|span| HighlightSpan {
+                            start: span.start.saturating_sub(row.start),
+                            end: span.end.min(row.end) - row.start,
+                            kind: span.kind,
+                        })
+                        .collect()
+                })
+                .collect();
+        }
+
+        let scroll_x = window.scroll_offset.0;
+        Self::visible_lines(buffer, window)
+            .map(|line_idx| {
+                highlights
+                    .line_spans(line_idx)
+                    .iter()
+                    .filter(|span| span.end > scroll_x)
+                    .map(|span| HighlightSpan {
+                        start: span.start.saturating_sub(scroll_x),
+                        end: span.end - scroll_x,
+                        kind: span.kind,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn new_scratch_buffer(&mut self) {
+        self.new_scratch_buffer_with_content("");
+    }
+
+    pub fn new_scratch_buffer_with_content(&mut self, content: &str) {
+        // Scratch buffers live only in memory until written with :w <filename>
+        let name = BufferName::Unnamed(self.next_scratch_id);
+        self.next_scratch_id += 1;
+
+        let buffer = Buffer::with_content(name, Rope::from_str(content));
+        let id = self.add_buffer(buffer);
+        self.set_current_buffer(id);
+    }
+
+    pub fn is_scratch_buffer(&self) -> bool {
+        matches!(
+            self.get_current_buffer().map(|buffer| &buffer.name),
+            Some(BufferName::Unnamed(_))
+        )
+    }
+
+    fn add_buffer(&mut self, buffer: Buffer) -> BufferId {
+        let id = BufferId(self.next_buffer_id);
+        self.next_buffer_id += 1;
+        self.buffers.insert(id, buffer);
+        id
+    }
+
+    /// Searches for `pattern` from the cursor, forward for `/` or backward
+    /// for `?`.
+    pub fn search(&mut self, pattern: &str, forward: bool) {
+        if pattern.is_empty() {
+            match &mut self.last_search {
+                Some(search) => search.forward = forward,
+                None => {
+                    set_error("No previous search".to_string());
+                    return;
+                }
+            }
+        } else {
+            self.last_search = Some(Search::new(pattern, &self.buffer_options(), forward));
+        }
+        self.search_next(false);
+    }
+
+    /// Moves to the next match of the last search, or the previous one when
+    /// `reverse` (`n` and `N`).
+    pub fn search_next(&mut self, reverse: bool) {
+        let Some(search) = &self.last_search else {
+            set_error("No previous search".to_string());
+            return;
+        };
+        let Some((buffer, window)) = self.current_view() else {
+            return;
+        };
+        match search.find(
+            &buffer.content,
+            window.cursor_pos,
+            search.forward != reverse,
+        ) {
+            Some(position) => {
+                if let Some((_, window)) = self.current_view_mut() {
+                    window.cursor_pos = position;
+                }
+                self.scroll();
+            }
+            None => {
+                set_error(format!("Pattern not found: {}", search.pattern()));
+                self.ring_bell();
+            }
+        }
+    }
+
+    /// "match 3 of 17" while the cursor is on a match of the last search.
+    pub fn search_status(&self) -> Option<String> {
+        let search = self.last_search.as_ref()?;
+        let id = self.get_current_buffer_id()?;
+        let (buffer, window) = self.current_view()?;
+        let (index, count) =
+            search.match_index(id, buffer.version, &buffer.content, window.cursor_pos)?;
+        Some(format!("match {} of {}", index, count))
+    }
+
+    /// Searches for the whole word under the cursor (`*` and `#`).
+    pub fn search_word_under_cursor(&mut self, forward: bool) {
+        let Some((buffer, window)) = self.current_view() else {
+            return;
+        };
+        let content = &buffer.content;
+        let line = content.char_to_line(window.cursor_pos);
+        let line_start = content.line_to_char(line);
+        let chars: Vec<char> = content.line(line).chars().collect();
+        let column = window.cursor_pos - line_start;
+
+        // Like vim, use the first word at or after the cursor
+        let Some(start) = (column..chars.len()).find(|&i| is_word_char(chars[i])) else {
+            set_error("No word under the cursor".to_string());
+            return;
+        };
+        let start = (0..start)
+            .rev()
+            .find(|&i| !is_word_char(chars[i]))
+            .map_or(0, |i| i + 1);
+        let end = (start..chars.len())
+            .find(|&i| !is_word_char(chars[i]))
+            .unwrap_or(chars.len());
+        let word: String = chars[start..end].iter().collect();
+
+        let search = Search::new(&word, &self.buffer_options(), forward).whole_word();
+        self.last_search = Some(search);
+        // Search from the word's start so `*` skips the word itself
+        if let Some((_, window)) = self.current_view_mut() {
+            window.cursor_pos = line_start + start;
+        }
+        self.search_next(false);
+    }
+
+    /// Runs `:s` on `lines`, returning a message such as "3 substitutions on
+    /// 2 lines".
+    pub fn substitute(
+        &mut self,
+        (start_line, end_line): (usize, usize),
+        substitution: &Substitution,
+    ) -> Result<String, String> {
+        let search = if substitution.pattern.is_empty() {
+            self.last_search.take().ok_or("No previous search")?
+        } else {
+            Search::new(&substitution.pattern, &self.buffer_options(), true)
+        };
+        let search = match substitution.ignore_case {
+            Some(ignore_case) => search.ignore_case(ignore_case),
+            None => search,
+        };
+
+        let buffer = self.get_current_buffer().ok_or("No active buffer")?;
+        let selection = if substitution.in_selection {
+            Some(buffer.last_selection.ok_or("No visual selection")?)
+        } else {
+            None
+        };
+        // The columns of each line that matches may be replaced in
+        let columns = |line: usize| match selection {
+            Some((start, end)) if buffer.block_selection => {
+                let (lines, columns) = Self::block(&buffer.content, start, end);
+                if lines.contains(&line) {
+                    columns
+                } else {
+                    0..0
+                }
+            }
+            Some((start, end)) => {
+                let line_start = buffer.content.line_to_char(line);
+                start.saturating_sub(line_start)..end.saturating_sub(line_start)
+            }
+            None => 0..usize::MAX,
+        };
+
+        let text = self
+            .get_lines((start_line, end_line))
+            .ok_or("No active buffer")?;
+        let (mut count, mut changed_lines) = (0, 0);
+        let lines: Vec<String> = text
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(index, line)| {
+                let (line, replaced) =
+                    substitution.apply(&search, line, columns(start_line + index));
+                count += replaced;
+                changed_lines += (replaced > 0) as usize;
+                line
+            })
+            .collect();
+        let pattern = search.pattern();
+        self.last_search = Some(search);
+
+        if count == 0 {
+            return Err(format!("Pattern not found: {}", pattern));
+        }
+        // Keep the substitution apart from earlier edits, so one undo
+        // reverts exactly it
+        self.commit_revision();
+        self.replace_lines((start_line, end_line), &lines.concat());
+        Ok(format!(
+            "{} substitution{} on {} line{}",
+            count,
+            if count == 1 { "" } else { "s" },
+            changed_lines,
+            if changed_lines == 1 { "" } else { "s" }
+        ))
+    }
+
+    /// Moves the cursor to the start of a line, counted from 0.
+    pub fn go_to_line(&mut self, line: usize) {
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let line = line.min(buffer.content.len_lines().saturating_sub(1));
+        window.cursor_pos = buffer.content.line_to_char(line);
+        self.scroll();
+    }
+
+    /// Opens the file a tag is defined in and moves to its definition.
+    pub fn jump_to_tag(&mut self, tag: &Tag) -> io::Result<()> {
+        self.open_file(&tag.file)?;
+        let line = self
+            .get_current_buffer()
+            .and_then(|buffer| tag.find_line(&buffer.content.to_string()));
+        match line {
+            Some(line) => self.go_to_line(line),
+            None => set_error(format!(
+                "{} is no longer where the tags file says",
+                tag.name
+            )),
+        }
+        Ok(())
+    }
+
+    /// Moves the cursor to a row and column of the current window's view,
+    /// as a mouse click does, keeping it within the text.
+    pub fn move_cursor_to_view(&mut self, row: usize, column: usize) {
+        if let Some(rows) = self.get_wrapped_rows(self.current_window) {
+            let Some((buffer, window)) = self.current_view_mut() else {
+                return;
+            };
+            let Some(row) = rows.get(row).or(rows.last()) else {
+                return;
+            };
+            // Past the end of a row that does not end its line is its last
+            // char, not the first of the next row
+            let end = if row.last { row.end } else { row.end - 1 };
+            window.cursor_pos =
+                buffer.content.line_to_char(row.line) + (row.start + column).min(end);
+            self.scroll();
+            return;
+        }
+        let Some((buffer, window)) = self.current_view_mut() else {
+            return;
+        };
+        let (scroll_x, scroll_y) = window.scroll_offset;
+        let line = (scroll_y + row).min(buffer.content.len_lines().saturating_sub(1));
+        let length = buffer
+            .content
+            .line(line)
+            .chars()
+            .take_while(|&c| c != '\n' && c != '\r')
+            .count();
+        window.cursor_pos = buffer.content.line_to_char(line) + (scroll_x + column).min(length);
+        self.scroll();
+    }
+
+    /// The row and column of a window's view its cursor is drawn at, or
+    /// None when it is scrolled out of view.
+    pub fn get_cursor_view_position(&self, id: WindowId) -> Option<(usize, usize)> {
+        let (buffer, window) = self.window_view(id)?;
+        let (line, column) = Self::line_and_column(buffer, window.cursor_pos);
+        let Some(rows) = self.get_wrapped_rows(id) else {
+            let (scroll_x, scroll_y) = window.scroll_offset;
+            return Some((line.checked_sub(scroll_y)?, column.checked_sub(scroll_x)?));
+        };
+        let index = rows.iter().position(|row| {
+            row.line == line && row.start <= column && (column < row.end || row.last)
+        })?;
+        // The cursor after a full row, or on spaces hanging past its edge,
+        // stays at the edge
+        let width = self.wrap_width(id)?;
+        Some((index, (column - rows[index].start).min(width - 1)))
+    }
+
+    pub fn get_cursor_screen_position(&self) -> Option<(usize, usize)> {
+        self.current_view()
+            .map(|(buffer, window)| Self::line_and_column(buffer, window.cursor_pos))
+    }
+
+    fn line_and_column(buffer: &Buffer, offset: usize) -> (usize, usize) {
+        let offset = offset.min(buffer.content.len_chars());
+        let line = buffer.content.char_to_line(offset);
+        (line, offset - buffer.content.line_to_char(line))
+    }
+
+    fn resolve_path(&self, path: &PathBuf) -> PathBuf {
+        if path.is_relative() {
+            Self::normalize_path(&self.get_working_directory().join(path))
+        } else {
+            Self::normalize_path(path)
+        }
+    }
+
+    fn normalize_path(path: &Path) -> PathBuf {
+        if let Ok(canonical) = fs::canonicalize(path) {
+            return canonical;
+        }
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => Self::normalize_path(parent).join(name),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    pub fn read_file_below_cursor(&mut self, path: &PathBuf) -> io::Result<usize> {
+        let resolved_path = self.resolve_path(path);
+        let content = fs::read_to_string(&resolved_path)?;
+
+        if let Some((buffer, window)) = self.current_view_mut() {
+            let line = buffer.content.char_to_line(window.cursor_pos);
+            let next_line_start = buffer.content.line_to_char(line + 1);
+            let ends_without_newline = line + 1 == buffer.content.len_lines()
+                && !buffer.content.line(line).chars().any(|c| c == '\n');
+
+            // Insert everything at once so the read counts as a single edit
+            let mut text = String::with_capacity(content.len() + 1);
+            if ends_without_newline {
+                text.push('\n');
+            }
+            text.push_str(&content);
+            if !ends_without_newline && !content.ends_with('\n') {
+                text.push('\n');
+            }
+
+            buffer.insert(next_line_start, &text);
+            window.cursor_pos = next_line_start + usize::from(ends_without_newline);
+            buffer.mark_lines_modified();
+            self.commit_revision();
+            self.scroll();
+            Ok(content.lines().count())
+        } else {
+            Err(io::Error::other("No active buffer to read into"))
+        }
+    }
+
+    pub fn open_file(&mut self, path: &PathBuf) -> io::Result<()> {
+        let resolved_path = self.resolve_path(path);
+
+        // Reuse the existing buffer so unsaved changes are not clobbered
+        if let Some(id) = self.find_buffer_by_path(&resolved_path) {
+            self.set_current_buffer(id);
+            self.apply_autochdir();
+            return Ok(());
+        }
+
+        // A missing file opens as an empty buffer that is created on first write
+        let content = match fs::read_to_string(&resolved_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        let name = BufferName::Named(resolved_path);
+        let mut buffer = Buffer::with_content(name, Rope::from_str(&content));
+        buffer.record_disk_mtime();
+        let id = self.add_buffer(buffer);
+        self.show_bookmarks(id);
+        self.show_diagnostics(id);
+        self.set_current_buffer(id);
+        self.apply_autochdir();
+        self.fire_hook(HookEvent::BufReadPost);
+        Ok(())
+    }
+
+    /// Reloads clean buffers whose file changed outside the editor and
+    /// warns about modified ones, which are left as they are.
+    pub fn check_external_changes(&mut self) {
+        let mut reloaded = Vec::new();
+        let mut conflicts = Vec::new();
+        for buffer in self.buffers.values_mut() {
+            if !buffer.changed_on_disk() {
+                continue;
+            }
+            if buffer.is_modified {
+                // Warn once per change on disk
+                buffer.record_disk_mtime();
+                conflicts.push(buffer.name.to_string());
+            } else if let Err(e) = buffer.read_from_disk() {
+                buffer.record_disk_mtime();
+                conflicts.push(format!("{} ({})", buffer.name, e));
+            } else {
+                reloaded.push(buffer.name.to_string());
+            }
+        }
+
+        if !conflicts.is_empty() {
+            set_error(format!(
+                "Changed on disk, keeping your unsaved edits: {}",
+                conflicts.join(", ")
+            ));
+        } else if !reloaded.is_empty() {
+            set_error(format!("Reloaded {}", reloaded.join(", ")));
+        }
+    }
+
+    /// Writes the current buffer when the `autosave` option is on and it
+    /// has unsaved changes to a named file.
+    pub fn autosave(&mut self) {
+        if !self.buffer_options().autosave {
+            return;
+        }
+        let Some(path) = self
+            .get_current_buffer()
+            .filter(|buffer| buffer.is_modified && !buffer.unloaded)
+            .and_then(|buffer| buffer.name.path().cloned())
+        else {
+            return;
+        };
+        if let Err(e) = self.write_current_buffer(path, false, false) {
+            set_error(format!("Autosave failed: {}", e));
+        }
+    }
+
+    pub fn toggle_debug_info(&mut self) {
+        self.show_debug_info = !self.show_debug_info;
+    }
+
+    pub fn is_debug_info_shown(&self) -> bool {
+        self.show_debug_info
+    }
+
+    pub fn toggle_zen(&mut self) {
+        self.zen = !self.zen;
+    }
+
+    pub fn is_zen(&self) -> bool {
+        self.zen
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        let loaded = self.buffers.values().filter(|buffer| !buffer.unloaded);
+        BufferStats {
+            buffers: self.buffers.len(),
+            loaded: loaded.clone().count(),
+            lines: loaded
+                .clone()
+                .map(|buffer| buffer.content.len_lines())
+                .sum(),
+            bytes: loaded.map(|buffer| buffer.memory_usage()).sum(),
+            version: self.get_current_buffer().map_or(0, |buffer| buffer.version),
+        }
+    }
+
+    /// Notes are left out, as they save by themselves on quitting.
+    pub fn has_unsaved_changes(&self) -> bool {
+        !self.get_unsaved_buffers().is_empty()
+    }
+
+    /// The files open in buffers and whether each has unsaved changes, for
+    /// marking them where files are listed.
+    pub fn open_files(&self) -> Vec<(PathBuf, bool)> {
+        self.buffers
+            .values()
+            .filter_map(|buffer| Some((buffer.name.path()?.clone(), buffer.is_modified)))
+            .collect()
+    }
+
+    /// Modified buffers in the order they were opened.
+    pub fn get_unsaved_buffers(&self) -> Vec<BufferId> {
+        let mut ids: Vec<BufferId> = self
+            .buffers
+            .iter()
+            .filter(|(id, buffer)| buffer.is_modified && !self.is_notes_buffer(**id))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    pub fn get_current_buffer(&self) -> Option<&Buffer> {
+        self.get_current_buffer_id()
+            .and_then(|id| self.buffers.get(&id))
+    }
+
+    fn get_current_buffer_mut(&mut self) -> Option<&mut Buffer> {
+        self.get_current_buffer_id()
+            .and_then(|id| self.buffers.get_mut(&id))
+    }
+
+    fn window_view(&self, id: WindowId) -> Option<(&Buffer, &Split)> {
+        let window = self.windows.get(&id)?;
+        Some((self.buffers.get(&window.buffer?)?, window))
+    }
+
+    fn window_view_mut(&mut self, id: WindowId) -> Option<(&mut Buffer, &mut Split)> {
+        let window = self.windows.get_mut(&id)?;
+        Some((self.buffers.get_mut(&window.buffer?)?, window))
+    }
+
+    fn current_view(&self) -> Option<(&Buffer, &Split)> {
+        self.window_view(self.current_window)
+    }
+
+    fn current_view_mut(&mut self) -> Option<(&mut Buffer, &mut Split)> {
+        self.window_view_mut(self.current_window)
+    }
+
+    fn move_cursor_left(window: &mut Split) {
+        if window.cursor_pos > 0 {
+            window.cursor_pos -= 1;
+        }
+    }
+
+    fn move_cursor_right(buffer: &Buffer, window: &mut Split) {
+        if window.cursor_pos < buffer.content.len_chars() {
+            window.cursor_pos += 1;
+        }
+    }
+
+    fn move_cursor_vertically(buffer: &Buffer, window: &mut Split, down: bool) {
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let target = if down { line + 1 } else { line.wrapping_sub(1) };
+        if target < buffer.content.len_lines() {
+            Self::move_cursor_to_line(buffer, window, target);
+        }
+    }
+
+    fn move_cursor_by_row(buffer: &Buffer, window: &mut Split, down: bool, width: usize) {
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let rows = Self::line_rows(buffer, line, width);
+        let column = window.cursor_pos - buffer.content.line_to_char(line);
+        let index = rows
+            .iter()
+            .position(|row| column < row.end || row.last)
+            .unwrap_or_default();
+        let column = match window.desired_column {
+            Some((position, column)) if position == window.cursor_pos => column,
+            _ => column - rows[index].start,
+        };
+        let target = if down {
+            match rows.get(index + 1) {
+                Some(&row) => row,
+                None if line + 1 < buffer.content.len_lines() => {
+                    Self::line_rows(buffer, line + 1, width)[0]
+                }
+                None => return,
+            }
+        } else if index > 0 {
+            rows[index - 1]
+        } else if line > 0 {
+            *Self::line_rows(buffer, line - 1, width).last().unwrap()
+        } else {
+            return;
+        };
+        // A row that does not end its line ends on the char before the next
+        // row, usually the space it wrapped at
+        let end = if target.last {
+            target.end
+        } else {
+            target.end - 1
+        };
+        window.cursor_pos =
+            buffer.content.line_to_char(target.line) + target.start.saturating_add(column).min(end);
+        window.desired_column = Some((window.cursor_pos, column));
+    }
+
+    fn move_cursor_to_line(buffer: &Buffer, window: &mut Split, target: usize) {
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let column = match window.desired_column {
+            Some((position, column)) if position == window.cursor_pos => column,
+            _ => window.cursor_pos - buffer.content.line_to_char(line),
+        };
+        let length = buffer
+            .content
+            .line(target)
+            .chars()
+            .take_while(|&c| c != '\n' && c != '\r')
+            .count();
+        window.cursor_pos = buffer.content.line_to_char(target) + column.min(length);
+        window.desired_column = Some((window.cursor_pos, column));
+    }
+
+    fn move_cursor_line_start(buffer: &Buffer, window: &mut Split) {
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        window.cursor_pos = buffer.content.line_to_char(line);
+    }
+
+    fn move_cursor_line_end(buffer: &Buffer, window: &mut Split) {
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let text = buffer.content.line(line);
+        // The last line may have no line break to stop before
+        let newline = (text.chars().last() == Some('\n')) as usize;
+        window.cursor_pos = buffer.content.line_to_char(line) + text.len_chars() - newline;
+        window.desired_column = Some((window.cursor_pos, usize::MAX));
+    }
+
+    fn first_non_blank(buffer: &Buffer, window: &Split) -> usize {
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let start = buffer.content.line_to_char(line);
+        let indent = buffer
+            .content
+            .line(line)
+            .chars()
+            .take_while(|&c| c == ' ' || c == '\t')
+            .count();
+        start + indent
+    }
+
+    fn move_cursor_last_non_blank(buffer: &Buffer, window: &mut Split) {
+        let line = buffer.content.char_to_line(window.cursor_pos);
+        let start = buffer.content.line_to_char(line);
+        let last = buffer
+            .content
+            .line(line)
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| !c.is_whitespace())
+            .last();
+        window.cursor_pos = last.map_or(start, |(offset, _)| start + offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor_with(content: &str) -> Editor {
+        let mut editor = Editor::new();
+        editor.new_scratch_buffer_with_content(content);
+        editor
+    }
+
+    fn set_cursor(editor: &mut Editor, cursor: usize) {
+        editor.current_view_mut().unwrap().1.cursor_pos = cursor;
+    }
+
+    fn cursor(editor: &Editor) -> usize {
+        editor.current_view().unwrap().1.cursor_pos
+    }
+
+    fn select_block(editor: &mut Editor, from: usize, to: usize) {
+        set_cursor(editor, from);
+        editor.enter_visual_block_mode();
+        set_cursor(editor, to);
+    }
+
+    #[test]
+    fn block_selection_copies_and_substitutes_its_columns() {
+        let mut editor = editor_with("aaaa\naaaa\naa\n");
+        select_block(&mut editor, 1, 12);
+        assert_eq!(editor.copy_selection().as_deref(), Some("aa\naa\na"));
+
+        editor.exit_visual_mode();
+        let substitution = Substitution::parse(r"/\%Va/b/g").unwrap();
+        assert!(editor.substitute((0, 2), &substitution).is_ok());
+        let content = editor.get_current_buffer().unwrap().content.to_string();
+        assert_eq!(content, "abba\nabba\nab\n");
+    }
+
+    #[test]
+    fn block_selection_deletes_its_columns() {
+        let mut editor = editor_with("abcd\nefgh\n");
+        select_block(&mut editor, 6, 2);
+        editor.delete_selection();
+        let buffer = editor.get_current_buffer().unwrap();
+        assert_eq!(buffer.content.to_string(), "ad\neh\n");
+        assert_eq!(cursor(&editor), 1);
+    }
+
+    #[test]
+    fn windows_onto_a_buffer_keep_their_own_cursors() {
+        let mut editor = editor_with("one\ntwo\n");
+        let first = editor.get_current_window();
+        set_cursor(&mut editor, 4);
+        editor.split_window(SplitDirection::Vertical);
+        set_cursor(&mut editor, 1);
+
+        editor.focus_window(first);
+        assert_eq!(cursor(&editor), 4);
+    }
+
+    #[test]
+    fn edits_move_the_cursors_of_other_windows() {
+        let mut editor = editor_with("one\ntwo\n");
+        let first = editor.get_current_window();
+        set_cursor(&mut editor, 4);
+        editor.split_window(SplitDirection::Vertical);
+        set_cursor(&mut editor, 0);
+        editor.insert_str("zero\n".to_string());
+
+        editor.focus_window(first);
+        assert_eq!(cursor(&editor), 9);
+        let buffer = editor.get_current_buffer().unwrap();
+        assert_eq!(buffer.content.char(cursor(&editor)), 't');
     }
 }
@@ -5,6 +5,8 @@ pub enum Mode {
     Normal,
     Insert,
     Visual,
+    /// Keys go to the shell in a terminal window
+    Terminal,
 }
 
 impl Display for Mode {
@@ -13,6 +15,7 @@ impl Display for Mode {
             Mode::Normal => f.write_str("NOR"),
             Mode::Insert => f.write_str("INS"),
             Mode::Visual => f.write_str("VIS"),
+            Mode::Terminal => f.write_str("TRM"),
         }
     }
 }
@@ -0,0 +1,87 @@
+/// An `f`, `t`, `F` or `T` search for a character on the cursor's line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CharFind {
+    pub target: char,
+    pub forward: bool,
+    /// Stop just before the character instead of on it
+    pub till: bool,
+}
+
+impl CharFind {
+    /// The find started by the motion key `key` (one of `fFtT`).
+    pub fn new(key: char, target: char) -> Option<Self> {
+        let (forward, till) = match key {
+            'f' => (true, false),
+            'F' => (false, false),
+            't' => (true, true),
+            'T' => (false, true),
+            _ => return None,
+        };
+        Some(Self {
+            target,
+            forward,
+            till,
+        })
+    }
+
+    /// The same find in the other direction, for `,`.
+    pub fn reversed(self) -> Self {
+        Self {
+            forward: !self.forward,
+            ..self
+        }
+    }
+
+    /// Column of `line` the find lands on from `column`.
+    pub fn column(self, line: &[char], column: usize, repeat: bool) -> Option<usize> {
+        let skip = (self.till && repeat) as usize;
+        if self.forward {
+            let from = column + 1 + skip;
+            let found = from + line.get(from..)?.iter().position(|&c| c == self.target)?;
+            Some(if self.till { found - 1 } else { found })
+        } else {
+            let to = column.checked_sub(skip)?;
+            let found = line[..to.min(line.len())]
+                .iter()
+                .rposition(|&c| c == self.target)?;
+            Some(if self.till { found + 1 } else { found })
+        }
+    }
+}
+
+/// A region selected with `i` or `a` after an operator or in visual mode.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextObject {
+    /// `f`: a function, or its body
+    Function,
+    /// `a`: an argument, or it with its comma
+    Argument,
+}
+
+impl TextObject {
+    pub fn from_key(key: char) -> Option<Self> {
+        match key {
+            'f' => Some(TextObject::Function),
+            'a' => Some(TextObject::Argument),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Operator {
+    /// `d`
+    Delete,
+    /// `c`: delete, then enter insert mode
+    Change,
+}
+
+impl Operator {
+    pub fn from_key(key: char) -> Option<Self> {
+        match key {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// Characters that open a block when they end a line
+const OPENERS: &[char] = &['{', '(', '['];
+// Characters that close a block when they start a line
+const CLOSERS: &[char] = &['}', ')', ']'];
+
+/// Columns of indentation at the start of `line`, counting a tab as
+/// `shiftwidth` columns.
+pub fn indent_width(line: &str, shiftwidth: usize) -> usize {
+    line.chars()
+        .map_while(|c| match c {
+            ' ' => Some(1),
+            '\t' => Some(shiftwidth),
+            _ => None,
+        })
+        .sum()
+}
+
+/// The indentation for lines moved below `above`, the nearest non-blank
+/// line over their new place, when the first of them is `first`: one
+/// level deeper after a line that opens a block, one shallower for a line
+/// that closes one.
+pub fn expected_indent(above: &str, first: &str, shiftwidth: usize) -> usize {
+    let mut width = indent_width(above, shiftwidth);
+    if above.trim_end().ends_with(OPENERS) {
+        width += shiftwidth;
+    }
+    if first.trim_start().starts_with(CLOSERS) {
+        width = width.saturating_sub(shiftwidth);
+    }
+    width
+}
+
+/// Shifts the non-blank lines of `lines` by `columns`, keeping how they
+/// are indented relative to each other.
+pub fn shift_indent(
+    lines: &[String],
+    columns: isize,
+    shiftwidth: usize,
+    expandtab: bool,
+) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| {
+            if columns == 0 || line.trim().is_empty() {
+                return line.clone();
+            }
+            let width = (indent_width(line, shiftwidth) as isize + columns).max(0) as usize;
+            let indent = if expandtab || shiftwidth == 0 {
+                " ".repeat(width)
+            } else {
+                "\t".repeat(width / shiftwidth) + &" ".repeat(width % shiftwidth)
+            };
+            indent + line.trim_start_matches([' ', '\t'])
+        })
+        .collect()
+}
@@ -0,0 +1,95 @@
+use crate::{
+    editor::BufferId,
+    utils::config::{digest, get_config_dir},
+};
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+// Starts every encrypted notes file, followed by the salt, the nonce and
+// the AES-256-GCM ciphertext
+const MAGIC: &[u8] = b"pyne-note-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// A project's notes open in a buffer, kept under the config directory
+/// rather than in the project.
+pub struct Notes {
+    pub buffer: BufferId,
+    pub path: PathBuf,
+    /// Set when the file is kept encrypted
+    pub passphrase: Option<String>,
+}
+
+/// What a passphrase is wanted for, until the frontend asks for it.
+#[derive(Clone, PartialEq, Debug)]
+pub enum PassphraseRequest {
+    /// To decrypt the notes file at the path
+    Unlock(PathBuf),
+    /// A new passphrase to encrypt the current notes with
+    Encrypt,
+}
+
+/// Where the notes for `project` are kept: named after the directory, with
+/// a digest of its path so projects of the same name do not share notes.
+pub fn notes_path(project: &Path) -> Option<PathBuf> {
+    let name = project
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "root".to_string());
+    let digest = digest(&project.to_string_lossy());
+    Some(
+        get_config_dir()?
+            .join("notes")
+            .join(format!("{}-{}.md", name, &digest[..8])),
+    )
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn encrypt(text: &str, passphrase: &str) -> io::Result<Vec<u8>> {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher(passphrase, &salt)?
+        .encrypt(&nonce, text.as_bytes())
+        .map_err(|_| io::Error::other("Encryption failed"))?;
+
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> io::Result<String> {
+    let data = data
+        .strip_prefix(MAGIC)
+        .filter(|data| data.len() >= SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| io::Error::other("Not an encrypted notes file"))?;
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let text = cipher(passphrase, salt)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| io::Error::other("Wrong passphrase or damaged file"))?;
+    String::from_utf8(text).map_err(|_| io::Error::other("Notes are not valid UTF-8"))
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> io::Result<Aes256Gcm> {
+    let mut key = [0; 32];
+    scrypt::scrypt(
+        passphrase.as_bytes(),
+        salt,
+        &scrypt::Params::recommended(),
+        &mut key,
+    )
+    .map_err(|e| io::Error::other(e.to_string()))?;
+    Aes256Gcm::new_from_slice(&key).map_err(|e| io::Error::other(e.to_string()))
+}
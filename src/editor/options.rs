@@ -0,0 +1,347 @@
+#[derive(Clone)]
+pub struct Options {
+    pub autochdir: bool,
+    /// Write the current buffer when the terminal loses focus
+    pub autosave: bool,
+    /// Search without regard to case
+    pub ignorecase: bool,
+    /// With `ignorecase`, match case when the pattern has an uppercase
+    /// letter
+    pub smartcase: bool,
+    pub updatetime: usize,
+    /// Lines kept visible above and below the cursor; a value of half the
+    /// window or more keeps the cursor centered
+    pub scrolloff: usize,
+    /// Columns kept visible left and right of the cursor
+    pub sidescrolloff: usize,
+    /// Blank columns on each side of the text in zen mode
+    pub zenpadding: usize,
+    /// Megabytes of file content to keep in memory before clean, hidden
+    /// buffers are unloaded; 0 disables unloading
+    pub bufferbudget: usize,
+    /// Comma-separated file extensions whose color literals get a swatch;
+    /// `*` enables every file and an empty value disables swatches
+    pub colorswatch: String,
+    /// Pastes longer than this many characters are shown for confirmation
+    /// before they are inserted; 0 inserts every paste straight away
+    pub pastepreview: usize,
+    /// How a paste preview shows invisible characters, as
+    /// `tab:→,space:·,eol:↵`
+    pub pastechars: String,
+    /// `p` pastes whole lines below the cursor's line re-indented to match
+    /// it, as `]p` does
+    pub pasteindent: bool,
+    /// Lines and columns the mouse wheel scrolls, as `ver:3,hor:6`
+    pub mousescroll: String,
+    /// What clicking the sign column toggles: `bookmark`, `breakpoint` or
+    /// `none`
+    pub signclick: String,
+    /// The first diagnostic of a line is shown dimmed after its text
+    pub virtualtext: bool,
+    /// Typing an opening bracket or quote inserts its closing half, which
+    /// typing it again steps over
+    pub autopairs: bool,
+    /// Tab inserts spaces rather than a tab character
+    pub expandtab: bool,
+    /// Spaces Tab inserts with `expandtab`
+    pub shiftwidth: usize,
+    /// Snapshots of each saved file kept for `:history`; 0 keeps none
+    pub localhistory: usize,
+    /// Opening the file explorer selects the current buffer's file
+    pub explorerfollow: bool,
+    /// Show the current file in the terminal's window title
+    pub title: bool,
+    /// Comma-separated words such as TODO that are highlighted and listed
+    /// by `:todo`; an empty value turns both off
+    pub todokeywords: String,
+    /// How errors and failed motions are signalled besides the message:
+    /// `none`, `beep` or `flash`
+    pub bell: String,
+    /// Soft wrap lines at `wrapwidth`, move the cursor by display lines and
+    /// keep `(` and `)` to sentences, for writing text and markdown
+    pub prose: bool,
+    /// Columns text wraps at in prose mode; 0 or more than the window wraps
+    /// at the window's edge
+    pub wrapwidth: usize,
+    /// Keep the cursor line vertically centered while writing
+    pub typewriter: bool,
+    /// Columns `gq` and `:reflow` fill lines to
+    pub textwidth: usize,
+    /// Break the line at a space when typing goes past `textwidth`
+    pub autowrap: bool,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self {
+            autochdir: false,
+            autosave: false,
+            ignorecase: false,
+            smartcase: false,
+            updatetime: 4000,
+            scrolloff: 6,
+            sidescrolloff: 6,
+            zenpadding: 20,
+            bufferbudget: 0,
+            colorswatch: "css,scss,sass,less,html,svg,vue".to_string(),
+            pastepreview: 10_000,
+            pastechars: "tab:→,space:·,eol:↵".to_string(),
+            pasteindent: false,
+            mousescroll: "ver:3,hor:6".to_string(),
+            signclick: "bookmark".to_string(),
+            autopairs: true,
+            virtualtext: true,
+            expandtab: true,
+            shiftwidth: 4,
+            localhistory: 50,
+            explorerfollow: false,
+            title: true,
+            todokeywords: "TODO,FIXME,HACK,XXX".to_string(),
+            bell: "none".to_string(),
+            prose: false,
+            wrapwidth: 80,
+            typewriter: false,
+            textwidth: 80,
+            autowrap: false,
+        }
+    }
+
+    /// Applies a `:set` argument such as `autochdir`, `noautochdir`,
+    /// `autochdir!` or `updatetime=1000`, returning a description of the
+    /// resulting value.
+    pub fn set(&mut self, assignment: &str) -> Result<String, String> {
+        let assignment = assignment.trim();
+
+        if let Some((name, value)) = assignment.split_once('=') {
+            if let Some(option) = self.string_option(name) {
+                let value = value.trim();
+                if let Some(choices) = Self::choices(name) {
+                    if !choices.contains(&value) {
+                        return Err(format!(
+                            "Invalid value for {}: {} (use {})",
+                            name,
+                            value,
+                            choices.join(", ")
+                        ));
+                    }
+                }
+                *option = value.to_string();
+                return Ok(format!("{}={}", name, option));
+            }
+            let option = self
+                .number_option(name)
+                .ok_or_else(|| format!("Unknown option: {}", name))?;
+            *option = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid number for {}: {}", name, value))?;
+            return Ok(format!("{}={}", name, option));
+        }
+
+        let (name, value) = if let Some(name) = assignment.strip_suffix('!') {
+            (name, None)
+        } else if self.bool_option(assignment).is_some() {
+            (assignment, Some(true))
+        } else if let Some(name) = assignment.strip_prefix("no") {
+            (name, Some(false))
+        } else {
+            (assignment, Some(true))
+        };
+
+        if let Some(option) = self.number_option(name) {
+            return Ok(format!("{}={}", name, option));
+        }
+        if let Some(option) = self.string_option(name) {
+            return Ok(format!("{}={}", name, option));
+        }
+
+        let option = self
+            .bool_option(name)
+            .ok_or_else(|| format!("Unknown option: {}", assignment))?;
+        *option = value.unwrap_or(!*option);
+
+        Ok(format!("{}{}", if *option { "" } else { "no" }, name))
+    }
+
+    /// These options with a buffer's `:setlocal` values in place of the
+    /// global ones.
+    pub fn with_local(&self, local: &LocalOptions) -> Options {
+        Options {
+            autosave: local.autosave.unwrap_or(self.autosave),
+            prose: local.prose.unwrap_or(self.prose),
+            wrapwidth: local.wrapwidth.unwrap_or(self.wrapwidth),
+            typewriter: local.typewriter.unwrap_or(self.typewriter),
+            textwidth: local.textwidth.unwrap_or(self.textwidth),
+            autowrap: local.autowrap.unwrap_or(self.autowrap),
+            colorswatch: local
+                .colorswatch
+                .clone()
+                .unwrap_or_else(|| self.colorswatch.clone()),
+            ..self.clone()
+        }
+    }
+
+    /// Applies a `:setlocal` argument to a buffer's `local` values, leaving
+    /// the global ones alone.
+    pub fn set_local(&self, local: &mut LocalOptions, assignment: &str) -> Result<String, String> {
+        let assignment = assignment.trim();
+        if let Some(name) = assignment.strip_suffix('<') {
+            return if local.reset(name) {
+                Ok(format!("{}<", name))
+            } else {
+                Err(format!("{} has no local value", name))
+            };
+        }
+
+        let mut options = self.with_local(local);
+        let description = options.set(assignment)?;
+        let name = assignment
+            .split(['=', '!'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        if !local.store(name, &options) {
+            return Err(format!("{} is a global option; use :set", name));
+        }
+        Ok(description)
+    }
+
+    /// The (vertical, horizontal) wheel step from `mousescroll`.
+    pub fn mouse_scroll(&self) -> (usize, usize) {
+        let (mut vertical, mut horizontal) = (3, 6);
+        for part in self.mousescroll.split(',') {
+            match part.trim().split_once(':') {
+                Some(("ver", amount)) => vertical = amount.parse().unwrap_or(vertical),
+                Some(("hor", amount)) => horizontal = amount.parse().unwrap_or(horizontal),
+                _ => {}
+            }
+        }
+        (vertical, horizontal)
+    }
+
+    fn bool_option(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "autochdir" | "acd" => Some(&mut self.autochdir),
+            "autosave" | "as" => Some(&mut self.autosave),
+            "ignorecase" | "ic" => Some(&mut self.ignorecase),
+            "smartcase" | "scs" => Some(&mut self.smartcase),
+            "expandtab" | "et" => Some(&mut self.expandtab),
+            "autopairs" | "ap" => Some(&mut self.autopairs),
+            "virtualtext" | "vt" => Some(&mut self.virtualtext),
+            "explorerfollow" | "ef" => Some(&mut self.explorerfollow),
+            "title" => Some(&mut self.title),
+            "pasteindent" | "pi" => Some(&mut self.pasteindent),
+            "prose" => Some(&mut self.prose),
+            "typewriter" => Some(&mut self.typewriter),
+            "autowrap" => Some(&mut self.autowrap),
+            _ => None,
+        }
+    }
+
+    fn number_option(&mut self, name: &str) -> Option<&mut usize> {
+        match name {
+            "updatetime" | "ut" => Some(&mut self.updatetime),
+            "scrolloff" | "so" => Some(&mut self.scrolloff),
+            "sidescrolloff" | "siso" => Some(&mut self.sidescrolloff),
+            "zenpadding" | "zp" => Some(&mut self.zenpadding),
+            "bufferbudget" | "bb" => Some(&mut self.bufferbudget),
+            "pastepreview" | "ppv" => Some(&mut self.pastepreview),
+            "shiftwidth" | "sw" => Some(&mut self.shiftwidth),
+            "localhistory" | "lh" => Some(&mut self.localhistory),
+            "wrapwidth" | "ww" => Some(&mut self.wrapwidth),
+            "textwidth" | "tw" => Some(&mut self.textwidth),
+            _ => None,
+        }
+    }
+
+    fn choices(name: &str) -> Option<&'static [&'static str]> {
+        match name {
+            "signclick" => Some(&["bookmark", "breakpoint", "none"]),
+            "bell" => Some(&["none", "beep", "flash"]),
+            _ => None,
+        }
+    }
+
+    fn string_option(&mut self, name: &str) -> Option<&mut String> {
+        match name {
+            "colorswatch" | "csw" => Some(&mut self.colorswatch),
+            "mousescroll" | "mousesc" => Some(&mut self.mousescroll),
+            "pastechars" | "pcs" => Some(&mut self.pastechars),
+            "signclick" => Some(&mut self.signclick),
+            "bell" => Some(&mut self.bell),
+            "todokeywords" | "tdk" => Some(&mut self.todokeywords),
+            _ => None,
+        }
+    }
+}
+
+/// Option values set with `:setlocal` for one buffer, shadowing the global
+/// ones set with `:set`.
+#[derive(Default)]
+pub struct LocalOptions {
+    autosave: Option<bool>,
+    colorswatch: Option<String>,
+    prose: Option<bool>,
+    wrapwidth: Option<usize>,
+    typewriter: Option<bool>,
+    textwidth: Option<usize>,
+    autowrap: Option<bool>,
+}
+
+impl LocalOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn store(&mut self, name: &str, options: &Options) -> bool {
+        match name {
+            "autosave" | "as" | "noautosave" | "noas" => self.autosave = Some(options.autosave),
+            "colorswatch" | "csw" => self.colorswatch = Some(options.colorswatch.clone()),
+            "prose" | "noprose" => self.prose = Some(options.prose),
+            "wrapwidth" | "ww" => self.wrapwidth = Some(options.wrapwidth),
+            "typewriter" | "notypewriter" => self.typewriter = Some(options.typewriter),
+            "textwidth" | "tw" => self.textwidth = Some(options.textwidth),
+            "autowrap" | "noautowrap" => self.autowrap = Some(options.autowrap),
+            _ => return false,
+        }
+        true
+    }
+
+    fn reset(&mut self, name: &str) -> bool {
+        match name {
+            "autosave" | "as" => self.autosave = None,
+            "colorswatch" | "csw" => self.colorswatch = None,
+            "prose" => self.prose = None,
+            "wrapwidth" | "ww" => self.wrapwidth = None,
+            "typewriter" => self.typewriter = None,
+            "textwidth" | "tw" => self.textwidth = None,
+            "autowrap" => self.autowrap = None,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enum_options_reject_unknown_values() {
+        let mut options = Options::new();
+        assert_eq!(options.set("bell=flash"), Ok("bell=flash".to_string()));
+        assert!(options.set("bell=loud").is_err());
+        assert_eq!(options.bell, "flash");
+        assert!(options.set("signclick=nothing").is_err());
+        assert_eq!(
+            options.set("pastechars=tab:>"),
+            Ok("pastechars=tab:>".to_string())
+        );
+    }
+}
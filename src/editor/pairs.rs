@@ -0,0 +1,242 @@
+use ropey::Rope;
+use std::ops::Range;
+
+/// Brackets that `%` matches and autopairs close
+pub const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+// How far from the cursor a textual search for an enclosing or matching
+// bracket looks, in chars, so a stray bracket in a huge file stays cheap
+const SCAN_LIMIT: usize = 100_000;
+
+pub fn closing_bracket(open: char) -> Option<char> {
+    BRACKETS
+        .iter()
+        .find(|(opening, _)| *opening == open)
+        .map(|(_, closing)| *closing)
+}
+
+pub fn is_closing_bracket(c: char) -> bool {
+    BRACKETS.iter().any(|(_, closing)| *closing == c)
+}
+
+/// Something autopairs closes: a bracket, a quote, or longer delimiters
+/// such as a markdown code fence.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pair {
+    pub open: String,
+    pub close: String,
+}
+
+impl Pair {
+    /// Parses a pair as the config writes it: two chars such as `()`, or
+    /// the opening and closing halves separated by a space, as in
+    /// `<!-- -->`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (open, close) = match text.split_once(' ') {
+            Some((open, close)) => (open.to_string(), close.trim().to_string()),
+            None => {
+                let mut chars = text.chars();
+                let pair = (chars.next()?.to_string(), chars.next()?.to_string());
+                if chars.next().is_some() {
+                    return None;
+                }
+                pair
+            }
+        };
+        (!open.is_empty() && !close.is_empty()).then_some(Self { open, close })
+    }
+
+    fn of(open: char, close: char) -> Self {
+        Self {
+            open: open.to_string(),
+            close: close.to_string(),
+        }
+    }
+
+    /// Whether the pair closes with the same text it opens with, as quotes
+    /// do.
+    pub fn is_quote(&self) -> bool {
+        self.open == self.close
+    }
+}
+
+/// The pairs autopairs closes in a file with `extension` when the config
+/// names none: the brackets and quotes, leaving out `'` in Rust for its
+/// lifetimes.
+pub fn default_pairs(extension: Option<&str>) -> Vec<Pair> {
+    let quotes: &[char] = match extension {
+        Some("rs") => &['"'],
+        _ => &['"', '\'', '`'],
+    };
+    BRACKETS
+        .iter()
+        .map(|&(open, close)| Pair::of(open, close))
+        .chain(quotes.iter().map(|&quote| Pair::of(quote, quote)))
+        .collect()
+}
+
+fn closes(pairs: &[Pair], c: char) -> bool {
+    pairs
+        .iter()
+        .any(|pair| !pair.is_quote() && pair.close.starts_with(c))
+}
+
+/// The closing half to insert after `c` typed with `before` (the end of
+/// the line up to the cursor) and `next` around it, if `c` completes the
+/// opening half of one of `pairs`; the longest such opening wins.
+pub fn pair_for<'a>(
+    pairs: &'a [Pair],
+    c: char,
+    before: &str,
+    next: Option<char>,
+) -> Option<&'a str> {
+    let before_space = next.is_none_or(|next| next.is_whitespace() || closes(pairs, next));
+    if !before_space {
+        return None;
+    }
+    let typed = format!("{}{}", before, c);
+    let pair = pairs
+        .iter()
+        .filter(|pair| typed.ends_with(&pair.open))
+        .max_by_key(|pair| pair.open.len())?;
+    let previous = before.chars().last();
+    let after_word = previous.is_some_and(|previous| previous.is_alphanumeric() || previous == c);
+    if pair.is_quote() && pair.open.chars().count() == 1 && after_word {
+        return None;
+    }
+    Some(&pair.close)
+}
+
+/// The pair among `pairs` whose closing half `c` steps over when typed
+/// right before it.
+pub fn closed_by(pairs: &[Pair], c: char) -> Option<&Pair> {
+    pairs.iter().find(|pair| pair.close.starts_with(c))
+}
+
+/// How many chars after the cursor Backspace removes along with the char
+/// before it: the closing half of a pair left empty between `before` and
+/// `after`, the text around the cursor on its line.
+pub fn empty_pair_length(pairs: &[Pair], before: &str, after: &str) -> usize {
+    pairs
+        .iter()
+        .filter(|pair| before.ends_with(&pair.open) && after.starts_with(&pair.close))
+        .map(|pair| pair.close.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Up to `reach` chars of the cursor's line on each side of `pos`.
+pub fn text_around(content: &Rope, pos: usize, reach: usize) -> (String, String) {
+    let line = content.char_to_line(pos);
+    let line_start = content.line_to_char(line);
+    let line_end = line_start + content.line(line).len_chars();
+    let before = content.slice(pos.saturating_sub(reach).max(line_start)..pos);
+    let after = content.slice(pos..(pos + reach).min(line_end));
+    (before.to_string(), after.to_string())
+}
+
+/// The bracket matching the one at `pos`, counting nested pairs of the
+/// same kind but reading strings and comments like any other text.
+pub fn matching_bracket(content: &Rope, pos: usize) -> Option<usize> {
+    let c = content.get_char(pos)?;
+    let (&(open, close), forward) = BRACKETS.iter().find_map(|pair| match c {
+        c if c == pair.0 => Some((pair, true)),
+        c if c == pair.1 => Some((pair, false)),
+        _ => None,
+    })?;
+    let mut depth = 0usize;
+    if forward {
+        let end = content.len_chars().min(pos + SCAN_LIMIT);
+        for (offset, c) in content.slice(pos..end).chars().enumerate() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos + offset);
+                }
+            }
+        }
+    } else {
+        let start = pos.saturating_sub(SCAN_LIMIT);
+        let mut chars = content.slice(start..=pos).chars_at(pos + 1 - start);
+        let mut index = pos + 1;
+        while let Some(c) = chars.prev() {
+            index -= 1;
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The argument around `pos` in the nearest enclosing `()` or `[]`, found
+/// by counting brackets and splitting on commas at the top level.
+pub fn argument_range(content: &Rope, pos: usize, inner: bool) -> Option<Range<usize>> {
+    let start = pos.saturating_sub(SCAN_LIMIT);
+    let end = content.len_chars().min(pos + SCAN_LIMIT);
+    let chars: Vec<char> = content.slice(start..end).chars().collect();
+    let cursor = pos - start;
+
+    // The opening bracket whose pair encloses the cursor
+    let mut depth = 0usize;
+    let open = (0..cursor.min(chars.len())).rev().find(|&index| {
+        match chars[index] {
+            ')' | ']' | '}' => depth += 1,
+            '(' | '[' | '{' if depth > 0 => depth -= 1,
+            '(' | '[' => return true,
+            _ => {}
+        }
+        false
+    })?;
+
+    // Commas at the top level of the pair, up to its closing bracket
+    let mut separators = vec![open];
+    let mut depth = 0usize;
+    let mut close = None;
+    for (index, &c) in chars.iter().enumerate().skip(open + 1) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth > 0 => depth -= 1,
+            ')' | ']' | '}' => {
+                close = Some(index);
+                break;
+            }
+            ',' if depth == 0 => separators.push(index),
+            _ => {}
+        }
+    }
+    let close = close?;
+    separators.push(close);
+
+    let slot = separators.windows(2).position(|pair| cursor <= pair[1])?;
+    let (before, after) = (separators[slot], separators[slot + 1]);
+    let trimmed_start = (before + 1..after)
+        .find(|&index| !chars[index].is_whitespace())
+        .unwrap_or(after);
+    let trimmed_end = (trimmed_start..after)
+        .rev()
+        .find(|&index| !chars[index].is_whitespace())
+        .map_or(trimmed_start, |index| index + 1);
+    let range = if inner {
+        trimmed_start..trimmed_end
+    } else if after != close {
+        // Take the comma after and the space before the next argument
+        let next = (after + 1..close)
+            .find(|&index| !chars[index].is_whitespace())
+            .unwrap_or(close);
+        trimmed_start..next
+    } else if before != open {
+        // The last argument takes the comma before it instead
+        before..trimmed_end
+    } else {
+        trimmed_start..trimmed_end
+    };
+    Some(start + range.start..start + range.end)
+}
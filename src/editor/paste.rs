@@ -0,0 +1,66 @@
+/// The characters a paste preview draws in place of invisible ones, from
+/// `pastechars` such as `tab:→,space:·,eol:↵`.
+pub struct Invisibles {
+    pub tab: char,
+    pub space: char,
+    pub eol: char,
+}
+
+impl Invisibles {
+    pub fn parse(spec: &str) -> Self {
+        let mut invisibles = Self {
+            tab: '→',
+            space: '·',
+            eol: '↵',
+        };
+        for part in spec.split(',') {
+            let Some((name, symbol)) = part.trim().split_once(':') else {
+                continue;
+            };
+            let mut chars = symbol.chars();
+            let (Some(symbol), None) = (chars.next(), chars.next()) else {
+                continue;
+            };
+            match name {
+                "tab" => invisibles.tab = symbol,
+                "space" => invisibles.space = symbol,
+                "eol" => invisibles.eol = symbol,
+                _ => {}
+            }
+        }
+        invisibles
+    }
+
+    /// `line` with tabs, spaces and the line ending drawn visibly and other
+    /// control characters as `^X`, in pieces that are flagged when they
+    /// stand for invisible characters.
+    pub fn show(&self, line: &str) -> Vec<(String, bool)> {
+        let (text, eol) = match line.strip_suffix('\n') {
+            Some(text) => (text, Some(self.eol)),
+            None => (line, None),
+        };
+        let mut pieces: Vec<(String, bool)> = Vec::new();
+        let mut push = |shown: String, invisible: bool| match pieces.last_mut() {
+            Some((last, flag)) if *flag == invisible => last.push_str(&shown),
+            _ => pieces.push((shown, invisible)),
+        };
+        for c in text.chars() {
+            match c {
+                '\t' => push(self.tab.to_string(), true),
+                ' ' => push(self.space.to_string(), true),
+                c if (c as u32) < 0x20 => push(format!("^{}", (c as u8 + b'@') as char), true),
+                c if c.is_control() => push(format!("<U+{:04X}>", c as u32), true),
+                c => push(c.to_string(), false),
+            }
+        }
+        if let Some(eol) = eol {
+            push(eol.to_string(), true);
+        }
+        pieces
+    }
+}
+
+/// Terminals send line breaks in pastes as `\r`; the buffer uses `\n`.
+pub fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
@@ -0,0 +1,289 @@
+use crate::editor::markdown_list::ListItem;
+use ropey::Rope;
+use std::ops::Range;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// Comment leaders that a reflowed comment repeats on each of its lines
+const COMMENT_LEADERS: &[&str] = &["///", "//!", "//", "#", "--", ";", ">"];
+
+// Closing quotes and brackets that may follow the punctuation ending a
+// sentence
+const SENTENCE_CLOSERS: &[char] = &[')', ']', '"', '\'', '’', '”'];
+
+/// The char offsets where the rows of `line` (without its line break)
+/// start when it is soft wrapped at `width` columns, beginning with 0.
+pub fn row_starts(line: &str, width: usize) -> Vec<usize> {
+    let width = width.max(1);
+    let mut starts = vec![0];
+    let mut row_start = 0;
+    let mut columns = 0;
+    // Where the row could break: just after its last space
+    let mut last_break = None;
+    let widths: Vec<usize> = line.chars().map(|c| c.width().unwrap_or(1)).collect();
+    for (index, c) in line.chars().enumerate() {
+        let char_width = widths[index];
+        let space = c == ' ' || c == '\t';
+        // Spaces may hang past the edge so that rows break after them
+        if columns + char_width > width && index > row_start && !space {
+            row_start = last_break.filter(|&at| at > row_start).unwrap_or(index);
+            starts.push(row_start);
+            columns = widths[row_start..index].iter().sum();
+            last_break = None;
+        }
+        columns += char_width;
+        if space {
+            last_break = Some(index + 1);
+        }
+    }
+    starts
+}
+
+/// A screen row of a soft wrapped line.
+#[derive(Clone, Copy, Debug)]
+pub struct Row {
+    pub line: usize,
+    /// The chars of the line the row holds
+    pub start: usize,
+    pub end: usize,
+    /// Whether the row ends its line
+    pub last: bool,
+}
+
+/// The rows line `line` of text `text` wraps into at `width` columns.
+pub fn line_rows(line: usize, text: &str, width: usize) -> Vec<Row> {
+    let starts = row_starts(text, width);
+    let length = text.chars().count();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, &start)| {
+            let next = starts.get(index + 1).copied();
+            Row {
+                line,
+                start,
+                end: next.unwrap_or(length),
+                last: next.is_none(),
+            }
+        })
+        .collect()
+}
+
+/// Which row of `starts` holds char `column` of the line.
+pub fn row_of(starts: &[usize], column: usize) -> usize {
+    starts
+        .partition_point(|&start| start <= column)
+        .saturating_sub(1)
+}
+
+fn is_sentence_start(content: &Rope, pos: usize) -> bool {
+    if content.get_char(pos).is_none_or(char::is_whitespace) {
+        return false;
+    }
+    let mut index = pos;
+    let mut newlines = 0;
+    while index > 0 && content.char(index - 1).is_whitespace() {
+        index -= 1;
+        newlines += (content.char(index) == '\n') as usize;
+    }
+    if index == 0 || newlines >= 2 {
+        return true;
+    }
+    if index == pos {
+        return false;
+    }
+    while index > 0 && SENTENCE_CLOSERS.contains(&content.char(index - 1)) {
+        index -= 1;
+    }
+    index > 0 && matches!(content.char(index - 1), '.' | '!' | '?')
+}
+
+/// The start of the sentence after the one at `pos` (`)`), or the end of
+/// the buffer.
+pub fn next_sentence_start(content: &Rope, pos: usize) -> usize {
+    let length = content.len_chars();
+    (pos + 1..length)
+        .find(|&index| is_sentence_start(content, index))
+        .unwrap_or(length)
+}
+
+/// The start of the sentence holding `pos`, or of the one before when
+/// `pos` is already at a start (`(`).
+pub fn previous_sentence_start(content: &Rope, pos: usize) -> usize {
+    (0..pos.min(content.len_chars()))
+        .rev()
+        .find(|&index| is_sentence_start(content, index))
+        .unwrap_or(0)
+}
+
+struct Prefix {
+    // The indent and comment leader, with the spaces after it
+    leader: String,
+    // The prefix the line keeps: the leader, then any list item marker
+    first: String,
+    // The prefix of the lines its text wraps onto: the leader, then spaces
+    // as wide as any list item marker
+    rest: String,
+    item: bool,
+}
+
+fn split_prefix(line: &str) -> (Prefix, &str) {
+    let trimmed = line.trim_start();
+    let mut end = line.len() - trimmed.len();
+    if let Some(comment) = COMMENT_LEADERS
+        .iter()
+        .find(|comment| trimmed.starts_with(**comment))
+    {
+        end += comment.len();
+        end += line[end..].len() - line[end..].trim_start().len();
+    }
+    let (leader, text) = line.split_at(end);
+    let Some(item) = ListItem::parse(text) else {
+        let prefix = Prefix {
+            leader: leader.to_string(),
+            first: leader.to_string(),
+            rest: leader.to_string(),
+            item: false,
+        };
+        return (prefix, text);
+    };
+    let marker = &text[..item.text_start];
+    let prefix = Prefix {
+        leader: leader.to_string(),
+        first: format!("{}{}", leader, marker),
+        rest: format!("{}{}", leader, " ".repeat(marker.width())),
+        item: true,
+    };
+    (prefix, &text[item.text_start..])
+}
+
+fn fill(prefix: &Prefix, words: &[&str], width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = prefix.first.clone();
+    let mut empty = true;
+    for word in words {
+        if !empty && line.width() + 1 + word.width() > width {
+            lines.push(std::mem::replace(&mut line, prefix.rest.clone()));
+            empty = true;
+        }
+        if !empty {
+            line.push(' ');
+        }
+        line.push_str(word);
+        empty = false;
+    }
+    lines.push(line);
+    lines
+}
+
+/// Re-wraps `lines` to `width` columns, as `gq` does.
+pub fn reflow(lines: &[&str], width: usize) -> Vec<String> {
+    let mut reflowed = Vec::new();
+    let mut paragraph: Option<(Prefix, Vec<&str>)> = None;
+    for line in lines {
+        let (prefix, text) = split_prefix(line);
+        if text.trim().is_empty() {
+            if let Some((prefix, words)) = paragraph.take() {
+                reflowed.extend(fill(&prefix, &words, width));
+            }
+            reflowed.push(line.to_string());
+            continue;
+        }
+        match &mut paragraph {
+            Some((first, words)) if !prefix.item && prefix.leader.trim() == first.leader.trim() => {
+                words.extend(text.split_whitespace());
+            }
+            _ => {
+                if let Some((prefix, words)) = paragraph.take() {
+                    reflowed.extend(fill(&prefix, &words, width));
+                }
+                paragraph = Some((prefix, text.split_whitespace().collect()));
+            }
+        }
+    }
+    if let Some((prefix, words)) = paragraph {
+        reflowed.extend(fill(&prefix, &words, width));
+    }
+    reflowed
+}
+
+/// Where typing has taken `line` past `width` columns and it should be
+/// broken: the bytes of the last run of spaces with no more than `width`
+/// columns before it, and the prefix the new line starts with.
+pub fn wrap_point(line: &str, width: usize) -> Option<(Range<usize>, String)> {
+    if line.width() <= width {
+        return None;
+    }
+    let (prefix, text) = split_prefix(line);
+    let text_start = line.len() - text.len();
+    let mut point = None;
+    let mut space_start = None;
+    for (index, c) in text.char_indices() {
+        let index = text_start + index;
+        if c == ' ' || c == '\t' {
+            space_start.get_or_insert(index);
+            continue;
+        }
+        if let Some(start) = space_start.take() {
+            if line[..start].width() > width {
+                break;
+            }
+            point = Some(start..index);
+        }
+    }
+    point.map(|point| (point, prefix.rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_break_after_spaces_or_inside_long_words() {
+        assert_eq!(row_starts("aaa bbb ccc", 8), [0, 8]);
+        assert_eq!(row_starts("abcdefghij", 4), [0, 4, 8]);
+        assert_eq!(row_of(&[0, 8], 7), 0);
+        assert_eq!(row_of(&[0, 8], 8), 1);
+
+        let rows = line_rows(3, "aaa bbb ccc", 8);
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[1].line, rows[1].start, rows[1].end), (3, 8, 11));
+        assert!(rows[1].last && !rows[0].last);
+    }
+
+    #[test]
+    fn sentences_start_after_their_punctuation() {
+        let content = Rope::from("One. Two!  Three");
+        assert_eq!(next_sentence_start(&content, 0), 5);
+        assert_eq!(next_sentence_start(&content, 5), 11);
+        assert_eq!(next_sentence_start(&content, 11), 16);
+        assert_eq!(previous_sentence_start(&content, 7), 5);
+        assert_eq!(previous_sentence_start(&content, 5), 0);
+
+        let quoted = Rope::from("He said \"Hi.\" Then\n\nNew");
+        assert_eq!(next_sentence_start(&quoted, 0), 14);
+        assert_eq!(next_sentence_start(&quoted, 14), 20);
+    }
+
+    #[test]
+    fn reflow_repeats_comment_leaders_and_indents_list_items() {
+        assert_eq!(
+            reflow(&["// one two three four"], 12),
+            ["// one two", "// three", "// four"]
+        );
+        assert_eq!(reflow(&["- one two three"], 9), ["- one two", "  three"]);
+        assert_eq!(
+            reflow(&["one", "two", "", "three"], 20),
+            ["one two", "", "three"]
+        );
+    }
+
+    #[test]
+    fn wraps_at_the_last_space_that_fits() {
+        assert_eq!(
+            wrap_point("// aaa bbb ccc", 10),
+            Some((10..11, "// ".to_string()))
+        );
+        assert_eq!(wrap_point("short", 10), None);
+        assert_eq!(wrap_point("averyveryverylongword", 10), None);
+    }
+}
@@ -0,0 +1,335 @@
+const INDENT: &str = "  ";
+
+/// Where reformatting failed, with `line` counted from 0 within the input.
+#[derive(Debug)]
+pub struct ReformatError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Pretty-prints or minifies JSON.
+pub fn json(text: &str, pretty: bool) -> Result<String, ReformatError> {
+    let mut formatter = JsonFormatter {
+        chars: text.chars().collect(),
+        position: 0,
+        line: 0,
+        out: String::new(),
+        pretty,
+    };
+    formatter.value(0)?;
+    formatter.skip_whitespace();
+    if formatter.position < formatter.chars.len() {
+        return Err(formatter.error("Unexpected characters after the JSON value"));
+    }
+    Ok(formatter.out)
+}
+
+struct JsonFormatter {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    out: String,
+    pretty: bool,
+}
+
+impl JsonFormatter {
+    fn error(&self, message: &str) -> ReformatError {
+        ReformatError {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            if c == '\n' {
+                self.line += 1;
+            }
+            self.position += 1;
+        }
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if self.pretty {
+            self.out.push('\n');
+            self.out.push_str(&INDENT.repeat(depth));
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<(), ReformatError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.container(depth, '{', '}'),
+            Some('[') => self.container(depth, '[', ']'),
+            Some('"') => self.string(),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t' | 'f' | 'n') => self.literal(),
+            Some(c) => Err(self.error(&format!("Unexpected '{}'", c))),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn container(&mut self, depth: usize, open: char, close: char) -> Result<(), ReformatError> {
+        self.position += 1;
+        self.out.push(open);
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.position += 1;
+            self.out.push(close);
+            return Ok(());
+        }
+
+        loop {
+            self.newline(depth + 1);
+            if open == '{' {
+                self.skip_whitespace();
+                if self.peek() != Some('"') {
+                    return Err(self.error("Expected a string key"));
+                }
+                self.string()?;
+                self.skip_whitespace();
+                if self.peek() != Some(':') {
+                    return Err(self.error("Expected ':' after the key"));
+                }
+                self.position += 1;
+                self.out.push_str(if self.pretty { ": " } else { ":" });
+            }
+            self.value(depth + 1)?;
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.position += 1;
+                    self.out.push(',');
+                }
+                Some(c) if c == close => {
+                    self.position += 1;
+                    self.newline(depth);
+                    self.out.push(close);
+                    return Ok(());
+                }
+                _ => return Err(self.error(&format!("Expected ',' or '{}'", close))),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<(), ReformatError> {
+        let start = self.position;
+        self.position += 1;
+        loop {
+            match self.peek() {
+                Some('"') => break,
+                Some('\\') => self.position += 2,
+                Some('\n') | None => return Err(self.error("Unterminated string")),
+                Some(_) => self.position += 1,
+            }
+        }
+        self.position += 1;
+        self.out.extend(&self.chars[start..self.position]);
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<(), ReformatError> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.position += 1;
+        }
+        let number: String = self.chars[start..self.position].iter().collect();
+        if number.parse::<f64>().is_err() {
+            return Err(self.error(&format!("Invalid number {}", number)));
+        }
+        self.out.push_str(&number);
+        Ok(())
+    }
+
+    fn literal(&mut self) -> Result<(), ReformatError> {
+        for literal in ["true", "false", "null"] {
+            let end = self.position + literal.len();
+            if self
+                .chars
+                .get(self.position..end)
+                .is_some_and(|chars| chars.iter().copied().eq(literal.chars()))
+            {
+                self.position = end;
+                self.out.push_str(literal);
+                return Ok(());
+            }
+        }
+        Err(self.error("Expected true, false or null"))
+    }
+}
+
+#[derive(Debug)]
+enum XmlToken {
+    Open(String),
+    Close(String),
+    // Self-closing tags, comments, declarations and CDATA
+    Leaf(String),
+    Text(String),
+}
+
+/// Pretty-prints or minifies XML, one element per line with elements that
+/// only hold text kept on one line.
+pub fn xml(text: &str, pretty: bool) -> Result<String, ReformatError> {
+    let tokens = xml_tokens(text)?;
+    let mut out = String::new();
+    let mut depth = 0;
+    let mut i = 0;
+
+    let push_line = |out: &mut String, depth: usize, text: &str| {
+        if pretty && !out.is_empty() {
+            out.push('\n');
+            out.push_str(&INDENT.repeat(depth));
+        }
+        out.push_str(text);
+    };
+
+    while i < tokens.len() {
+        match &tokens[i] {
+            XmlToken::Open(tag) => {
+                // Keep <a>text</a> and <a></a> together
+                match (tokens.get(i + 1), tokens.get(i + 2)) {
+                    (Some(XmlToken::Text(text)), Some(XmlToken::Close(close))) => {
+                        push_line(&mut out, depth, &format!("{}{}{}", tag, text, close));
+                        i += 3;
+                        continue;
+                    }
+                    (Some(XmlToken::Close(close)), _) => {
+                        push_line(&mut out, depth, &format!("{}{}", tag, close));
+                        i += 2;
+                        continue;
+                    }
+                    _ => {
+                        push_line(&mut out, depth, tag);
+                        depth += 1;
+                    }
+                }
+            }
+            XmlToken::Close(tag) => {
+                depth = depth.saturating_sub(1);
+                push_line(&mut out, depth, tag);
+            }
+            XmlToken::Leaf(text) | XmlToken::Text(text) => push_line(&mut out, depth, text),
+        }
+        i += 1;
+    }
+    Ok(out)
+}
+
+fn xml_tokens(text: &str) -> Result<Vec<XmlToken>, ReformatError> {
+    let mut tokens = Vec::new();
+    // Open elements with the line they started on
+    let mut stack: Vec<(String, usize)> = Vec::new();
+    let mut line = 0;
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let start_line = line;
+        let error = |message: String| ReformatError {
+            line: start_line,
+            message,
+        };
+
+        let (token, len) = if !rest.starts_with('<') {
+            let len = rest.find('<').unwrap_or(rest.len());
+            let text = rest[..len].trim();
+            let token = (!text.is_empty()).then(|| XmlToken::Text(text.to_string()));
+            (token, len)
+        } else {
+            let end_marker = if rest.starts_with("<!--") {
+                "-->"
+            } else if rest.starts_with("<![CDATA[") {
+                "]]>"
+            } else if rest.starts_with("<?") {
+                "?>"
+            } else {
+                ">"
+            };
+            let end = rest
+                .find(end_marker)
+                .ok_or_else(|| error("Unterminated tag".to_string()))?
+                + end_marker.len();
+            let tag = &rest[..end];
+
+            let token = if let Some(name) = tag.strip_prefix("</") {
+                let name = name.trim_end_matches('>').trim();
+                match stack.pop() {
+                    Some((open, _)) if open == name => XmlToken::Close(tag.to_string()),
+                    Some((open, _)) => {
+                        return Err(error(format!(
+                            "Mismatched closing tag </{}>, expected </{}>",
+                            name, open
+                        )))
+                    }
+                    None => return Err(error(format!("Unexpected closing tag </{}>", name))),
+                }
+            } else if tag.starts_with("<!") || tag.starts_with("<?") || tag.ends_with("/>") {
+                XmlToken::Leaf(tag.to_string())
+            } else {
+                let name = tag[1..tag.len() - 1]
+                    .split(|c: char| c.is_whitespace())
+                    .next()
+                    .unwrap_or_default();
+                stack.push((name.to_string(), start_line));
+                XmlToken::Open(tag.to_string())
+            };
+            (Some(token), end)
+        };
+
+        line += rest[..len].matches('\n').count();
+        tokens.extend(token);
+        rest = &rest[len..];
+    }
+
+    match stack.pop() {
+        Some((name, line)) => Err(ReformatError {
+            line,
+            message: format!("Unclosed tag <{}>", name),
+        }),
+        None => Ok(tokens),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_keeps_keys_and_numbers_as_written() {
+        let text = r#"{ "b": [1, 2.5e3, true], "a": {} }"#;
+        assert_eq!(json(text, false).unwrap(), r#"{"b":[1,2.5e3,true],"a":{}}"#);
+        assert_eq!(
+            json(r#"{"a": [1, null]}"#, true).unwrap(),
+            "{\n  \"a\": [\n    1,\n    null\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn json_errors_name_their_line() {
+        let error = json("{\n\"a\" 1}", true).unwrap_err();
+        assert_eq!(error.line, 1);
+        assert!(json("[1] x", false).is_err());
+        assert!(json("[1,", false).is_err());
+    }
+
+    #[test]
+    fn xml_keeps_text_elements_on_one_line() {
+        assert_eq!(
+            xml("<a><b>text</b><c/><d></d></a>", true).unwrap(),
+            "<a>\n  <b>text</b>\n  <c/>\n  <d></d>\n</a>"
+        );
+        assert_eq!(
+            xml("<a>\n  <b>x</b>\n</a>", false).unwrap(),
+            "<a><b>x</b></a>"
+        );
+        assert!(xml("<a><b></a>", true).is_err());
+        assert!(xml("<a", true).is_err());
+    }
+}
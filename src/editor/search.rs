@@ -0,0 +1,222 @@
+use crate::editor::{buffer::BufferId, options::Options};
+use ropey::Rope;
+use std::{cell::RefCell, ops::Range};
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A literal pattern looked for by `/`, `?`, `*`, `#` and `:s`.
+pub struct Search {
+    pattern: Vec<char>,
+    ignore_case: bool,
+    // Only match whole words, as `*` and `#` do
+    whole_word: bool,
+    pub forward: bool,
+    // Where the matches are in the last buffer version counted
+    matches: RefCell<Option<MatchCache>>,
+}
+
+struct MatchCache {
+    buffer: BufferId,
+    version: u64,
+    starts: Vec<usize>,
+}
+
+impl Search {
+    /// Ignores case when `ignorecase` is on, unless `smartcase` is on too
+    /// and the pattern has an uppercase letter.
+    pub fn new(pattern: &str, options: &Options, forward: bool) -> Self {
+        let ignore_case =
+            options.ignorecase && !(options.smartcase && pattern.chars().any(char::is_uppercase));
+        Self {
+            pattern: pattern.chars().collect(),
+            ignore_case,
+            whole_word: false,
+            forward,
+            matches: RefCell::new(None),
+        }
+    }
+
+    pub fn whole_word(mut self) -> Self {
+        self.whole_word = true;
+        self
+    }
+
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    pub fn pattern(&self) -> String {
+        self.pattern.iter().collect()
+    }
+
+    fn matches_at(&self, line: &[char], start: usize) -> bool {
+        let Some(candidate) = line.get(start..start + self.pattern.len()) else {
+            return false;
+        };
+        let same = candidate
+            .iter()
+            .zip(&self.pattern)
+            .all(|(&a, &b)| a == b || (self.ignore_case && a.to_lowercase().eq(b.to_lowercase())));
+        let end = start + self.pattern.len();
+        same && (!self.whole_word
+            || (start.checked_sub(1).is_none_or(|i| !is_word_char(line[i]))
+                && line.get(end).is_none_or(|&c| !is_word_char(c))))
+    }
+
+    /// Starts of the non-overlapping matches in `line`.
+    pub fn matches(&self, line: &[char]) -> Vec<usize> {
+        let mut matches = Vec::new();
+        if self.pattern.is_empty() {
+            return matches;
+        }
+        let mut start = 0;
+        while start + self.pattern.len() <= line.len() {
+            if self.matches_at(line, start) {
+                matches.push(start);
+                start += self.pattern.len();
+            } else {
+                start += 1;
+            }
+        }
+        matches
+    }
+
+    /// Which match starts at `position` and how many there are in the
+    /// buffer, as (index from 1, count).
+    pub fn match_index(
+        &self,
+        buffer: BufferId,
+        version: u64,
+        content: &Rope,
+        position: usize,
+    ) -> Option<(usize, usize)> {
+        let line = content.char_to_line(position);
+        let line_start = content.line_to_char(line);
+        let chars: Vec<char> = content.line(line).chars().collect();
+        if !self.matches_at(&chars, position - line_start) {
+            return None;
+        }
+
+        let mut cache = self.matches.borrow_mut();
+        if !cache
+            .as_ref()
+            .is_some_and(|cache| cache.buffer == buffer && cache.version == version)
+        {
+            let mut starts = Vec::new();
+            for (line, text) in content.lines().enumerate() {
+                let chars: Vec<char> = text.chars().collect();
+                let line_start = content.line_to_char(line);
+                starts.extend(
+                    self.matches(&chars)
+                        .into_iter()
+                        .map(|start| line_start + start),
+                );
+            }
+            *cache = Some(MatchCache {
+                buffer,
+                version,
+                starts,
+            });
+        }
+        let starts = &cache.as_ref()?.starts;
+        let index = starts.binary_search(&position).ok()?;
+        Some((index + 1, starts.len()))
+    }
+
+    /// The char offset of the nearest match after (or before) `from`,
+    /// wrapping around the ends of the buffer.
+    pub fn find(&self, content: &Rope, from: usize, forward: bool) -> Option<usize> {
+        let line_count = content.len_lines();
+        let from_line = content.char_to_line(from);
+        let column = from - content.line_to_char(from_line);
+
+        for step in 0..=line_count {
+            let line = if forward {
+                (from_line + step) % line_count
+            } else {
+                (from_line + line_count - step % line_count) % line_count
+            };
+            let chars: Vec<char> = content.line(line).chars().collect();
+            let matches = self.matches(&chars);
+            let found = match (forward, step) {
+                (true, 0) => matches.into_iter().find(|&start| start > column),
+                (false, 0) => matches.into_iter().rev().find(|&start| start < column),
+                (true, _) => matches.into_iter().next(),
+                (false, _) => matches.into_iter().next_back(),
+            };
+            if let Some(start) = found {
+                return Some(content.line_to_char(line) + start);
+            }
+        }
+        None
+    }
+}
+
+// Limits a substitution pattern to the last visual selection
+const SELECTION_ATOM: &str = "\\%V";
+
+/// The arguments of `:s/pattern/replacement/flags`.
+pub struct Substitution {
+    pub pattern: String,
+    pub replacement: String,
+    /// Replace every match on a line, not just the first (`g`)
+    pub global: bool,
+    /// `i` forces ignoring case and `I` matching it
+    pub ignore_case: Option<bool>,
+    /// Only replace matches inside the last visual selection (`\%V`)
+    pub in_selection: bool,
+}
+
+impl Substitution {
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let usage = || "Usage: :s/pattern/replacement/[g][i|I]".to_string();
+        let delimiter = args.chars().next().ok_or_else(usage)?;
+        let mut parts = args[delimiter.len_utf8()..].splitn(3, delimiter);
+        let pattern = parts.next().ok_or_else(usage)?;
+        let in_selection = pattern.contains(SELECTION_ATOM);
+        let pattern = pattern.replace(SELECTION_ATOM, "");
+        let replacement = parts.next().ok_or_else(usage)?.to_string();
+
+        let mut substitution = Self {
+            pattern,
+            replacement,
+            global: false,
+            ignore_case: None,
+            in_selection,
+        };
+        for flag in parts.next().unwrap_or_default().trim().chars() {
+            match flag {
+                'g' => substitution.global = true,
+                'i' => substitution.ignore_case = Some(true),
+                'I' => substitution.ignore_case = Some(false),
+                _ => return Err(format!("Unknown substitute flag: {}", flag)),
+            }
+        }
+        Ok(substitution)
+    }
+
+    /// Replaces the matches of `search` that lie within the `columns` of
+    /// `line`, returning how many were replaced.
+    pub fn apply(&self, search: &Search, line: &str, columns: Range<usize>) -> (String, usize) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut matches = search.matches(&chars);
+        matches
+            .retain(|&start| start >= columns.start && start + search.pattern.len() <= columns.end);
+        if !self.global {
+            matches.truncate(1);
+        }
+
+        let mut result = String::with_capacity(line.len());
+        let mut position = 0;
+        for &start in &matches {
+            result.extend(&chars[position..start]);
+            result.push_str(&self.replacement);
+            position = start + search.pattern.len();
+        }
+        result.extend(&chars[position..]);
+        (result, matches.len())
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// A symbol shown in the sign column beside a line.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sign {
+    pub symbol: char,
+    /// Names the color the theme gives the sign, e.g. `error` or `git_added`
+    pub group: String,
+    /// When several signs share a line, the one with the highest priority
+    /// is shown
+    pub priority: u16,
+}
+
+impl Sign {
+    pub fn new(symbol: char, group: &str, priority: u16) -> Self {
+        Self {
+            symbol,
+            group: group.to_string(),
+            priority,
+        }
+    }
+}
+
+/// The signs of one buffer, kept per source (`lsp`, `git`, `marks`, ...)
+/// so each can replace its own signs without touching the others'.
+#[derive(Default)]
+pub struct SignColumn {
+    sources: HashMap<String, BTreeMap<usize, Sign>>,
+}
+
+impl SignColumn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn place(&mut self, source: &str, line: usize, sign: Sign) {
+        self.sources
+            .entry(source.to_string())
+            .or_default()
+            .insert(line, sign);
+    }
+
+    pub fn unplace(&mut self, source: &str, line: usize) {
+        if let Some(signs) = self.sources.get_mut(source) {
+            signs.remove(&line);
+        }
+    }
+
+    /// Places `sign` on `line`, or removes the source's sign there if it
+    /// already has one.
+    pub fn toggle(&mut self, source: &str, line: usize, sign: Sign) -> bool {
+        let signs = self.sources.entry(source.to_string()).or_default();
+        if signs.remove(&line).is_some() {
+            return false;
+        }
+        signs.insert(line, sign);
+        true
+    }
+
+    /// The lines a source has signs on, in order.
+    pub fn lines(&self, source: &str) -> Vec<usize> {
+        self.sources
+            .get(source)
+            .map(|signs| signs.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&mut self, source: &str) {
+        self.sources.remove(source);
+    }
+
+    /// The sign to show on `line`.
+    pub fn at(&self, line: usize) -> Option<&Sign> {
+        self.sources
+            .iter()
+            .filter_map(|(source, signs)| Some((source, signs.get(&line)?)))
+            .max_by(|(a_source, a), (b_source, b)| {
+                a.priority
+                    .cmp(&b.priority)
+                    .then_with(|| b_source.cmp(a_source))
+            })
+            .map(|(_, sign)| sign)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.values().all(|signs| signs.is_empty())
+    }
+}
@@ -0,0 +1,310 @@
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HighlightKind {
+    Keyword,
+    String,
+    Comment,
+    Number,
+}
+
+/// A highlighted run of chars within a single line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HighlightSpan {
+    pub start: usize,
+    pub end: usize,
+    pub kind: HighlightKind,
+}
+
+/// Scanner state carried from the end of one line to the start of the next.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum LineState {
+    #[default]
+    Normal,
+    InBlockComment,
+}
+
+pub struct Syntax {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    string_delimiters: &'static [char],
+}
+
+const SYNTAXES: &[Syntax] = &[
+    Syntax {
+        name: "rust",
+        extensions: &["rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        string_delimiters: &['"'],
+    },
+    Syntax {
+        name: "python",
+        extensions: &["py", "pyi"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "True", "try", "while", "with", "yield",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        string_delimiters: &['"', '\''],
+    },
+    Syntax {
+        name: "javascript",
+        extensions: &["js", "jsx", "mjs", "cjs", "ts", "tsx"],
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "false",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "null",
+            "return",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "type",
+            "typeof",
+            "undefined",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        string_delimiters: &['"', '\'', '`'],
+    },
+    Syntax {
+        name: "c",
+        extensions: &["c", "h", "cc", "cpp", "cxx", "hpp", "hh"],
+        keywords: &[
+            "auto",
+            "break",
+            "case",
+            "char",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extern",
+            "false",
+            "float",
+            "for",
+            "goto",
+            "if",
+            "inline",
+            "int",
+            "long",
+            "namespace",
+            "new",
+            "nullptr",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "short",
+            "signed",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "template",
+            "this",
+            "true",
+            "typedef",
+            "union",
+            "unsigned",
+            "using",
+            "virtual",
+            "void",
+            "volatile",
+            "while",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        string_delimiters: &['"', '\''],
+    },
+    Syntax {
+        name: "go",
+        extensions: &["go"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "fallthrough",
+            "false",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "nil",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "true",
+            "type",
+            "var",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        string_delimiters: &['"', '\'', '`'],
+    },
+    Syntax {
+        name: "shell",
+        extensions: &["sh", "bash", "zsh"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        string_delimiters: &['"', '\''],
+    },
+];
+
+impl Syntax {
+    pub fn for_path(path: &Path) -> Option<&'static Syntax> {
+        let extension = path.extension()?.to_str()?;
+        SYNTAXES
+            .iter()
+            .find(|syntax| syntax.extensions.contains(&extension))
+    }
+
+    /// Highlights one line (without its line break), starting in `state`,
+    /// and returns the spans together with the state at the end of the line.
+    pub fn highlight_line(&self, line: &str, state: LineState) -> (Vec<HighlightSpan>, LineState) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut spans = Vec::new();
+        let mut state = state;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let start = i;
+
+            if state == LineState::InBlockComment {
+                let (_, end) = self.block_comment.unwrap_or_default();
+                match find(&chars, i, end) {
+                    Some(close) => {
+                        i = close + end.chars().count();
+                        state = LineState::Normal;
+                    }
+                    None => i = chars.len(),
+                }
+                spans.push(span(start, i, HighlightKind::Comment));
+                continue;
+            }
+
+            if self
+                .line_comment
+                .is_some_and(|prefix| starts_with(&chars, i, prefix))
+            {
+                spans.push(span(start, chars.len(), HighlightKind::Comment));
+                break;
+            }
+
+            if let Some((open, close)) = self.block_comment {
+                if starts_with(&chars, i, open) {
+                    i = match find(&chars, i + open.chars().count(), close) {
+                        Some(end) => end + close.chars().count(),
+                        None => {
+                            state = LineState::InBlockComment;
+                            chars.len()
+                        }
+                    };
+                    spans.push(span(start, i, HighlightKind::Comment));
+                    continue;
+                }
+            }
+
+            let c = chars[i];
+            if self.string_delimiters.contains(&c) {
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(chars.len());
+                spans.push(span(start, i, HighlightKind::String));
+            } else if c.is_alphanumeric() || c == '_' {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if c.is_ascii_digit() {
+                    spans.push(span(start, i, HighlightKind::Number));
+                } else if self.keywords.contains(&word.as_str()) {
+                    spans.push(span(start, i, HighlightKind::Keyword));
+                }
+            } else {
+                i += 1;
+            }
+        }
+
+        (spans, state)
+    }
+}
+
+fn span(start: usize, end: usize, kind: HighlightKind) -> HighlightSpan {
+    HighlightSpan { start, end, kind }
+}
+
+fn starts_with(chars: &[char], at: usize, pattern: &str) -> bool {
+    (at..)
+        .zip(pattern.chars())
+        .all(|(index, c)| chars.get(index) == Some(&c))
+}
+
+fn find(chars: &[char], from: usize, pattern: &str) -> Option<usize> {
+    (from..chars.len()).find(|&i| starts_with(chars, i, pattern))
+}
@@ -0,0 +1,253 @@
+use std::{ops::Range, path::Path};
+use tree_sitter::{Language, Node, Parser, Tree};
+
+struct Grammar {
+    extensions: &'static [&'static str],
+    language: fn() -> Language,
+    // Whether brackets are tokens of their own in the tree
+    brackets: bool,
+    // Nodes whose text is not code, where brackets and quotes are left
+    // alone
+    literals: &'static [&'static str],
+    // Nodes `if` and `af` select, with the field holding the inside
+    functions: &'static [(&'static str, &'static str)],
+    // Nodes whose named children are the arguments `ia` and `aa` select
+    argument_lists: &'static [&'static str],
+    // Nodes where `<` and `>` pair up like brackets
+    angle_brackets: &'static [&'static str],
+}
+
+const GRAMMARS: &[Grammar] = &[
+    Grammar {
+        extensions: &["rs"],
+        language: || tree_sitter_rust::LANGUAGE.into(),
+        brackets: true,
+        literals: &[
+            "string_literal",
+            "raw_string_literal",
+            "char_literal",
+            "line_comment",
+            "block_comment",
+        ],
+        functions: &[("function_item", "body"), ("closure_expression", "body")],
+        argument_lists: &[
+            "arguments",
+            "parameters",
+            "closure_parameters",
+            "type_arguments",
+            "type_parameters",
+            "tuple_expression",
+            "tuple_pattern",
+            "tuple_type",
+            "array_expression",
+        ],
+        angle_brackets: &["type_arguments", "type_parameters"],
+    },
+    Grammar {
+        extensions: &["md", "markdown"],
+        language: || tree_sitter_md::LANGUAGE.into(),
+        brackets: false,
+        literals: &[],
+        // The unit of code in a document is a fenced code block
+        functions: &[("fenced_code_block", "code_fence_content")],
+        argument_lists: &[],
+        angle_brackets: &[],
+    },
+];
+
+const BRACKET_PAIRS: &[(&str, &str)] = &[("(", ")"), ("[", "]"), ("{", "}"), ("<", ">")];
+
+/// A buffer's text parsed with the grammar for its language.
+pub struct SyntaxTree {
+    tree: Tree,
+    grammar: &'static Grammar,
+}
+
+impl SyntaxTree {
+    /// Parses `text` if there is a grammar for the extension of `path`.
+    pub fn parse(path: &Path, text: &str) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        let grammar = GRAMMARS
+            .iter()
+            .find(|grammar| grammar.extensions.contains(&extension))?;
+        let mut parser = Parser::new();
+        parser.set_language(&(grammar.language)()).ok()?;
+        let tree = parser.parse(text, None)?;
+        Some(Self { tree, grammar })
+    }
+
+    fn node_at(&self, byte: usize) -> Option<Node<'_>> {
+        self.tree
+            .root_node()
+            .descendant_for_byte_range(byte, byte + 1)
+    }
+
+    fn enclosing<'tree>(
+        &'tree self,
+        byte: usize,
+        accept: impl Fn(&Node<'tree>) -> bool,
+    ) -> Option<Node<'tree>> {
+        let mut node = self.node_at(byte);
+        while let Some(current) = node {
+            if accept(&current) {
+                return Some(current);
+            }
+            node = current.parent();
+        }
+        None
+    }
+
+    /// Whether `byte` is inside a string, character literal or comment, or
+    /// None when the grammar has no such nodes.
+    pub fn in_literal(&self, byte: usize) -> Option<bool> {
+        if self.grammar.literals.is_empty() {
+            return None;
+        }
+        let literal = self.enclosing(byte, |node| self.grammar.literals.contains(&node.kind()));
+        Some(literal.is_some_and(|literal| literal.start_byte() < byte))
+    }
+
+    /// Whether the char at `byte` is a bracket of the code rather than part
+    /// of a string or comment, or None when the grammar does not say.
+    pub fn is_bracket(&self, byte: usize) -> Option<bool> {
+        if !self.grammar.brackets {
+            return None;
+        }
+        let node = self.node_at(byte)?;
+        Some(node.start_byte() == byte && self.pair_kind(&node).is_some())
+    }
+
+    fn pair_kind(&self, node: &Node) -> Option<(&'static str, bool)> {
+        let kind = node.kind();
+        let (open, close) = BRACKET_PAIRS
+            .iter()
+            .find(|(open, close)| kind == *open || kind == *close)?;
+        if *open == "<" {
+            let parent = node.parent()?;
+            if !self.grammar.angle_brackets.contains(&parent.kind()) {
+                return None;
+            }
+        }
+        Some(if kind == *open {
+            (close, true)
+        } else {
+            (open, false)
+        })
+    }
+
+    /// The bracket paired with the one at `byte`: the other bracket of the
+    /// same node, so brackets in strings and comments are never matched.
+    pub fn matching_bracket(&self, byte: usize) -> Option<usize> {
+        if !self.grammar.brackets {
+            return None;
+        }
+        let node = self
+            .node_at(byte)
+            .filter(|node| node.start_byte() == byte)?;
+        let (other, forward) = self.pair_kind(&node)?;
+        let parent = node.parent()?;
+        let mut cursor = parent.walk();
+        let siblings: Vec<Node> = parent.children(&mut cursor).collect();
+        let index = siblings
+            .iter()
+            .position(|sibling| sibling.id() == node.id())?;
+        let found = if forward {
+            siblings[index + 1..]
+                .iter()
+                .find(|sibling| sibling.kind() == other)
+        } else {
+            siblings[..index]
+                .iter()
+                .rev()
+                .find(|sibling| sibling.kind() == other)
+        };
+        found.map(Node::start_byte)
+    }
+
+    /// The function around `byte`, or with `inner` its body without the
+    /// braces.
+    pub fn function_range(&self, text: &str, byte: usize, inner: bool) -> Option<Range<usize>> {
+        let function = self.enclosing(byte, |node| {
+            self.grammar
+                .functions
+                .iter()
+                .any(|(kind, _)| node.kind() == *kind)
+        })?;
+        if !inner {
+            return Some(function.byte_range());
+        }
+        let (_, field) = self
+            .grammar
+            .functions
+            .iter()
+            .find(|(kind, _)| function.kind() == *kind)?;
+        let body = function.child_by_field_name(field).or_else(|| {
+            // Markdown gives the content of a code block no field name
+            let mut cursor = function.walk();
+            let content = function
+                .children(&mut cursor)
+                .find(|child| child.kind() == *field);
+            content
+        })?;
+        let mut range = body.byte_range();
+        if body.kind() == "block" {
+            range = range.start + 1..range.end - 1;
+        }
+        Some(whole_lines(text, range))
+    }
+
+    /// The argument around `byte` in a call, parameter list, tuple or
+    /// array.
+    pub fn argument_range(&self, text: &str, byte: usize, inner: bool) -> Option<Range<usize>> {
+        let list = self.enclosing(byte, |node| {
+            self.grammar.argument_lists.contains(&node.kind())
+                && node.start_byte() < byte
+                && byte < node.end_byte()
+        })?;
+        let mut cursor = list.walk();
+        let children: Vec<Node> = list.children(&mut cursor).collect();
+        // The argument is the named child the cursor is on, or the first
+        // after it when the cursor is on a comma or space
+        let index = children
+            .iter()
+            .position(|child| child.is_named() && child.end_byte() > byte)?;
+        let argument = children[index];
+        let range = argument.byte_range();
+        if inner {
+            return Some(range);
+        }
+        let is_comma = |node: &Node| node.kind() == ",";
+        if let Some(comma) = children.get(index + 1).filter(|node| is_comma(node)) {
+            // Up to the next argument, or the closing bracket
+            let end = children
+                .get(index + 2)
+                .filter(|node| node.is_named())
+                .map_or(comma.end_byte(), Node::start_byte);
+            return Some(range.start..end);
+        }
+        match index.checked_sub(1).map(|before| children[before]) {
+            Some(comma) if is_comma(&comma) => {
+                let start = text[..comma.start_byte()].trim_end().len();
+                Some(start..range.end)
+            }
+            _ => Some(range),
+        }
+    }
+}
+
+fn whole_lines(text: &str, range: Range<usize>) -> Range<usize> {
+    let inside = &text[range.clone()];
+    let Some(first_break) = inside.find('\n') else {
+        return range;
+    };
+    let Some(last_break) = inside.rfind('\n') else {
+        return range;
+    };
+    let leading_blank = inside[..first_break].trim().is_empty();
+    let trailing_blank = inside[last_break..].trim().is_empty();
+    if leading_blank && trailing_blank && first_break < last_break {
+        range.start + first_break + 1..range.start + last_break + 1
+    } else {
+        range
+    }
+}
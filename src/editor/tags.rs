@@ -0,0 +1,92 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Where a tag's definition is in its file.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TagAddress {
+    /// A line number, counted from 1 as ctags writes it
+    Line(usize),
+    /// The whole text of the line, from a `/^...$/` search pattern
+    Pattern(String),
+}
+
+/// A symbol definition read from a ctags `tags` file.
+#[derive(Clone, Debug)]
+pub struct Tag {
+    pub name: String,
+    /// Absolute, resolved against the directory of the tags file
+    pub file: PathBuf,
+    pub address: TagAddress,
+    /// Such as `function` or `struct`, when ctags recorded it
+    pub kind: Option<String>,
+}
+
+impl Tag {
+    fn parse(line: &str, directory: &Path) -> Option<Self> {
+        if line.starts_with("!_TAG_") {
+            return None;
+        }
+        let mut parts = line.splitn(3, '\t');
+        let name = parts.next()?;
+        let file = parts.next()?;
+        let rest = parts.next()?;
+        let (address, fields) = match rest.rfind(";\"") {
+            Some(end) => (&rest[..end], &rest[end + 2..]),
+            None => (rest, ""),
+        };
+
+        let address = if let Ok(line) = address.trim().parse() {
+            TagAddress::Line(line)
+        } else {
+            let pattern = address
+                .trim()
+                .strip_prefix(['/', '?'])?
+                .strip_suffix(['/', '?'])?;
+            let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+            let pattern = pattern.strip_suffix('$').unwrap_or(pattern);
+            TagAddress::Pattern(pattern.replace("\\/", "/").replace("\\\\", "\\"))
+        };
+
+        // The kind is either a bare letter or word, or `kind:name`
+        let kind = fields
+            .split('\t')
+            .map(str::trim)
+            .find(|field| !field.is_empty() && (!field.contains(':') || field.starts_with("kind:")))
+            .map(|field| field.trim_start_matches("kind:").to_string());
+
+        Some(Tag {
+            name: name.to_string(),
+            file: directory.join(file),
+            address,
+            kind,
+        })
+    }
+
+    /// The line of `text` the tag points at, counted from 0.
+    pub fn find_line(&self, text: &str) -> Option<usize> {
+        match &self.address {
+            TagAddress::Line(line) => Some(line.saturating_sub(1)),
+            TagAddress::Pattern(pattern) => text.lines().position(|line| line == pattern),
+        }
+    }
+}
+
+/// The `tags` file in `directory` or the nearest directory above it.
+pub fn find_tags_file(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .map(|directory| directory.join("tags"))
+        .find(|path| path.is_file())
+}
+
+/// Reads every tag from a tags file, skipping lines it cannot parse.
+pub fn load_tags(path: &Path) -> io::Result<Vec<Tag>> {
+    let content = fs::read_to_string(path)?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    Ok(content
+        .lines()
+        .filter_map(|line| Tag::parse(line, directory))
+        .collect())
+}
@@ -0,0 +1,106 @@
+use crate::editor::hooks;
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    process::{self, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TaskStatus {
+    Running,
+    /// Exit code, or None when the task was killed by a signal
+    Exited(Option<i32>),
+}
+
+#[derive(Default)]
+struct TaskOutput {
+    // Output not yet copied into the task's buffer
+    pending: String,
+    status: Option<Option<i32>>,
+}
+
+/// A configured task running in the background.
+pub struct RunningTask {
+    pub name: String,
+    output: Arc<Mutex<TaskOutput>>,
+    child: Arc<Mutex<process::Child>>,
+}
+
+impl RunningTask {
+    pub fn spawn(name: &str, command: &str, directory: &Path) -> io::Result<Self> {
+        let mut child = hooks::shell_command(command)
+            .current_dir(directory)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let output = Arc::new(Mutex::new(TaskOutput::default()));
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let shared = Arc::clone(&output);
+        let child = Arc::new(Mutex::new(child));
+        let waited = Arc::clone(&child);
+        thread::spawn(move || {
+            let stderr = stderr.map(|stderr| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || stream(stderr, &shared))
+            });
+            if let Some(stdout) = stdout {
+                stream(stdout, &shared);
+            }
+            if let Some(stderr) = stderr {
+                let _ = stderr.join();
+            }
+
+            let code = waited
+                .lock()
+                .ok()
+                .and_then(|mut child| child.wait().ok())
+                .and_then(|status| status.code());
+            if let Ok(mut output) = shared.lock() {
+                output.status = Some(code);
+            }
+        });
+
+        Ok(Self {
+            name: name.to_string(),
+            output,
+            child,
+        })
+    }
+
+    /// Kills the task's process.
+    pub fn kill(&self) -> io::Result<()> {
+        match self.child.try_lock() {
+            Ok(mut child) => child.kill(),
+            // Already being waited on once its output ended
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Returns the output produced since the last call and the status.
+    pub fn take_output(&self) -> (String, TaskStatus) {
+        let Ok(mut output) = self.output.lock() else {
+            return (String::new(), TaskStatus::Exited(None));
+        };
+        let status = match output.status {
+            Some(code) => TaskStatus::Exited(code),
+            None => TaskStatus::Running,
+        };
+        (std::mem::take(&mut output.pending), status)
+    }
+}
+
+fn stream(pipe: impl Read, output: &Mutex<TaskOutput>) {
+    let mut reader = BufReader::new(pipe);
+    let mut line = Vec::new();
+    while matches!(reader.read_until(b'\n', &mut line), Ok(1..)) {
+        if let Ok(mut output) = output.lock() {
+            output.pending.push_str(&String::from_utf8_lossy(&line));
+        }
+        line.clear();
+    }
+}
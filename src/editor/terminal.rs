@@ -0,0 +1,145 @@
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use std::{
+    env,
+    io::{self, Read, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+};
+
+// Lines of output kept for scrolling back in normal mode.
+const SCROLLBACK_LINES: usize = 10_000;
+
+/// A shell running in a pseudo terminal.
+pub struct TerminalPane {
+    parser: Arc<Mutex<vt100::Parser>>,
+    output: Arc<AtomicBool>,
+    writer: Box<dyn Write + Send>,
+    master: Box<dyn MasterPty + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    size: (u16, u16),
+}
+
+impl TerminalPane {
+    /// Starts the user's shell (`$SHELL`, falling back to the platform's
+    /// default) in `directory`.
+    pub fn spawn(directory: &Path, rows: u16, cols: u16) -> io::Result<Self> {
+        let size = PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        let pair = native_pty_system().openpty(size).map_err(pty_error)?;
+
+        let mut command = CommandBuilder::new(default_shell());
+        command.cwd(directory);
+        let child = pair.slave.spawn_command(command).map_err(pty_error)?;
+        // The child holds its own handle; ours would keep the pty open
+        // after the shell exits
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader().map_err(pty_error)?;
+        let writer = pair.master.take_writer().map_err(pty_error)?;
+        let parser = Arc::new(Mutex::new(vt100::Parser::new(rows, cols, SCROLLBACK_LINES)));
+        let output = Arc::new(AtomicBool::new(false));
+
+        let (thread_parser, thread_output) = (Arc::clone(&parser), Arc::clone(&output));
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            while let Ok(read @ 1..) = reader.read(&mut buf) {
+                if let Ok(mut parser) = thread_parser.lock() {
+                    parser.process(&buf[..read]);
+                }
+                thread_output.store(true, Ordering::Release);
+            }
+            // Wake the frontend once more so it notices the exit
+            thread_output.store(true, Ordering::Release);
+        });
+
+        Ok(Self {
+            parser,
+            output,
+            writer,
+            master: pair.master,
+            child,
+            size: (rows, cols),
+        })
+    }
+
+    pub fn write_input(&mut self, bytes: &[u8]) -> io::Result<()> {
+        // Typing returns the view to the live screen
+        self.scroll_to_bottom();
+        self.writer.write_all(bytes)?;
+        self.writer.flush()
+    }
+
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        if self.size == (rows, cols) || rows == 0 || cols == 0 {
+            return;
+        }
+        self.size = (rows, cols);
+        if let Ok(mut parser) = self.parser.lock() {
+            parser.set_size(rows, cols);
+        }
+        let _ = self.master.resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        });
+    }
+
+    /// Moves the view `lines` further back into the scrollback (negative
+    /// values move towards the live screen).
+    pub fn scroll(&mut self, lines: isize) {
+        if let Ok(mut parser) = self.parser.lock() {
+            let offset = parser.screen().scrollback().saturating_add_signed(lines);
+            parser.set_scrollback(offset);
+        }
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        if let Ok(mut parser) = self.parser.lock() {
+            parser.set_scrollback(0);
+        }
+    }
+
+    pub fn parser(&self) -> MutexGuard<'_, vt100::Parser> {
+        self.parser.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether the shell printed anything since the last call.
+    pub fn take_output(&self) -> bool {
+        self.output.swap(false, Ordering::Acquire)
+    }
+
+    pub fn has_exited(&mut self) -> bool {
+        !matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for TerminalPane {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        // Reap the shell so that it does not linger as a zombie
+        let _ = self.child.wait();
+    }
+}
+
+fn default_shell() -> String {
+    env::var("SHELL").unwrap_or_else(|_| {
+        if cfg!(windows) {
+            "cmd.exe".to_string()
+        } else {
+            "/bin/sh".to_string()
+        }
+    })
+}
+
+fn pty_error(e: impl std::fmt::Display) -> io::Error {
+    io::Error::other(e.to_string())
+}
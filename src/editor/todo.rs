@@ -0,0 +1,93 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Files larger than this are left out of `:todo`
+const MAX_SCANNED_BYTES: u64 = 4 * 1024 * 1024;
+// Characters of a line kept for the list
+const TEXT_CHARS: usize = 100;
+
+/// A keyword such as `TODO` found in a line, as char offsets.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeywordSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A line of a file that has a keyword, for the `:todo` list.
+pub struct TodoItem {
+    pub path: PathBuf,
+    /// Counted from 0
+    pub line: usize,
+    pub text: String,
+}
+
+/// The keywords of a `todokeywords` value such as `TODO,FIXME,HACK`.
+pub fn parse_keywords(spec: &str) -> Vec<String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|keyword| !keyword.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Finds the keywords in `line` that stand as whole words, matching case
+/// so that `todo!()` or a variable named `hack` are left alone.
+pub fn find_keywords(line: &str, keywords: &[String]) -> Vec<KeywordSpan> {
+    let chars: Vec<char> = line.chars().collect();
+    let is_word_char = |index: usize| {
+        chars
+            .get(index)
+            .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+    };
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let boundary = i == 0 || !is_word_char(i - 1);
+        let found = keywords.iter().find(|keyword| {
+            let length = keyword.chars().count();
+            boundary
+                && chars[i..].len() >= length
+                && chars[i..i + length].iter().copied().eq(keyword.chars())
+                && !is_word_char(i + length)
+        });
+        match found {
+            Some(keyword) => {
+                let end = i + keyword.chars().count();
+                spans.push(KeywordSpan { start: i, end });
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+    spans
+}
+
+/// The lines of `text` with a keyword, as items of `path`.
+pub fn scan_text(path: &Path, text: &str, keywords: &[String]) -> Vec<TodoItem> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            if find_keywords(line, keywords).is_empty() {
+                return None;
+            }
+            Some(TodoItem {
+                path: path.to_path_buf(),
+                line: index,
+                text: line.trim().chars().take(TEXT_CHARS).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Like `scan_text` for a file on disk, skipping large and binary files.
+pub fn scan_file(path: &Path, keywords: &[String]) -> Vec<TodoItem> {
+    if fs::metadata(path).map_or(true, |metadata| metadata.len() > MAX_SCANNED_BYTES) {
+        return Vec::new();
+    }
+    match fs::read_to_string(path) {
+        Ok(text) if !text.contains('\0') => scan_text(path, &text, keywords),
+        _ => Vec::new(),
+    }
+}
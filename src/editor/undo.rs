@@ -0,0 +1,138 @@
+use crate::editor::buffer::Edit;
+
+// A count of chars at an offset and the text that replaces them
+pub type Replacement = (usize, usize, String);
+
+// An edit with the text it removed and inserted, enough to undo and redo it
+struct Change {
+    edit: Edit,
+    removed: String,
+    inserted: String,
+}
+
+// The changes `u` undoes together, with the cursor from before and after them
+struct Step {
+    changes: Vec<Change>,
+    cursor_before: usize,
+    cursor_after: usize,
+}
+
+/// The changes made to a buffer, grouped into steps for `u` and `Ctrl-r`.
+#[derive(Default)]
+pub struct UndoStack {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    // Changes since the last step was closed
+    open: Vec<Change>,
+    // The cursor when the last step was closed, where the open one started
+    cursor: usize,
+}
+
+impl UndoStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, edit: Edit, removed: String, inserted: String) {
+        self.open.push(Change {
+            edit,
+            removed,
+            inserted,
+        });
+    }
+
+    /// Ends the step the changes since the last call belong to, with the
+    /// cursor where it is now.
+    pub fn close(&mut self, cursor: usize) {
+        if !self.open.is_empty() {
+            self.undo.push(Step {
+                changes: std::mem::take(&mut self.open),
+                cursor_before: self.cursor,
+                cursor_after: cursor,
+            });
+            self.redo.clear();
+        }
+        self.cursor = cursor;
+    }
+
+    /// The replacements that undo the last step, in order, and the cursor
+    /// to put back, or None when there is nothing to undo.
+    pub fn undo(&mut self) -> Option<(Vec<Replacement>, usize)> {
+        let step = self.undo.pop()?;
+        let replacements = (step.changes.iter().rev())
+            .map(|change| {
+                let edit = change.edit;
+                (edit.start, edit.inserted, change.removed.clone())
+            })
+            .collect();
+        let cursor = step.cursor_before;
+        self.cursor = cursor;
+        self.redo.push(step);
+        Some((replacements, cursor))
+    }
+
+    /// The replacements that redo the step undone last, in order, and the
+    /// cursor to put back, or None when nothing was undone since the last
+    /// change.
+    pub fn redo(&mut self) -> Option<(Vec<Replacement>, usize)> {
+        let step = self.redo.pop()?;
+        let replacements = (step.changes.iter())
+            .map(|change| {
+                let edit = change.edit;
+                (edit.start, edit.removed, change.inserted.clone())
+            })
+            .collect();
+        let cursor = step.cursor_after;
+        self.cursor = cursor;
+        self.undo.push(step);
+        Some((replacements, cursor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(start: usize, removed: usize, inserted: usize) -> Edit {
+        Edit {
+            start,
+            removed,
+            inserted,
+            line: 0,
+            removed_lines: 0,
+            inserted_lines: 0,
+        }
+    }
+
+    #[test]
+    fn changes_between_closes_are_one_step() {
+        let mut stack = UndoStack::new();
+        stack.close(2);
+        stack.record(edit(2, 0, 1), String::new(), "a".to_string());
+        stack.record(edit(3, 0, 1), String::new(), "b".to_string());
+        stack.close(4);
+
+        let (replacements, cursor) = stack.undo().unwrap();
+        assert_eq!(replacements, [(3, 1, String::new()), (2, 1, String::new())]);
+        assert_eq!(cursor, 2);
+        assert!(stack.undo().is_none());
+
+        let (replacements, cursor) = stack.redo().unwrap();
+        assert_eq!(
+            replacements,
+            [(2, 0, "a".to_string()), (3, 0, "b".to_string())]
+        );
+        assert_eq!(cursor, 4);
+    }
+
+    #[test]
+    fn a_new_change_forgets_what_was_undone() {
+        let mut stack = UndoStack::new();
+        stack.record(edit(0, 1, 0), "x".to_string(), String::new());
+        stack.close(0);
+        stack.undo();
+        stack.record(edit(0, 0, 1), String::new(), "y".to_string());
+        stack.close(1);
+        assert!(stack.redo().is_none());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::editor::BufferId;
+
+// Smallest size the layout gives a window when there is room, in cells
+const MIN_HEIGHT: u16 = 2;
+const MIN_WIDTH: u16 = 8;
+// Split ratios are thousandths of the area, fine enough to resize
+// windows by single cells
+const RATIO_SCALE: u32 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WindowId(pub u64);
+
+pub struct Split {
+    pub buffer: Option<BufferId>,
+    /// The window's cursor and scroll position in its buffer
+    pub cursor_pos: usize,
+    pub scroll_offset: (usize, usize),
+    /// The column Up and Down aim for, with the cursor position it was
+    /// left at so any other movement forgets it.
+    pub desired_column: Option<(usize, usize)>,
+    /// Size of the text area the window was last rendered at
+    pub viewport: (usize, usize),
+    /// Scroll together with the other scrollbound windows (`:set scrollbind`)
+    pub scrollbind: bool,
+}
+
+impl Split {
+    pub fn new() -> Self {
+        Self {
+            buffer: None,
+            cursor_pos: 0,
+            scroll_offset: (0, 0),
+            desired_column: None,
+            viewport: (80, 24),
+            scrollbind: false,
+        }
+    }
+
+    pub fn with_buffer(buffer: Option<BufferId>) -> Self {
+        Self {
+            buffer,
+            ..Self::new()
+        }
+    }
+}
+
+impl Default for Split {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A screen region in cells.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Area {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Area {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn extent(&self, direction: SplitDirection) -> u16 {
+        match direction {
+            SplitDirection::Horizontal => self.height,
+            SplitDirection::Vertical => self.width,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SplitDirection {
+    /// Windows stacked on top of each other (`:split`)
+    Horizontal,
+    /// Windows side by side (`:vsplit`)
+    Vertical,
+}
+
+/// Binary layout tree of windows.
+pub enum LayoutNode {
+    Window(WindowId),
+    Split {
+        direction: SplitDirection,
+        ratio: u16,
+        first: Box<LayoutNode>,
+        second: Box<LayoutNode>,
+    },
+}
+
+impl LayoutNode {
+    /// Replaces `target` with a split holding `target` and `new_window`.
+    pub fn split(&mut self, target: WindowId, new_window: WindowId, direction: SplitDirection) {
+        match self {
+            LayoutNode::Window(id) if *id == target => {
+                *self = LayoutNode::Split {
+                    direction,
+                    ratio: (RATIO_SCALE / 2) as u16,
+                    first: Box::new(LayoutNode::Window(new_window)),
+                    second: Box::new(LayoutNode::Window(target)),
+                };
+            }
+            LayoutNode::Window(_) => {}
+            LayoutNode::Split { first, second, .. } => {
+                first.split(target, new_window, direction);
+                second.split(target, new_window, direction);
+            }
+        }
+    }
+
+    /// Removes `target`, letting its sibling take over the freed space.
+    pub fn remove(&mut self, target: WindowId) {
+        if let LayoutNode::Split { first, second, .. } = self {
+            if matches!(**first, LayoutNode::Window(id) if id == target) {
+                *self = std::mem::replace(second, LayoutNode::Window(target));
+            } else if matches!(**second, LayoutNode::Window(id) if id == target) {
+                *self = std::mem::replace(first, LayoutNode::Window(target));
+            } else {
+                first.remove(target);
+                second.remove(target);
+            }
+        }
+    }
+
+    /// Windows in layout order (top-to-bottom, left-to-right).
+    pub fn windows(&self) -> Vec<WindowId> {
+        match self {
+            LayoutNode::Window(id) => vec![*id],
+            LayoutNode::Split { first, second, .. } => {
+                let mut windows = first.windows();
+                windows.extend(second.windows());
+                windows
+            }
+        }
+    }
+
+    pub fn rects(&self, area: Area) -> Vec<(WindowId, Area)> {
+        match self {
+            LayoutNode::Window(id) => vec![(*id, area)],
+            LayoutNode::Split { first, second, .. } => {
+                let (first_area, second_area) = self.child_areas(area);
+                let mut rects = first.rects(first_area);
+                rects.extend(second.rects(second_area));
+                rects
+            }
+        }
+    }
+
+    fn child_areas(&self, area: Area) -> (Area, Area) {
+        let LayoutNode::Split {
+            direction,
+            ratio,
+            first,
+            second,
+        } = self
+        else {
+            return (area, area);
+        };
+        let total = area.extent(*direction);
+        let mut size = (total as u32 * *ratio as u32 / RATIO_SCALE) as u16;
+        let (first_min, second_min) = (first.min_size(*direction), second.min_size(*direction));
+        if total >= first_min + second_min {
+            size = size.clamp(first_min, total - second_min);
+        }
+
+        match direction {
+            SplitDirection::Horizontal => (
+                Area::new(area.x, area.y, area.width, size),
+                Area::new(area.x, area.y + size, area.width, area.height - size),
+            ),
+            SplitDirection::Vertical => (
+                Area::new(area.x, area.y, size, area.height),
+                Area::new(area.x + size, area.y, area.width - size, area.height),
+            ),
+        }
+    }
+
+    fn min_size(&self, direction: SplitDirection) -> u16 {
+        match self {
+            LayoutNode::Window(_) => match direction {
+                SplitDirection::Horizontal => MIN_HEIGHT,
+                SplitDirection::Vertical => MIN_WIDTH,
+            },
+            LayoutNode::Split {
+                direction: split,
+                first,
+                second,
+                ..
+            } if *split == direction => first.min_size(direction) + second.min_size(direction),
+            LayoutNode::Split { first, second, .. } => {
+                first.min_size(direction).max(second.min_size(direction))
+            }
+        }
+    }
+
+    /// Grows `target` by `delta` cells along `direction`, or shrinks it for
+    /// a negative `delta`, moving the border it shares with its neighbour
+    /// in the nearest split that way.
+    pub fn resize(
+        &mut self,
+        target: WindowId,
+        direction: SplitDirection,
+        delta: i32,
+        area: Area,
+    ) -> bool {
+        let (first_area, second_area) = self.child_areas(area);
+        let LayoutNode::Split {
+            direction: split,
+            ratio,
+            first,
+            second,
+        } = self
+        else {
+            return false;
+        };
+        let in_first = first.windows().contains(&target);
+        let resized = if in_first {
+            first.resize(target, direction, delta, first_area)
+        } else {
+            second.resize(target, direction, delta, second_area)
+        };
+        if resized || *split != direction {
+            return resized;
+        }
+
+        let total = area.extent(direction) as i32;
+        if total == 0 {
+            return true;
+        }
+        let size = first_area.extent(direction) as i32 + if in_first { delta } else { -delta };
+        let size = size.clamp(0, total);
+        // Round up so the floor in `child_areas` lands on `size`
+        *ratio = ((size * RATIO_SCALE as i32 + total - 1) / total) as u16;
+        true
+    }
+
+    /// Gives every window the same share of the space along each split
+    /// (`Ctrl-w =`).
+    pub fn equalize(&mut self) {
+        if let LayoutNode::Split {
+            direction,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            first.equalize();
+            second.equalize();
+            let (first_count, second_count) = (first.count(*direction), second.count(*direction));
+            *ratio = (first_count * RATIO_SCALE / (first_count + second_count)) as u16;
+        }
+    }
+
+    fn count(&self, direction: SplitDirection) -> u32 {
+        match self {
+            LayoutNode::Split {
+                direction: split,
+                first,
+                second,
+                ..
+            } if *split == direction => first.count(direction) + second.count(direction),
+            _ => 1,
+        }
+    }
+}
@@ -1,3 +1,13 @@
+//! The pyne editor engine.
+//!
+//! [`editor`] holds the frontend-agnostic core: buffers, windows and their
+//! layout, the normal, visual and insert mode keymaps ([`editor::keymap`]),
+//! ex commands (driven through [`ui::command_bar`]), options and the hook
+//! events. It has no terminal dependencies, so building with
+//! `--no-default-features` yields just the engine for embedding in another
+//! frontend. The `tui` feature (on by default) adds the ratatui rendering,
+//! popups and file explorer used by the `pyne` binary.
+
 pub mod editor;
 pub mod ui;
 pub mod utils;
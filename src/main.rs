@@ -1,68 +1,161 @@
 use command_bar::CommandBar;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    cursor::{MoveTo, SetCursorStyle},
+    event::{
+        self, DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        KeyboardEnhancementFlags, MouseButton, MouseEvent, MouseEventKind,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen, SetTitle,
+    },
 };
-use error_handler::{clear_error, set_error};
+use error_handler::{clear_error, get_error, set_error};
 use file_explorer::FileExplorer;
 use pyne::{
     ui::command_bar,
-    utils::{error_handler, file_explorer},
+    utils::{config::Config, error_handler, file_explorer, media, progress},
 };
 use ratatui::{
     backend::CrosstermBackend,
     crossterm,
-    layout::{Constraint, Direction, Layout, Position},
-    style::{Color, Style},
-    text::{Line, Span},
-    widgets::Paragraph,
+    layout::{Position, Rect},
+    style::Modifier,
     Terminal,
 };
-use std::{env, error::Error, io, path::PathBuf};
-
-const SUGGESTIONS_PER_PAGE: usize = 5;
+use std::{
+    collections::VecDeque,
+    env,
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
 
 use pyne::editor::cursor_movement::CursorMovement;
+use pyne::editor::hooks::HookEvent;
+use pyne::editor::keymap::{self, Key, KeyAction};
+use pyne::editor::lsp::Severity;
 use pyne::editor::mode::Mode;
+use pyne::editor::tags;
 use pyne::editor::Editor;
-use pyne::ui::gutter::Gutter;
+use pyne::ui::char_picker::CharPicker;
+use pyne::ui::command_palette::{CommandPalette, PaletteAction, PaletteEntry, PaletteSources};
+use pyne::ui::debug_overlay::DebugOverlay;
+use pyne::ui::key_script::{parse_keys, KeyRecorder};
+use pyne::ui::menu_popup::MenuPopup;
+use pyne::ui::passphrase_prompt::PassphrasePrompt;
+use pyne::ui::paste_preview::PastePreview;
+use pyne::ui::popup::PopupLayer;
+use pyne::ui::quit_triage::QuitTriage;
+use pyne::ui::render::{
+    layout, render_ui, status_click_at, text_position_at, window_at, WindowSpot,
+};
+use pyne::ui::status_line::StatusClick;
+use pyne::ui::terminal_view::key_to_bytes;
+use pyne::utils::alloc_counter::CountingAllocator;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+struct CliArgs {
+    file: Option<String>,
+    // Keys to replay before reading from the terminal (`-s`)
+    script: Vec<event::KeyEvent>,
+    // File to record every key into (`-w`)
+    record: Option<KeyRecorder>,
+}
+
+impl CliArgs {
+    fn parse() -> Result<Self, Box<dyn Error>> {
+        let mut cli = CliArgs {
+            file: None,
+            script: Vec::new(),
+            record: None,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-s" => {
+                    let path = args.next().ok_or("-s requires a key script")?;
+                    let script = fs::read_to_string(&path)
+                        .map_err(|e| format!("Failed to read key script {}: {}", path, e))?;
+                    cli.script = parse_keys(&script)?;
+                }
+                "-w" => {
+                    let path = args.next().ok_or("-w requires a file to record to")?;
+                    cli.record = Some(KeyRecorder::create(Path::new(&path))?);
+                }
+                _ => cli.file = Some(arg),
+            }
+        }
+        Ok(cli)
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let cli = CliArgs::parse()?;
     let mut terminal = setup_terminal()?;
+    install_stop_handlers();
     let mut editor = Editor::new();
+    match Config::load() {
+        Ok(config) => editor.apply_config(config),
+        Err(e) => set_error(e),
+    }
+    editor.load_bookmarks();
     let mut file_explorer = FileExplorer::new(&env::current_dir()?)?;
 
     // Store the starting directory
     let starting_directory = env::current_dir()?;
 
     // Determine the file to open
-    let default_file_path = env::args().nth(1).unwrap_or_else(|| "".to_string());
+    let default_file_path = cli.file.unwrap_or_default();
     let file_path = PathBuf::from(&default_file_path);
 
-    // Open the file if it exists or initialize a scratch buffer
-    if file_path.exists() {
+    // Open the file (a missing file starts as a new buffer) or initialize a scratch buffer
+    if !default_file_path.is_empty() {
         if let Err(err) = editor.open_file(&file_path) {
             set_error(format!("Failed to open file: {:?}", err));
         }
-    } else if !default_file_path.is_empty() {
-        set_error(format!("File does not exist: {:?}", file_path.display()));
     } else {
-        editor.new_scratch_buffer()?;
+        editor.new_scratch_buffer();
         editor.set_starting_directory(starting_directory.clone());
         file_explorer.set_starting_directory(starting_directory.clone());
     }
 
     // Set the file explorer's directory to the starting directory if it's a scratch buffer
-    if editor.is_scratch_buffer() {
-        file_explorer.set_current_directory(starting_directory)?;
-    } else if let Some(file_dir) = file_path.parent() {
-        file_explorer.set_current_directory(file_dir.to_path_buf())?;
+    match editor.get_current_file_path() {
+        Some(path) if !editor.is_scratch_buffer() => {
+            if let Some(file_dir) = path.parent().filter(|dir| dir.is_dir()) {
+                file_explorer.set_current_directory(file_dir.to_path_buf())?;
+            }
+        }
+        _ => file_explorer.set_current_directory(starting_directory)?,
     }
+    editor.load_project_config();
+    // Index the project in the background so the file picker opens at once
+    editor.file_index();
 
-    let result = run_app(&mut terminal, &mut editor, &mut file_explorer);
+    let result = run_app(
+        &mut terminal,
+        &mut editor,
+        &mut file_explorer,
+        cli.script.into(),
+        cli.record,
+    );
 
     restore_terminal(&mut terminal)?;
+    // Notes are kept however the editor is quit
+    if let Err(e) = editor.save_notes() {
+        eprintln!("Failed to save notes: {}", e);
+    }
     if let Err(err) = result {
         eprintln!("Error: {:?}", err);
     }
@@ -70,257 +163,1069 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+// How often terminal windows and tasks are checked for new output.
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(30);
+// How often language servers are checked for new diagnostics.
+const LANGUAGE_SERVER_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Whether the kitty keyboard protocol flags were pushed and need popping.
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+fn enable_keyboard_enhancement(stdout: &mut impl io::Write) -> io::Result<()> {
+    if supports_keyboard_enhancement().unwrap_or(false) {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )?;
+        KEYBOARD_ENHANCED.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+fn disable_keyboard_enhancement(stdout: &mut impl io::Write) -> io::Result<()> {
+    if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+        execute!(stdout, PopKeyboardEnhancementFlags)?;
+    }
+    Ok(())
+}
+
+// Saves the window title on the terminal's title stack (XTWINOPS 22)
+const PUSH_TITLE: &str = "\x1b[22;0t";
+// Restores the window title saved by `PUSH_TITLE`
+const POP_TITLE: &str = "\x1b[23;0t";
+
 fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>, Box<dyn Error>> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        Print(PUSH_TITLE),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange,
+        EnableBracketedPaste
+    )?;
+    enable_keyboard_enhancement(&mut stdout)?;
     let backend = CrosstermBackend::new(stdout);
     Terminal::new(backend).map_err(|e| e.into())
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CursorShape {
+    Block,
+    Bar,
+}
+
+impl CursorShape {
+    fn for_mode(mode: &Mode, command_line: bool) -> Self {
+        match mode {
+            _ if command_line => CursorShape::Bar,
+            Mode::Insert => CursorShape::Bar,
+            Mode::Normal | Mode::Visual => CursorShape::Block,
+            // The shell sets its own cursor shape
+            Mode::Terminal => CursorShape::Bar,
+        }
+    }
+
+    fn style(self) -> SetCursorStyle {
+        match self {
+            CursorShape::Block => SetCursorStyle::SteadyBlock,
+            CursorShape::Bar => SetCursorStyle::SteadyBar,
+        }
+    }
+}
+
 fn restore_terminal(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
 ) -> Result<(), Box<dyn Error>> {
+    disable_keyboard_enhancement(terminal.backend_mut())?;
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
+        SetCursorStyle::DefaultUserShape,
         LeaveAlternateScreen,
-        DisableMouseCapture
+        Print(POP_TITLE),
+        DisableMouseCapture,
+        DisableFocusChange,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
     Ok(())
 }
 
+fn resume_terminal(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+) -> Result<(), Box<dyn Error>> {
+    execute!(terminal.backend_mut(), Print(PUSH_TITLE))?;
+    reclaim_terminal(terminal)
+}
+
+fn reclaim_terminal(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+) -> Result<(), Box<dyn Error>> {
+    enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange,
+        EnableBracketedPaste
+    )?;
+    enable_keyboard_enhancement(terminal.backend_mut())?;
+    terminal.clear()?;
+    Ok(())
+}
+
 fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     editor: &mut Editor,
     file_explorer: &mut FileExplorer,
+    mut script: VecDeque<event::KeyEvent>,
+    mut recorder: Option<KeyRecorder>,
 ) -> Result<(), Box<dyn Error>> {
     let mut command_bar = CommandBar::new();
+    let mut cursor_hold_fired = false;
+    let mut popups = PopupLayer::new();
+    let mut palette = CommandPalette::new();
+    let mut menu_popup = MenuPopup::new();
+    let mut char_picker = CharPicker::new();
+    let mut paste_preview = PastePreview::new();
+    let mut passphrase_prompt = PassphrasePrompt::new();
+    let mut quit_triage = QuitTriage::new();
+    let mut clicks = ClickCounter::new();
+    let mut debug_overlay = DebugOverlay::new();
+    let mut event_start: Option<Instant> = None;
+    let mut cursor_shape = None;
+    let mut window_title = None;
+    let mut shown_image = None;
+    let mut last_key = Instant::now();
 
     loop {
-        terminal.draw(|f| render_ui(f, editor, file_explorer, &command_bar))?;
+        if STOP_REQUESTED.swap(false, Ordering::Relaxed) {
+            suspend(terminal)?;
+        }
+        // Stopped by something other than SIGTSTP, such as SIGSTOP
+        if CONTINUED.swap(false, Ordering::Relaxed) {
+            reclaim_terminal(terminal)?;
+        }
+        // Keys are handled through several early `continue`s, so the time
+        // spent on the last one is taken here
+        if let Some(start) = event_start.take() {
+            debug_overlay.record_event(start.elapsed());
+        }
+        file_explorer.poll_search();
+        if let Some(menu) = editor.take_menu() {
+            menu_popup.open(&mut popups, menu);
+        }
+        if let Some(query) = editor.take_char_picker() {
+            char_picker.open(&mut popups, &query);
+        }
+        if let Some(text) = editor.take_paste_preview() {
+            paste_preview.open(&mut popups, editor, text);
+        }
+        if editor.take_quit_triage() {
+            quit_triage.open(&mut popups, editor);
+        }
+        if editor.take_reveal() {
+            if let Err(e) = open_file_explorer(editor, file_explorer, true) {
+                set_error(format!("Failed to open the file explorer: {}", e));
+            }
+        }
+        if let Some(request) = editor.take_passphrase_request() {
+            passphrase_prompt.open(&mut popups, request);
+        }
+        if editor.take_bell() {
+            ring_bell(terminal, editor, file_explorer, &command_bar, &mut popups)?;
+        }
+        debug_overlay.update(&mut popups, editor, terminal.size()?.width);
+        let render_start = Instant::now();
+        terminal.draw(|f| {
+            layout(editor, f.area());
+            render_ui(f, editor, file_explorer, &command_bar, &mut popups)
+        })?;
+        debug_overlay.record_render(render_start.elapsed());
+        show_image_preview(terminal, file_explorer, &mut shown_image)?;
+
+        let shape = CursorShape::for_mode(&editor.get_mode(), command_bar.is_active());
+        if cursor_shape != Some(shape) {
+            execute!(terminal.backend_mut(), shape.style())?;
+            cursor_shape = Some(shape);
+        }
+        update_window_title(terminal, editor, &mut window_title)?;
+
+        // Replay the -s script before taking keys from the terminal
+        let key = if let Some(key) = script.pop_front() {
+            key
+        } else {
+            // Fire CursorHold once whenever no key arrives within updatetime.
+            // Terminal windows, task output, the debugger and progress
+            // redraw as they change, so poll more often while any are active.
+            let update_time = Duration::from_millis(editor.get_options().updatetime as u64);
+            let timeout = if editor.has_terminals()
+                || editor.has_running_tasks()
+                || editor.is_debugging()
+                || progress::is_active()
+            {
+                OUTPUT_POLL_INTERVAL.min(update_time)
+            } else if editor.has_language_servers() {
+                LANGUAGE_SERVER_POLL_INTERVAL.min(update_time)
+            } else {
+                update_time
+            };
+            if !event::poll(timeout)? {
+                editor.poll_terminals();
+                editor.poll_tasks();
+                editor.poll_fetches();
+                editor.poll_language_servers();
+                editor.poll_debugger();
+                if !cursor_hold_fired && last_key.elapsed() >= update_time {
+                    cursor_hold_fired = true;
+                    editor.fire_hook(HookEvent::CursorHold);
+                    if run_pending_commands(editor, &command_bar)? {
+                        break;
+                    }
+                }
+                continue;
+            }
+            match event::read()? {
+                // Releases are only reported under the kitty protocol
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                Event::Mouse(mouse) => {
+                    let size = terminal.size()?;
+                    let screen = Rect::new(0, 0, size.width, size.height);
+                    handle_mouse(
+                        editor,
+                        file_explorer,
+                        &mut command_bar,
+                        &mut clicks,
+                        mouse,
+                        screen,
+                    );
+                    continue;
+                }
+                Event::Paste(text) => {
+                    if !paste_preview.is_open() {
+                        handle_paste(editor, &mut command_bar, &text);
+                    }
+                    continue;
+                }
+                Event::FocusGained => {
+                    editor.check_external_changes();
+                    editor.fire_hook(HookEvent::FocusGained);
+                    if run_pending_commands(editor, &command_bar)? {
+                        break;
+                    }
+                    continue;
+                }
+                Event::FocusLost => {
+                    editor.autosave();
+                    editor.fire_hook(HookEvent::FocusLost);
+                    if run_pending_commands(editor, &command_bar)? {
+                        break;
+                    }
+                    continue;
+                }
+                _ => continue,
+            }
+        };
 
-        if let Event::Key(key) = event::read()? {
-            if handle_input(editor, file_explorer, &mut command_bar, key)? {
+        event_start = Some(Instant::now());
+        last_key = Instant::now();
+        if let Some(Err(e)) = recorder.as_mut().map(|recorder| recorder.record(&key)) {
+            set_error(format!("Stopped recording keys: {}", e));
+            recorder = None;
+        }
+
+        cursor_hold_fired = false;
+        if editor.project_prompt_message().is_some() {
+            handle_project_prompt(editor, key);
+            continue;
+        }
+        if editor.get_write_prompt().is_some() {
+            handle_write_prompt(terminal, editor, &mut command_bar, key)?;
+            // The terminal may have been handed to a password prompt
+            cursor_shape = None;
+            continue;
+        }
+        if palette.is_open() {
+            if let Some(action) = palette.handle_key(&mut popups, key) {
+                let quit = run_palette_action(editor, file_explorer, &mut command_bar, action)?;
+                if quit {
+                    break;
+                }
+            }
+            continue;
+        }
+        if menu_popup.is_open() {
+            menu_popup.handle_key(&mut popups, editor, key);
+            continue;
+        }
+        if char_picker.is_open() {
+            if let Some(c) = char_picker.handle_key(&mut popups, key) {
+                // Part of the insert being typed, or a change of its own
+                if editor.get_mode() == Mode::Insert {
+                    editor.insert(c);
+                } else {
+                    editor.insert_paste(&c.to_string());
+                }
+            }
+            continue;
+        }
+        if paste_preview.is_open() {
+            paste_preview.handle_key(&mut popups, editor, key);
+            continue;
+        }
+        if passphrase_prompt.is_open() {
+            passphrase_prompt.handle_key(&mut popups, editor, key);
+            continue;
+        }
+        if quit_triage.is_open() {
+            if quit_triage.handle_key(&mut popups, editor, key) {
                 break;
             }
+            continue;
+        }
+        // Popup owners handle their own keys; Esc dismisses whatever has focus
+        if let (Some(popup), KeyCode::Esc) = (popups.focused(), key.code) {
+            popups.close(popup);
+            continue;
+        }
+        if cancel_background(editor, &command_bar, key) {
+            continue;
+        }
+        if is_suspend_key(key) && editor.get_mode() == Mode::Normal && !command_bar.is_active() {
+            suspend(terminal)?;
+            cursor_shape = None;
+            window_title = None;
+            continue;
+        }
+        if is_palette_key(key)
+            && editor.get_mode() == Mode::Normal
+            && !command_bar.is_active()
+            && !file_explorer.open
+        {
+            let sources = PaletteSources {
+                tags_file: tags::find_tags_file(editor.get_working_directory()),
+                files: Some(editor.file_index()),
+            };
+            palette.open(&mut popups, palette_entries(&command_bar), sources);
+            continue;
+        }
+        if key.modifiers == KeyModifiers::CONTROL
+            && key.code == KeyCode::Char('k')
+            && editor.get_mode() == Mode::Insert
+            && !command_bar.is_active()
+        {
+            char_picker.open(&mut popups, "");
+            continue;
+        }
+        if handle_key(editor, file_explorer, &mut command_bar, key)? {
+            break;
         }
     }
     Ok(())
 }
 
-fn render_ui(
-    f: &mut ratatui::Frame,
+fn cancel_background(editor: &mut Editor, command_bar: &CommandBar, key: event::KeyEvent) -> bool {
+    let ctrl_c = key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('c');
+    let esc = key.modifiers == KeyModifiers::NONE && key.code == KeyCode::Esc;
+    let cancelled = if ctrl_c && editor.get_mode() != Mode::Terminal {
+        let mut cancelled = progress::cancel_all();
+        cancelled.extend(editor.kill_tasks());
+        cancelled
+    } else if esc && editor.get_mode() == Mode::Normal && !command_bar.is_active() {
+        progress::cancel_all()
+    } else {
+        return false;
+    };
+    if cancelled.is_empty() {
+        return false;
+    }
+    set_error(format!("Cancelled: {}", cancelled.join(", ")));
+    true
+}
+
+fn handle_key(
     editor: &mut Editor,
     file_explorer: &mut FileExplorer,
-    command_bar: &CommandBar,
-) {
-    let area = f.area();
-    if file_explorer.open {
-        file_explorer.render(f, area);
-    } else {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Min(1),    // Editor area
-                Constraint::Length(1), // Command description
-                Constraint::Length(1), // Status bar / Command bar
-                Constraint::Length(1), // Error message
-            ])
-            .split(area);
-
-        let editor_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(6), Constraint::Min(1)])
-            .split(chunks[0]);
-
-        editor.set_viewport((editor_chunks[1].width as usize, chunks[0].height as usize));
-        render_gutter(f, editor, editor_chunks[0]);
-        render_content(f, editor, editor_chunks[1]);
-        render_command_description(f, command_bar, chunks[1]);
-        render_status_line(f, editor, command_bar, chunks[2]);
-        render_autocomplete_suggestions(f, command_bar, chunks[3]);
-        error_handler::render_error(f, chunks[2]);
-        // help_handler::render_help(f, chunks[4]);
-
-        // Handle Option types for cursor position and scroll offset
-        if let (Some((cursor_line, cursor_column)), Some((scroll_x, scroll_y))) = (
-            editor.get_cursor_screen_position(),
-            editor.get_scroll_offset(),
-        ) {
-            let cursor_screen_x = (cursor_column as i32 - scroll_x as i32).max(0) as u16;
-            let cursor_screen_y = (cursor_line as i32 - scroll_y as i32).max(0) as u16;
-            f.set_cursor_position(Position::new(
-                editor_chunks[1].x + cursor_screen_x,
-                chunks[0].y + cursor_screen_y,
-            ));
-        }
-    }
-}
-
-fn render_command_description(
-    f: &mut ratatui::Frame,
-    command_bar: &CommandBar,
-    area: ratatui::layout::Rect,
-) {
-    if command_bar.is_active() {
-        if let Some(description) = command_bar.get_current_command_description() {
-            let description_widget =
-                Paragraph::new(description).style(Style::default().fg(Color::Yellow));
-            f.render_widget(description_widget, area);
+    command_bar: &mut CommandBar,
+    key: event::KeyEvent,
+) -> Result<bool, Box<dyn Error>> {
+    // The rest of a multi-key command goes to the editor whatever it is
+    if editor.has_pending_keys() {
+        return run_editor_key(editor, file_explorer, command_bar, key);
+    }
+    if handle_input(editor, file_explorer, command_bar, key)? {
+        return Ok(true);
+    }
+    if run_pending_commands(editor, command_bar)? {
+        return Ok(true);
+    }
+    run_normal_commands(editor, file_explorer, command_bar)
+}
+
+fn run_normal_commands(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &mut CommandBar,
+) -> Result<bool, Box<dyn Error>> {
+    for (lines, keys) in editor.take_pending_normal() {
+        let keys = match parse_keys(&keys) {
+            Ok(keys) => keys,
+            Err(e) => {
+                set_error(e);
+                continue;
+            }
+        };
+        let Some((start, mut end)) = lines else {
+            if run_normal_keys(editor, file_explorer, command_bar, &keys)? {
+                return Ok(true);
+            }
+            continue;
+        };
+
+        let mut line = start;
+        while line <= end {
+            editor.go_to_line(line);
+            let before = editor.window_line_count(editor.get_current_window()) as isize;
+            if run_normal_keys(editor, file_explorer, command_bar, &keys)? {
+                return Ok(true);
+            }
+            // Follow the range as the keys add or delete lines
+            let added = editor.window_line_count(editor.get_current_window()) as isize - before;
+            end = (end as isize + added).max(0) as usize;
+            line = (line as isize + 1 + added).max(line as isize) as usize;
         }
     }
+    Ok(false)
 }
 
-fn render_autocomplete_suggestions(
-    f: &mut ratatui::Frame,
-    command_bar: &CommandBar,
-    area: ratatui::layout::Rect,
-) {
+fn run_normal_keys(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &mut CommandBar,
+    keys: &[event::KeyEvent],
+) -> Result<bool, Box<dyn Error>> {
+    for &key in keys {
+        if handle_key(editor, file_explorer, command_bar, key)? {
+            return Ok(true);
+        }
+    }
     if command_bar.is_active() {
-        let suggestions = command_bar.get_suggestions();
-        let current_index = command_bar.get_suggestion_index();
-        let page = command_bar.suggestion_page;
-        let total_pages = (suggestions.len() + SUGGESTIONS_PER_PAGE - 1) / SUGGESTIONS_PER_PAGE;
+        command_bar.deactivate();
+        command_bar.reset_suggestion_index();
+    }
+    let escape = event::KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+    if editor.has_pending_keys() {
+        handle_key(editor, file_explorer, command_bar, escape)?;
+    }
+    if editor.get_mode() != Mode::Normal {
+        handle_key(editor, file_explorer, command_bar, escape)?;
+    }
+    Ok(false)
+}
 
-        let start_index = page * SUGGESTIONS_PER_PAGE;
-        let end_index = (start_index + SUGGESTIONS_PER_PAGE).min(suggestions.len());
+// Clicks within this long on the same cell count as a double or triple
+// click.
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
 
-        let mut spans = Vec::new();
+struct ClickCounter {
+    last: Option<(Instant, Position)>,
+    count: usize,
+}
 
-        // Add left arrow for previous page
-        if page > 0 {
-            spans.push(Span::styled("< ", Style::default().fg(Color::Yellow)));
+impl ClickCounter {
+    fn new() -> Self {
+        Self {
+            last: None,
+            count: 0,
         }
+    }
 
-        for (index, suggestion) in suggestions[start_index..end_index].iter().enumerate() {
-            let absolute_index = start_index + index;
-            if absolute_index == current_index {
-                spans.push(Span::styled(
-                    suggestion.name.clone(),
-                    Style::default().fg(Color::Black).bg(Color::White),
-                ));
-            } else {
-                spans.push(Span::styled(
-                    suggestion.name.clone(),
-                    Style::default().fg(Color::Blue),
-                ));
-            }
+    fn click(&mut self, position: Position) -> usize {
+        let now = Instant::now();
+        let repeated = self.last.is_some_and(|(time, last)| {
+            last == position && now.duration_since(time) <= MULTI_CLICK_INTERVAL
+        });
+        self.count = if repeated { self.count % 3 + 1 } else { 1 };
+        self.last = Some((now, position));
+        self.count
+    }
+}
 
-            if index < end_index - start_index - 1 {
-                spans.push(Span::raw(" "));
+fn handle_mouse(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &mut CommandBar,
+    clicks: &mut ClickCounter,
+    mouse: MouseEvent,
+    screen: Rect,
+) {
+    let position = Position::new(mouse.column, mouse.row);
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) if !file_explorer.open => {
+            if let Some(target) = status_click_at(editor, command_bar, screen, position) {
+                click_status_line(editor, command_bar, target);
+                return;
             }
+            let count = clicks.click(position);
+            click(editor, position, screen, count);
         }
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Err(e) = file_explorer.click_breadcrumb(position) {
+                file_explorer.show_error(&format!("Error: {}", e));
+            }
+        }
+        _ => scroll_with_mouse(editor, file_explorer, mouse, screen),
+    }
+}
 
-        // Add right arrow for next page
-        if page < total_pages - 1 {
-            spans.push(Span::styled(" >", Style::default().fg(Color::Yellow)));
+fn click_status_line(editor: &mut Editor, command_bar: &mut CommandBar, target: StatusClick) {
+    match target {
+        StatusClick::Buffers => editor.list_buffers(),
+        // The command line goes to a line number typed alone, as in :42
+        StatusClick::GotoLine => {
+            command_bar.activate();
+            command_bar.reset_suggestion_index();
         }
+        StatusClick::Branches => editor.list_branches(),
+    }
+}
 
-        // Add page indicator
-        let page_indicator = format!(" [{}/{}]", page + 1, total_pages);
-        spans.push(Span::styled(
-            page_indicator,
-            Style::default().fg(Color::Gray),
-        ));
+fn click(editor: &mut Editor, position: Position, screen: Rect, count: usize) {
+    let Some((window, row, spot)) = text_position_at(editor, screen, position) else {
+        return;
+    };
+    let mode = editor.get_mode();
+    if mode == Mode::Visual {
+        editor.exit_visual_mode();
+    }
+    editor.focus_window(window);
+    if editor.is_terminal_window() {
+        return;
+    }
+    if mode == Mode::Terminal {
+        editor.set_mode(Mode::Normal);
+    }
+
+    match spot {
+        WindowSpot::Sign => editor.click_sign(row),
+        WindowSpot::LineNumber => {
+            editor.move_cursor_to_view(row, 0);
+            editor.move_cursor(CursorMovement::LineStart);
+        }
+        WindowSpot::Text(column) => {
+            editor.move_cursor_to_view(row, column);
+            match count {
+                2 => editor.select_word(),
+                3 => editor.select_line(),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn scroll_with_mouse(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    mouse: MouseEvent,
+    screen: Rect,
+) {
+    let (vertical, horizontal) = editor.get_options().mouse_scroll();
+    let (vertical, horizontal) = (vertical as isize, horizontal as isize);
+    let sideways = mouse.modifiers.contains(KeyModifiers::SHIFT);
+    let (lines, columns) = match mouse.kind {
+        MouseEventKind::ScrollUp if sideways => (0, -horizontal),
+        MouseEventKind::ScrollDown if sideways => (0, horizontal),
+        MouseEventKind::ScrollUp => (-vertical, 0),
+        MouseEventKind::ScrollDown => (vertical, 0),
+        MouseEventKind::ScrollLeft => (0, -horizontal),
+        MouseEventKind::ScrollRight => (0, horizontal),
+        _ => return,
+    };
+
+    let position = Position::new(mouse.column, mouse.row);
+    if file_explorer.open {
+        file_explorer.scroll_preview_at(position, lines);
+        return;
+    }
+    let Some(window) = window_at(editor, screen, position) else {
+        return;
+    };
+    if let Some(pane) = editor.get_terminal_mut(window) {
+        // Terminal scrollback counts lines up from the bottom
+        pane.scroll(-lines);
+    } else {
+        editor.scroll_view(window, lines, columns);
+    }
+}
 
-        let suggestions_line = Line::from(spans);
-        let suggestions_widget = Paragraph::new(vec![suggestions_line]);
-        f.render_widget(suggestions_widget, area);
+fn handle_paste(editor: &mut Editor, command_bar: &mut CommandBar, text: &str) {
+    if editor.get_mode() == Mode::Terminal {
+        if let Some(pane) = editor.get_current_terminal_mut() {
+            if let Err(e) = pane.write_input(text.as_bytes()) {
+                set_error(format!("Failed to write to terminal: {}", e));
+            }
+        }
+    } else if command_bar.is_active() {
+        for c in text.chars().filter(|c| !c.is_control()) {
+            command_bar.input(c);
+        }
+        command_bar.reset_suggestion_index();
+    } else if !editor.is_terminal_window() {
+        editor.paste(text);
     }
 }
 
-fn render_gutter(f: &mut ratatui::Frame, editor: &Editor, area: ratatui::layout::Rect) {
-    let line_numbers = Gutter::get_visible_line_numbers(editor);
-    let gutter_content =
-        Paragraph::new(line_numbers.join("\n")).style(Style::default().fg(Color::DarkGray));
-    f.render_widget(gutter_content, area);
+fn is_suspend_key(key: event::KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('z')
 }
 
-fn render_content(f: &mut ratatui::Frame, editor: &Editor, area: ratatui::layout::Rect) {
-    if let Some(content) = editor.get_visible_content() {
-        let selection = editor.get_selection();
-        let lines: Vec<Line> = content
-            .lines()
-            .enumerate()
-            .map(|(line_idx, line)| {
-                if let Some((start, end)) = selection {
-                    let line_start = editor
-                        .get_current_buffer()
-                        .unwrap()
-                        .content
-                        .line_to_char(line_idx);
-                    let line_end = line_start + line.len();
+// Set by SIGTSTP from outside, such as `kill -TSTP`, for the main loop
+// to suspend as Ctrl-Z does
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+// Set by SIGCONT, for the main loop to set the terminal up again
+static CONTINUED: AtomicBool = AtomicBool::new(false);
 
-                    if line_start < end && line_end > start {
-                        let sel_start = start.saturating_sub(line_start);
-                        let sel_end = (end - line_start).min(line.len());
+#[cfg(unix)]
+extern "C" fn on_stop_signal(_: libc::c_int) {
+    STOP_REQUESTED.store(true, Ordering::Relaxed);
+    // crossterm wakes the main loop on SIGWINCH
+    // SAFETY: raise is async-signal-safe
+    unsafe {
+        libc::raise(libc::SIGWINCH);
+    }
+}
 
-                        let mut spans = Vec::new();
-                        if sel_start > 0 {
-                            spans.push(Span::raw(&line[..sel_start]));
-                        }
-                        spans.push(Span::styled(
-                            &line[sel_start..sel_end],
-                            Style::default().bg(Color::Gray).fg(Color::Black),
-                        ));
-                        if sel_end < line.len() {
-                            spans.push(Span::raw(&line[sel_end..]));
-                        }
-                        Line::from(spans)
-                    } else {
-                        Line::from(line.to_string())
-                    }
-                } else {
-                    Line::from(line.to_string())
-                }
-            })
-            .collect();
+#[cfg(unix)]
+extern "C" fn on_continue_signal(_: libc::c_int) {
+    CONTINUED.store(true, Ordering::Relaxed);
+    // SAFETY: raise is async-signal-safe
+    unsafe {
+        libc::raise(libc::SIGWINCH);
+    }
+}
 
-        let paragraph =
-            ratatui::widgets::Paragraph::new(lines).block(ratatui::widgets::Block::default());
-        f.render_widget(paragraph, area);
-    } else {
-        let paragraph =
-            ratatui::widgets::Paragraph::new("").block(ratatui::widgets::Block::default());
-        f.render_widget(paragraph, area);
+#[cfg(unix)]
+fn install_stop_handlers() {
+    // SAFETY: the handlers only store to atomics and raise a signal
+    unsafe {
+        libc::signal(
+            libc::SIGTSTP,
+            on_stop_signal as *const () as libc::sighandler_t,
+        );
+        libc::signal(
+            libc::SIGCONT,
+            on_continue_signal as *const () as libc::sighandler_t,
+        );
     }
 }
 
-fn render_status_line(
-    f: &mut ratatui::Frame,
-    editor: &Editor,
+#[cfg(not(unix))]
+fn install_stop_handlers() {}
+
+#[cfg(unix)]
+fn suspend(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), Box<dyn Error>> {
+    restore_terminal(terminal)?;
+    // SAFETY: raise only delivers a signal to this process, and the default
+    // action for SIGTSTP stops it until SIGCONT
+    unsafe {
+        libc::signal(libc::SIGTSTP, libc::SIG_DFL);
+        libc::raise(libc::SIGTSTP);
+        libc::signal(
+            libc::SIGTSTP,
+            on_stop_signal as *const () as libc::sighandler_t,
+        );
+    }
+    resume_terminal(terminal)?;
+    CONTINUED.store(false, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn suspend(_terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), Box<dyn Error>> {
+    set_error("Suspending is not supported on this platform".to_string());
+    Ok(())
+}
+
+// How long the screen stays inverted for `:set bell=flash`
+const FLASH_DURATION: Duration = Duration::from_millis(60);
+
+fn ring_bell(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
     command_bar: &CommandBar,
-    area: ratatui::layout::Rect,
-) {
-    let mode_text = format!(" {} ", editor.get_mode());
-    let cursor_info = match editor.get_cursor_screen_position() {
-        Some((line, column)) => format!("{}:{} ", line + 1, column + 1),
-        None => String::from("No active buffer "),
-    };
+    popups: &mut PopupLayer,
+) -> Result<(), Box<dyn Error>> {
+    match editor.get_options().bell.as_str() {
+        "beep" => execute!(terminal.backend_mut(), Print('\x07'))?,
+        "flash" => {
+            terminal.draw(|f| {
+                layout(editor, f.area());
+                render_ui(f, editor, file_explorer, command_bar, popups);
+                for cell in f.buffer_mut().content.iter_mut() {
+                    cell.modifier.toggle(Modifier::REVERSED);
+                }
+            })?;
+            thread::sleep(FLASH_DURATION);
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
-    let status_text = if command_bar.is_active() {
-        format!(":{}", command_bar.get_input())
-    } else {
-        let available_width = area.width as usize;
-        let mode_width = mode_text.len();
-        let cursor_info_width = cursor_info.len();
-
-        if available_width > mode_width + cursor_info_width {
-            let padding = " ".repeat(available_width - mode_width - cursor_info_width);
-            format!("{}{}{}", mode_text, padding, cursor_info)
-        } else if available_width > mode_width {
-            let truncated_cursor_info = &cursor_info[..available_width - mode_width];
-            format!("{}{}", mode_text, truncated_cursor_info)
+fn update_window_title(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    editor: &Editor,
+    shown: &mut Option<String>,
+) -> io::Result<()> {
+    let title = editor.get_options().title.then(|| {
+        let Some(buffer) = editor.get_current_buffer() else {
+            return "pyne".to_string();
+        };
+        let name = buffer
+            .name
+            .path()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| buffer.name.to_string());
+        if buffer.is_modified {
+            format!("{} (modified) — pyne", name)
         } else {
-            mode_text[..available_width.min(mode_text.len())].to_string()
+            format!("{} — pyne", name)
         }
-    };
+    });
+    if title == *shown {
+        return Ok(());
+    }
+    match &title {
+        Some(title) => execute!(terminal.backend_mut(), SetTitle(title))?,
+        // Take the saved title back off the stack and save it again
+        None => execute!(terminal.backend_mut(), Print(POP_TITLE), Print(PUSH_TITLE))?,
+    }
+    *shown = title;
+    Ok(())
+}
+
+fn is_palette_key(key: event::KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && matches!(key.code, KeyCode::Char('p' | 'P'))
+}
+
+fn palette_entries(command_bar: &CommandBar) -> Vec<PaletteEntry> {
+    let mut entries: Vec<PaletteEntry> = command_bar
+        .get_suggestions()
+        .into_iter()
+        .flat_map(|command| {
+            let bang = command.bang.as_ref().map(|bang| PaletteEntry {
+                name: format!(":{}!", command.name),
+                description: bang.description.clone(),
+                action: PaletteAction::Command(format!("{}!", command.name)),
+            });
+            [PaletteEntry {
+                name: format!(":{}", command.name),
+                description: command.description.clone(),
+                action: PaletteAction::Command(command.name.clone()),
+            }]
+            .into_iter()
+            .chain(bang)
+        })
+        .collect();
 
-    let status_style = Style::default().bg(Color::from_u32(0x202020));
-    let status_line = Paragraph::new(status_text).style(status_style);
-    f.render_widget(status_line, area);
+    let key = |code, modifiers| event::KeyEvent::new(code, modifiers);
+    let bindings = [
+        (
+            "v",
+            "Enter visual mode",
+            vec![key(KeyCode::Char('v'), KeyModifiers::NONE)],
+        ),
+        (
+            "Ctrl-v",
+            "Enter visual block mode",
+            vec![key(KeyCode::Char('v'), KeyModifiers::CONTROL)],
+        ),
+        (
+            "i",
+            "Enter insert mode",
+            vec![key(KeyCode::Char('i'), KeyModifiers::NONE)],
+        ),
+        (
+            "A",
+            "Append at the end of the line",
+            vec![key(KeyCode::Char('A'), KeyModifiers::SHIFT)],
+        ),
+        (
+            "-",
+            "Open the file explorer",
+            vec![key(KeyCode::Char('-'), KeyModifiers::NONE)],
+        ),
+        (
+            "Ctrl-Shift-T",
+            "Reopen the last closed buffer",
+            vec![key(
+                KeyCode::Char('T'),
+                KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+            )],
+        ),
+        (
+            "]p",
+            "Paste lines below, matching the indentation",
+            vec![
+                key(KeyCode::Char(']'), KeyModifiers::NONE),
+                key(KeyCode::Char('p'), KeyModifiers::NONE),
+            ],
+        ),
+        (
+            "Alt-Up",
+            "Move the line up",
+            vec![key(KeyCode::Up, KeyModifiers::ALT)],
+        ),
+        (
+            "Alt-Down",
+            "Move the line down",
+            vec![key(KeyCode::Down, KeyModifiers::ALT)],
+        ),
+        (
+            "Alt-Shift-Down",
+            "Duplicate the line",
+            vec![key(KeyCode::Down, KeyModifiers::ALT | KeyModifiers::SHIFT)],
+        ),
+        (
+            "D",
+            "Toggle debug info",
+            vec![key(KeyCode::Char('D'), KeyModifiers::SHIFT)],
+        ),
+        (
+            "^",
+            "Go to the first non-blank character",
+            vec![key(KeyCode::Char('^'), KeyModifiers::NONE)],
+        ),
+        (
+            "g_",
+            "Go to the last non-blank character",
+            vec![
+                key(KeyCode::Char('g'), KeyModifiers::NONE),
+                key(KeyCode::Char('_'), KeyModifiers::NONE),
+            ],
+        ),
+        (
+            "mb",
+            "Toggle a bookmark on the cursor line",
+            vec![
+                key(KeyCode::Char('m'), KeyModifiers::NONE),
+                key(KeyCode::Char('b'), KeyModifiers::NONE),
+            ],
+        ),
+        (
+            "p",
+            "Paste from the clipboard",
+            vec![key(KeyCode::Char('p'), KeyModifiers::NONE)],
+        ),
+        (
+            "n",
+            "Go to the next search match",
+            vec![key(KeyCode::Char('n'), KeyModifiers::NONE)],
+        ),
+        (
+            "N",
+            "Go to the previous search match",
+            vec![key(KeyCode::Char('N'), KeyModifiers::SHIFT)],
+        ),
+        (
+            "*",
+            "Search for the word under the cursor",
+            vec![key(KeyCode::Char('*'), KeyModifiers::NONE)],
+        ),
+        (
+            "#",
+            "Search backward for the word under the cursor",
+            vec![key(KeyCode::Char('#'), KeyModifiers::NONE)],
+        ),
+        (
+            "Ctrl-e",
+            "Scroll the view down a line",
+            vec![key(KeyCode::Char('e'), KeyModifiers::CONTROL)],
+        ),
+        (
+            "Ctrl-y",
+            "Scroll the view up a line",
+            vec![key(KeyCode::Char('y'), KeyModifiers::CONTROL)],
+        ),
+        ("Ctrl-w w", "Focus the next window", window_keys('w')),
+        ("Ctrl-w W", "Focus the previous window", window_keys('W')),
+        (
+            "Ctrl-w s",
+            "Split the window horizontally",
+            window_keys('s'),
+        ),
+        ("Ctrl-w v", "Split the window vertically", window_keys('v')),
+        ("Ctrl-w c", "Close the window", window_keys('c')),
+        ("Ctrl-w o", "Close all other windows", window_keys('o')),
+        (
+            "Ctrl-w =",
+            "Make all windows the same size",
+            window_keys('='),
+        ),
+        ("Ctrl-w +", "Make the window taller", window_keys('+')),
+        ("Ctrl-w -", "Make the window shorter", window_keys('-')),
+        ("Ctrl-w >", "Make the window wider", window_keys('>')),
+        ("Ctrl-w <", "Make the window narrower", window_keys('<')),
+    ];
+    entries.extend(
+        bindings
+            .into_iter()
+            .map(|(name, description, keys)| PaletteEntry {
+                name: name.to_string(),
+                description: description.to_string(),
+                action: PaletteAction::Keys(keys),
+            }),
+    );
+    entries
+}
+
+fn window_keys(c: char) -> Vec<event::KeyEvent> {
+    vec![
+        event::KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+        event::KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+    ]
+}
+
+fn run_palette_action(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &mut CommandBar,
+    action: PaletteAction,
+) -> Result<bool, Box<dyn Error>> {
+    match action {
+        // Commands that need an argument wait on the command line for it
+        PaletteAction::Command(command) if command_bar.needs_argument(&command) => {
+            command_bar.activate_with_input(&format!("{} ", command));
+            Ok(false)
+        }
+        PaletteAction::Command(command) => {
+            if command_bar.execute(editor, &command)? {
+                return Ok(true);
+            }
+            run_pending_commands(editor, command_bar)
+        }
+        PaletteAction::Keys(keys) => {
+            for key in keys {
+                if handle_key(editor, file_explorer, command_bar, key)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        PaletteAction::Open(path) => {
+            if let Err(e) = editor.open_file(&path) {
+                set_error(format!("Failed to open {}: {}", path.display(), e));
+            }
+            Ok(false)
+        }
+        PaletteAction::Tag(tag) => {
+            if let Err(e) = editor.jump_to_tag(&tag) {
+                set_error(format!("Failed to open {}: {}", tag.file.display(), e));
+            }
+            Ok(false)
+        }
+    }
+}
+
+fn handle_write_prompt(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    editor: &mut Editor,
+    command_bar: &mut CommandBar,
+    key: event::KeyEvent,
+) -> Result<(), Box<dyn Error>> {
+    match key.code {
+        KeyCode::Char('s') => {
+            editor.dismiss_write_prompt();
+            clear_error();
+            command_bar.activate_with_input("saveas ");
+        }
+        KeyCode::Char('r') => {
+            // The escalation command may need to ask for a password on the terminal
+            restore_terminal(terminal)?;
+            let result = editor.retry_write_with_privileges();
+            resume_terminal(terminal)?;
+            match result {
+                Ok(()) => set_error("File saved with elevated privileges.".to_string()),
+                Err(e) => set_error(format!("Privileged write failed: {}", e)),
+            }
+        }
+        KeyCode::Char('c') | KeyCode::Esc => {
+            editor.dismiss_write_prompt();
+            set_error("Write cancelled.".to_string());
+        }
+        _ => {
+            if let Some(prompt) = editor.get_write_prompt() {
+                set_error(prompt.message());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn show_image_preview(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    file_explorer: &FileExplorer,
+    shown: &mut Option<(PathBuf, Rect)>,
+) -> io::Result<()> {
+    let wanted = file_explorer.image_preview();
+    if wanted == *shown {
+        return Ok(());
+    }
+    if shown.is_some() {
+        execute!(terminal.backend_mut(), Print(media::KITTY_CLEAR))?;
+    }
+    if let Some((path, area)) = &wanted {
+        if let Some(image) = media::kitty_image(path, area.width, area.height) {
+            execute!(terminal.backend_mut(), MoveTo(area.x, area.y), Print(image))?;
+        }
+    }
+    *shown = wanted;
+    Ok(())
+}
+
+fn handle_project_prompt(editor: &mut Editor, key: event::KeyEvent) {
+    match key.code {
+        KeyCode::Char('t') => {
+            // Keep errors from applying the config visible
+            clear_error();
+            match editor.trust_project() {
+                Ok(()) => {
+                    if get_error().is_none() {
+                        set_error("Project config loaded.".to_string());
+                    }
+                }
+                Err(e) => set_error(format!("Failed to trust project config: {}", e)),
+            }
+        }
+        KeyCode::Char('i') | KeyCode::Esc => {
+            editor.dismiss_project_prompt();
+            set_error("Project config ignored.".to_string());
+        }
+        _ => {
+            if let Some(message) = editor.project_prompt_message() {
+                set_error(message);
+            }
+        }
+    }
+}
+
+fn run_pending_commands(
+    editor: &mut Editor,
+    command_bar: &CommandBar,
+) -> Result<bool, Box<dyn Error>> {
+    for command in editor.take_pending_commands() {
+        if command_bar.execute(editor, &command)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
 }
 
 fn handle_input(
@@ -334,13 +1239,85 @@ fn handle_input(
 
     if file_explorer.open {
         handle_file_explorer_input(editor, file_explorer, key)
+    } else if command_bar.is_active() {
+        // Also the expression prompt of Ctrl-r = in insert mode
+        handle_command_bar(editor, command_bar, key)
     } else {
         match editor.get_mode() {
-            Mode::Normal => handle_normal_mode(editor, file_explorer, command_bar, key),
-            Mode::Insert => handle_insert_mode(editor, key),
-            Mode::Visual => handle_visual_mode(editor, key),
+            Mode::Normal if editor.is_terminal_window() => {
+                handle_terminal_normal_mode(editor, file_explorer, command_bar, key)
+            }
+            Mode::Normal if editor.is_debug_panel() => {
+                handle_debug_panel(editor, file_explorer, command_bar, key)
+            }
+            Mode::Normal if editor.is_diagnostics_panel() => {
+                handle_diagnostics_panel(editor, file_explorer, command_bar, key)
+            }
+            Mode::Normal if editor.is_file_history_panel() => {
+                handle_file_history_panel(editor, file_explorer, command_bar, key)
+            }
+            Mode::Terminal => handle_terminal_mode(editor, key),
+            _ => run_editor_key(editor, file_explorer, command_bar, key),
+        }
+    }
+}
+
+fn run_editor_key(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &mut CommandBar,
+    key: event::KeyEvent,
+) -> Result<bool, Box<dyn Error>> {
+    match keymap::handle_key(editor, Key::from(key)) {
+        Some(KeyAction::CommandLine(input)) => {
+            command_bar.activate_with_input(&input);
+            command_bar.reset_suggestion_index();
+        }
+        Some(KeyAction::Search(forward)) => command_bar.activate_search(forward),
+        Some(KeyAction::Expression) => command_bar.activate_expression(),
+        Some(KeyAction::FileExplorer) => {
+            let follow = editor.get_options().explorerfollow;
+            open_file_explorer(editor, file_explorer, follow)?;
         }
+        None => {}
+    }
+    Ok(false)
+}
+
+fn handle_terminal_mode(editor: &mut Editor, key: event::KeyEvent) -> Result<bool, Box<dyn Error>> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('\\') {
+        editor.set_mode(Mode::Normal);
+        return Ok(false);
+    }
+    if let Some(pane) = editor.get_current_terminal_mut() {
+        let application_cursor = pane.parser().screen().application_cursor();
+        if let Err(e) = pane.write_input(&key_to_bytes(key, application_cursor)) {
+            set_error(format!("Failed to write to terminal: {}", e));
+        }
+    }
+    Ok(false)
+}
+
+fn handle_terminal_normal_mode(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &mut CommandBar,
+    key: event::KeyEvent,
+) -> Result<bool, Box<dyn Error>> {
+    let page = editor.get_viewport(editor.get_current_window()).1 as isize;
+    let Some(pane) = editor.get_current_terminal_mut() else {
+        return Ok(false);
+    };
+    match (key.modifiers, key.code) {
+        (KeyModifiers::NONE, KeyCode::Char('i' | 'a')) => editor.set_mode(Mode::Terminal),
+        (KeyModifiers::NONE, KeyCode::Char('k') | KeyCode::Up) => pane.scroll(1),
+        (KeyModifiers::NONE, KeyCode::Char('j') | KeyCode::Down) => pane.scroll(-1),
+        (_, KeyCode::PageUp) => pane.scroll(page),
+        (_, KeyCode::PageDown) => pane.scroll(-page),
+        (KeyModifiers::SHIFT, KeyCode::Char('G')) => pane.scroll_to_bottom(),
+        _ => return run_editor_key(editor, file_explorer, command_bar, key),
     }
+    Ok(false)
 }
 
 fn handle_file_explorer_input(
@@ -348,11 +1325,71 @@ fn handle_file_explorer_input(
     file_explorer: &mut FileExplorer,
     key: event::KeyEvent,
 ) -> Result<bool, Box<dyn Error>> {
+    if file_explorer.is_confirming_delete() {
+        file_explorer.confirm_delete(key.code == KeyCode::Char('y'))?;
+        return Ok(false);
+    }
+    if file_explorer.is_editing_permissions() {
+        if let Err(e) = file_explorer.handle_chmod_key(key.code) {
+            set_error(format!("Failed to change permissions: {}", e));
+        }
+        return Ok(false);
+    }
+    let browsing = !file_explorer.is_in_search_mode();
     match (key.modifiers, key.code) {
-        (KeyModifiers::NONE, KeyCode::Char('/')) if !file_explorer.is_in_search_mode() => {
+        (KeyModifiers::NONE, KeyCode::Char('/')) if browsing => {
             file_explorer.enter_search_mode();
         }
+        (KeyModifiers::NONE, KeyCode::Tab) | (KeyModifiers::NONE, KeyCode::Char(' '))
+            if browsing || key.code == KeyCode::Tab =>
+        {
+            file_explorer.toggle_mark()?;
+        }
+        (KeyModifiers::NONE, KeyCode::Char(c @ ('d' | 'm' | 'c' | 'o' | 'u')))
+            if browsing && file_explorer.has_marks() =>
+        {
+            match c {
+                'd' => file_explorer.request_delete(),
+                'm' => file_explorer.move_marked_here()?,
+                'c' => file_explorer.copy_marked_here()?,
+                'u' => file_explorer.clear_marks(),
+                _ => {
+                    let mut files = file_explorer.take_marked_files();
+                    files.retain(|path| !file_explorer.is_binary_or_non_utf8(path).unwrap_or(true));
+                    // Open in reverse so the first file ends up current
+                    let (mut opened, mut failure) = (0, None);
+                    for path in files.iter().rev() {
+                        match editor.open_file(path) {
+                            Ok(()) => opened += 1,
+                            // Reported for the first file in the list that failed
+                            Err(e) => {
+                                failure = Some(format!("Failed on {}: {}", path.display(), e))
+                            }
+                        }
+                    }
+                    if opened > 0 {
+                        file_explorer.open = false;
+                    }
+                    match failure {
+                        Some(failure) => set_error(failure),
+                        None if opened > 0 => set_error(format!("Opened {} files", opened)),
+                        None => {}
+                    }
+                }
+            }
+        }
+        (KeyModifiers::SHIFT, KeyCode::Char('L')) if browsing => {
+            if let Err(e) = file_explorer.follow_link() {
+                set_error(format!("Failed to follow link: {}", e));
+            }
+        }
+        (KeyModifiers::NONE, KeyCode::Char('p')) if browsing => {
+            if let Err(e) = file_explorer.edit_permissions() {
+                set_error(format!("Failed to read permissions: {}", e));
+            }
+        }
         (KeyModifiers::NONE, KeyCode::Enter) => {
+            let line = file_explorer.selected_match_line();
             if let Some(path) = file_explorer.enter_directory()? {
                 if file_explorer.is_binary_or_non_utf8(&path)? {
                     file_explorer.show_error(&format!(
@@ -362,6 +1399,9 @@ fn handle_file_explorer_input(
                 } else {
                     file_explorer.open = false;
                     editor.open_file(&path)?;
+                    if let Some(line) = line {
+                        editor.go_to_line(line);
+                    }
                 }
             }
         }
@@ -381,9 +1421,13 @@ fn handle_file_explorer_input(
         }
         (KeyModifiers::NONE, KeyCode::Right) => {
             if !file_explorer.is_in_search_mode() {
+                let line = file_explorer.selected_match_line();
                 if let Some(path) = file_explorer.enter_directory()? {
                     file_explorer.open = false;
                     editor.open_file(&path)?;
+                    if let Some(line) = line {
+                        editor.go_to_line(line);
+                    }
                 }
             }
         }
@@ -395,11 +1439,16 @@ fn handle_file_explorer_input(
             }
         }
         (KeyModifiers::SHIFT, KeyCode::Char('G')) => {
-            file_explorer.toggle_global_search()?;
+            file_explorer.cycle_search_scope()?;
+        }
+        (KeyModifiers::ALT, KeyCode::Char('c')) => {
+            file_explorer.toggle_case_sensitive()?;
         }
         (KeyModifiers::NONE, KeyCode::Char(c)) => {
             if file_explorer.is_in_search_mode() {
                 file_explorer.handle_search_input(c)?;
+            } else if let Some(number) = c.to_digit(10) {
+                file_explorer.go_to_breadcrumb(number as usize)?;
             }
         }
         _ => {}
@@ -407,172 +1456,132 @@ fn handle_file_explorer_input(
     Ok(false)
 }
 
-fn handle_normal_mode(
+fn open_file_explorer(
+    editor: &Editor,
+    file_explorer: &mut FileExplorer,
+    reveal: bool,
+) -> io::Result<()> {
+    file_explorer.open = true;
+
+    let working_directory = editor.get_working_directory().to_path_buf();
+    file_explorer.set_starting_directory(working_directory.clone());
+
+    match editor.get_current_file_path() {
+        Some(path) if reveal && path.is_file() => file_explorer.reveal(&path),
+        Some(path) if !editor.is_scratch_buffer() => {
+            file_explorer.open_current_file_directory(Some(&path))
+        }
+        _ => file_explorer.set_current_directory(working_directory),
+    }
+}
+
+fn handle_diagnostics_panel(
     editor: &mut Editor,
     file_explorer: &mut FileExplorer,
     command_bar: &mut CommandBar,
     key: event::KeyEvent,
 ) -> Result<bool, Box<dyn Error>> {
-    if command_bar.is_active() {
-        match key.code {
-            KeyCode::Char(':') if command_bar.get_input().is_empty() => Ok(false),
-            KeyCode::Char(c) => {
-                command_bar.input(c);
-                command_bar.reset_suggestion_index();
-                Ok(false)
-            }
-            KeyCode::Backspace => {
-                command_bar.backspace();
-                command_bar.reset_suggestion_index();
-                Ok(false)
-            }
-            KeyCode::Tab => {
-                command_bar.cycle_suggestion(true);
-                Ok(false)
-            }
-            KeyCode::BackTab => {
-                command_bar.cycle_suggestion(false);
-                Ok(false)
-            }
-            KeyCode::Right => {
-                command_bar.next_suggestion_page();
-                Ok(false)
-            }
-            KeyCode::Left => {
-                command_bar.prev_suggestion_page();
-                Ok(false)
-            }
-            KeyCode::Enter => {
-                let result = command_bar.execute_command(editor);
-                command_bar.deactivate();
-                command_bar.reset_suggestion_index();
-                result
-            }
-            KeyCode::Esc => {
-                command_bar.deactivate();
-                command_bar.reset_suggestion_index();
-                Ok(false)
-            }
-            _ => Ok(false),
+    match (key.modifiers, key.code) {
+        (KeyModifiers::NONE, KeyCode::Enter) => editor.jump_to_diagnostic(),
+        (KeyModifiers::NONE, KeyCode::Char('s')) => editor.toggle_diagnostics_sort(),
+        (KeyModifiers::NONE, KeyCode::Char('e')) => {
+            editor.filter_diagnostics_panel(Severity::Error)
         }
-    } else {
-        match (key.modifiers, key.code) {
-            (KeyModifiers::NONE, KeyCode::Char('v')) => {
-                editor.enter_visual_mode();
-                Ok(false)
-            }
-            (KeyModifiers::NONE, KeyCode::Char(':')) => {
-                command_bar.activate();
-                command_bar.reset_suggestion_index();
-                Ok(false)
-            }
-            (KeyModifiers::NONE, KeyCode::Char('i')) => {
-                editor.set_mode(Mode::Insert);
-                Ok(false)
-            }
-            (KeyModifiers::NONE, KeyCode::Char('f')) => {
-                file_explorer.open = true;
-
-                if editor.is_scratch_buffer() || editor.get_current_file_path().is_none() {
-                    if let Some(starting_dir) = editor.get_starting_directory() {
-                        file_explorer.set_current_directory(starting_dir.clone())?;
-                    } else {
-                        // Fallback to current directory if starting directory is not set
-                        file_explorer.set_current_directory(env::current_dir()?)?;
-                    }
-                } else {
-                    // Otherwise, open the directory of the current file
-                    file_explorer
-                        .open_current_file_directory(editor.get_current_file_path().as_deref())?;
-                }
-                Ok(false)
-            }
-            (KeyModifiers::SHIFT, KeyCode::Char('D')) => {
-                editor.toggle_debug_info();
-                Ok(false)
-            }
-            (KeyModifiers::NONE, KeyCode::Left) => {
-                editor.move_cursor(CursorMovement::Left);
-                Ok(false)
-            }
-            (KeyModifiers::NONE, KeyCode::Right) => {
-                editor.move_cursor(CursorMovement::Right);
-                Ok(false)
-            }
-            (KeyModifiers::NONE, KeyCode::Up) => {
-                editor.move_cursor(CursorMovement::Up);
-                Ok(false)
-            }
-            (KeyModifiers::NONE, KeyCode::Down) => {
-                editor.move_cursor(CursorMovement::Down);
-                Ok(false)
-            }
-            _ => Ok(false),
+        (KeyModifiers::NONE, KeyCode::Char('w')) => {
+            editor.filter_diagnostics_panel(Severity::Warning)
+        }
+        (KeyModifiers::NONE, KeyCode::Char('i')) => {
+            editor.filter_diagnostics_panel(Severity::Information)
         }
+        (KeyModifiers::NONE, KeyCode::Char('a')) => editor.filter_diagnostics_panel(Severity::Hint),
+        (KeyModifiers::NONE, KeyCode::Char('q')) if !editor.close_window() => {
+            set_error("Cannot close the last window.".to_string());
+        }
+        (KeyModifiers::NONE, KeyCode::Char('q')) => {}
+        _ => return run_editor_key(editor, file_explorer, command_bar, key),
     }
+    Ok(false)
 }
 
-fn handle_visual_mode(editor: &mut Editor, key: event::KeyEvent) -> Result<bool, Box<dyn Error>> {
+fn handle_file_history_panel(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &mut CommandBar,
+    key: event::KeyEvent,
+) -> Result<bool, Box<dyn Error>> {
     match (key.modifiers, key.code) {
-        (KeyModifiers::NONE, KeyCode::Esc) => {
-            editor.exit_visual_mode();
-            Ok(false)
+        (KeyModifiers::NONE, KeyCode::Enter) => editor.open_file_revision(false),
+        (KeyModifiers::NONE, KeyCode::Char('d')) => editor.open_file_revision(true),
+        (KeyModifiers::NONE, KeyCode::Char('q')) if !editor.close_window() => {
+            set_error("Cannot close the last window.".to_string());
         }
-        (KeyModifiers::NONE, KeyCode::Char('d')) => {
-            editor.delete_selection();
-            editor.exit_visual_mode();
-            Ok(false)
+        (KeyModifiers::NONE, KeyCode::Char('q')) => {}
+        _ => return run_editor_key(editor, file_explorer, command_bar, key),
+    }
+    Ok(false)
+}
+
+fn handle_debug_panel(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &mut CommandBar,
+    key: event::KeyEvent,
+) -> Result<bool, Box<dyn Error>> {
+    match (key.modifiers, key.code) {
+        (KeyModifiers::NONE, KeyCode::Enter) => editor.select_frame_at_cursor(),
+        (KeyModifiers::NONE, KeyCode::Char('q')) if !editor.close_window() => {
+            set_error("Cannot close the last window.".to_string());
         }
-        (KeyModifiers::NONE, KeyCode::Char('y')) => {
-            if let Some(selected_text) = editor.copy_selection() {
-                editor.copy_to_clipboard(&selected_text)?;
-                set_error("Copied to clipboard successfully.".to_string());
-            }
-            editor.exit_visual_mode();
+        (KeyModifiers::NONE, KeyCode::Char('q')) => {}
+        _ => return run_editor_key(editor, file_explorer, command_bar, key),
+    }
+    Ok(false)
+}
+
+fn handle_command_bar(
+    editor: &mut Editor,
+    command_bar: &mut CommandBar,
+    key: event::KeyEvent,
+) -> Result<bool, Box<dyn Error>> {
+    match key.code {
+        KeyCode::Char(':') if command_bar.get_input().is_empty() => Ok(false),
+        KeyCode::Char(c) => {
+            command_bar.input(c);
+            command_bar.reset_suggestion_index();
             Ok(false)
         }
-        (KeyModifiers::NONE, KeyCode::Left) => {
-            editor.move_cursor(CursorMovement::Left);
+        KeyCode::Backspace => {
+            command_bar.backspace();
+            command_bar.reset_suggestion_index();
             Ok(false)
         }
-        (KeyModifiers::NONE, KeyCode::Right) => {
-            editor.move_cursor(CursorMovement::Right);
+        KeyCode::Tab => {
+            command_bar.cycle_suggestion(true);
             Ok(false)
         }
-        (KeyModifiers::NONE, KeyCode::Up) => {
-            editor.move_cursor(CursorMovement::Up);
+        KeyCode::BackTab => {
+            command_bar.cycle_suggestion(false);
             Ok(false)
         }
-        (KeyModifiers::NONE, KeyCode::Down) => {
-            editor.move_cursor(CursorMovement::Down);
+        KeyCode::Right => {
+            command_bar.next_suggestion_page();
             Ok(false)
         }
-        (KeyModifiers::NONE, KeyCode::Home) => {
-            editor.move_cursor(CursorMovement::LineStart);
+        KeyCode::Left => {
+            command_bar.prev_suggestion_page();
             Ok(false)
         }
-        (KeyModifiers::NONE, KeyCode::End) => {
-            editor.move_cursor(CursorMovement::LineEnd);
+        KeyCode::Enter => {
+            let result = command_bar.execute_command(editor);
+            command_bar.deactivate();
+            command_bar.reset_suggestion_index();
+            result
+        }
+        KeyCode::Esc => {
+            command_bar.deactivate();
+            command_bar.reset_suggestion_index();
             Ok(false)
         }
         _ => Ok(false),
     }
 }
-
-fn handle_insert_mode(editor: &mut Editor, key: event::KeyEvent) -> Result<bool, Box<dyn Error>> {
-    match key.code {
-        KeyCode::Char(c) => editor.insert(c),
-        KeyCode::Backspace => editor.delete(),
-        KeyCode::Enter => editor.insert_new_line(),
-        KeyCode::Left => editor.move_cursor(CursorMovement::Left),
-        KeyCode::Right => editor.move_cursor(CursorMovement::Right),
-        KeyCode::Up => editor.move_cursor(CursorMovement::Up),
-        KeyCode::Down => editor.move_cursor(CursorMovement::Down),
-        KeyCode::Home => editor.move_cursor(CursorMovement::LineStart),
-        KeyCode::End => editor.move_cursor(CursorMovement::LineEnd),
-        KeyCode::Tab => editor.insert_str("    ".to_string()),
-        KeyCode::Esc => editor.set_mode(Mode::Normal),
-        _ => {}
-    }
-    Ok(false)
-}
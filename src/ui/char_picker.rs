@@ -0,0 +1,201 @@
+use crate::{
+    ui::{
+        popup::{Placement, Popup, PopupId, PopupLayer},
+        render::marked_spans,
+    },
+    utils::{
+        fuzzy::{fuzzy_match, fuzzy_score},
+        unicode_names::UNICODE_NAMES,
+    },
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+use unicode_width::UnicodeWidthChar;
+
+const PICKER_WIDTH: u16 = 64;
+const PICKER_RESULTS: usize = 12;
+
+pub struct CharPicker {
+    popup: Option<PopupId>,
+    query: String,
+    // The characters matching the query with their names, best first
+    matches: Vec<(char, String)>,
+    selected: usize,
+}
+
+impl CharPicker {
+    pub fn new() -> Self {
+        Self {
+            popup: None,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn open(&mut self, popups: &mut PopupLayer, query: &str) {
+        self.close(popups);
+        self.query = query.to_string();
+        self.update_matches();
+
+        let mut popup = Popup::new(
+            Text::default(),
+            Placement::Cursor,
+            PICKER_WIDTH,
+            PICKER_RESULTS as u16 + 3,
+        );
+        popup.title = Some("Insert Character".to_string());
+        popup.focusable = true;
+        popup.z_index = 10;
+        self.popup = Some(popups.open(popup));
+        self.update_popup(popups);
+    }
+
+    pub fn close(&mut self, popups: &mut PopupLayer) {
+        if let Some(popup) = self.popup.take() {
+            popups.close(popup);
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    pub fn handle_key(&mut self, popups: &mut PopupLayer, key: KeyEvent) -> Option<char> {
+        match key.code {
+            KeyCode::Esc => self.close(popups),
+            KeyCode::Enter => {
+                let chosen = self.matches.get(self.selected).map(|(c, _)| *c);
+                self.close(popups);
+                return chosen;
+            }
+            KeyCode::Up | KeyCode::BackTab => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1));
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_matches();
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        self.update_popup(popups);
+        None
+    }
+
+    fn update_matches(&mut self) {
+        let query = self.query.trim();
+        let mut matches = Vec::new();
+
+        // A code point, or a character pasted in to see what it is
+        if let Some(c) = code_point(query) {
+            matches.push((c, name_of(c).unwrap_or_default().to_string()));
+        }
+        let mut chars = query.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if let Some(name) = name_of(c) {
+                matches.push((c, name.to_string()));
+            }
+        }
+
+        let mut scored: Vec<(i64, usize)> = UNICODE_NAMES
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (_, name))| fuzzy_score(query, name).map(|score| (score, index)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for (_, index) in scored {
+            let (c, name) = UNICODE_NAMES[index];
+            if !matches.iter().any(|(found, _)| *found == c) {
+                matches.push((c, name.to_string()));
+            }
+        }
+
+        self.matches = matches;
+        self.selected = 0;
+    }
+
+    fn update_popup(&self, popups: &mut PopupLayer) {
+        let Some(popup) = self.popup.and_then(|id| popups.get_mut(id)) else {
+            return;
+        };
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Yellow)),
+            Span::raw(self.query.clone()),
+        ])];
+
+        let first = self.selected.saturating_sub(PICKER_RESULTS - 1);
+        for (index, (c, name)) in self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(PICKER_RESULTS)
+        {
+            let name_style = if index == self.selected {
+                Style::default().fg(Color::Black).bg(Color::White)
+            } else {
+                Style::default().fg(Color::Blue)
+            };
+            let marked = name_style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+            let positions = fuzzy_match(self.query.trim(), name)
+                .map(|(_, found)| found)
+                .unwrap_or_default();
+
+            let mut spans = vec![
+                glyph(*c),
+                Span::styled(
+                    format!(" U+{:04X}  ", *c as u32),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ];
+            spans.extend(marked_spans(name, &positions, name_style, marked));
+            lines.push(Line::from(spans));
+        }
+        if self.matches.is_empty() {
+            lines.push(Line::styled(
+                "No matching characters".to_string(),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        popup.content = Text::from(lines);
+    }
+}
+
+fn code_point(query: &str) -> Option<char> {
+    let hex = query
+        .strip_prefix("U+")
+        .or_else(|| query.strip_prefix("u+"))?;
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+fn name_of(c: char) -> Option<&'static str> {
+    UNICODE_NAMES
+        .iter()
+        .find(|(named, _)| *named == c)
+        .map(|(_, name)| *name)
+}
+
+fn glyph(c: char) -> Span<'static> {
+    match c.width() {
+        Some(2) => Span::raw(c.to_string()),
+        Some(1) if !c.is_whitespace() => Span::raw(format!("{} ", c)),
+        _ => Span::styled("· ", Style::default().fg(Color::DarkGray)),
+    }
+}
+
+impl Default for CharPicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,18 +1,58 @@
-use crate::{editor::Editor, utils::error_handler::set_error, utils::help_handler::set_help_topic};
-use std::path::PathBuf;
+use crate::{
+    editor::{
+        align,
+        ex_range::{Address, ExRange, LineAddress},
+        export::ExportFormat,
+        fetch,
+        history::TimeTravel,
+        reformat,
+        search::Substitution,
+        signs::Sign,
+        window::SplitDirection,
+        Editor,
+    },
+    ui::ex_command::{edit_distance, Arg, CommandLine, Invocation, Signature},
+    utils::error_handler::{get_error, get_message_history, set_error},
+    utils::help_handler::set_help_topic,
+    utils::path_expander::{expand_env, expand_path, expand_single_path},
+};
+use std::{io, path::PathBuf, time::SystemTime};
 
 const SUGGESTIONS_PER_PAGE: usize = 5;
+// Typos further than this from every command name get no suggestion
+const MAX_TYPO_DISTANCE: usize = 2;
+const SIGN_SOURCE: &str = "user";
+const SIGN_USAGE: &str = "Usage: :sign place <symbol> [group] [priority] | unplace | clear";
+
+pub type CommandAction = fn(&mut Editor, &Invocation) -> Result<bool, Box<dyn std::error::Error>>;
 
 pub struct Command {
     pub name: String,
     pub description: String,
-    pub action: fn(&mut Editor) -> Result<bool, Box<dyn std::error::Error>>,
+    // The range and argument the command accepts, checked before it runs
+    pub signature: Signature,
+    pub action: CommandAction,
     pub help_topic: String,
+    // What the command does when written as `name!`
+    pub bang: Option<Bang>,
+}
+
+impl Command {
+    pub fn usage(&self) -> String {
+        self.signature.usage(&self.name, self.bang.is_some())
+    }
+}
+
+pub struct Bang {
+    pub description: String,
+    pub action: CommandAction,
 }
 
 pub struct CommandBar {
     input: String,
     active: bool,
+    // `:` for commands, `/` or `?` for a search forward or backward
+    prompt: char,
     commands: Vec<Command>,
     suggestion_index: usize,
     pub suggestion_page: usize,
@@ -23,47 +63,82 @@ impl CommandBar {
         CommandBar {
             input: String::new(),
             active: false,
+            prompt: ':',
             commands: vec![
                 Command {
                     name: "q".to_string(),
                     description: "Quit the editor".to_string(),
-                    action: |editor| {
-                        if editor.has_unsaved_changes() {
-                            set_error("Unsaved changes. Use :q! to force quit.".to_string());
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        if editor.close_window() {
+                            Ok(false)
+                        } else if editor.has_unsaved_changes() {
+                            let names: Vec<String> = editor
+                                .get_unsaved_buffers()
+                                .into_iter()
+                                .filter_map(|id| editor.get_buffer(id))
+                                .map(|buffer| buffer.name.to_string())
+                                .collect();
+                            set_error(format!(
+                                "Unsaved changes in {}. Use :qa to review them or :q! to force quit.",
+                                names.join(", ")
+                            ));
                             Ok(false)
                         } else {
                             Ok(true) // Signal to quit the application
                         }
                     },
                     help_topic: "quit".to_string(),
+                    bang: Some(Bang {
+                        description: "Force quit without saving".to_string(),
+                        action: |editor, _| Ok(!editor.close_window()), // Force quit
+                    }),
                 },
                 Command {
-                    name: "q!".to_string(),
-                    description: "Force quit without saving".to_string(),
-                    action: |_| Ok(true), // Force quit
-                    help_topic: "force_quit".to_string(),
+                    name: "qa".to_string(),
+                    description: "Quit, choosing what to do with each unsaved buffer".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        if editor.has_unsaved_changes() {
+                            editor.request_quit_triage();
+                            Ok(false)
+                        } else {
+                            Ok(true)
+                        }
+                    },
+                    help_topic: "quit".to_string(),
+                    bang: Some(Bang {
+                        description: "Quit discarding all unsaved changes".to_string(),
+                        action: |_, _| Ok(true),
+                    }),
                 },
                 Command {
                     name: "w".to_string(),
                     description: "Save the current file".to_string(),
-                    action: |editor| {
-                        if let Some(path) = editor.get_current_file_path() {
-                            editor.save_file(&path)?;
-                            set_error("File saved successfully.".to_string());
-                        } else {
-                            set_error("No file path set. Use :w <filename> to save.".to_string());
-                        }
+                    signature: Signature::optional(Arg::File).with_range(),
+                    action: |editor, invocation| {
+                        Self::write(editor, invocation, false);
                         Ok(false)
                     },
                     help_topic: "save".to_string(),
+                    bang: Some(Bang {
+                        description: "Save even if the file is read-only or changed on disk"
+                            .to_string(),
+                        action: |editor, invocation| {
+                            Self::write(editor, invocation, true);
+                            Ok(false)
+                        },
+                    }),
                 },
                 Command {
                     name: "wq".to_string(),
                     description: "Save and quit".to_string(),
-                    action: |editor| {
+                    signature: Signature::NONE,
+                    action: |editor, _| {
                         if let Some(path) = editor.get_current_file_path() {
-                            editor.save_file(&path)?;
-                            Ok(true) // Signal to quit after saving
+                            let result = editor.save_file(&path);
+                            // Signal to quit only once the file is saved
+                            Ok(Self::report_save(editor, result, String::new()))
                         } else {
                             set_error(
                                 "No file path set. Use :w <filename> to save before quitting."
@@ -73,51 +148,789 @@ impl CommandBar {
                         }
                     },
                     help_topic: "save_and_quit".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "saveas".to_string(),
+                    description: "Save the current buffer under a new name".to_string(),
+                    signature: Signature::required(Arg::File),
+                    action: |editor, invocation| {
+                        if let Some(path) = Self::expand_target(editor, invocation.args) {
+                            let result = editor.save_file_as(&path);
+                            Self::report_save(editor, result, format!("Saved as {:?}", path));
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "saveas".to_string(),
+                    bang: None,
                 },
                 Command {
                     name: "e".to_string(),
                     description: "Edit a file".to_string(),
-                    action: |_| {
-                        set_error("Use :e <filename> to open a file.".to_string());
+                    signature: Signature::required(Arg::File),
+                    action: |editor, invocation| {
+                        Self::open_files(editor, invocation.args);
                         Ok(false)
                     },
                     help_topic: "edit".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "r".to_string(),
+                    description: "Insert a file below the current line".to_string(),
+                    signature: Signature::required(Arg::File),
+                    action: |editor, invocation| {
+                        if let Some(path) = Self::expand_target(editor, invocation.args) {
+                            match editor.read_file_below_cursor(&path) {
+                                Ok(lines) => {
+                                    set_error(format!("Inserted {} line(s) from {:?}", lines, path))
+                                }
+                                Err(e) => set_error(format!(
+                                    "Failed to read file: {:?}. Error: {}",
+                                    path, e
+                                )),
+                            }
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "read".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "cd".to_string(),
+                    description: "Change the working directory".to_string(),
+                    signature: Signature::optional(Arg::File).hint("<dir>"),
+                    action: |editor, invocation| {
+                        Self::change_directory(editor, &expand_env(invocation.args), false);
+                        Ok(false)
+                    },
+                    help_topic: "cd".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "lcd".to_string(),
+                    description: "Change the working directory of the current buffer".to_string(),
+                    signature: Signature::optional(Arg::File).hint("<dir>"),
+                    action: |editor, invocation| {
+                        Self::change_directory(editor, &expand_env(invocation.args), true);
+                        Ok(false)
+                    },
+                    help_topic: "lcd".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "pwd".to_string(),
+                    description: "Show the working directory".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        set_error(editor.get_working_directory().display().to_string());
+                        Ok(false)
+                    },
+                    help_topic: "pwd".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "earlier".to_string(),
+                    description: "Go back to an earlier state of the buffer".to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("N|Ns|Nm|Nh|Nd"),
+                    action: |editor, invocation| {
+                        Self::time_travel(editor, invocation.args, false);
+                        Ok(false)
+                    },
+                    help_topic: "earlier".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "later".to_string(),
+                    description: "Go forward to a later state of the buffer".to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("N|Ns|Nm|Nh|Nd"),
+                    action: |editor, invocation| {
+                        Self::time_travel(editor, invocation.args, true);
+                        Ok(false)
+                    },
+                    help_topic: "later".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "history".to_string(),
+                    description: "Compare or restore earlier saves of the current file".to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("[restore <n>]"),
+                    action: |editor, invocation| {
+                        editor.local_history(invocation.args);
+                        Ok(false)
+                    },
+                    help_topic: "history".to_string(),
+                    bang: None,
                 },
                 Command {
                     name: "help".to_string(),
                     description: "Show help information".to_string(),
-                    action: |_| {
-                        set_help_topic("commands");
+                    signature: Signature::optional(Arg::Rest).hint("<topic>"),
+                    action: |_, invocation| {
+                        match invocation.args {
+                            "" => set_help_topic("commands"),
+                            topic => set_help_topic(topic),
+                        }
                         Ok(false)
                     },
                     help_topic: "help".to_string(),
+                    bang: None,
                 },
                 Command {
                     name: "set".to_string(),
                     description: "Set editor options".to_string(),
-                    action: |_| {
-                        set_error("Use :set <option> to set an editor option.".to_string());
+                    signature: Signature::required(Arg::Option),
+                    action: |editor, invocation| {
+                        editor.handle_set_command(invocation.args);
+                        Ok(false)
+                    },
+                    help_topic: "set_options".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "setlocal".to_string(),
+                    description: "Set an option for the current buffer only".to_string(),
+                    signature: Signature::required(Arg::Option),
+                    action: |editor, invocation| {
+                        editor.handle_setlocal_command(invocation.args);
                         Ok(false)
                     },
                     help_topic: "set_options".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "autocmd".to_string(),
+                    description: "Run an action when an editor event fires".to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("<event> <pattern> <action>"),
+                    action: |editor, invocation| {
+                        if invocation.args.is_empty() {
+                            set_error(format!(
+                                "{} hook(s) registered. Use :autocmd <event> <pattern> <action>.",
+                                editor.hook_count()
+                            ));
+                        } else {
+                            editor.handle_autocmd_command(invocation.args);
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "autocmd".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "noautocmd".to_string(),
+                    description: "Remove all registered hooks".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.clear_hooks();
+                        set_error("Removed all hooks.".to_string());
+                        Ok(false)
+                    },
+                    help_topic: "autocmd".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "messages".to_string(),
+                    description: "Show the message history in a scratch buffer".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.new_scratch_buffer_with_content(&get_message_history().join("\n"));
+                        Ok(false)
+                    },
+                    help_topic: "messages".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "zen".to_string(),
+                    description: "Toggle distraction-free zen mode".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.toggle_zen();
+                        Ok(false)
+                    },
+                    help_topic: "zen".to_string(),
+                    bang: None,
                 },
                 Command {
                     name: "split".to_string(),
                     description: "Split the window horizontally".to_string(),
-                    action: |_| {
-                        set_error("Split view not implemented yet.".to_string());
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.split_window(SplitDirection::Horizontal);
                         Ok(false)
                     },
                     help_topic: "split".to_string(),
+                    bang: None,
                 },
                 Command {
                     name: "vsplit".to_string(),
                     description: "Split the window vertically".to_string(),
-                    action: |_| {
-                        set_error("Vertical split view not implemented yet.".to_string());
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.split_window(SplitDirection::Vertical);
                         Ok(false)
                     },
                     help_topic: "vsplit".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "s".to_string(),
+                    description: "Replace a pattern on the current line or a range".to_string(),
+                    signature: Signature::required(Arg::Rest)
+                        .with_range()
+                        .hint("/pattern/replacement/[g][i|I]"),
+                    action: |editor, invocation| {
+                        let current_line = LineAddress {
+                            address: Address::CurrentLine,
+                            offset: 0,
+                        };
+                        if let Some(lines) =
+                            Self::lines_or(editor, invocation, ExRange::Single(current_line))
+                        {
+                            match Substitution::parse(invocation.args)
+                                .and_then(|sub| editor.substitute(lines, &sub))
+                            {
+                                Ok(message) => set_error(message),
+                                Err(message) => {
+                                    set_error(message);
+                                    editor.ring_bell();
+                                }
+                            }
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "substitute".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "align".to_string(),
+                    description: "Line up a delimiter across the selected lines".to_string(),
+                    signature: Signature::required(Arg::Rest)
+                        .with_range()
+                        .hint("<delimiter>"),
+                    action: |editor, invocation| {
+                        let delimiter = invocation.args.trim();
+                        match invocation.lines {
+                            Some(lines) => Self::align(editor, lines, delimiter),
+                            // Without a range, align the block of lines around the cursor
+                            None => {
+                                match editor.block_around_cursor(|line| line.contains(delimiter)) {
+                                    Some(lines) => Self::align(editor, lines, delimiter),
+                                    None => {
+                                        set_error(format!("No '{}' on the current line", delimiter))
+                                    }
+                                }
+                            }
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "align".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "move".to_string(),
+                    description: "Move the current line or a range up or down one line".to_string(),
+                    signature: Signature::required(Arg::Rest).with_range().hint("up|down"),
+                    action: |editor, invocation| {
+                        let down = match invocation.args.trim() {
+                            "up" => false,
+                            "down" => true,
+                            _ => {
+                                set_error("Usage: :move up|down".to_string());
+                                return Ok(false);
+                            }
+                        };
+                        if let Some(lines) = invocation.lines.or_else(|| editor.selected_lines()) {
+                            editor.move_lines(lines, down);
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "move".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "duplicate".to_string(),
+                    description: "Copy the current line or a range below itself, or above"
+                        .to_string(),
+                    signature: Signature::optional(Arg::Rest).with_range().hint("[up]"),
+                    action: |editor, invocation| {
+                        let down = match invocation.args.trim() {
+                            "" | "down" => true,
+                            "up" => false,
+                            _ => {
+                                set_error("Usage: :duplicate [up|down]".to_string());
+                                return Ok(false);
+                            }
+                        };
+                        if let Some(lines) = invocation.lines.or_else(|| editor.selected_lines()) {
+                            editor.duplicate_lines(lines, down);
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "duplicate".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "table".to_string(),
+                    description: "Realign the markdown table under the cursor".to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("format|row|column"),
+                    action: |editor, invocation| {
+                        let in_table = match invocation.args.trim() {
+                            "" | "format" => editor.format_table(),
+                            "row" => editor.table_insert_row(),
+                            "column" => editor.table_insert_column(),
+                            _ => {
+                                set_error("Usage: :table format|row|column".to_string());
+                                return Ok(false);
+                            }
+                        };
+                        if !in_table {
+                            set_error("The cursor is not in a markdown table".to_string());
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "table".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "checkbox".to_string(),
+                    description: "Check or uncheck the list item checkboxes of a range".to_string(),
+                    signature: Signature::NONE.with_range(),
+                    action: |editor, invocation| {
+                        let current_line = LineAddress {
+                            address: Address::CurrentLine,
+                            offset: 0,
+                        };
+                        if let Some(lines) =
+                            Self::lines_or(editor, invocation, ExRange::Single(current_line))
+                        {
+                            if !editor.toggle_checkboxes(lines) {
+                                set_error("No list items to check".to_string());
+                            }
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "checkbox".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "renumber".to_string(),
+                    description: "Renumber the ordered list under the cursor or in a range"
+                        .to_string(),
+                    signature: Signature::NONE.with_range(),
+                    action: |editor, invocation| {
+                        let lines = invocation.lines.or_else(|| editor.list_around_cursor());
+                        if !lines.is_some_and(|lines| editor.renumber_list(lines)) {
+                            set_error("Nothing to renumber".to_string());
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "checkbox".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "reflow".to_string(),
+                    description: "Re-wrap the paragraph under the cursor or a range to textwidth"
+                        .to_string(),
+                    signature: Signature::NONE.with_range(),
+                    action: |editor, invocation| {
+                        let lines = invocation
+                            .lines
+                            .or_else(|| editor.paragraph_around_cursor());
+                        if !lines.is_some_and(|lines| editor.reflow_lines(lines)) {
+                            set_error("Nothing to reflow".to_string());
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "reflow".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "normal".to_string(),
+                    description: "Run normal mode keys on each line of a range".to_string(),
+                    signature: Signature::required(Arg::Rest).with_range().hint("<keys>"),
+                    action: |editor, invocation| {
+                        editor.queue_normal(invocation.lines, invocation.args);
+                        Ok(false)
+                    },
+                    help_topic: "normal".to_string(),
+                    // There are no mappings to skip, so `:normal!` is the same
+                    bang: Some(Bang {
+                        description: "Run normal mode keys on each line of a range".to_string(),
+                        action: |editor, invocation| {
+                            editor.queue_normal(invocation.lines, invocation.args);
+                            Ok(false)
+                        },
+                    }),
+                },
+                Command {
+                    name: "convert".to_string(),
+                    description: "Convert the number or date under the cursor".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.show_conversions();
+                        Ok(false)
+                    },
+                    help_topic: "convert".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "bookmarks".to_string(),
+                    description: "List the project's bookmarks and jump to one".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.show_bookmarks_menu();
+                        Ok(false)
+                    },
+                    help_topic: "bookmarks".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "json".to_string(),
+                    description: "Pretty-print or minify JSON in the buffer or range".to_string(),
+                    signature: Signature::required(Arg::Rest)
+                        .with_range()
+                        .hint("pretty|minify"),
+                    action: |editor, invocation| {
+                        if let Some(lines) = Self::lines_or(editor, invocation, ExRange::WholeFile)
+                        {
+                            Self::reformat(editor, lines, "json", invocation.args);
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "json".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "xml".to_string(),
+                    description: "Pretty-print or minify XML in the buffer or range".to_string(),
+                    signature: Signature::required(Arg::Rest)
+                        .with_range()
+                        .hint("pretty|minify"),
+                    action: |editor, invocation| {
+                        if let Some(lines) = Self::lines_or(editor, invocation, ExRange::WholeFile)
+                        {
+                            Self::reformat(editor, lines, "xml", invocation.args);
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "xml".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "export".to_string(),
+                    description: "Export the buffer with highlighting as HTML or ANSI".to_string(),
+                    signature: Signature::required(Arg::Rest).hint("html|ansi <path>"),
+                    action: |editor, invocation| {
+                        Self::export(editor, invocation.args);
+                        Ok(false)
+                    },
+                    help_topic: "export".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "sign".to_string(),
+                    description: "Place or remove a sign on the cursor line".to_string(),
+                    signature: Signature::required(Arg::Rest)
+                        .hint("place <symbol> [group] [priority] | unplace | clear"),
+                    action: |editor, invocation| {
+                        Self::sign(editor, invocation.args);
+                        Ok(false)
+                    },
+                    help_topic: "sign".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "task".to_string(),
+                    description: "Run a task from the config".to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("<name>"),
+                    action: |editor, invocation| {
+                        if !invocation.args.is_empty() {
+                            editor.run_task(invocation.args.trim());
+                            return Ok(false);
+                        }
+                        match editor.task_names().as_slice() {
+                            [] => set_error("No tasks configured.".to_string()),
+                            names => set_error(format!("Tasks: {}", names.join(", "))),
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "task".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "reveal".to_string(),
+                    description: "Show the current file in the file explorer".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.request_reveal();
+                        Ok(false)
+                    },
+                    help_topic: "reveal".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "dirdiff".to_string(),
+                    description: "Compare two directory trees".to_string(),
+                    signature: Signature::required(Arg::Rest).hint("<dir> <dir>"),
+                    action: |editor, invocation| {
+                        editor.dir_diff(invocation.args);
+                        Ok(false)
+                    },
+                    help_topic: "dirdiff".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "bd".to_string(),
+                    description: "Close the current buffer".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.close_buffer(false);
+                        Ok(false)
+                    },
+                    help_topic: "bd".to_string(),
+                    bang: Some(Bang {
+                        description: "Close the current buffer, discarding its changes".to_string(),
+                        action: |editor, _| {
+                            editor.close_buffer(true);
+                            Ok(false)
+                        },
+                    }),
+                },
+                Command {
+                    name: "reopen".to_string(),
+                    description: "Reopen the most recently closed buffer".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.reopen_closed_buffer(None);
+                        Ok(false)
+                    },
+                    help_topic: "reopen".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "closed".to_string(),
+                    description: "Pick a recently closed buffer to reopen".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.list_closed_buffers();
+                        Ok(false)
+                    },
+                    help_topic: "closed".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "buffers".to_string(),
+                    description: "Pick an open buffer to show".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.list_buffers();
+                        Ok(false)
+                    },
+                    help_topic: "buffers".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "git".to_string(),
+                    description: "Pick a git branch or stash, or list the commits of the file"
+                        .to_string(),
+                    signature: Signature::required(Arg::Rest).hint("branch|stash|log %"),
+                    action: |editor, invocation| {
+                        let args: Vec<&str> = invocation.args.split_whitespace().collect();
+                        match args.as_slice() {
+                            ["branch"] => editor.list_branches(),
+                            ["stash"] => editor.list_stashes(),
+                            ["log"] | ["log", "%"] => editor.show_file_history(),
+                            _ => set_error("Usage: :git branch|stash|log %".to_string()),
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "git".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "calc".to_string(),
+                    description: "Open a scratch buffer that evaluates lines ending in ="
+                        .to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.open_calc_buffer();
+                        Ok(false)
+                    },
+                    help_topic: "calc".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "todo".to_string(),
+                    description: "List the TODO, FIXME and HACK comments of the project"
+                        .to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("[keyword]"),
+                    action: |editor, invocation| {
+                        editor.list_todos(invocation.args.trim());
+                        Ok(false)
+                    },
+                    help_topic: "todo".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "format".to_string(),
+                    description: "Format the buffer or range with the formatter or language server"
+                        .to_string(),
+                    signature: Signature::NONE.with_range(),
+                    action: |editor, invocation| {
+                        if let Some(lines) = Self::lines_or(editor, invocation, ExRange::WholeFile)
+                        {
+                            if let Err(e) = editor.format_lines(lines) {
+                                set_error(e);
+                            }
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "format".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "debug".to_string(),
+                    description: "Start debugging with a [[debug]] setup from the config"
+                        .to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("[setup]"),
+                    action: |editor, invocation| {
+                        editor.start_debugging(invocation.args.trim());
+                        Ok(false)
+                    },
+                    help_topic: "debug".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "debugstop".to_string(),
+                    description: "End the debug session, stopping the program".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.stop_debugging();
+                        Ok(false)
+                    },
+                    help_topic: "debug".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "debugeval".to_string(),
+                    description: "Evaluate an expression in the debugged program".to_string(),
+                    signature: Signature::required(Arg::Rest).hint("<expression>"),
+                    action: |editor, invocation| {
+                        editor.evaluate_in_debugger(invocation.args.trim());
+                        Ok(false)
+                    },
+                    help_topic: "debug".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "debugpanel".to_string(),
+                    description: "Show the stack, variables and console of the debugger"
+                        .to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.show_debug_panel();
+                        Ok(false)
+                    },
+                    help_topic: "debug".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "breakpoint".to_string(),
+                    description: "Toggle a breakpoint on the cursor line".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.toggle_breakpoint_at_cursor();
+                        Ok(false)
+                    },
+                    help_topic: "debug".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "diagnostics".to_string(),
+                    description: "List the language server diagnostics of all files in a panel"
+                        .to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("[error|warning|info] [file]"),
+                    action: |editor, invocation| {
+                        editor.show_diagnostics_panel(invocation.args.trim());
+                        Ok(false)
+                    },
+                    help_topic: "diagnostics".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "rename".to_string(),
+                    description: "Rename the symbol under the cursor with the language server"
+                        .to_string(),
+                    signature: Signature::required(Arg::Rest).hint("<newname>"),
+                    action: |editor, invocation| {
+                        if let Err(e) = editor.rename_symbol(invocation.args.trim()) {
+                            set_error(e);
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "rename".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "codeaction".to_string(),
+                    description:
+                        "Pick a code action for the cursor's line from the language server"
+                            .to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        if let Err(e) = editor.show_code_actions() {
+                            set_error(e);
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "codeaction".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "emoji".to_string(),
+                    description: "Pick a character or emoji by name and insert it".to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("[name]"),
+                    action: |editor, invocation| {
+                        editor.request_char_picker(invocation.args.trim());
+                        Ok(false)
+                    },
+                    help_topic: "emoji".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "note".to_string(),
+                    description: "Open the notes for this project".to_string(),
+                    signature: Signature::optional(Arg::Rest).hint("[encrypt|decrypt]"),
+                    action: |editor, invocation| {
+                        let result = match invocation.args.trim() {
+                            "" => editor.open_notes(),
+                            "encrypt" => editor.encrypt_notes(),
+                            "decrypt" => editor.decrypt_notes().map(|()| {
+                                set_error("Notes saved unencrypted".to_string());
+                            }),
+                            other => Err(io::Error::other(format!(
+                                "Unknown argument: {}. Use encrypt or decrypt",
+                                other
+                            ))),
+                        };
+                        if let Err(e) = result {
+                            set_error(format!("Notes: {}", e));
+                        }
+                        Ok(false)
+                    },
+                    help_topic: "note".to_string(),
+                    bang: None,
+                },
+                Command {
+                    name: "terminal".to_string(),
+                    description: "Open a shell in a new window".to_string(),
+                    signature: Signature::NONE,
+                    action: |editor, _| {
+                        editor.open_terminal();
+                        Ok(false)
+                    },
+                    help_topic: "terminal".to_string(),
+                    bang: None,
                 },
             ],
             suggestion_index: 0,
@@ -126,8 +939,66 @@ impl CommandBar {
     }
 
     pub fn get_command(&self) -> Option<&Command> {
-        let input = self.input.trim();
-        self.commands.iter().find(|cmd| cmd.name == input)
+        let line = CommandLine::parse(&self.input).ok()?;
+        self.find_command(line.name).ok()
+    }
+
+    fn find_command(&self, name: &str) -> Result<&Command, String> {
+        if let Some(command) = self.commands.iter().find(|cmd| cmd.name == name) {
+            return Ok(command);
+        }
+        let matches: Vec<&Command> = self
+            .commands
+            .iter()
+            .filter(|cmd| cmd.name.starts_with(name))
+            .collect();
+        match matches.as_slice() {
+            [command] => Ok(command),
+            [] => Err(match self.did_you_mean(name) {
+                Some(suggestion) => format!("Unknown command: {}. {}", name, suggestion),
+                None => format!("Unknown command: {}", name),
+            }),
+            _ => Err(format!(
+                "Ambiguous command {}: {}",
+                name,
+                matches
+                    .iter()
+                    .map(|cmd| cmd.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    fn did_you_mean(&self, word: &str) -> Option<String> {
+        let scored: Vec<(usize, String)> = self
+            .commands
+            .iter()
+            .flat_map(|command| {
+                let bang = command.bang.as_ref().map(|_| format!("{}!", command.name));
+                [command.name.clone()].into_iter().chain(bang)
+            })
+            .map(|name| (edit_distance(word, &name), name))
+            .collect();
+        let best = scored
+            .iter()
+            .map(|(distance, _)| *distance)
+            .min()
+            .filter(|&distance| distance <= MAX_TYPO_DISTANCE && distance < word.chars().count())?;
+        let names: Vec<String> = scored
+            .into_iter()
+            .filter(|(distance, _)| *distance == best)
+            .map(|(_, name)| format!(":{}", name))
+            .take(3)
+            .collect();
+        Some(format!("Did you mean {}?", names.join(" or ")))
+    }
+
+    pub fn needs_argument(&self, input: &str) -> bool {
+        CommandLine::parse(input)
+            .ok()
+            .and_then(|line| self.find_command(line.name).ok())
+            .is_some_and(|command| command.signature.needs_argument())
     }
 
     pub fn get_suggestions(&self) -> Vec<&Command> {
@@ -136,9 +1007,36 @@ impl CommandBar {
 
     pub fn activate(&mut self) {
         self.active = true;
+        self.prompt = ':';
+        self.input.clear();
+    }
+
+    pub fn activate_with_input(&mut self, input: &str) {
+        self.active = true;
+        self.prompt = ':';
+        self.input = input.to_string();
+    }
+
+    pub fn activate_expression(&mut self) {
+        self.active = true;
+        self.prompt = '=';
         self.input.clear();
     }
 
+    pub fn activate_search(&mut self, forward: bool) {
+        self.active = true;
+        self.prompt = if forward { '/' } else { '?' };
+        self.input.clear();
+    }
+
+    pub fn get_prompt(&self) -> char {
+        self.prompt
+    }
+
+    fn is_search(&self) -> bool {
+        self.prompt != ':'
+    }
+
     pub fn deactivate(&mut self) {
         self.active = false;
         self.input.clear();
@@ -162,10 +1060,13 @@ impl CommandBar {
         &self.input
     }
 
-    pub fn get_current_command_description(&self) -> Option<&str> {
+    pub fn get_current_command_description(&self) -> Option<String> {
+        if let Some(command) = self.get_command().filter(|_| !self.is_search()) {
+            return Some(format!("{}  {}", command.usage(), command.description));
+        }
         self.get_suggestions()
             .get(self.suggestion_index)
-            .map(|cmd| cmd.description.as_str())
+            .map(|cmd| cmd.description.clone())
     }
 
     pub fn get_suggestion_index(&self) -> usize {
@@ -177,6 +1078,9 @@ impl CommandBar {
     }
 
     pub fn cycle_suggestion(&mut self, forward: bool) {
+        if self.is_search() {
+            return;
+        }
         let total_suggestions = self.commands.len();
         if total_suggestions > 0 {
             if forward {
@@ -209,44 +1113,315 @@ impl CommandBar {
     }
 
     pub fn execute_command(&self, editor: &mut Editor) -> Result<bool, Box<dyn std::error::Error>> {
-        let input = self.input.trim();
-
-        // Handle commands with arguments
-        if input.starts_with("help ") {
-            let topic = &input[5..];
-            set_help_topic(topic);
-            return Ok(false);
+        match self.prompt {
+            '=' => editor.insert_expression(&self.input),
+            '/' | '?' => editor.search(&self.input, self.prompt == '/'),
+            _ => return self.execute(editor, &self.input),
         }
+        Ok(false)
+    }
 
-        // Handle commands with arguments
-        if input.starts_with("w ") {
-            let path = PathBuf::from(&input[2..]);
-            editor.save_file(&path)?;
-            set_error("File saved successfully.".to_string());
+    pub fn execute(
+        &self,
+        editor: &mut Editor,
+        input: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        if input.trim().is_empty() {
             return Ok(false);
-        } else if input.starts_with("e ") {
-            let path = PathBuf::from(&input[2..]);
-            match editor.open_file(&path) {
-                Ok(_) => {
-                    set_error(format!("Opened file: {:?}", path));
-                    return Ok(false);
-                }
+        }
+        // A range alone, such as :42 or :$, goes to its last line
+        if let Ok((Some(range), "")) = ExRange::parse(input.trim()) {
+            match editor.resolve_range(&range) {
+                Ok((_, line)) => editor.go_to_line(line),
                 Err(e) => {
-                    set_error(format!("Failed to open file: {:?}. Error: {}", path, e));
-                    return Ok(false);
+                    set_error(e);
+                    editor.ring_bell();
                 }
             }
-        } else if input.starts_with("set ") {
-            editor.handle_set_command(&input[4..]);
             return Ok(false);
         }
+        let line = match CommandLine::parse(input) {
+            Ok(line) => line,
+            Err(e) => {
+                set_error(e);
+                editor.ring_bell();
+                return Ok(false);
+            }
+        };
+        let command = match self.find_command(line.name) {
+            Ok(command) => command,
+            Err(e) => {
+                set_error(e);
+                editor.ring_bell();
+                return Ok(false);
+            }
+        };
+        if let Err(e) = line.check(&command.name, &command.signature, command.bang.is_some()) {
+            // Arguments typed straight after the name may be a mistyped name
+            let glued = line.word.trim_end_matches('!') != line.name;
+            match self.did_you_mean(line.word).filter(|_| glued) {
+                Some(suggestion) => set_error(format!("{}. {}", e, suggestion)),
+                None => set_error(e),
+            }
+            editor.ring_bell();
+            return Ok(false);
+        }
+        let lines = match line.range.map(|range| editor.resolve_range(&range)) {
+            Some(Ok(lines)) => Some(lines),
+            Some(Err(e)) => {
+                set_error(e);
+                editor.ring_bell();
+                return Ok(false);
+            }
+            None => None,
+        };
 
-        if let Some(command) = self.get_command() {
-            set_help_topic(&command.help_topic);
-            return (command.action)(editor);
+        let invocation = Invocation {
+            lines,
+            bang: line.bang,
+            args: line.args,
+        };
+        set_help_topic(&command.help_topic);
+        match (&command.bang, line.bang) {
+            (Some(bang), true) => (bang.action)(editor, &invocation),
+            _ => (command.action)(editor, &invocation),
         }
+    }
 
-        set_error(format!("Unknown command: {}", input));
-        Ok(false)
+    fn report_save(editor: &Editor, result: io::Result<()>, message: String) -> bool {
+        match result {
+            Ok(()) => {
+                // Keep a failure reported by a save hook visible
+                if get_error().is_none() && !message.is_empty() {
+                    set_error(message);
+                }
+                true
+            }
+            Err(e) => {
+                match editor.get_write_prompt() {
+                    Some(prompt) => set_error(prompt.message()),
+                    None => set_error(format!("Failed to save file. Error: {}", e)),
+                }
+                false
+            }
+        }
+    }
+
+    fn sign(editor: &mut Editor, args: &str) {
+        let Some((line, _)) = editor.get_cursor_screen_position() else {
+            return;
+        };
+        let args: Vec<&str> = args.split_whitespace().collect();
+        match args.as_slice() {
+            ["place", symbol, rest @ ..] if symbol.chars().count() == 1 && rest.len() <= 2 => {
+                let group = rest.first().copied().unwrap_or("mark");
+                let Ok(priority) = rest.get(1).map_or(Ok(10), |priority| priority.parse()) else {
+                    set_error(SIGN_USAGE.to_string());
+                    return;
+                };
+                let symbol = symbol.chars().next().unwrap_or('>');
+                editor.place_sign(SIGN_SOURCE, line, Sign::new(symbol, group, priority));
+            }
+            ["unplace"] => editor.unplace_sign(SIGN_SOURCE, line),
+            ["clear"] => editor.clear_signs(SIGN_SOURCE),
+            _ => set_error(SIGN_USAGE.to_string()),
+        }
+    }
+
+    fn export(editor: &Editor, args: &str) {
+        let (format, target) = args.trim().split_once(' ').unwrap_or((args.trim(), ""));
+        let Some(format) = ExportFormat::parse(format) else {
+            set_error("Usage: :export html|ansi <path>".to_string());
+            return;
+        };
+        if target.trim().is_empty() {
+            set_error("Usage: :export html|ansi <path>".to_string());
+            return;
+        }
+        if let Some(path) = Self::expand_target(editor, target.trim()) {
+            match editor.export_buffer(format, &path) {
+                Ok(()) => set_error(format!("Exported to {}", path.display())),
+                Err(e) => set_error(format!("Failed to export: {}", e)),
+            }
+        }
+    }
+
+    fn expand_target(editor: &Editor, arg: &str) -> Option<PathBuf> {
+        match expand_single_path(arg, editor.get_working_directory()) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                set_error(e);
+                None
+            }
+        }
+    }
+
+    fn open_files(editor: &mut Editor, arg: &str) {
+        let arg = arg.trim();
+        if fetch::is_url(arg) {
+            if let Err(e) = editor.open_url(arg) {
+                set_error(format!("Failed to fetch {}: {}", arg, e));
+            }
+            return;
+        }
+        let paths = match expand_path(arg, editor.get_working_directory()) {
+            Ok(paths) => paths,
+            Err(e) => {
+                set_error(e);
+                return;
+            }
+        };
+
+        // Open in reverse so the first match ends up as the current buffer
+        for path in paths.iter().rev() {
+            if let Err(e) = editor.open_file(path) {
+                set_error(format!("Failed to open file: {:?}. Error: {}", path, e));
+                return;
+            }
+        }
+
+        match paths.as_slice() {
+            [path] => set_error(format!("Opened file: {:?}", path)),
+            _ => set_error(format!("Opened {} files", paths.len())),
+        }
+    }
+
+    fn time_travel(editor: &mut Editor, arg: &str, forward: bool) {
+        let travel = match TimeTravel::parse(arg) {
+            Ok(travel) => travel,
+            Err(e) => {
+                set_error(e);
+                return;
+            }
+        };
+
+        if let Some(time) = editor.time_travel(&travel, forward) {
+            let age = SystemTime::now()
+                .duration_since(time)
+                .unwrap_or_default()
+                .as_secs();
+            let age = match age {
+                0..=59 => format!("{} seconds", age),
+                60..=3599 => format!("{} minutes", age / 60),
+                _ => format!("{} hours", age / 3600),
+            };
+            set_error(format!("Restored the buffer as of {} ago", age));
+        }
+    }
+
+    fn change_directory(editor: &mut Editor, path: &str, local: bool) {
+        match editor.change_directory(path, local) {
+            Ok(directory) => set_error(directory.display().to_string()),
+            Err(e) => set_error(format!("Failed to change directory: {}", e)),
+        }
+    }
+
+    fn lines_or(
+        editor: &Editor,
+        invocation: &Invocation,
+        default: ExRange,
+    ) -> Option<(usize, usize)> {
+        if invocation.lines.is_some() {
+            return invocation.lines;
+        }
+        match editor.resolve_range(&default) {
+            Ok(lines) => Some(lines),
+            Err(e) => {
+                set_error(e);
+                None
+            }
+        }
+    }
+
+    fn write(editor: &mut Editor, invocation: &Invocation, force: bool) {
+        let (append, target) = match invocation.args.strip_prefix(">>") {
+            Some(target) => (true, target.trim()),
+            None => (false, invocation.args.trim()),
+        };
+        if append || invocation.lines.is_some() {
+            if target.is_empty() && !append {
+                set_error("Use :<range>w <filename> to write part of the buffer.".to_string());
+            } else if let Some(lines) = Self::lines_or(editor, invocation, ExRange::WholeFile) {
+                Self::write_lines(editor, lines, append, target);
+            }
+            return;
+        }
+
+        let path = if target.is_empty() {
+            match editor.get_current_file_path() {
+                Some(path) => path,
+                None => {
+                    set_error("No file path set. Use :w <filename> to save.".to_string());
+                    return;
+                }
+            }
+        } else {
+            match Self::expand_target(editor, target) {
+                Some(path) => path,
+                None => return,
+            }
+        };
+        let result = if force {
+            editor.force_save_file(&path)
+        } else {
+            editor.save_file(&path)
+        };
+        Self::report_save(editor, result, "File saved successfully.".to_string());
+    }
+
+    fn align(editor: &mut Editor, lines: (usize, usize), delimiter: &str) {
+        if delimiter.is_empty() {
+            set_error("Usage: :align <delimiter>".to_string());
+            return;
+        }
+        if let Some(text) = editor.get_lines(lines) {
+            editor.replace_lines(lines, &align::align(&text, delimiter));
+        }
+    }
+
+    fn reformat(editor: &mut Editor, lines: (usize, usize), language: &str, args: &str) {
+        let pretty = match args.trim() {
+            "pretty" => true,
+            "minify" => false,
+            _ => {
+                set_error(format!("Usage: :{} pretty|minify", language));
+                return;
+            }
+        };
+        let Some(text) = editor.get_lines(lines) else {
+            set_error("No active buffer".to_string());
+            return;
+        };
+
+        let result = if language == "json" {
+            reformat::json(&text, pretty)
+        } else {
+            reformat::xml(&text, pretty)
+        };
+        match result {
+            Ok(formatted) => editor.replace_lines(lines, &formatted),
+            Err(e) => set_error(format!(
+                "Invalid {} on line {}: {}",
+                language.to_uppercase(),
+                lines.0 + e.line + 1,
+                e.message
+            )),
+        }
+    }
+
+    fn write_lines(editor: &Editor, lines: (usize, usize), append: bool, target: &str) {
+        if target.is_empty() {
+            set_error("Use :w >> <filename> to append to a file.".to_string());
+            return;
+        }
+
+        let Some(path) = Self::expand_target(editor, target) else {
+            return;
+        };
+        match editor.write_range(&path, lines, append) {
+            Ok(count) if append => set_error(format!("Appended {} line(s) to {:?}", count, path)),
+            Ok(count) => set_error(format!("Wrote {} line(s) to {:?}", count, path)),
+            Err(e) => set_error(format!("Failed to write file: {:?}. Error: {}", path, e)),
+        }
     }
 }
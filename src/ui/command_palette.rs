@@ -0,0 +1,445 @@
+use crate::{
+    editor::{
+        file_index::FileIndex,
+        tags::{load_tags, Tag, TagAddress},
+    },
+    ui::{
+        popup::{Placement, Popup, PopupId, PopupLayer},
+        preview::highlighted_preview,
+        render::marked_spans,
+    },
+    utils::fuzzy::{fuzzy_match, fuzzy_score},
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const PALETTE_WIDTH: u16 = 70;
+const PALETTE_RESULTS: usize = 12;
+// Symbol and file searches are wider, to fit the preview
+const PREVIEW_WIDTH: u16 = 100;
+// Lines of the file shown for the highlighted candidate
+const PREVIEW_LINES: usize = 12;
+// Starts a query that searches symbols instead of commands
+const SYMBOL_PREFIX: char = '@';
+// Starts a query that searches the files of the project
+const FILE_PREFIX: char = '/';
+
+pub enum PaletteAction {
+    // An ex command, run as if typed into the command bar
+    Command(String),
+    // A key sequence replayed in normal mode
+    Keys(Vec<KeyEvent>),
+    // A symbol definition to jump to
+    Tag(Tag),
+    // A file to open
+    Open(PathBuf),
+}
+
+pub struct PaletteEntry {
+    pub name: String,
+    pub description: String,
+    pub action: PaletteAction,
+}
+
+#[derive(Default)]
+pub struct PaletteSources {
+    pub tags_file: Option<PathBuf>,
+    pub files: Option<FileIndex>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Commands,
+    Symbols,
+    Files,
+}
+
+impl Source {
+    fn of(query: &str) -> Self {
+        match query.chars().next() {
+            Some(SYMBOL_PREFIX) => Source::Symbols,
+            Some(FILE_PREFIX) => Source::Files,
+            _ => Source::Commands,
+        }
+    }
+}
+
+pub struct CommandPalette {
+    popup: Option<PopupId>,
+    query: String,
+    entries: Vec<PaletteEntry>,
+    sources: PaletteSources,
+    // Read from the tags file the first time a symbol query is typed
+    symbols: Option<Result<Vec<PaletteEntry>, String>>,
+    // Taken from the file index, again on each key until it is complete
+    files: Option<Vec<PaletteEntry>>,
+    files_complete: bool,
+    // The file last previewed, kept while moving between its symbols
+    preview_file: Option<(PathBuf, Result<String, String>)>,
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        Self {
+            popup: None,
+            query: String::new(),
+            entries: Vec::new(),
+            sources: PaletteSources::default(),
+            symbols: None,
+            files: None,
+            files_complete: false,
+            preview_file: None,
+            matches: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn open(
+        &mut self,
+        popups: &mut PopupLayer,
+        entries: Vec<PaletteEntry>,
+        sources: PaletteSources,
+    ) {
+        self.close(popups);
+        self.entries = entries;
+        self.sources = sources;
+        self.symbols = None;
+        self.files = None;
+        self.files_complete = false;
+        self.preview_file = None;
+        self.query.clear();
+        self.update_matches();
+
+        let mut popup = Popup::new(
+            Text::default(),
+            Placement::Centered,
+            PALETTE_WIDTH,
+            PALETTE_RESULTS as u16 + 3,
+        );
+        popup.title = Some("Command Palette".to_string());
+        popup.focusable = true;
+        popup.z_index = 10;
+        self.popup = Some(popups.open(popup));
+        self.update_popup(popups);
+    }
+
+    pub fn close(&mut self, popups: &mut PopupLayer) {
+        if let Some(popup) = self.popup.take() {
+            popups.close(popup);
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    pub fn handle_key(&mut self, popups: &mut PopupLayer, key: KeyEvent) -> Option<PaletteAction> {
+        match key.code {
+            KeyCode::Esc => self.close(popups),
+            KeyCode::Enter => {
+                let index = self.matches.get(self.selected).copied();
+                self.close(popups);
+                let entries = match self.source() {
+                    Source::Commands => &mut self.entries,
+                    Source::Symbols => match &mut self.symbols {
+                        Some(Ok(symbols)) => symbols,
+                        _ => return None,
+                    },
+                    Source::Files => self.files.as_mut()?,
+                };
+                return index.map(|index| entries.swap_remove(index).action);
+            }
+            KeyCode::Up | KeyCode::BackTab => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1));
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_matches();
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        self.update_popup(popups);
+        None
+    }
+
+    fn source(&self) -> Source {
+        Source::of(&self.query)
+    }
+
+    fn match_query(&self) -> &str {
+        match self.source() {
+            Source::Commands => &self.query,
+            Source::Symbols | Source::Files => &self.query[1..],
+        }
+    }
+
+    fn source_entries(&self) -> Result<&[PaletteEntry], &str> {
+        match self.source() {
+            Source::Commands => Ok(&self.entries),
+            Source::Symbols => match &self.symbols {
+                Some(Ok(symbols)) => Ok(symbols),
+                Some(Err(e)) => Err(e),
+                None => Ok(&[]),
+            },
+            Source::Files => match (&self.files, &self.sources.files) {
+                (_, None) => Err("No project to search"),
+                (Some(files), _) => Ok(files),
+                (None, _) => Ok(&[]),
+            },
+        }
+    }
+
+    fn load_symbols(&mut self) {
+        if self.symbols.is_some() {
+            return;
+        }
+        let Some(path) = &self.sources.tags_file else {
+            self.symbols = Some(Err("No tags file found; run ctags -R".to_string()));
+            return;
+        };
+        let directory = path.parent().unwrap_or(Path::new(""));
+        self.symbols = Some(
+            load_tags(path)
+                .map(|tags| {
+                    tags.into_iter()
+                        .map(|tag| symbol_entry(tag, directory))
+                        .collect()
+                })
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e)),
+        );
+    }
+
+    fn load_files(&mut self) {
+        if self.files_complete {
+            return;
+        }
+        let Some(index) = &self.sources.files else {
+            return;
+        };
+        let (files, complete) = index.files();
+        self.files = Some(
+            files
+                .into_iter()
+                .map(|file| PaletteEntry {
+                    name: file.display().to_string(),
+                    description: String::new(),
+                    action: PaletteAction::Open(index.root().join(file)),
+                })
+                .collect(),
+        );
+        self.files_complete = complete;
+    }
+
+    fn update_matches(&mut self) {
+        let source = self.source();
+        match source {
+            Source::Commands => {}
+            Source::Symbols => self.load_symbols(),
+            Source::Files => self.load_files(),
+        }
+        let query = self.match_query();
+        let entries = self.source_entries().unwrap_or_default();
+        let mut scored: Vec<(i64, usize)> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                // Prefer hits on the name over hits on the description;
+                // symbols and files match on their name alone
+                let name = fuzzy_score(query, &entry.name).map(|score| score + 10);
+                if source != Source::Commands {
+                    return name.map(|score| (score, index));
+                }
+                let description = fuzzy_score(query, &entry.description);
+                name.max(description).map(|score| (score, index))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+
+    fn candidate_preview(&mut self) -> Vec<Line<'static>> {
+        let Some(entry) = self
+            .matches
+            .get(self.selected)
+            .and_then(|&index| self.source_entries().ok()?.get(index))
+        else {
+            return Vec::new();
+        };
+        let (path, tag) = match &entry.action {
+            PaletteAction::Tag(tag) => (tag.file.clone(), Some(tag.clone())),
+            PaletteAction::Open(path) => (path.clone(), None),
+            _ => return Vec::new(),
+        };
+        if self
+            .preview_file
+            .as_ref()
+            .is_none_or(|(previewed, _)| *previewed != path)
+        {
+            let text = fs::read_to_string(&path).map_err(|e| e.to_string());
+            self.preview_file = Some((path, text));
+        }
+        match &self.preview_file {
+            Some((path, Ok(text))) => {
+                let focus = tag.and_then(|tag| tag.find_line(text));
+                highlighted_preview(path, text, focus, PREVIEW_LINES)
+            }
+            Some((_, Err(e))) => vec![Line::styled(
+                format!("Cannot preview: {}", e),
+                Style::default().fg(Color::DarkGray),
+            )],
+            None => Vec::new(),
+        }
+    }
+
+    fn update_popup(&mut self, popups: &mut PopupLayer) {
+        let source = self.source();
+        let preview = if source == Source::Commands {
+            None
+        } else {
+            Some(self.candidate_preview())
+        };
+        let Some(popup) = self.popup.and_then(|id| popups.get_mut(id)) else {
+            return;
+        };
+        popup.title = Some(
+            match source {
+                Source::Commands => "Command Palette",
+                Source::Symbols => "Symbols",
+                Source::Files if self.files_complete => "Files",
+                Source::Files => "Files (indexing…)",
+            }
+            .to_string(),
+        );
+        let (entries, problem) = match self.source_entries() {
+            Ok(entries) => (entries, None),
+            Err(problem) => (&[][..], Some(problem)),
+        };
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Yellow)),
+            Span::raw(self.query.clone()),
+        ])];
+
+        // Keep the selection visible when it moves past the first page
+        let first = self.selected.saturating_sub(PALETTE_RESULTS - 1);
+        for (offset, &index) in self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(PALETTE_RESULTS)
+        {
+            let entry = &entries[index];
+            let name_style = if offset == self.selected {
+                Style::default().fg(Color::Black).bg(Color::White)
+            } else {
+                Style::default().fg(Color::Blue)
+            };
+            let description_style = Style::default().fg(Color::DarkGray);
+            let marked = |style: Style| style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+
+            // Mark the characters of whichever part the entry matched on,
+            // preferring the name as the scoring does
+            let query = self.match_query();
+            let name_match =
+                fuzzy_match(query, &entry.name).map(|(score, found)| (score + 10, found));
+            let description_match = fuzzy_match(query, &entry.description)
+                .filter(|_| source == Source::Commands)
+                .filter(|(score, _)| name_match.as_ref().is_none_or(|(name, _)| score > name));
+            let (name_positions, description_positions) = match description_match {
+                Some((_, found)) => (Vec::new(), found),
+                None => (
+                    name_match.map(|(_, found)| found).unwrap_or_default(),
+                    Vec::new(),
+                ),
+            };
+
+            let mut spans = marked_spans(
+                &format!("{:<16}", entry.name),
+                &name_positions,
+                name_style,
+                marked(name_style),
+            );
+            spans.push(Span::raw(" "));
+            spans.extend(marked_spans(
+                &entry.description,
+                &description_positions,
+                description_style,
+                marked(description_style.fg(Color::Yellow)),
+            ));
+            lines.push(Line::from(spans));
+        }
+        if self.matches.is_empty() {
+            let message = match (problem, source) {
+                (Some(problem), _) => problem,
+                (None, Source::Commands) => "No matching commands",
+                (None, Source::Symbols) => "No matching symbols",
+                (None, Source::Files) if !self.files_complete => "Indexing files…",
+                (None, Source::Files) => "No matching files",
+            };
+            lines.push(Line::styled(
+                message.to_string(),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+
+        match preview {
+            Some(preview) => {
+                // Keep the preview in place as the number of matches changes
+                lines.resize(PALETTE_RESULTS + 1, Line::default());
+                lines.push(Line::styled(
+                    "─".repeat(PREVIEW_WIDTH as usize),
+                    Style::default().fg(Color::DarkGray),
+                ));
+                lines.extend(preview);
+                popup.width = PREVIEW_WIDTH;
+                popup.height = (PALETTE_RESULTS + PREVIEW_LINES) as u16 + 4;
+            }
+            None => {
+                popup.width = PALETTE_WIDTH;
+                popup.height = PALETTE_RESULTS as u16 + 3;
+            }
+        }
+        popup.content = Text::from(lines);
+    }
+}
+
+fn symbol_entry(tag: Tag, directory: &Path) -> PaletteEntry {
+    let file = tag.file.strip_prefix(directory).unwrap_or(&tag.file);
+    let location = match tag.address {
+        TagAddress::Line(line) => format!("{}:{}", file.display(), line),
+        TagAddress::Pattern(_) => file.display().to_string(),
+    };
+    PaletteEntry {
+        name: tag.name.clone(),
+        description: match &tag.kind {
+            Some(kind) => format!("{} {}", kind, location),
+            None => location,
+        },
+        action: PaletteAction::Tag(tag),
+    }
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
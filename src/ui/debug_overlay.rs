@@ -0,0 +1,120 @@
+use crate::{
+    editor::Editor,
+    ui::popup::{Placement, Popup, PopupId, PopupLayer},
+    utils::alloc_counter::{allocation_stats, AllocationStats},
+};
+use ratatui::text::{Line, Text};
+use std::{collections::VecDeque, time::Duration};
+
+const OVERLAY_WIDTH: u16 = 36;
+// Frames averaged over for the timings
+const SAMPLES: usize = 60;
+
+#[derive(Clone, Copy, Default)]
+struct FrameSample {
+    render: Duration,
+    event: Duration,
+    allocations: usize,
+}
+
+pub struct DebugOverlay {
+    popup: Option<PopupId>,
+    samples: VecDeque<FrameSample>,
+    current: FrameSample,
+    frame_start: AllocationStats,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self {
+            popup: None,
+            samples: VecDeque::with_capacity(SAMPLES),
+            current: FrameSample::default(),
+            frame_start: allocation_stats(),
+        }
+    }
+
+    pub fn record_render(&mut self, elapsed: Duration) {
+        self.current.render = elapsed;
+    }
+
+    pub fn record_event(&mut self, elapsed: Duration) {
+        self.current.event += elapsed;
+    }
+
+    pub fn update(&mut self, popups: &mut PopupLayer, editor: &Editor, screen_width: u16) {
+        let allocations = allocation_stats();
+        self.current.allocations = allocations.allocations - self.frame_start.allocations;
+        self.frame_start = allocations;
+        if self.samples.len() == SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(std::mem::take(&mut self.current));
+
+        if !editor.is_debug_info_shown() {
+            if let Some(popup) = self.popup.take() {
+                popups.close(popup);
+            }
+            return;
+        }
+
+        let content = self.content(editor, allocations);
+        let height = content.lines.len() as u16 + 2;
+        let placement = Placement::At(screen_width.saturating_sub(OVERLAY_WIDTH), 0);
+        let popup = match self.popup.and_then(|id| popups.get_mut(id)) {
+            Some(popup) => popup,
+            None => {
+                let mut popup = Popup::new(Text::default(), placement, OVERLAY_WIDTH, height);
+                popup.title = Some("Debug".to_string());
+                popup.z_index = 20;
+                let id = popups.open(popup);
+                self.popup = Some(id);
+                popups.get_mut(id).expect("popup was just opened")
+            }
+        };
+        popup.content = content;
+        popup.placement = placement;
+        popup.height = height;
+    }
+
+    fn content(&self, editor: &Editor, allocations: AllocationStats) -> Text<'static> {
+        let last = self.samples.back().copied().unwrap_or_default();
+        let count = self.samples.len().max(1) as u32;
+        let average_render = self.samples.iter().map(|s| s.render).sum::<Duration>() / count;
+        let average_event = self.samples.iter().map(|s| s.event).sum::<Duration>() / count;
+        let stats = editor.buffer_stats();
+
+        Text::from(vec![
+            Line::from(format!(
+                "render  {:>8.2?} avg {:>8.2?}",
+                last.render, average_render
+            )),
+            Line::from(format!(
+                "events  {:>8.2?} avg {:>8.2?}",
+                last.event, average_event
+            )),
+            Line::from(format!("allocs  {} last frame", last.allocations)),
+            Line::from(format!(
+                "heap    {} KB live, {} total",
+                allocations.live_bytes / 1024,
+                allocations.allocations
+            )),
+            Line::from(format!(
+                "buffers {} ({} loaded)",
+                stats.buffers, stats.loaded
+            )),
+            Line::from(format!(
+                "content {} lines, {} KB",
+                stats.lines,
+                stats.bytes / 1024
+            )),
+            Line::from(format!("version {}", stats.version)),
+        ])
+    }
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
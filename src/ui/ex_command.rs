@@ -0,0 +1,180 @@
+use crate::editor::ex_range::ExRange;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Arg {
+    None,
+    // A file path, with `~`, `$VARS` and globs expanded by the command
+    File,
+    // An option assignment such as `scrolloff=4` or `noautosave`
+    Option,
+    // Free text the command interprets itself, such as keys or a pattern
+    Rest,
+}
+
+impl Arg {
+    fn placeholder(self) -> &'static str {
+        match self {
+            Arg::None => "",
+            Arg::File => "<file>",
+            Arg::Option => "<option>",
+            Arg::Rest => "<args>",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Signature {
+    pub arg: Arg,
+    // Whether the argument may be left out
+    pub optional: bool,
+    // Whether a line range may come before the name
+    pub range: bool,
+    // How the argument is written in usage messages, such as
+    // `pretty|minify`; empty for a placeholder like `<file>`
+    pub hint: &'static str,
+}
+
+impl Signature {
+    // Takes nothing but the name.
+    pub const NONE: Signature = Signature {
+        arg: Arg::None,
+        optional: true,
+        range: false,
+        hint: "",
+    };
+
+    pub const fn required(arg: Arg) -> Self {
+        Self {
+            arg,
+            optional: false,
+            range: false,
+            hint: "",
+        }
+    }
+
+    pub const fn optional(arg: Arg) -> Self {
+        Self {
+            arg,
+            optional: true,
+            range: false,
+            hint: "",
+        }
+    }
+
+    pub const fn with_range(self) -> Self {
+        Self {
+            range: true,
+            ..self
+        }
+    }
+
+    pub const fn hint(self, hint: &'static str) -> Self {
+        Self { hint, ..self }
+    }
+
+    pub fn usage(&self, name: &str, bang: bool) -> String {
+        let mut usage = format!(
+            ":{}{}{}",
+            if self.range { "[range]" } else { "" },
+            name,
+            if bang { "[!]" } else { "" }
+        );
+        if self.arg == Arg::None {
+            return usage;
+        }
+        let hint = if self.hint.is_empty() {
+            self.arg.placeholder()
+        } else {
+            self.hint
+        };
+        // Arguments such as `/pattern/` follow the name directly
+        if hint.starts_with(|c: char| c.is_alphanumeric() || c == '<' || c == '[') {
+            usage.push(' ');
+        }
+        if self.optional {
+            usage.push_str(&format!("[{}]", hint));
+        } else {
+            usage.push_str(hint);
+        }
+        usage
+    }
+
+    pub fn needs_argument(&self) -> bool {
+        self.arg != Arg::None && !self.optional
+    }
+}
+
+#[derive(Debug)]
+pub struct CommandLine<'a> {
+    pub range: Option<ExRange>,
+    pub name: &'a str,
+    pub bang: bool,
+    pub args: &'a str,
+    // The command as typed up to the first space, arguments written
+    // straight after the name included
+    pub word: &'a str,
+}
+
+impl<'a> CommandLine<'a> {
+    pub fn parse(input: &'a str) -> Result<Self, String> {
+        let (range, rest) = ExRange::parse(input.trim())?;
+        let word = rest.split_whitespace().next().unwrap_or_default();
+        let name_end = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let (name, rest) = rest.split_at(name_end);
+        if name.is_empty() {
+            return Err(format!("Not an editor command: {}", input.trim()));
+        }
+        let (bang, args) = match rest.strip_prefix('!') {
+            Some(args) => (true, args),
+            None => (false, rest),
+        };
+        Ok(Self {
+            range,
+            name,
+            bang,
+            args: args.trim_start(),
+            word,
+        })
+    }
+
+    pub fn check(&self, name: &str, signature: &Signature, bang: bool) -> Result<(), String> {
+        let problem = if self.bang && !bang {
+            format!("No ! allowed for :{}", name)
+        } else if self.range.is_some() && !signature.range {
+            format!(":{} does not accept a range", name)
+        } else if signature.arg == Arg::None && !self.args.is_empty() {
+            format!("Trailing characters: {}", self.args)
+        } else if signature.needs_argument() && self.args.is_empty() {
+            format!(":{} needs an argument", name)
+        } else {
+            return Ok(());
+        };
+        Err(format!(
+            "{}. Usage: {}",
+            problem,
+            signature.usage(name, bang)
+        ))
+    }
+}
+
+pub struct Invocation<'a> {
+    pub lines: Option<(usize, usize)>,
+    pub bang: bool,
+    pub args: &'a str,
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = previous[j] + (a != b) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -1,31 +1,59 @@
-use crate::editor::Editor;
+use crate::editor::{window::WindowId, Editor};
+
+// Line numbers take at least this many digits, so the gutter keeps its
+// width while a small file grows
+const MIN_DIGITS: usize = 4;
+// Columns after the numbers: one for signs and fold markers and one of
+// padding before the text
+const MARGIN: usize = 2;
 
 pub struct Gutter;
 
 impl Gutter {
-    pub fn get_visible_line_numbers(editor: &Editor) -> Vec<String> {
-        let (_, scroll_y) = match editor.get_scroll_offset() {
+    // Columns from the right edge of the gutter to the sign column
+    pub const SIGN_OFFSET: u16 = 2;
+
+    pub fn width(line_count: usize) -> u16 {
+        (line_count.to_string().len().max(MIN_DIGITS) + MARGIN) as u16
+    }
+
+    pub fn get_visible_line_numbers(editor: &Editor, window: WindowId) -> Vec<String> {
+        let (_, scroll_y) = match editor.get_scroll_offset(window) {
             Some((x, y)) => (x, y),
             None => (0, 0),
         };
 
-        let (_, viewport_height) = match editor.get_viewport() {
+        let (_, viewport_height) = match editor.get_viewport(window) {
             (width, height) => (width, height),
         };
 
-        let content = editor.get_content().unwrap_or_default();
-        let total_lines = content.len_lines();
+        let total_lines = editor.window_line_count(window);
+        let digits = Self::width(total_lines) as usize - MARGIN;
+
+        // Soft wrapped lines are numbered on their first row only
+        if let Some(rows) = editor.get_wrapped_rows(window) {
+            let mut line_numbers: Vec<String> = rows
+                .iter()
+                .map(|row| match row.start {
+                    0 => format!("{:>digits$}", row.line + 1),
+                    _ => " ".repeat(digits),
+                })
+                .collect();
+            line_numbers.resize(viewport_height, " ".repeat(digits));
+            return line_numbers;
+        }
+
         let start_line = scroll_y;
         let end_line = (scroll_y + viewport_height).min(total_lines);
 
         let mut line_numbers = Vec::with_capacity(viewport_height);
 
         for line_idx in start_line..end_line {
-            line_numbers.push(format!("{:>4}", line_idx + 1));
+            line_numbers.push(format!("{:>digits$}", line_idx + 1));
         }
 
         for _ in end_line..(scroll_y + viewport_height) {
-            line_numbers.push("    ".to_string());
+            line_numbers.push(" ".repeat(digits));
         }
 
         line_numbers
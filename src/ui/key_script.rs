@@ -0,0 +1,226 @@
+use crate::editor::keymap::{self, Key, Modifiers};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+pub struct KeyRecorder {
+    file: File,
+}
+
+impl KeyRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            file: File::create(path)?,
+        })
+    }
+
+    pub fn record(&mut self, key: &KeyEvent) -> io::Result<()> {
+        let mut notation = format_key(key);
+        // Start a new line after each Enter so longer scripts stay readable
+        if key.code == KeyCode::Enter {
+            notation.push('\n');
+        }
+        self.file.write_all(notation.as_bytes())?;
+        self.file.flush()
+    }
+}
+
+pub fn format_key(key: &KeyEvent) -> String {
+    let mut modifiers = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        modifiers.push_str("C-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        modifiers.push_str("A-");
+    }
+    // Characters carry Shift in their case, and BackTab in its name
+    if key.modifiers.contains(KeyModifiers::SHIFT)
+        && !matches!(key.code, KeyCode::Char(_) | KeyCode::BackTab)
+    {
+        modifiers.push_str("S-");
+    }
+
+    let name = match key.code {
+        KeyCode::Char('<') => "lt".to_string(),
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) if modifiers.is_empty() => return c.to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "CR".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Backspace => "BS".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::BackTab => "S-Tab".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::Insert => "Insert".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        _ => "Nop".to_string(),
+    };
+    format!("<{}{}>", modifiers, name)
+}
+
+pub fn parse_keys(script: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut keys = Vec::new();
+    let mut rest = script;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest
+                .find('>')
+                .ok_or_else(|| format!("Unterminated key name: {}", rest))?;
+            keys.push(parse_key_name(&rest[1..end])?);
+            rest = &rest[end + 1..];
+        } else {
+            if c != '\n' && c != '\r' {
+                let modifiers = if c.is_uppercase() {
+                    KeyModifiers::SHIFT
+                } else {
+                    KeyModifiers::NONE
+                };
+                keys.push(KeyEvent::new(KeyCode::Char(c), modifiers));
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Ok(keys)
+}
+
+fn parse_key_name(name: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut base = name;
+    loop {
+        if let Some(stripped) = base.strip_prefix("C-") {
+            modifiers |= KeyModifiers::CONTROL;
+            base = stripped;
+        } else if let Some(stripped) = base.strip_prefix("A-") {
+            modifiers |= KeyModifiers::ALT;
+            base = stripped;
+        } else if let Some(stripped) = base.strip_prefix("S-").filter(|s| !s.is_empty()) {
+            modifiers |= KeyModifiers::SHIFT;
+            base = stripped;
+        } else {
+            break;
+        }
+    }
+
+    let code = match base {
+        "lt" => KeyCode::Char('<'),
+        "Space" => KeyCode::Char(' '),
+        "CR" | "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "BS" => KeyCode::Backspace,
+        "Tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        "Tab" => KeyCode::Tab,
+        "Del" => KeyCode::Delete,
+        "Insert" => KeyCode::Insert,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Nop" => KeyCode::Null,
+        _ => {
+            let mut chars = base.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                (Some('F'), Some(_)) => base[1..]
+                    .parse()
+                    .map(KeyCode::F)
+                    .map_err(|_| format!("Unknown key: <{}>", name))?,
+                _ => return Err(format!("Unknown key: <{}>", name)),
+            }
+        }
+    };
+
+    if let KeyCode::Char(c) = code {
+        if c.is_uppercase() {
+            modifiers |= KeyModifiers::SHIFT;
+        }
+    }
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+impl From<KeyEvent> for Key {
+    fn from(key: KeyEvent) -> Self {
+        let code = match key.code {
+            KeyCode::Char(c) => keymap::KeyCode::Char(c),
+            KeyCode::F(n) => keymap::KeyCode::F(n),
+            KeyCode::Enter => keymap::KeyCode::Enter,
+            KeyCode::Esc => keymap::KeyCode::Esc,
+            KeyCode::Backspace => keymap::KeyCode::Backspace,
+            KeyCode::Tab => keymap::KeyCode::Tab,
+            KeyCode::BackTab => keymap::KeyCode::BackTab,
+            KeyCode::Delete => keymap::KeyCode::Delete,
+            KeyCode::Insert => keymap::KeyCode::Insert,
+            KeyCode::Up => keymap::KeyCode::Up,
+            KeyCode::Down => keymap::KeyCode::Down,
+            KeyCode::Left => keymap::KeyCode::Left,
+            KeyCode::Right => keymap::KeyCode::Right,
+            KeyCode::Home => keymap::KeyCode::Home,
+            KeyCode::End => keymap::KeyCode::End,
+            KeyCode::PageUp => keymap::KeyCode::PageUp,
+            KeyCode::PageDown => keymap::KeyCode::PageDown,
+            _ => keymap::KeyCode::Other,
+        };
+        let modifiers = Modifiers {
+            shift: key.modifiers.contains(KeyModifiers::SHIFT),
+            control: key.modifiers.contains(KeyModifiers::CONTROL),
+            alt: key.modifiers.contains(KeyModifiers::ALT),
+        };
+        Key::new(code, modifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatted_keys_parse_back() {
+        let keys = [
+            KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('X'), KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+            KeyEvent::new(
+                KeyCode::Char('P'),
+                KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+            ),
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::F(5), KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::F(11), KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::Up, KeyModifiers::ALT | KeyModifiers::SHIFT),
+            KeyEvent::new(
+                KeyCode::End,
+                KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT,
+            ),
+        ];
+        for key in keys {
+            let notation = format_key(&key);
+            assert_eq!(parse_keys(&notation), Ok(vec![key]), "{}", notation);
+        }
+    }
+
+    #[test]
+    fn shifted_named_keys_are_written_with_s() {
+        let key = KeyEvent::new(KeyCode::F(5), KeyModifiers::SHIFT);
+        assert_eq!(format_key(&key), "<S-F5>");
+        let key = KeyEvent::new(KeyCode::Up, KeyModifiers::ALT | KeyModifiers::SHIFT);
+        assert_eq!(format_key(&key), "<A-S-Up>");
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{
+    editor::{menu::Menu, Editor},
+    ui::popup::{Placement, Popup, PopupId, PopupLayer},
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    style::{Color, Style},
+    text::{Line, Span, Text},
+};
+use unicode_width::UnicodeWidthStr;
+
+// Items shown at once; longer menus scroll with the selection
+const MAX_ROWS: usize = 15;
+
+pub struct MenuPopup {
+    popup: Option<PopupId>,
+    menu: Option<Menu>,
+    selected: usize,
+}
+
+impl MenuPopup {
+    pub fn new() -> Self {
+        Self {
+            popup: None,
+            menu: None,
+            selected: 0,
+        }
+    }
+
+    pub fn open(&mut self, popups: &mut PopupLayer, menu: Menu) {
+        self.close(popups);
+        let label_width = menu.items.iter().map(|item| item.label.width()).max();
+        let value_width = menu.items.iter().map(|item| item.value.width()).max();
+        let width = label_width.unwrap_or(0) + value_width.unwrap_or(0) + 4;
+        let width = width.max(menu.title.width() + 4);
+
+        let mut popup = Popup::new(
+            Text::default(),
+            Placement::Cursor,
+            width as u16,
+            menu.items.len().min(MAX_ROWS) as u16 + 2,
+        );
+        popup.title = Some(menu.title.clone());
+        popup.focusable = true;
+        popup.z_index = 10;
+        self.popup = Some(popups.open(popup));
+        self.menu = Some(menu);
+        self.selected = 0;
+        self.update_popup(popups);
+    }
+
+    pub fn close(&mut self, popups: &mut PopupLayer) {
+        if let Some(popup) = self.popup.take() {
+            popups.close(popup);
+        }
+        self.menu = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    pub fn handle_key(&mut self, popups: &mut PopupLayer, editor: &mut Editor, key: KeyEvent) {
+        let count = self.menu.as_ref().map_or(0, |menu| menu.items.len());
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.close(popups),
+            KeyCode::Enter => {
+                if let Some(menu) = &self.menu {
+                    editor.choose_menu_item(menu, self.selected);
+                }
+                self.close(popups);
+            }
+            KeyCode::Up | KeyCode::BackTab | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(count.saturating_sub(1));
+            }
+            _ => {}
+        }
+        self.update_popup(popups);
+    }
+
+    fn update_popup(&self, popups: &mut PopupLayer) {
+        let (Some(popup), Some(menu)) = (self.popup.and_then(|id| popups.get_mut(id)), &self.menu)
+        else {
+            return;
+        };
+
+        let label_width = menu.items.iter().map(|item| item.label.width()).max();
+        let first = (self.selected + 1).saturating_sub(MAX_ROWS);
+        let lines: Vec<Line> = menu
+            .items
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(MAX_ROWS)
+            .map(|(index, item)| {
+                let value_style = if index == self.selected {
+                    Style::default().fg(Color::Black).bg(Color::White)
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$}", item.label, width = label_width.unwrap_or(0)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw("  "),
+                    Span::styled(item.value.clone(), value_style),
+                ])
+            })
+            .collect();
+        popup.content = Text::from(lines);
+    }
+}
+
+impl Default for MenuPopup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
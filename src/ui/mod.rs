@@ -1,3 +1,28 @@
+#[cfg(feature = "tui")]
+pub mod char_picker;
 pub mod command_bar;
+#[cfg(feature = "tui")]
+pub mod command_palette;
+#[cfg(feature = "tui")]
+pub mod debug_overlay;
+pub mod ex_command;
 pub mod gutter;
-pub mod split;
+#[cfg(feature = "tui")]
+pub mod key_script;
+#[cfg(feature = "tui")]
+pub mod menu_popup;
+#[cfg(feature = "tui")]
+pub mod passphrase_prompt;
+#[cfg(feature = "tui")]
+pub mod paste_preview;
+#[cfg(feature = "tui")]
+pub mod popup;
+#[cfg(feature = "tui")]
+pub mod preview;
+#[cfg(feature = "tui")]
+pub mod quit_triage;
+#[cfg(feature = "tui")]
+pub mod render;
+pub mod status_line;
+#[cfg(feature = "tui")]
+pub mod terminal_view;
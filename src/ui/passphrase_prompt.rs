@@ -0,0 +1,140 @@
+use crate::{
+    editor::{notes::PassphraseRequest, Editor},
+    ui::popup::{Placement, Popup, PopupId, PopupLayer},
+    utils::error_handler::set_error,
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    style::{Color, Style},
+    text::{Line, Text},
+};
+
+const WIDTH: u16 = 50;
+
+pub struct PassphrasePrompt {
+    popup: Option<PopupId>,
+    request: Option<PassphraseRequest>,
+    input: String,
+    // The first entry of a new passphrase, once given
+    first: Option<String>,
+}
+
+impl PassphrasePrompt {
+    pub fn new() -> Self {
+        Self {
+            popup: None,
+            request: None,
+            input: String::new(),
+            first: None,
+        }
+    }
+
+    pub fn open(&mut self, popups: &mut PopupLayer, request: PassphraseRequest) {
+        self.close(popups);
+        let mut popup = Popup::new(Text::default(), Placement::Centered, WIDTH, 5);
+        popup.title = Some(
+            match request {
+                PassphraseRequest::Unlock(_) => "Unlock notes",
+                PassphraseRequest::Encrypt => "Encrypt notes",
+            }
+            .to_string(),
+        );
+        popup.focusable = true;
+        popup.z_index = 10;
+        self.popup = Some(popups.open(popup));
+        self.request = Some(request);
+        self.update_popup(popups);
+    }
+
+    pub fn close(&mut self, popups: &mut PopupLayer) {
+        if let Some(popup) = self.popup.take() {
+            popups.close(popup);
+        }
+        self.request = None;
+        self.input.clear();
+        self.first = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    pub fn handle_key(&mut self, popups: &mut PopupLayer, editor: &mut Editor, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.close(popups);
+                set_error("Cancelled".to_string());
+                return;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Enter => return self.submit(popups, editor),
+            _ => {}
+        }
+        self.update_popup(popups);
+    }
+
+    fn submit(&mut self, popups: &mut PopupLayer, editor: &mut Editor) {
+        let Some(request) = self.request.clone() else {
+            return;
+        };
+        let input = std::mem::take(&mut self.input);
+        if input.is_empty() {
+            return;
+        }
+        if request == PassphraseRequest::Encrypt {
+            match self.first.take() {
+                None => {
+                    self.first = Some(input);
+                    self.update_popup(popups);
+                    return;
+                }
+                Some(first) if first != input => {
+                    self.close(popups);
+                    set_error("The passphrases differ; notes left as they were".to_string());
+                    return;
+                }
+                Some(_) => {}
+            }
+        }
+        self.close(popups);
+        match editor.submit_passphrase(request.clone(), input) {
+            Ok(()) if request == PassphraseRequest::Encrypt => {
+                set_error("Notes encrypted".to_string())
+            }
+            Ok(()) => {}
+            Err(e) => set_error(format!("Notes: {}", e)),
+        }
+    }
+
+    fn update_popup(&self, popups: &mut PopupLayer) {
+        let Some(popup) = self.popup.and_then(|id| popups.get_mut(id)) else {
+            return;
+        };
+        let label = if self.first.is_some() {
+            "Repeat passphrase: "
+        } else {
+            "Passphrase: "
+        };
+        popup.content = Text::from(vec![
+            Line::raw(format!(
+                "{}{}",
+                label,
+                "*".repeat(self.input.chars().count())
+            )),
+            Line::default(),
+            Line::styled(
+                "Enter confirm, Esc cancel",
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]);
+    }
+}
+
+impl Default for PassphrasePrompt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    editor::Editor,
+    ui::popup::{Placement, Popup, PopupId, PopupLayer},
+    utils::error_handler::set_error,
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    style::{Color, Style},
+    text::{Line, Span, Text},
+};
+use unicode_width::UnicodeWidthStr;
+
+// Lines of the paste shown in the preview
+const PREVIEW_LINES: usize = 20;
+// Widest the preview gets, border included
+const MAX_WIDTH: usize = 100;
+
+pub struct PastePreview {
+    popup: Option<PopupId>,
+    text: Option<String>,
+}
+
+impl PastePreview {
+    pub fn new() -> Self {
+        Self {
+            popup: None,
+            text: None,
+        }
+    }
+
+    pub fn open(&mut self, popups: &mut PopupLayer, editor: &Editor, text: String) {
+        self.close(popups);
+        let invisibles = editor.paste_invisibles();
+        let line_count = text.split_inclusive('\n').count();
+        let hint = Style::default().fg(Color::DarkGray);
+
+        let mut lines: Vec<Line> = text
+            .split_inclusive('\n')
+            .take(PREVIEW_LINES)
+            .map(|line| {
+                Line::from(
+                    invisibles
+                        .show(line)
+                        .into_iter()
+                        .map(|(shown, invisible)| {
+                            if invisible {
+                                Span::styled(shown, hint)
+                            } else {
+                                Span::raw(shown)
+                            }
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        if line_count > PREVIEW_LINES {
+            lines.push(Line::styled(
+                format!("… {} more lines", line_count - PREVIEW_LINES),
+                hint,
+            ));
+        }
+        lines.push(Line::default());
+        lines.push(Line::styled("Enter/y paste, Esc/n cancel", hint));
+
+        let title = format!(
+            "Paste {} characters on {} lines?",
+            text.chars().count(),
+            line_count
+        );
+        let width = lines
+            .iter()
+            .map(Line::width)
+            .chain([title.width() + 2])
+            .max()
+            .unwrap_or(0)
+            + 2;
+
+        let height = lines.len() as u16 + 2;
+        let mut popup = Popup::new(
+            Text::from(lines),
+            Placement::Centered,
+            width.min(MAX_WIDTH) as u16,
+            height,
+        );
+        popup.title = Some(title);
+        popup.focusable = true;
+        popup.z_index = 10;
+        self.popup = Some(popups.open(popup));
+        self.text = Some(text);
+    }
+
+    pub fn close(&mut self, popups: &mut PopupLayer) {
+        if let Some(popup) = self.popup.take() {
+            popups.close(popup);
+        }
+        self.text = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    pub fn handle_key(&mut self, popups: &mut PopupLayer, editor: &mut Editor, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                if let Some(text) = &self.text {
+                    editor.insert_paste(text);
+                }
+                self.close(popups);
+            }
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => {
+                self.close(popups);
+                set_error("Paste cancelled".to_string());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for PastePreview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,177 @@
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Color, Style},
+    text::Text,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PopupId(u64);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Placement {
+    // Centered on the screen
+    Centered,
+    // Just below the text cursor, or above it when there is no room below
+    Cursor,
+    // At a fixed screen position
+    At(u16, u16),
+}
+
+pub struct Popup {
+    pub title: Option<String>,
+    pub content: Text<'static>,
+    pub placement: Placement,
+    pub width: u16,
+    pub height: u16,
+    // Popups with a higher z-index are drawn on top
+    pub z_index: u16,
+    // Whether the popup takes focus when opened
+    pub focusable: bool,
+}
+
+impl Popup {
+    pub fn new(content: Text<'static>, placement: Placement, width: u16, height: u16) -> Self {
+        Self {
+            title: None,
+            content,
+            placement,
+            width,
+            height,
+            z_index: 0,
+            focusable: false,
+        }
+    }
+}
+
+pub struct PopupLayer {
+    popups: Vec<(PopupId, Popup)>,
+    focused: Option<PopupId>,
+    cursor: Option<Position>,
+    next_id: u64,
+}
+
+impl PopupLayer {
+    pub fn new() -> Self {
+        Self {
+            popups: Vec::new(),
+            focused: None,
+            cursor: None,
+            next_id: 1,
+        }
+    }
+
+    pub fn open(&mut self, popup: Popup) -> PopupId {
+        let id = PopupId(self.next_id);
+        self.next_id += 1;
+        if popup.focusable {
+            self.focused = Some(id);
+        }
+        self.popups.push((id, popup));
+        id
+    }
+
+    pub fn close(&mut self, id: PopupId) {
+        self.popups.retain(|(popup_id, _)| *popup_id != id);
+        if self.focused == Some(id) {
+            self.focused = self
+                .popups
+                .iter()
+                .rev()
+                .find(|(_, popup)| popup.focusable)
+                .map(|(id, _)| *id);
+        }
+    }
+
+    pub fn get_mut(&mut self, id: PopupId) -> Option<&mut Popup> {
+        self.popups
+            .iter_mut()
+            .find(|(popup_id, _)| *popup_id == id)
+            .map(|(_, popup)| popup)
+    }
+
+    pub fn is_open(&self, id: PopupId) -> bool {
+        self.popups.iter().any(|(popup_id, _)| *popup_id == id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.popups.is_empty()
+    }
+
+    pub fn focused(&self) -> Option<PopupId> {
+        self.focused
+    }
+
+    pub fn focus(&mut self, id: PopupId) {
+        if self.is_open(id) {
+            self.focused = Some(id);
+        }
+    }
+
+    pub fn set_cursor(&mut self, cursor: Option<Position>) {
+        self.cursor = cursor;
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let mut popups: Vec<&(PopupId, Popup)> = self.popups.iter().collect();
+        popups.sort_by_key(|(_, popup)| popup.z_index);
+
+        for (id, popup) in popups {
+            let popup_area = self.popup_area(popup, area);
+            let border_color = if self.focused == Some(*id) {
+                Color::Yellow
+            } else {
+                Color::DarkGray
+            };
+
+            let mut block = Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(border_color));
+            if let Some(title) = &popup.title {
+                block = block.title(title.as_str());
+            }
+
+            f.render_widget(Clear, popup_area);
+            f.render_widget(
+                Paragraph::new(popup.content.clone()).block(block),
+                popup_area,
+            );
+        }
+    }
+
+    fn popup_area(&self, popup: &Popup, area: Rect) -> Rect {
+        let width = popup.width.min(area.width);
+        let height = popup.height.min(area.height);
+        let max_x = area.x + area.width - width;
+        let max_y = area.y + area.height - height;
+
+        let (x, y) = match popup.placement {
+            Placement::Centered => (
+                area.x + (area.width - width) / 2,
+                area.y + (area.height - height) / 2,
+            ),
+            Placement::At(x, y) => (x, y),
+            Placement::Cursor => match self.cursor {
+                Some(cursor) if cursor.y + 1 + height <= area.y + area.height => {
+                    (cursor.x, cursor.y + 1)
+                }
+                Some(cursor) => (cursor.x, cursor.y.saturating_sub(height)),
+                None => (area.x, area.y),
+            },
+        };
+
+        Rect::new(
+            x.clamp(area.x, max_x),
+            y.clamp(area.y, max_y),
+            width,
+            height,
+        )
+    }
+}
+
+impl Default for PopupLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{
+    editor::syntax::{LineState, Syntax},
+    ui::render::highlighted_line,
+};
+use ratatui::{
+    style::{Color, Style},
+    text::Line,
+};
+use std::path::Path;
+
+pub fn highlighted_preview(
+    path: &Path,
+    text: &str,
+    focus: Option<usize>,
+    height: usize,
+) -> Vec<Line<'static>> {
+    // Keep some context above the focus line
+    let first = focus.map_or(0, |focus| focus.saturating_sub(height / 3));
+    let syntax = Syntax::for_path(path);
+    let mut state = LineState::default();
+    let mut lines = Vec::new();
+    for (index, line) in text.lines().enumerate().take(first.saturating_add(height)) {
+        // Earlier lines still go through the highlighter for its state
+        let spans = syntax.map(|syntax| {
+            let (spans, next) = syntax.highlight_line(line, state);
+            state = next;
+            spans
+        });
+        if index < first {
+            continue;
+        }
+        let mut line = highlighted_line(line, spans.as_ref());
+        if Some(index) == focus {
+            line.style = Style::default().bg(Color::from_u32(0x303030));
+        }
+        lines.push(line);
+    }
+    lines
+}
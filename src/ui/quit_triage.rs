@@ -0,0 +1,211 @@
+use crate::{
+    editor::{BufferId, Editor},
+    ui::popup::{Placement, Popup, PopupId, PopupLayer},
+    utils::error_handler::set_error,
+};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    style::{Color, Style},
+    text::{Line, Span, Text},
+};
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Clone, PartialEq)]
+enum Choice {
+    Undecided,
+    Saved,
+    Discarded,
+    // The save was tried and failed, with why
+    Failed(String),
+}
+
+struct Entry {
+    buffer: BufferId,
+    name: String,
+    choice: Choice,
+}
+
+impl Entry {
+    fn is_settled(&self) -> bool {
+        matches!(self.choice, Choice::Saved | Choice::Discarded)
+    }
+}
+
+pub struct QuitTriage {
+    popup: Option<PopupId>,
+    entries: Vec<Entry>,
+    selected: usize,
+}
+
+impl QuitTriage {
+    pub fn new() -> Self {
+        Self {
+            popup: None,
+            entries: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn open(&mut self, popups: &mut PopupLayer, editor: &Editor) {
+        self.close(popups);
+        self.entries = editor
+            .get_unsaved_buffers()
+            .into_iter()
+            .filter_map(|id| {
+                Some(Entry {
+                    buffer: id,
+                    name: editor.get_buffer(id)?.name.to_string(),
+                    choice: Choice::Undecided,
+                })
+            })
+            .collect();
+        self.selected = 0;
+
+        // Sizes are clamped to the screen, so this covers all of it
+        let mut popup = Popup::new(Text::default(), Placement::Centered, u16::MAX, u16::MAX);
+        popup.title = Some("Unsaved changes".to_string());
+        popup.focusable = true;
+        popup.z_index = 20;
+        self.popup = Some(popups.open(popup));
+        self.update_popup(popups);
+    }
+
+    pub fn close(&mut self, popups: &mut PopupLayer) {
+        if let Some(popup) = self.popup.take() {
+            popups.close(popup);
+        }
+        self.entries.clear();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    pub fn handle_key(
+        &mut self,
+        popups: &mut PopupLayer,
+        editor: &mut Editor,
+        key: KeyEvent,
+    ) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('c') | KeyCode::Char('q') => {
+                self.close(popups);
+                set_error("Quit cancelled".to_string());
+                return false;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+            }
+            KeyCode::Char('s') => {
+                self.save(editor, self.selected);
+                self.select_next_unsettled();
+            }
+            KeyCode::Char('d') => {
+                if let Some(entry) = self.entries.get_mut(self.selected) {
+                    entry.choice = Choice::Discarded;
+                }
+                self.select_next_unsettled();
+            }
+            KeyCode::Char('S') => {
+                for index in 0..self.entries.len() {
+                    if !self.entries[index].is_settled() {
+                        self.save(editor, index);
+                    }
+                }
+                self.select_next_unsettled();
+            }
+            KeyCode::Char('D') => {
+                for entry in &mut self.entries {
+                    if !entry.is_settled() {
+                        entry.choice = Choice::Discarded;
+                    }
+                }
+            }
+            _ => {}
+        }
+        if self.entries.iter().all(Entry::is_settled) {
+            self.close(popups);
+            return true;
+        }
+        self.update_popup(popups);
+        false
+    }
+
+    fn save(&mut self, editor: &mut Editor, index: usize) {
+        let Some(entry) = self.entries.get_mut(index) else {
+            return;
+        };
+        entry.choice = match editor.save_buffer(entry.buffer) {
+            Ok(()) => Choice::Saved,
+            Err(e) => Choice::Failed(e.to_string()),
+        };
+    }
+
+    fn select_next_unsettled(&mut self) {
+        let count = self.entries.len();
+        if let Some(next) = (1..=count)
+            .map(|offset| (self.selected + offset) % count)
+            .find(|&index| !self.entries[index].is_settled())
+        {
+            self.selected = next;
+        }
+    }
+
+    fn update_popup(&self, popups: &mut PopupLayer) {
+        let Some(popup) = self.popup.and_then(|id| popups.get_mut(id)) else {
+            return;
+        };
+        let hint = Style::default().fg(Color::DarkGray);
+
+        let mut lines = vec![
+            Line::raw(match self.entries.len() {
+                1 => "1 buffer has unsaved changes. Choose what to do with it before quitting."
+                    .to_string(),
+                n => format!(
+                    "{} buffers have unsaved changes. Choose what to do with each before quitting.",
+                    n
+                ),
+            }),
+            Line::default(),
+        ];
+        let name_width = self.entries.iter().map(|entry| entry.name.width()).max();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let (state, style) = match &entry.choice {
+                Choice::Undecided => ("unsaved".to_string(), Style::default().fg(Color::Yellow)),
+                Choice::Saved => ("saved".to_string(), Style::default().fg(Color::Green)),
+                Choice::Discarded => ("discard".to_string(), hint),
+                Choice::Failed(e) => (
+                    format!("save failed: {}", e),
+                    Style::default().fg(Color::Red),
+                ),
+            };
+            let name_style = if index == self.selected {
+                Style::default().fg(Color::Black).bg(Color::White)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::raw(if index == self.selected { "> " } else { "  " }),
+                Span::styled(entry.name.clone(), name_style),
+                Span::raw(" ".repeat(name_width.unwrap_or(0) - entry.name.width())),
+                Span::raw("  "),
+                Span::styled(state, style),
+            ]));
+        }
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            "s save, d discard, S save all, D discard all, j/k move, Esc cancel quitting",
+            hint,
+        ));
+        popup.content = Text::from(lines);
+    }
+}
+
+impl Default for QuitTriage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
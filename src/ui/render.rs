@@ -0,0 +1,825 @@
+use crate::{
+    editor::{
+        color_swatch::find_colors,
+        syntax::{HighlightKind, HighlightSpan},
+        todo::find_keywords,
+        window::{Area, WindowId},
+        Editor,
+    },
+    ui::{
+        command_bar::CommandBar,
+        gutter::Gutter,
+        popup::PopupLayer,
+        status_line::{self, Segment, Side, StatusClick},
+        terminal_view::render_terminal,
+    },
+    utils::{
+        error_handler::get_error, file_explorer::FileExplorer, help_handler::get_help_text,
+        progress,
+    },
+};
+use ratatui::{
+    backend::TestBackend,
+    layout::{Constraint, Direction, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Terminal,
+};
+use std::{ops::Range, rc::Rc};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const SUGGESTIONS_PER_PAGE: usize = 5;
+
+pub fn render_to_buffer(
+    editor: &mut Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &CommandBar,
+    popups: &mut PopupLayer,
+    width: u16,
+    height: u16,
+) -> std::io::Result<Vec<String>> {
+    let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+    terminal.draw(|f| {
+        layout(editor, f.area());
+        render_ui(f, editor, file_explorer, command_bar, popups)
+    })?;
+
+    let buffer = terminal.backend().buffer();
+    Ok((0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+        })
+        .collect())
+}
+
+pub fn layout(editor: &mut Editor, area: Rect) {
+    let layout_area = screen_layout(editor, area)[0];
+    editor.set_layout_area(Area::new(
+        layout_area.x,
+        layout_area.y,
+        layout_area.width,
+        layout_area.height,
+    ));
+    for (window, window_area) in window_areas(editor, layout_area) {
+        let window_area = Rect::new(
+            window_area.x,
+            window_area.y,
+            window_area.width,
+            window_area.height,
+        );
+        let width = if editor.is_terminal(window) {
+            window_area.width
+        } else {
+            window_layout(editor, window, window_area).1.width
+        };
+        editor.set_viewport(window, (width as usize, window_area.height as usize));
+    }
+}
+
+pub fn render_ui(
+    f: &mut ratatui::Frame,
+    editor: &Editor,
+    file_explorer: &mut FileExplorer,
+    command_bar: &CommandBar,
+    popups: &mut PopupLayer,
+) {
+    let area = f.area();
+    if file_explorer.open {
+        file_explorer.render(f, area, &editor.open_files());
+    } else {
+        let chunks = screen_layout(editor, area);
+
+        let focused = editor.get_current_window();
+        let mut cursor = None;
+        for (window, window_area) in window_areas(editor, chunks[0]) {
+            let window_area = Rect::new(
+                window_area.x,
+                window_area.y,
+                window_area.width,
+                window_area.height,
+            );
+            let window_cursor = render_window(f, editor, window, window_area);
+            if window == focused {
+                cursor = window_cursor;
+            }
+        }
+
+        if let Some(cursor) = cursor {
+            f.set_cursor_position(cursor);
+        }
+        popups.set_cursor(cursor);
+
+        render_command_description(f, command_bar, chunks[1]);
+        render_status_line(f, editor, command_bar, chunks[2]);
+        render_autocomplete_suggestions(f, command_bar, chunks[3]);
+        render_error(f, chunks[2]);
+        // help_handler::render_help(f, chunks[4]);
+    }
+
+    popups.render(f, area);
+}
+
+fn screen_layout(editor: &Editor, area: Rect) -> Rc<[Rect]> {
+    let bar = if editor.is_zen() { 0 } else { 1 };
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),      // Editor area
+            Constraint::Length(bar), // Command description
+            Constraint::Length(1),   // Status bar / Command bar
+            Constraint::Length(bar), // Error message
+        ])
+        .split(area)
+}
+
+// Narrowest text zen mode pads down to
+const ZEN_MIN_WIDTH: u16 = 20;
+
+fn window_layout(editor: &Editor, window: WindowId, area: Rect) -> (Rect, Rect) {
+    if editor.is_zen() {
+        let padding = (editor.get_options().zenpadding.min(u16::MAX as usize) as u16)
+            .min(area.width.saturating_sub(ZEN_MIN_WIDTH) / 2);
+        let text = Rect::new(
+            area.x + padding,
+            area.y,
+            area.width - 2 * padding,
+            area.height,
+        );
+        return (Rect::new(text.x, area.y, 0, area.height), text);
+    }
+    let gutter_width = Gutter::width(editor.window_line_count(window));
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(gutter_width), Constraint::Min(1)])
+        .split(area);
+    (chunks[0], chunks[1])
+}
+
+fn window_areas(editor: &Editor, area: Rect) -> Vec<(WindowId, Area)> {
+    editor.window_rects(Area::new(area.x, area.y, area.width, area.height))
+}
+
+pub fn window_at(editor: &Editor, screen: Rect, position: Position) -> Option<WindowId> {
+    window_area_at(editor, screen, position).map(|(window, _)| window)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WindowSpot {
+    LineNumber,
+    Sign,
+    // A column of the text, counted from the left of the view
+    Text(usize),
+}
+
+pub fn text_position_at(
+    editor: &Editor,
+    screen: Rect,
+    position: Position,
+) -> Option<(WindowId, usize, WindowSpot)> {
+    let (window, area) = window_area_at(editor, screen, position)?;
+    let (gutter, text) = window_layout(editor, window, area);
+    let spot = if position.x >= text.x {
+        WindowSpot::Text((position.x - text.x) as usize)
+    } else if position.x < gutter.x {
+        // Zen mode's padding
+        WindowSpot::Text(0)
+    } else if position.x == text.x - Gutter::SIGN_OFFSET {
+        WindowSpot::Sign
+    } else {
+        WindowSpot::LineNumber
+    };
+    Some((window, (position.y - area.y) as usize, spot))
+}
+
+fn window_area_at(editor: &Editor, screen: Rect, position: Position) -> Option<(WindowId, Rect)> {
+    window_areas(editor, screen_layout(editor, screen)[0])
+        .into_iter()
+        .map(|(window, area)| (window, Rect::new(area.x, area.y, area.width, area.height)))
+        .find(|(_, area)| area.contains(position))
+}
+
+fn render_window(
+    f: &mut ratatui::Frame,
+    editor: &Editor,
+    window: WindowId,
+    area: ratatui::layout::Rect,
+) -> Option<Position> {
+    if let Some(pane) = editor.get_terminal(window) {
+        return render_terminal(f, pane, area);
+    }
+
+    let (gutter_area, text_area) = window_layout(editor, window, area);
+    if gutter_area.width > 0 {
+        render_gutter(f, editor, window, gutter_area);
+    }
+    render_content(f, editor, window, text_area);
+
+    // The mouse wheel can scroll the cursor out of view
+    let (cursor_screen_y, cursor_screen_x) = editor.get_cursor_view_position(window)?;
+    if cursor_screen_x >= text_area.width as usize || cursor_screen_y >= area.height as usize {
+        return None;
+    }
+    Some(Position::new(
+        text_area.x + cursor_screen_x as u16,
+        area.y + cursor_screen_y as u16,
+    ))
+}
+
+fn render_error(f: &mut ratatui::Frame, area: Rect) {
+    if let Some(error_message) = get_error() {
+        let error_paragraph = Paragraph::new(error_message).style(Style::default().fg(Color::Red));
+        f.render_widget(error_paragraph, area);
+    }
+}
+
+pub fn render_help(f: &mut ratatui::Frame, area: Rect) {
+    if let Some(help_text) = get_help_text() {
+        let help_paragraph = Paragraph::new(help_text).style(Style::default().fg(Color::Yellow));
+        f.render_widget(help_paragraph, area);
+    }
+}
+
+fn render_command_description(
+    f: &mut ratatui::Frame,
+    command_bar: &CommandBar,
+    area: ratatui::layout::Rect,
+) {
+    if command_bar.is_active() {
+        if let Some(description) = command_bar.get_current_command_description() {
+            let description_widget =
+                Paragraph::new(description).style(Style::default().fg(Color::Yellow));
+            f.render_widget(description_widget, area);
+        }
+    }
+}
+
+fn render_autocomplete_suggestions(
+    f: &mut ratatui::Frame,
+    command_bar: &CommandBar,
+    area: ratatui::layout::Rect,
+) {
+    if command_bar.is_active() {
+        let suggestions = command_bar.get_suggestions();
+        let current_index = command_bar.get_suggestion_index();
+        let page = command_bar.suggestion_page;
+        let total_pages = (suggestions.len() + SUGGESTIONS_PER_PAGE - 1) / SUGGESTIONS_PER_PAGE;
+
+        let start_index = page * SUGGESTIONS_PER_PAGE;
+        let end_index = (start_index + SUGGESTIONS_PER_PAGE).min(suggestions.len());
+
+        let mut spans = Vec::new();
+
+        // Add left arrow for previous page
+        if page > 0 {
+            spans.push(Span::styled("< ", Style::default().fg(Color::Yellow)));
+        }
+
+        for (index, suggestion) in suggestions[start_index..end_index].iter().enumerate() {
+            let absolute_index = start_index + index;
+            if absolute_index == current_index {
+                spans.push(Span::styled(
+                    suggestion.name.clone(),
+                    Style::default().fg(Color::Black).bg(Color::White),
+                ));
+            } else {
+                spans.push(Span::styled(
+                    suggestion.name.clone(),
+                    Style::default().fg(Color::Blue),
+                ));
+            }
+
+            if index < end_index - start_index - 1 {
+                spans.push(Span::raw(" "));
+            }
+        }
+
+        // Add right arrow for next page
+        if page < total_pages - 1 {
+            spans.push(Span::styled(" >", Style::default().fg(Color::Yellow)));
+        }
+
+        // Add page indicator
+        let page_indicator = format!(" [{}/{}]", page + 1, total_pages);
+        spans.push(Span::styled(
+            page_indicator,
+            Style::default().fg(Color::Gray),
+        ));
+
+        let suggestions_line = Line::from(spans);
+        let suggestions_widget = Paragraph::new(vec![suggestions_line]);
+        f.render_widget(suggestions_widget, area);
+    }
+}
+
+fn render_gutter(
+    f: &mut ratatui::Frame,
+    editor: &Editor,
+    window: WindowId,
+    area: ratatui::layout::Rect,
+) {
+    let line_numbers = Gutter::get_visible_line_numbers(editor, window);
+    let signs = editor.get_visible_signs(window);
+    let lines: Vec<Line> = line_numbers
+        .into_iter()
+        .enumerate()
+        .map(
+            |(index, number)| match signs.get(index).and_then(Option::as_ref) {
+                Some(sign) => Line::from(vec![
+                    Span::raw(number),
+                    Span::styled(
+                        sign.symbol.to_string(),
+                        Style::default().fg(sign_color(editor, &sign.group)),
+                    ),
+                ]),
+                None => Line::raw(number),
+            },
+        )
+        .collect();
+    let gutter_content = Paragraph::new(lines).style(Style::default().fg(Color::DarkGray));
+    f.render_widget(gutter_content, area);
+}
+
+fn sign_color(editor: &Editor, group: &str) -> Color {
+    if let Some(color) = editor
+        .sign_color(group)
+        .and_then(|color| color.parse().ok())
+    {
+        return color;
+    }
+    match group {
+        "error" => Color::Red,
+        "warning" => Color::Yellow,
+        "info" => Color::Blue,
+        "hint" => Color::Cyan,
+        "breakpoint" => Color::LightRed,
+        "stopped" => Color::LightYellow,
+        "mark" | "bookmark" => Color::Magenta,
+        "git_added" => Color::Green,
+        "git_changed" => Color::Yellow,
+        "git_removed" => Color::Red,
+        _ => Color::Gray,
+    }
+}
+
+fn render_content(
+    f: &mut ratatui::Frame,
+    editor: &Editor,
+    window: WindowId,
+    area: ratatui::layout::Rect,
+) {
+    if let Some(content) = editor.get_visible_content(window) {
+        let selections = editor.get_visible_selections(window);
+        let highlights = editor.get_visible_highlights(window);
+        let lines: Vec<Line> = content
+            .lines()
+            .enumerate()
+            .map(|(line_idx, line)| {
+                if let Some(selection) = selections.get(line_idx).cloned().flatten() {
+                    // The selection counts chars; slicing needs bytes
+                    let byte = |column: usize| {
+                        line.char_indices()
+                            .nth(column)
+                            .map_or(line.len(), |(i, _)| i)
+                    };
+                    let (sel_start, sel_end) = (byte(selection.start), byte(selection.end));
+
+                    if sel_start < sel_end {
+                        let mut spans = Vec::new();
+                        if sel_start > 0 {
+                            spans.push(Span::raw(&line[..sel_start]));
+                        }
+                        spans.push(Span::styled(
+                            &line[sel_start..sel_end],
+                            Style::default().bg(Color::Gray).fg(Color::Black),
+                        ));
+                        if sel_end < line.len() {
+                            spans.push(Span::raw(&line[sel_end..]));
+                        }
+                        Line::from(spans)
+                    } else {
+                        highlighted_line(line, highlights.get(line_idx))
+                    }
+                } else {
+                    highlighted_line(line, highlights.get(line_idx))
+                }
+            })
+            .collect();
+
+        let paragraph =
+            ratatui::widgets::Paragraph::new(lines).block(ratatui::widgets::Block::default());
+        f.render_widget(paragraph, area);
+
+        if editor.color_swatches_enabled(window) {
+            render_color_swatches(f, &content, area);
+        }
+        render_todo_keywords(f, &content, area, &editor.todo_keywords());
+        render_diagnostic_text(f, editor, window, &content, area);
+    } else {
+        let paragraph =
+            ratatui::widgets::Paragraph::new("").block(ratatui::widgets::Block::default());
+        f.render_widget(paragraph, area);
+    }
+}
+
+fn render_color_swatches(f: &mut ratatui::Frame, content: &str, area: Rect) {
+    let buffer = f.buffer_mut();
+    for (row, line) in (area.y..area.bottom()).zip(content.lines()) {
+        for color in find_colors(line) {
+            let (r, g, b) = color.rgb;
+            let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+            let style = Style::default()
+                .bg(Color::Rgb(r, g, b))
+                .fg(if luminance > 140.0 {
+                    Color::Black
+                } else {
+                    Color::White
+                });
+            paint_chars(buffer, area, row, line, color.start..color.end, style);
+        }
+    }
+}
+
+fn render_todo_keywords(f: &mut ratatui::Frame, content: &str, area: Rect, keywords: &[String]) {
+    if keywords.is_empty() {
+        return;
+    }
+    let buffer = f.buffer_mut();
+    for (row, line) in (area.y..area.bottom()).zip(content.lines()) {
+        for span in find_keywords(line, keywords) {
+            let keyword: String = line
+                .chars()
+                .skip(span.start)
+                .take(span.end - span.start)
+                .collect();
+            let color = match keyword.as_str() {
+                "FIXME" | "XXX" | "BUG" => Color::Red,
+                "HACK" | "WARN" => Color::Magenta,
+                "NOTE" | "INFO" => Color::Cyan,
+                _ => Color::Yellow,
+            };
+            let style = Style::default()
+                .fg(Color::Black)
+                .bg(color)
+                .add_modifier(Modifier::BOLD);
+            paint_chars(buffer, area, row, line, span.start..span.end, style);
+        }
+    }
+}
+
+fn render_diagnostic_text(
+    f: &mut ratatui::Frame,
+    editor: &Editor,
+    window: WindowId,
+    content: &str,
+    area: Rect,
+) {
+    let diagnostics = editor.get_visible_diagnostics(window);
+    let buffer = f.buffer_mut();
+    for ((row, line), diagnostic) in (area.y..area.bottom())
+        .zip(content.lines())
+        .zip(diagnostics)
+    {
+        let Some(diagnostic) = diagnostic else {
+            continue;
+        };
+        let column = area.x + line.width().min(area.width as usize) as u16 + 2;
+        if column >= area.right() {
+            continue;
+        }
+        let message = diagnostic.message.lines().next().unwrap_or_default();
+        let style = Style::default()
+            .fg(sign_color(editor, diagnostic.severity.group()))
+            .add_modifier(Modifier::DIM | Modifier::ITALIC);
+        buffer.set_stringn(
+            column,
+            row,
+            format!("■ {}", message),
+            (area.right() - column) as usize,
+            style,
+        );
+    }
+}
+
+fn paint_chars(
+    buffer: &mut ratatui::buffer::Buffer,
+    area: Rect,
+    row: u16,
+    line: &str,
+    range: Range<usize>,
+    style: Style,
+) {
+    let mut column = area.x;
+    for (index, c) in line.chars().enumerate() {
+        let width = c.width().unwrap_or(1) as u16;
+        if index >= range.end || column + width > area.right() {
+            break;
+        }
+        if index >= range.start {
+            for x in column..column + width {
+                buffer[(x, row)].set_style(style);
+            }
+        }
+        column += width;
+    }
+}
+
+pub(crate) fn marked_spans(
+    text: &str,
+    positions: &[usize],
+    base: Style,
+    marked: Style,
+) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut positions = positions.iter().peekable();
+    for (index, c) in text.chars().enumerate() {
+        let is_marked = positions.next_if_eq(&&index).is_some();
+        let style = if is_marked { marked } else { base };
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    spans
+}
+
+pub(crate) fn highlighted_line(line: &str, spans: Option<&Vec<HighlightSpan>>) -> Line<'static> {
+    let Some(spans) = spans.filter(|spans| !spans.is_empty()) else {
+        return Line::from(line.to_string());
+    };
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut parts = Vec::new();
+    let mut position = 0;
+    for span in spans {
+        let start = span.start.min(chars.len());
+        let end = span.end.min(chars.len());
+        if start > position {
+            parts.push(Span::raw(chars[position..start].iter().collect::<String>()));
+        }
+        if end > start.max(position) {
+            let text: String = chars[start.max(position)..end].iter().collect();
+            parts.push(Span::styled(text, highlight_style(span.kind)));
+            position = end;
+        }
+    }
+    if position < chars.len() {
+        parts.push(Span::raw(chars[position..].iter().collect::<String>()));
+    }
+    Line::from(parts)
+}
+
+fn highlight_style(kind: HighlightKind) -> Style {
+    match kind {
+        HighlightKind::Keyword => Style::default().fg(Color::Magenta),
+        HighlightKind::String => Style::default().fg(Color::Green),
+        HighlightKind::Comment => Style::default().fg(Color::DarkGray),
+        HighlightKind::Number => Style::default().fg(Color::Yellow),
+    }
+}
+
+fn status_segments(editor: &Editor) -> Vec<Segment> {
+    let mut segments = vec![Segment::new(
+        format!(" {} ", editor.get_mode()),
+        Side::Left,
+        4,
+    )];
+
+    if let Some(buffer) = editor.get_current_buffer() {
+        let name = match buffer.name.path() {
+            Some(path) => path
+                .strip_prefix(editor.get_working_directory())
+                .unwrap_or(path)
+                .display()
+                .to_string(),
+            None => buffer.name.to_string(),
+        };
+        let modified = match (buffer.is_modified, buffer.read_only) {
+            (true, _) => " [+]",
+            (false, true) => " [RO]",
+            (false, false) => "",
+        };
+        segments.push(
+            Segment::new(format!(" {}{} ", name, modified), Side::Left, 3)
+                .shrinkable()
+                .on_click(StatusClick::Buffers),
+        );
+    }
+    if let Some(branch) = editor.git_branch() {
+        segments.push(
+            Segment::new(format!(" {} ", branch), Side::Left, 1).on_click(StatusClick::Branches),
+        );
+    }
+
+    if let Some(progress) = progress::status() {
+        segments.push(Segment::new(format!("{}  ", progress), Side::Right, 0));
+    }
+    if let Some(task) = editor.task_status() {
+        segments.push(Segment::new(format!("{}  ", task), Side::Right, 0));
+    }
+    let diagnostics: Vec<String> = ['E', 'W', 'I', 'H']
+        .iter()
+        .zip(editor.diagnostic_counts())
+        .filter(|(_, count)| *count > 0)
+        .map(|(sign, count)| format!("{}:{}", sign, count))
+        .collect();
+    if !diagnostics.is_empty() {
+        segments.push(Segment::new(
+            format!("{}  ", diagnostics.join(" ")),
+            Side::Right,
+            1,
+        ));
+    }
+    if let Some(search) = editor.search_status() {
+        segments.push(Segment::new(format!("{}  ", search), Side::Right, 1));
+    }
+    let cursor_info = match editor.get_cursor_screen_position() {
+        Some((line, column)) => format!("{}:{} ", line + 1, column + 1),
+        None => String::from("No active buffer "),
+    };
+    segments.push(Segment::new(cursor_info, Side::Right, 2).on_click(StatusClick::GotoLine));
+    segments
+}
+
+pub fn status_click_at(
+    editor: &Editor,
+    command_bar: &CommandBar,
+    screen: Rect,
+    position: Position,
+) -> Option<StatusClick> {
+    let area = screen_layout(editor, screen)[2];
+    if command_bar.is_active() || editor.is_zen() || !area.contains(position) {
+        return None;
+    }
+    status_line::click_at(
+        status_segments(editor),
+        area.width as usize,
+        (position.x - area.x) as usize,
+    )
+}
+
+fn render_status_line(
+    f: &mut ratatui::Frame,
+    editor: &Editor,
+    command_bar: &CommandBar,
+    area: ratatui::layout::Rect,
+) {
+    let status_text = if command_bar.is_active() {
+        format!("{}{}", command_bar.get_prompt(), command_bar.get_input())
+    } else if editor.is_zen() {
+        String::new()
+    } else {
+        status_line::layout(status_segments(editor), area.width as usize)
+    };
+
+    let status_style = if editor.is_zen() {
+        Style::default()
+    } else {
+        Style::default().bg(Color::from_u32(0x202020))
+    };
+    let status_line = Paragraph::new(status_text).style(status_style);
+    f.render_widget(status_line, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{mode::Mode, window::SplitDirection};
+
+    fn render(editor: &mut Editor, width: u16, height: u16) -> Vec<String> {
+        let mut file_explorer = FileExplorer::new(&std::env::temp_dir()).unwrap();
+        render_with_explorer(editor, &mut file_explorer, width, height)
+    }
+
+    fn render_with_explorer(
+        editor: &mut Editor,
+        file_explorer: &mut FileExplorer,
+        width: u16,
+        height: u16,
+    ) -> Vec<String> {
+        let mut popups = PopupLayer::new();
+        render_to_buffer(
+            editor,
+            file_explorer,
+            &CommandBar::new(),
+            &mut popups,
+            width,
+            height,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn draws_the_buffer_beside_its_line_numbers() {
+        let mut editor = Editor::new();
+        editor.new_scratch_buffer_with_content("hello\nworld");
+        let screen = render(&mut editor, 24, 5);
+        assert_eq!(
+            screen[..3],
+            [
+                "   1  hello             ",
+                "   2  world             ",
+                "                        ",
+            ]
+        );
+    }
+
+    #[test]
+    fn draws_each_window_at_its_own_scroll_position() {
+        let content: String = (1..=10).map(|line| format!("line {}\n", line)).collect();
+        let mut editor = Editor::new();
+        editor.new_scratch_buffer_with_content(&content);
+        editor.split_window(SplitDirection::Vertical);
+        editor.go_to_line(9);
+        let screen = render(&mut editor, 40, 6);
+        assert_eq!(
+            screen[..3],
+            [
+                "   9  line 9           1  line 1        ",
+                "  10  line 10          2  line 2        ",
+                "  11                   3  line 3        ",
+            ]
+        );
+    }
+
+    #[test]
+    fn zen_mode_drops_the_gutter() {
+        let mut editor = Editor::new();
+        editor.new_scratch_buffer_with_content("hello");
+        editor.toggle_zen();
+        let screen = render(&mut editor, 24, 3);
+        assert_eq!(
+            screen[..2],
+            ["  hello                 ", "                        "]
+        );
+    }
+
+    // A directory of its own under the system one, removed again on drop
+    struct TempDir(std::path::PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("pyne-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&path).unwrap();
+            Self(std::fs::canonicalize(path).unwrap())
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn file_explorer_lists_the_directory_beside_a_preview() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let temp = TempDir::new("render-explorer");
+        let project = temp.0.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("notes.txt"), "hello\n").unwrap();
+        for (path, mode) in [(&temp.0, 0o755), (&project.join("src"), 0o755)] {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let notes = project.join("notes.txt");
+        fs::set_permissions(&notes, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut file_explorer = FileExplorer::new(&project).unwrap();
+        file_explorer.open = true;
+        file_explorer.move_selection(2).unwrap();
+        let screen = render_with_explorer(&mut Editor::new(), &mut file_explorer, 80, 16);
+        assert_eq!(
+            screen[4..10],
+            [
+                "      ┌Files (● open, [+] unsaved, p┐┌Preview────────────────────────────┐      ",
+                "      │  rwxr-xr-x ..               ││hello                              │      ",
+                "      │  rwxr-xr-x src/             ││                                   │      ",
+                "      │> rw-r--r-- notes.txt        ││                                   │      ",
+                "      │                             ││                                   │      ",
+                "      └─────────────────────────────┘└───────────────────────────────────┘      ",
+            ]
+        );
+    }
+
+    #[test]
+    fn status_line_shows_the_mode_file_and_cursor() {
+        let temp = TempDir::new("render-status");
+        let notes = temp.0.join("notes.txt");
+        std::fs::write(&notes, "hello\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.open_file(&notes).unwrap();
+        editor
+            .change_directory(temp.0.to_str().unwrap(), true)
+            .unwrap();
+        editor.set_mode(Mode::Insert);
+        editor.type_char('x');
+        let screen = render(&mut editor, 40, 4);
+        // The right side may also show progress from other tests
+        assert!(screen[2].starts_with(" INS  notes.txt [+]  "));
+        assert!(screen[2].ends_with("  1:2 "));
+    }
+}
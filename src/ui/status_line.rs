@@ -0,0 +1,166 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const ELLIPSIS: char = '…';
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatusClick {
+    // The file name: a picker of the open buffers
+    Buffers,
+    // The cursor position: the command line, to type a line number
+    GotoLine,
+    // The git branch: a picker of the branches to check out
+    Branches,
+}
+
+pub struct Segment {
+    pub text: String,
+    pub side: Side,
+    pub priority: u8,
+    // Keep the end of the text when cutting it, as for file paths
+    pub shrinkable: bool,
+    pub click: Option<StatusClick>,
+}
+
+impl Segment {
+    pub fn new(text: String, side: Side, priority: u8) -> Self {
+        Self {
+            text,
+            side,
+            priority,
+            shrinkable: false,
+            click: None,
+        }
+    }
+
+    pub fn shrinkable(mut self) -> Self {
+        self.shrinkable = true;
+        self
+    }
+
+    pub fn on_click(mut self, click: StatusClick) -> Self {
+        self.click = Some(click);
+        self
+    }
+}
+
+pub fn layout(mut segments: Vec<Segment>, width: usize) -> String {
+    fit(&mut segments, width);
+    let side = |side: Side| -> String {
+        segments
+            .iter()
+            .filter(|segment| segment.side == side)
+            .map(|segment| segment.text.as_str())
+            .collect()
+    };
+    let padding = width.saturating_sub(used(&segments));
+    let line = format!(
+        "{}{}{}",
+        side(Side::Left),
+        " ".repeat(padding),
+        side(Side::Right)
+    );
+    truncate_end(&line, width)
+}
+
+pub fn click_at(mut segments: Vec<Segment>, width: usize, column: usize) -> Option<StatusClick> {
+    fit(&mut segments, width);
+    let side_width = |side: Side| -> usize {
+        segments
+            .iter()
+            .filter(|segment| segment.side == side)
+            .map(|segment| segment.text.width())
+            .sum()
+    };
+    let mut left = 0;
+    let mut right = width
+        .saturating_sub(side_width(Side::Right))
+        .max(side_width(Side::Left));
+    segments.iter().find_map(|segment| {
+        let start = match segment.side {
+            Side::Left => &mut left,
+            Side::Right => &mut right,
+        };
+        let columns = *start..*start + segment.text.width();
+        *start = columns.end;
+        segment.click.filter(|_| columns.contains(&column))
+    })
+}
+
+fn used(segments: &[Segment]) -> usize {
+    segments.iter().map(|segment| segment.text.width()).sum()
+}
+
+fn fit(segments: &mut Vec<Segment>, width: usize) {
+    let mut order: Vec<u8> = segments.iter().map(|segment| segment.priority).collect();
+    order.sort_unstable();
+    order.dedup();
+    // The most important segments stay, cut short if need be
+    order.pop();
+    for priority in order {
+        while used(segments) > width {
+            let Some(index) = segments
+                .iter()
+                .position(|segment| segment.priority == priority)
+            else {
+                break;
+            };
+            let excess = used(segments) - width;
+            let segment = &mut segments[index];
+            let own = segment.text.width();
+            // Shrinking to a lone ellipsis would say nothing
+            if segment.shrinkable && own > excess + 1 {
+                segment.text = truncate_start(&segment.text, own - excess);
+            } else {
+                segments.remove(index);
+            }
+        }
+    }
+}
+
+pub fn truncate_end(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        used += char_width;
+        out.push(c);
+    }
+    if width > 0 {
+        out.push(ELLIPSIS);
+    }
+    out
+}
+
+pub fn truncate_start(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut tail = Vec::new();
+    let mut used = 0;
+    for c in text.chars().rev() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        used += char_width;
+        tail.push(c);
+    }
+    let mut out = String::new();
+    if width > 0 {
+        out.push(ELLIPSIS);
+    }
+    out.extend(tail.into_iter().rev());
+    out
+}
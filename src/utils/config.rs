@@ -0,0 +1,213 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+const PROJECT_FILE_NAME: &str = ".pyne.toml";
+// Project configs the user agreed to load, one `digest<Tab>path` per line
+const TRUSTED_FILE_NAME: &str = "trusted_projects";
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub save_hooks: Vec<SaveHook>,
+    // Command used to retry a write that failed with permission denied.
+    pub privilege_command: String,
+    pub tasks: Vec<Task>,
+    // Colors for sign groups by name, as `#rrggbb` or a color name, e.g.
+    pub sign_colors: HashMap<String, String>,
+    // Language server commands by file extension, for `:rename` and
+    // `:codeaction`, e.g.
+    pub language_servers: HashMap<String, String>,
+    // Formatter commands by file extension for `:format`, which pipes the
+    // lines through them, e.g.
+    pub formatters: HashMap<String, String>,
+    pub debug: Vec<DebugConfig>,
+    // The pairs autopairs closes by file extension, or `*` for every
+    // other file, replacing the default brackets and quotes, e.g.
+    pub autopairs: HashMap<String, Vec<String>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            save_hooks: Vec::new(),
+            privilege_command: "sudo tee {file} > /dev/null".to_string(),
+            tasks: Vec::new(),
+            sign_colors: HashMap::new(),
+            language_servers: HashMap::from([("rs".to_string(), "rust-analyzer".to_string())]),
+            formatters: HashMap::new(),
+            debug: Vec::new(),
+            autopairs: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SaveHook {
+    pub pattern: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Task {
+    pub name: String,
+    pub command: String,
+}
+
+#[derive(Deserialize)]
+pub struct DebugConfig {
+    pub name: String,
+    pub adapter: String,
+    #[serde(default = "default_debug_request")]
+    pub request: String,
+    #[serde(default)]
+    pub arguments: serde_json::Map<String, serde_json::Value>,
+}
+
+fn default_debug_request() -> String {
+    "launch".to_string()
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ProjectConfig {
+    pub set: Vec<String>,
+    pub exclude: Vec<String>,
+    pub tasks: Vec<Task>,
+    pub save_hooks: Vec<SaveHook>,
+    pub debug: Vec<DebugConfig>,
+    pub formatters: HashMap<String, String>,
+}
+
+pub struct ProjectFile {
+    pub path: PathBuf,
+    pub config: ProjectConfig,
+    // Of the file content, so an edited file needs trusting again
+    pub digest: String,
+}
+
+impl ProjectFile {
+    pub fn find(directory: &Path) -> Option<Result<Self, String>> {
+        let path = directory
+            .ancestors()
+            .map(|directory| directory.join(PROJECT_FILE_NAME))
+            .find(|path| path.is_file())?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => return Some(Err(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        Some(
+            toml::from_str(&content)
+                .map(|config| Self {
+                    digest: trust_digest(&content),
+                    path: path.clone(),
+                    config,
+                })
+                .map_err(|e| format!("Invalid project config {}: {}", path.display(), e)),
+        )
+    }
+
+    pub fn root(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+
+    pub fn is_trusted(&self) -> bool {
+        let Some(path) = get_config_dir().map(|dir| dir.join(TRUSTED_FILE_NAME)) else {
+            return false;
+        };
+        let entry = self.trust_entry();
+        fs::read_to_string(path)
+            .map(|content| content.lines().any(|line| line == entry))
+            .unwrap_or(false)
+    }
+
+    pub fn trust(&self) -> io::Result<()> {
+        let dir = get_config_dir().ok_or_else(|| io::Error::other("No config directory"))?;
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(TRUSTED_FILE_NAME);
+        let suffix = format!("\t{}", self.path.display());
+        let mut lines: Vec<String> = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.ends_with(&suffix))
+                .map(str::to_string)
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        lines.push(self.trust_entry());
+        fs::write(path, lines.join("\n") + "\n")
+    }
+
+    fn trust_entry(&self) -> String {
+        format!("{}\t{}", self.digest, self.path.display())
+    }
+}
+
+fn trust_digest(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub(crate) fn digest(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+impl Config {
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = get_config_dir().map(|dir| dir.join(CONFIG_FILE_NAME)) else {
+            return Ok(Self::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| format!("Invalid config {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read config {}: {}", path.display(), e)),
+        }
+    }
+}
+
+pub fn get_config_dir() -> Option<PathBuf> {
+    if let Ok(home) = env::var("HOME") {
+        let mut config_path = PathBuf::from(home);
+        config_path.push(".config/pyne");
+        Some(config_path)
+    } else if let Ok(appdata) = env::var("APPDATA") {
+        let mut config_path = PathBuf::from(appdata);
+        config_path.push("pyne");
+        Some(config_path)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trust_digest_is_sha256() {
+        assert_eq!(
+            trust_digest("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn project_config_reads_formatters() {
+        let config: ProjectConfig = toml::from_str("[formatters]\nrs = \"rustfmt\"\n").unwrap();
+        assert_eq!(config.formatters["rs"], "rustfmt");
+    }
+}
@@ -1,25 +1,37 @@
 use once_cell::sync::Lazy;
-use ratatui::{
-    layout::Rect,
-    style::{Color, Style},
-    widgets::Paragraph,
-    Frame,
-};
 use std::sync::Mutex;
 
+const MAX_HISTORY: usize = 500;
+
 pub struct ErrorHandler {
     message: Option<String>,
+    history: Vec<String>,
 }
 
 impl ErrorHandler {
     pub fn new() -> Self {
-        ErrorHandler { message: None }
+        ErrorHandler {
+            message: None,
+            history: Vec::new(),
+        }
     }
 
     pub fn set_error(&mut self, message: String) {
+        self.add_to_history(message.clone());
         self.message = Some(message);
     }
 
+    pub fn add_to_history(&mut self, message: String) {
+        self.history.push(message);
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    pub fn get_history(&self) -> &[String] {
+        &self.history
+    }
+
     pub fn clear_error(&mut self) {
         self.message = None;
     }
@@ -31,13 +43,6 @@ impl ErrorHandler {
 
 pub static ERROR_HANDLER: Lazy<Mutex<ErrorHandler>> = Lazy::new(|| Mutex::new(ErrorHandler::new()));
 
-pub fn render_error(f: &mut Frame, area: Rect) {
-    if let Some(error_message) = get_error() {
-        let error_paragraph = Paragraph::new(error_message).style(Style::default().fg(Color::Red));
-        f.render_widget(error_paragraph, area);
-    }
-}
-
 pub fn set_error(message: String) {
     ERROR_HANDLER.lock().unwrap().set_error(message);
 }
@@ -49,3 +54,11 @@ pub fn clear_error() {
 pub fn get_error() -> Option<String> {
     ERROR_HANDLER.lock().unwrap().get_error().cloned()
 }
+
+pub fn add_message(message: String) {
+    ERROR_HANDLER.lock().unwrap().add_to_history(message);
+}
+
+pub fn get_message_history() -> Vec<String> {
+    ERROR_HANDLER.lock().unwrap().get_history().to_vec()
+}
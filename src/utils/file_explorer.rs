@@ -1,18 +1,132 @@
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    crossterm::event::KeyCode,
+    layout::{Constraint, Direction, Layout, Margin, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
 use std::{
     cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashSet},
     fs::{self, File},
 };
 
-use crate::utils::error_handler::{clear_error, set_error};
+use crate::ui::{preview::highlighted_preview, render::marked_spans};
+use crate::utils::error_handler::{clear_error, get_error, set_error};
+use crate::utils::fuzzy::find_ignore_case;
+use crate::utils::media;
+use crate::utils::progress::Progress;
+use glob::{MatchOptions, Pattern};
+use unicode_width::UnicodeWidthStr;
+
+const PREVIEW_BYTES: usize = 64 * 1024;
+// Ancestors shown in the breadcrumbs, numbered for the keys 1 to 9
+const MAX_BREADCRUMBS: usize = 9;
+// Names of the permission bits, from the owner's read bit down
+const PERMISSION_BITS: [char; 9] = ['r', 'w', 'x', 'r', 'w', 'x', 'r', 'w', 'x'];
+
+// Files larger than this are left out of content searches
+const MAX_SEARCHED_BYTES: u64 = 4 * 1024 * 1024;
+// A content search stops once it has found this many lines
+const MAX_CONTENT_MATCHES: usize = 10_000;
+// Longest part of a matching line shown in the list
+const SNIPPET_CHARS: usize = 120;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SearchScope {
+    // Names in the directory shown
+    Directory,
+    // Paths anywhere under the directory shown
+    Tree,
+    // The text of files under the directory shown
+    Contents,
+}
+
+impl SearchScope {
+    fn label(self) -> &'static str {
+        match self {
+            SearchScope::Directory => "Filename",
+            SearchScope::Tree => "Global",
+            SearchScope::Contents => "Contents",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SearchQuery {
+    text: String,
+    case_sensitive: bool,
+    glob: Option<Pattern>,
+}
+
+impl SearchQuery {
+    fn new(text: &str, case_sensitive: bool) -> Self {
+        let glob = text
+            .contains(['*', '?', '['])
+            .then(|| Pattern::new(text).ok())
+            .flatten();
+        SearchQuery {
+            text: text.to_string(),
+            case_sensitive,
+            glob,
+        }
+    }
+
+    fn is_glob(&self) -> bool {
+        self.glob.is_some()
+    }
+
+    fn glob_matches(&self, text: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: self.case_sensitive,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        self.glob
+            .as_ref()
+            .is_some_and(|glob| glob.matches_with(text, options))
+    }
+
+    fn contains(&self, haystack: &str) -> bool {
+        if self.case_sensitive {
+            haystack.contains(&self.text)
+        } else {
+            haystack.to_lowercase().contains(&self.text.to_lowercase())
+        }
+    }
+
+    fn matches_path(&self, root: &Path, path: &Path) -> bool {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !self.is_glob() {
+            return self.contains(&path.to_string_lossy());
+        }
+        if self.text.contains('/') {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            self.glob_matches(&relative.to_string_lossy().replace('\\', "/"))
+        } else {
+            self.glob_matches(&name)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct SearchMatch {
+    path: PathBuf,
+    // Counted from 0, with the line's text
+    line: Option<(usize, String)>,
+}
+
+struct ChmodDialog {
+    path: PathBuf,
+    mode: u32,
+    // The bit being edited, counted from the owner's read bit
+    bit: usize,
+}
 
 pub struct FileExplorer {
     starting_path: PathBuf,
@@ -20,10 +134,36 @@ pub struct FileExplorer {
     entries: Vec<PathBuf>,
     list_state: ListState,
     preview_content: String,
+    // Lines of the preview scrolled past with the mouse wheel
+    preview_scroll: u16,
+    // Where the preview was last drawn, to tell when the mouse is over it
+    preview_area: Rect,
+    // The selected image, when the terminal can draw it in the preview
+    preview_image: Option<PathBuf>,
+    // Where each breadcrumb was last drawn, for clicks
+    breadcrumb_spots: Vec<(Rect, PathBuf)>,
+    // Entries marked for a batch operation, kept across directories and
+    // searches
+    marked: BTreeSet<PathBuf>,
+    // Set while asking to confirm deleting the marked entries
+    confirming_delete: bool,
+    // Open while editing the permissions of an entry
+    chmod: Option<ChmodDialog>,
     pub open: bool,
     search_query: String,
     search_mode: bool,
-    global_search: bool,
+    search_scope: SearchScope,
+    // Set to tell upper and lower case apart in searches
+    case_sensitive: bool,
+    // Matches streamed in by a global search still walking the tree;
+    // dropping it stops the search
+    search_results: Option<Receiver<SearchMatch>>,
+    // Global search matches by the directory they are in; each directory
+    // is a header row in the list, followed by its matches unless collapsed
+    search_groups: BTreeMap<PathBuf, Vec<SearchMatch>>,
+    // The matching line for each row of `entries` in a content search
+    entry_lines: Vec<Option<(usize, String)>>,
+    collapsed_groups: HashSet<PathBuf>,
 }
 
 impl FileExplorer {
@@ -34,10 +174,22 @@ impl FileExplorer {
             entries: Vec::new(),
             list_state: ListState::default(),
             preview_content: String::new(),
+            preview_scroll: 0,
+            preview_area: Rect::default(),
+            preview_image: None,
+            breadcrumb_spots: Vec::new(),
+            marked: BTreeSet::new(),
+            confirming_delete: false,
+            chmod: None,
             open: false,
             search_query: String::new(),
             search_mode: false,
-            global_search: false,
+            search_scope: SearchScope::Directory,
+            case_sensitive: false,
+            search_results: None,
+            search_groups: BTreeMap::new(),
+            collapsed_groups: HashSet::new(),
+            entry_lines: Vec::new(),
         };
         explorer.refresh_entries()?;
         Ok(explorer)
@@ -63,6 +215,22 @@ impl FileExplorer {
         Ok(())
     }
 
+    pub fn reveal(&mut self, file: &Path) -> io::Result<()> {
+        let file = fs::canonicalize(file)?;
+        let directory = file
+            .parent()
+            .ok_or_else(|| io::Error::other("No directory to show"))?;
+        self.search_mode = false;
+        self.search_query.clear();
+        self.set_current_directory(directory.to_path_buf())?;
+        if let Some(index) = self.entries.iter().position(|entry| *entry == file) {
+            self.list_state.select(Some(index));
+            self.update_preview()?;
+        }
+        self.open = true;
+        Ok(())
+    }
+
     pub fn clear_error_message(&mut self) {
         clear_error();
     }
@@ -71,22 +239,105 @@ impl FileExplorer {
         set_error(message.to_string());
     }
 
-    fn get_relative_path(&self) -> String {
-        match self.current_path.strip_prefix(&self.starting_path) {
-            Ok(rel_path) => rel_path.display().to_string(),
-            Err(_) => self.current_path.display().to_string(),
+    fn breadcrumbs(&self) -> (Vec<(String, PathBuf)>, bool) {
+        let mut crumbs: Vec<(String, PathBuf)> = self
+            .current_path
+            .ancestors()
+            .map(|path| {
+                let name = path.file_name().map_or_else(
+                    || path.display().to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                );
+                (name, path.to_path_buf())
+            })
+            .take(MAX_BREADCRUMBS + 1)
+            .collect();
+        let truncated = crumbs.len() > MAX_BREADCRUMBS;
+        crumbs.truncate(MAX_BREADCRUMBS);
+        crumbs.reverse();
+        (crumbs, truncated)
+    }
+
+    pub fn go_to_breadcrumb(&mut self, number: usize) -> io::Result<()> {
+        let (crumbs, _) = self.breadcrumbs();
+        match number.checked_sub(1).and_then(|index| crumbs.get(index)) {
+            Some((_, path)) => self.set_current_directory(path.clone()),
+            None => Ok(()),
         }
     }
 
+    pub fn click_breadcrumb(&mut self, position: Position) -> io::Result<bool> {
+        let Some((_, path)) = self
+            .breadcrumb_spots
+            .iter()
+            .find(|(spot, _)| spot.contains(position))
+        else {
+            return Ok(false);
+        };
+        self.set_current_directory(path.clone())?;
+        Ok(true)
+    }
+
+    fn render_breadcrumbs(&mut self, f: &mut Frame, area: Rect) {
+        let (crumbs, truncated) = self.breadcrumbs();
+        let number_style = Style::default().fg(Color::DarkGray);
+        let mut spans = Vec::new();
+        if truncated {
+            spans.push(Span::styled("… / ", number_style));
+        }
+        self.breadcrumb_spots.clear();
+        let mut x = area.x + 1 + if truncated { 4 } else { 0 };
+        let last = crumbs.len().saturating_sub(1);
+        for (index, (name, path)) in crumbs.into_iter().enumerate() {
+            let label = format!("{} ", index + 1);
+            let width = (label.width() + name.width()) as u16;
+            self.breadcrumb_spots
+                .push((Rect::new(x, area.y + 1, width, 1), path));
+            x += width;
+            spans.push(Span::styled(label, number_style));
+            let name_style = if index == last {
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Green)
+            };
+            spans.push(Span::styled(name, name_style));
+            if index != last {
+                spans.push(Span::raw(" / "));
+                x += 3;
+            }
+        }
+        let breadcrumbs = Paragraph::new(Line::from(spans)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Current Path (1-9 or click to go up)"),
+        );
+        f.render_widget(breadcrumbs, area);
+    }
+
     pub fn is_in_search_mode(&self) -> bool {
         self.search_mode
     }
 
-    pub fn toggle_global_search(&mut self) -> io::Result<()> {
-        self.global_search = !self.global_search;
+    pub fn cycle_search_scope(&mut self) -> io::Result<()> {
+        self.search_scope = match self.search_scope {
+            SearchScope::Directory => SearchScope::Tree,
+            SearchScope::Tree => SearchScope::Contents,
+            SearchScope::Contents => SearchScope::Directory,
+        };
         self.update_search()
     }
 
+    pub fn toggle_case_sensitive(&mut self) -> io::Result<()> {
+        self.case_sensitive = !self.case_sensitive;
+        self.update_search()
+    }
+
+    fn search_query(&self) -> SearchQuery {
+        SearchQuery::new(&self.search_query, self.case_sensitive)
+    }
+
     pub fn handle_search_input(&mut self, c: char) -> io::Result<()> {
         if !self.search_mode {
             self.search_mode = true;
@@ -108,7 +359,7 @@ impl FileExplorer {
     pub fn clear_search(&mut self) -> io::Result<()> {
         self.search_mode = false;
         self.search_query.clear();
-        self.global_search = false;
+        self.search_scope = SearchScope::Directory;
         self.refresh_entries()
     }
 
@@ -116,7 +367,7 @@ impl FileExplorer {
         self.preview_content.clear();
         if self.search_query.is_empty() {
             self.refresh_entries()
-        } else if self.global_search {
+        } else if self.search_scope != SearchScope::Directory {
             self.perform_global_search()
         } else {
             self.perform_filename_search()
@@ -124,46 +375,178 @@ impl FileExplorer {
     }
 
     fn perform_global_search(&mut self) -> io::Result<()> {
-        let search_results = self.search_recursive(&self.current_path, &self.search_query)?;
-        self.entries = search_results;
+        let (sender, receiver) = mpsc::channel();
+        let root = self.current_path.clone();
+        let query = self.search_query();
+        let contents = self.search_scope == SearchScope::Contents;
+        thread::spawn(move || {
+            let progress = Progress::start(format!("Searching for {}", query.text));
+            let mut scanned = 0;
+            let mut found = 0;
+            // Symlinked directories are followed, but each real directory is
+            // walked once so a link loop cannot trap the search
+            let mut visited = HashSet::new();
+            visited.insert(root.clone());
+            let mut directories = vec![root.clone()];
+            while let Some(dir) = directories.pop() {
+                if progress.is_cancelled() {
+                    return;
+                }
+                let Ok(entries) = fs::read_dir(&dir) else {
+                    continue;
+                };
+                for entry in entries.filter_map(|entry| entry.ok()) {
+                    let path = entry.path();
+                    scanned += 1;
+                    if path.is_dir() {
+                        if fs::canonicalize(&path).is_ok_and(|real| visited.insert(real)) {
+                            directories.push(path);
+                        }
+                    } else if contents {
+                        for (line, text) in matching_lines(&path, &query) {
+                            let found_match = SearchMatch {
+                                path: path.clone(),
+                                line: Some((line, text)),
+                            };
+                            found += 1;
+                            // Stop when the search was replaced or closed
+                            if sender.send(found_match).is_err() || found >= MAX_CONTENT_MATCHES {
+                                return;
+                            }
+                        }
+                    } else if query.matches_path(&root, &path)
+                        && sender.send(SearchMatch { path, line: None }).is_err()
+                    {
+                        // The search was replaced or closed
+                        return;
+                    }
+                }
+                progress.set_count(scanned);
+            }
+        });
+        self.entries.clear();
+        self.entry_lines.clear();
+        self.search_groups.clear();
+        self.collapsed_groups.clear();
         self.list_state.select(Some(0));
+        self.search_results = Some(receiver);
         Ok(())
     }
 
+    pub fn poll_search(&mut self) -> bool {
+        let Some(receiver) = &self.search_results else {
+            return false;
+        };
+        let was_empty = self.entries.is_empty();
+        let mut changed = false;
+        loop {
+            match receiver.try_recv() {
+                Ok(found) => {
+                    let directory = found.path.parent().unwrap_or(Path::new("")).to_path_buf();
+                    self.search_groups.entry(directory).or_default().push(found);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.search_results = None;
+                    break;
+                }
+            }
+        }
+        if changed {
+            self.rebuild_search_rows();
+        }
+        if was_empty && changed {
+            let _ = self.update_preview();
+        }
+        changed
+    }
+
+    fn rebuild_search_rows(&mut self) {
+        let selected = self.list_state.selected().and_then(|index| {
+            Some((
+                self.entries.get(index)?.clone(),
+                self.entry_lines.get(index).cloned().flatten(),
+            ))
+        });
+        self.entries.clear();
+        self.entry_lines.clear();
+        for (directory, matches) in &mut self.search_groups {
+            self.entries.push(directory.clone());
+            self.entry_lines.push(None);
+            if !self.collapsed_groups.contains(directory) {
+                matches.sort_by(|a, b| {
+                    (&a.path, a.line.as_ref().map(|(line, _)| *line))
+                        .cmp(&(&b.path, b.line.as_ref().map(|(line, _)| *line)))
+                });
+                for found in matches.iter() {
+                    self.entries.push(found.path.clone());
+                    self.entry_lines.push(found.line.clone());
+                }
+            }
+        }
+        let index = selected
+            .and_then(|(path, line)| {
+                (0..self.entries.len())
+                    .position(|row| self.entries[row] == path && self.entry_lines[row] == line)
+            })
+            .unwrap_or(0);
+        self.list_state.select(Some(index));
+    }
+
+    pub fn selected_match_line(&self) -> Option<usize> {
+        let index = self.list_state.selected()?;
+        self.entry_lines.get(index)?.as_ref().map(|(line, _)| *line)
+    }
+
+    fn is_group_header(&self, path: &Path) -> bool {
+        self.search_groups.contains_key(path)
+    }
+
+    fn toggle_group(&mut self) -> io::Result<bool> {
+        let Some(directory) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+            .filter(|path| self.is_group_header(path))
+            .cloned()
+        else {
+            return Ok(false);
+        };
+        if !self.collapsed_groups.remove(&directory) {
+            self.collapsed_groups.insert(directory);
+        }
+        self.rebuild_search_rows();
+        self.update_preview()?;
+        Ok(true)
+    }
+
     fn perform_filename_search(&mut self) -> io::Result<()> {
+        self.search_results = None;
+        self.search_groups.clear();
+        self.entry_lines.clear();
+        let query = self.search_query();
         self.entries = fs::read_dir(&self.current_path)?
             .filter_map(|entry| entry.ok())
             .map(|entry| entry.path())
             .filter(|path| {
-                path.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| n.to_lowercase().contains(&self.search_query.to_lowercase()))
-                    .unwrap_or(false)
+                // Names only, as everything listed is in this directory
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if query.is_glob() {
+                    query.glob_matches(&name)
+                } else {
+                    query.contains(&name)
+                }
             })
             .collect();
         self.list_state.select(Some(0));
         Ok(())
     }
 
-    fn search_recursive(&self, dir: &Path, query: &str) -> io::Result<Vec<PathBuf>> {
-        let mut results = Vec::new();
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                results.extend(self.search_recursive(&path, query)?);
-            } else if path
-                .to_str()
-                .map(|s| s.to_lowercase().contains(&query.to_lowercase()))
-                .unwrap_or(false)
-            {
-                results.push(path);
-            }
-        }
-        Ok(results)
-    }
-
     fn refresh_entries(&mut self) -> io::Result<()> {
+        self.search_results = None;
+        self.search_groups.clear();
+        self.entry_lines.clear();
         self.entries.clear();
         for entry in fs::read_dir(&self.current_path)? {
             let entry = entry?;
@@ -207,6 +590,9 @@ impl FileExplorer {
     }
 
     pub fn enter_directory(&mut self) -> io::Result<Option<PathBuf>> {
+        if self.toggle_group()? {
+            return Ok(None);
+        }
         let selected_path = self
             .list_state
             .selected()
@@ -227,6 +613,233 @@ impl FileExplorer {
         }
     }
 
+    pub fn follow_link(&mut self) -> io::Result<()> {
+        let Some(path) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+            .filter(|path| path.is_symlink())
+        else {
+            set_error("Not a symlink".to_string());
+            return Ok(());
+        };
+        let target = fs::canonicalize(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("{} is a broken link: {}", path.display(), e),
+            )
+        })?;
+        self.reveal(&target)
+    }
+
+    pub fn toggle_mark(&mut self) -> io::Result<()> {
+        let Some(path) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+            .filter(|path| **path != self.current_path.join("..") && !self.is_group_header(path))
+        else {
+            return Ok(());
+        };
+        if !self.marked.remove(path) {
+            self.marked.insert(path.clone());
+        }
+        self.move_selection(1)
+    }
+
+    pub fn has_marks(&self) -> bool {
+        !self.marked.is_empty()
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    pub fn take_marked_files(&mut self) -> Vec<PathBuf> {
+        let files: Vec<PathBuf> = self
+            .marked
+            .iter()
+            .filter(|path| path.is_file())
+            .cloned()
+            .collect();
+        for file in &files {
+            self.marked.remove(file);
+        }
+        files
+    }
+
+    pub fn is_confirming_delete(&self) -> bool {
+        self.confirming_delete
+    }
+
+    pub fn request_delete(&mut self) {
+        self.confirming_delete = true;
+        set_error(format!(
+            "Delete {} marked entries? (y/n)",
+            self.marked.len()
+        ));
+    }
+
+    pub fn confirm_delete(&mut self, confirmed: bool) -> io::Result<()> {
+        self.confirming_delete = false;
+        if !confirmed {
+            set_error("Delete cancelled".to_string());
+            return Ok(());
+        }
+        let marked = std::mem::take(&mut self.marked);
+        let (done, failed) = batch(&marked, |path| {
+            if path.is_dir() && !path.is_symlink() {
+                fs::remove_dir_all(path)
+            } else {
+                fs::remove_file(path)
+            }
+        });
+        self.marked = failed;
+        self.report_batch("Deleted", done)
+    }
+
+    pub fn move_marked_here(&mut self) -> io::Result<()> {
+        let directory = self.current_path.clone();
+        let marked = std::mem::take(&mut self.marked);
+        let (done, failed) = batch(&marked, |path| {
+            let target = target_in(&directory, path)?;
+            // Renaming fails across filesystems; copy and remove there
+            fs::rename(path, &target).or_else(|_| {
+                copy_recursive(path, &target)?;
+                if path.is_dir() {
+                    fs::remove_dir_all(path)
+                } else {
+                    fs::remove_file(path)
+                }
+            })
+        });
+        self.marked = failed;
+        self.report_batch("Moved", done)
+    }
+
+    pub fn copy_marked_here(&mut self) -> io::Result<()> {
+        let directory = self.current_path.clone();
+        let (done, _) = batch(&self.marked, |path| {
+            copy_recursive(path, &target_in(&directory, path)?)
+        });
+        self.report_batch("Copied", done)
+    }
+
+    fn report_batch(&mut self, verb: &str, done: usize) -> io::Result<()> {
+        if get_error().is_none() {
+            set_error(format!("{} {} entries", verb, done));
+        }
+        self.refresh_entries()
+    }
+
+    pub fn is_editing_permissions(&self) -> bool {
+        self.chmod.is_some()
+    }
+
+    pub fn edit_permissions(&mut self) -> io::Result<()> {
+        let Some(path) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+            .filter(|path| **path != self.current_path.join(".."))
+        else {
+            return Ok(());
+        };
+        let mode = permission_bits(&fs::metadata(path)?)
+            .ok_or_else(|| io::Error::other("Permissions cannot be changed on this platform"))?;
+        self.chmod = Some(ChmodDialog {
+            path: path.clone(),
+            mode,
+            bit: 0,
+        });
+        Ok(())
+    }
+
+    pub fn handle_chmod_key(&mut self, code: KeyCode) -> io::Result<()> {
+        let Some(dialog) = self.chmod.as_mut() else {
+            return Ok(());
+        };
+        match code {
+            KeyCode::Left | KeyCode::Char('h') => dialog.bit = dialog.bit.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => dialog.bit = (dialog.bit + 1).min(8),
+            KeyCode::Char(' ') => dialog.mode ^= 0o400 >> dialog.bit,
+            KeyCode::Char('x') => dialog.mode |= (dialog.mode & 0o444) >> 2,
+            KeyCode::Enter => {
+                let Some(dialog) = self.chmod.take() else {
+                    return Ok(());
+                };
+                set_permission_bits(&dialog.path, dialog.mode)?;
+                set_error(format!(
+                    "{} is now {}",
+                    dialog.path.display(),
+                    mode_string(dialog.mode)
+                ));
+                self.update_preview()?;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.chmod = None,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn render_chmod(&self, f: &mut Frame, area: Rect) {
+        let Some(dialog) = &self.chmod else {
+            return;
+        };
+        let hint = Style::default().fg(Color::DarkGray);
+        let mut bits = Vec::new();
+        for (index, name) in PERMISSION_BITS.iter().enumerate() {
+            if index > 0 && index % 3 == 0 {
+                bits.push(Span::raw(" "));
+            }
+            let set = dialog.mode & (0o400 >> index) != 0;
+            let mut style = if set {
+                Style::default().fg(Color::Green)
+            } else {
+                hint
+            };
+            if index == dialog.bit {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            bits.push(Span::styled(
+                if set { *name } else { '-' }.to_string(),
+                style,
+            ));
+        }
+        let name = dialog
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let lines = vec![
+            Line::styled("owner group other", hint),
+            Line::from(bits),
+            Line::default(),
+            Line::styled(
+                "←→ pick, Space toggle, x executable, Enter apply, Esc cancel",
+                hint,
+            ),
+        ];
+        let width = (lines[3].width() as u16 + 4).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let dialog_area = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        f.render_widget(Clear, dialog_area);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Permissions of {}", name)),
+            ),
+            dialog_area,
+        );
+    }
+
     pub fn enter_search_mode(&mut self) {
         self.search_mode = true;
         self.search_query.clear();
@@ -245,8 +858,21 @@ impl FileExplorer {
         Ok(())
     }
 
+    pub fn scroll_preview_at(&mut self, position: Position, lines: isize) {
+        if !self.preview_area.contains(position) {
+            return;
+        }
+        let max = self.preview_content.lines().count().saturating_sub(1) as u16;
+        self.preview_scroll = self
+            .preview_scroll
+            .saturating_add_signed(lines as i16)
+            .min(max);
+    }
+
     fn update_preview(&mut self) -> io::Result<()> {
         self.preview_content.clear();
+        self.preview_scroll = 0;
+        self.preview_image = None;
 
         if let Some(selected_index) = self.list_state.selected() {
             if let Some(selected_path) = self.entries.get(selected_index) {
@@ -260,6 +886,13 @@ impl FileExplorer {
                         Ok(content) => self.preview_content = content,
                         Err(e) => self.preview_content = format!("Error reading file: {}", e),
                     }
+                    if media::graphics_supported() && media::is_image(selected_path) {
+                        self.preview_image = Some(selected_path.clone());
+                    }
+                    // Open a content match with some lines above it in view
+                    if let Some(line) = self.selected_match_line() {
+                        self.preview_scroll = line.saturating_sub(3).min(u16::MAX as usize) as u16;
+                    }
                 }
             }
         }
@@ -293,17 +926,26 @@ impl FileExplorer {
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
 
-        if buffer.len() > 1024 {
-            buffer.truncate(1024);
+        // Enough to scroll through with the mouse wheel
+        if buffer.len() > PREVIEW_BYTES {
+            buffer.truncate(PREVIEW_BYTES);
         }
+        let head: Vec<u8> = buffer.iter().take(32).copied().collect();
 
         match String::from_utf8(buffer) {
             Ok(content) => Ok(content),
-            Err(_) => Ok(format!("Binary file: {} bytes", file.metadata()?.len())),
+            // The cut may have split a character in two
+            Err(e) if e.utf8_error().error_len().is_none() => {
+                let valid = e.utf8_error().valid_up_to();
+                let mut bytes = e.into_bytes();
+                bytes.truncate(valid);
+                Ok(String::from_utf8(bytes).unwrap_or_default())
+            }
+            Err(_) => Ok(media::describe(path, &head, file.metadata()?.len())),
         }
     }
 
-    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+    pub fn render(&mut self, f: &mut Frame, area: Rect, open_files: &[(PathBuf, bool)]) {
         let main_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -340,12 +982,7 @@ impl FileExplorer {
             ])
             .split(centered_area);
 
-        // Render path
-        let relative_path = self.get_relative_path();
-        let path_display = Paragraph::new(relative_path)
-            .style(Style::default().fg(Color::Green))
-            .block(Block::default().borders(Borders::ALL).title("Current Path"));
-        f.render_widget(path_display, explorer_area[0]);
+        self.render_breadcrumbs(f, explorer_area[0]);
 
         let main_area = explorer_area[1];
         let chunks = Layout::default()
@@ -353,27 +990,33 @@ impl FileExplorer {
             .constraints([Constraint::Percentage(45), Constraint::Percentage(55)].as_ref())
             .split(main_area);
 
-        self.render_file_list(f, chunks[0]);
+        self.render_file_list(f, chunks[0], open_files);
         self.render_preview(f, chunks[1]);
+        self.render_chmod(f, main_area);
 
         // Render search bar
-        let search_mode = if self.global_search {
-            "Global"
+        let mut search_mode = self.search_scope.label().to_string();
+        if self.case_sensitive {
+            search_mode.push_str(", Match Case");
+        }
+        if self.search_query().is_glob() && self.search_scope != SearchScope::Contents {
+            search_mode.push_str(", Glob");
+        }
+        let search_bar = Paragraph::new(format!(
+            "Search ({search_mode}, Alt-c case): {}",
+            self.search_query
+        ))
+        .style(Style::default().fg(if self.is_in_search_mode() {
+            Color::Yellow
         } else {
-            "Filename"
-        };
-        let search_bar = Paragraph::new(format!("Search ({search_mode}): {}", self.search_query))
-            .style(Style::default().fg(if self.is_in_search_mode() {
-                Color::Yellow
-            } else {
-                Color::White
-            }))
-            .block(Block::default().borders(Borders::ALL));
+            Color::White
+        }))
+        .block(Block::default().borders(Borders::ALL));
         f.render_widget(search_bar, explorer_area[2]);
 
         // Render instruction bar
         let instructions =
-    " / - Search | ESC - Exit | ↑↓ - Navigate | ENTER - Select | G - Toggle Global Search | BACKSPACE - Previous Directory";
+    " / - Search | ESC - Exit | ↑↓ - Navigate | ENTER - Select | G - Search Names/Paths/Contents | BACKSPACE - Previous Directory | SPACE/TAB - Mark";
         let instruction_bar = Paragraph::new(instructions)
             .style(
                 Style::default()
@@ -398,12 +1041,16 @@ impl FileExplorer {
         }
     }
 
-    fn render_file_list(&mut self, f: &mut Frame, area: Rect) {
+    fn render_file_list(&mut self, f: &mut Frame, area: Rect, open_files: &[(PathBuf, bool)]) {
         let items: Vec<ListItem> = self
             .entries
             .iter()
             .enumerate()
             .map(|(index, path)| {
+                if let Some(matches) = self.search_groups.get(path) {
+                    return self.group_header(index, path, matches.len());
+                }
+                let line = self.entry_lines.get(index).and_then(Option::as_ref);
                 let content = if path == &self.current_path.join("..") {
                     "..".to_string()
                 } else {
@@ -415,35 +1062,295 @@ impl FileExplorer {
                     if path.is_dir() {
                         name.push('/');
                     }
+                    if let Some((line, _)) = line {
+                        name.push_str(&format!(":{}", line + 1));
+                    }
                     name
                 };
-                let style = if path.is_dir() {
+                // Symlinks stand apart from what they point to, and broken
+                // ones are in red
+                let link_target = fs::read_link(path).ok();
+                let style = if link_target.is_some() && !path.exists() {
+                    Style::default().fg(Color::Red)
+                } else if link_target.is_some() {
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::ITALIC)
+                } else if path.is_dir() {
                     Style::default().fg(Color::Cyan)
                 } else {
                     Style::default()
                 };
-                let prefix = if Some(index) == self.list_state.selected() {
-                    "> "
+                let marked = self.marked.contains(path);
+                let prefix = match (Some(index) == self.list_state.selected(), marked) {
+                    (true, true) => ">*",
+                    (true, false) => "> ",
+                    (false, true) => " *",
+                    (false, false) => "  ",
+                };
+                let style = if marked {
+                    style.fg(Color::Magenta)
+                } else {
+                    style
+                };
+                // Show where the search query matched in the name
+                let positions = if self.search_query.is_empty() || line.is_some() {
+                    Vec::new()
+                } else {
+                    find_ignore_case(&self.search_query, &content).unwrap_or_default()
+                };
+                let permissions = fs::symlink_metadata(path)
+                    .ok()
+                    .and_then(|metadata| permission_bits(&metadata))
+                    .map_or_else(String::new, |mode| mode_string(mode) + " ");
+                // Matches sit indented under their directory's header
+                let indent = if self.search_groups.is_empty() {
+                    ""
                 } else {
                     "  "
                 };
-                ListItem::new(Line::from(vec![
+                let mut spans = vec![
                     Span::raw(prefix),
-                    Span::styled(content, style),
-                ]))
+                    Span::raw(indent),
+                    Span::styled(permissions, Style::default().fg(Color::DarkGray)),
+                ];
+                spans.extend(marked_spans(
+                    &content,
+                    &positions,
+                    style,
+                    style
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                ));
+                if let Some((_, text)) = line {
+                    let positions = find_ignore_case(&self.search_query, text).unwrap_or_default();
+                    let hint = Style::default().fg(Color::DarkGray);
+                    spans.push(Span::raw("  "));
+                    spans.extend(marked_spans(
+                        text,
+                        &positions,
+                        hint,
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD),
+                    ));
+                }
+                if let Some(target) = link_target {
+                    spans.push(Span::styled(
+                        format!(" -> {}", target.display()),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                // A directory is dirty when any file under it is
+                let is_dir = path.is_dir();
+                let open = !is_dir && open_files.iter().any(|(open, _)| open == path);
+                let modified = open_files.iter().any(|(open, modified)| {
+                    *modified
+                        && if is_dir {
+                            open.starts_with(path)
+                        } else {
+                            open == path
+                        }
+                });
+                if open {
+                    spans.push(Span::styled(" ●", Style::default().fg(Color::Green)));
+                }
+                if modified {
+                    spans.push(Span::styled(" [+]", Style::default().fg(Color::Yellow)));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
+        let title = if !self.search_groups.is_empty() {
+            let count: usize = self.search_groups.values().map(Vec::len).sum();
+            format!(
+                "{} matches in {} directories (Enter folds a directory)",
+                count,
+                self.search_groups.len()
+            )
+        } else if self.marked.is_empty() {
+            "Files (● open, [+] unsaved, p permissions, L follow link)".to_string()
+        } else {
+            format!(
+                "Files ({} marked: d delete, m move here, c copy here, o open, u unmark)",
+                self.marked.len()
+            )
+        };
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Files"))
+            .block(Block::default().borders(Borders::ALL).title(title))
             .highlight_style(Style::default().fg(Color::Yellow));
 
         f.render_stateful_widget(list, area, &mut self.list_state);
     }
 
-    fn render_preview(&self, f: &mut Frame, area: Rect) {
-        let preview = Paragraph::new(self.preview_content.as_str())
+    fn group_header(&self, index: usize, directory: &Path, count: usize) -> ListItem<'static> {
+        let name = match directory.strip_prefix(&self.current_path) {
+            Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+            Ok(relative) => relative.display().to_string(),
+            Err(_) => directory.display().to_string(),
+        };
+        let fold = if self.collapsed_groups.contains(directory) {
+            "▸"
+        } else {
+            "▾"
+        };
+        let selected = if Some(index) == self.list_state.selected() {
+            "> "
+        } else {
+            "  "
+        };
+        ListItem::new(Line::from(vec![
+            Span::raw(selected),
+            Span::styled(
+                format!("{} {}/", fold, name),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!(" ({})", count),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]))
+    }
+
+    fn render_preview(&mut self, f: &mut Frame, area: Rect) {
+        self.preview_area = area;
+        let file = self
+            .list_state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+            .filter(|path| path.is_file());
+        let text = match file {
+            Some(path) => Text::from(highlighted_preview(
+                path,
+                &self.preview_content,
+                self.selected_match_line(),
+                usize::MAX,
+            )),
+            None => Text::raw(self.preview_content.as_str()),
+        };
+        let preview = Paragraph::new(text)
+            .scroll((self.preview_scroll, 0))
             .block(Block::default().borders(Borders::ALL).title("Preview"));
         f.render_widget(preview, area);
     }
+
+    pub fn image_preview(&self) -> Option<(PathBuf, Rect)> {
+        let path = self.preview_image.clone().filter(|_| self.open)?;
+        let inner = self.preview_area.inner(Margin::new(1, 1));
+        let skip = self.preview_content.lines().count() as u16 + 1;
+        let area = Rect {
+            y: inner.y + skip,
+            height: inner.height.checked_sub(skip)?,
+            ..inner
+        };
+        (!area.is_empty()).then_some((path, area))
+    }
+}
+
+fn batch(
+    paths: &BTreeSet<PathBuf>,
+    mut operation: impl FnMut(&Path) -> io::Result<()>,
+) -> (usize, BTreeSet<PathBuf>) {
+    let mut failed = BTreeSet::new();
+    for path in paths {
+        if let Err(e) = operation(path) {
+            if failed.is_empty() {
+                set_error(format!("Failed on {}: {}", path.display(), e));
+            }
+            failed.insert(path.clone());
+        }
+    }
+    (paths.len() - failed.len(), failed)
+}
+
+fn target_in(directory: &Path, path: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other("No file name"))?;
+    let target = directory.join(name);
+    if target.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    if directory.starts_with(path) {
+        return Err(io::Error::other(
+            "Cannot copy or move a directory into itself",
+        ));
+    }
+    Ok(target)
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() || from.is_symlink() {
+        return fs::copy(from, to).map(|_| ());
+    }
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+fn matching_lines(path: &Path, query: &SearchQuery) -> Vec<(usize, String)> {
+    if fs::metadata(path).map_or(true, |metadata| metadata.len() > MAX_SEARCHED_BYTES) {
+        return Vec::new();
+    }
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    if text.contains('\0') {
+        return Vec::new();
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| query.contains(line))
+        .map(|(index, line)| (index, line.trim().chars().take(SNIPPET_CHARS).collect()))
+        .collect()
+}
+
+#[cfg(unix)]
+fn permission_bits(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn permission_bits(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_permission_bits(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    // Keep setuid, setgid and sticky bits as they were
+    permissions.set_mode(permissions.mode() & !0o777 | mode);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn set_permission_bits(_path: &Path, _mode: u32) -> io::Result<()> {
+    Err(io::Error::other(
+        "Permissions cannot be changed on this platform",
+    ))
+}
+
+fn mode_string(mode: u32) -> String {
+    PERMISSION_BITS
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            if mode & (0o400 >> index) != 0 {
+                *name
+            } else {
+                '-'
+            }
+        })
+        .collect()
 }
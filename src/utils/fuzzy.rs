@@ -0,0 +1,54 @@
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    fuzzy_match(query, candidate).map(|(score, _)| score)
+}
+
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut matched = Vec::new();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in query.chars().flat_map(char::to_lowercase) {
+        let index = (position..candidate.len())
+            .find(|&i| candidate[i].to_lowercase().eq(std::iter::once(query_char)))?;
+
+        score += 1;
+        match previous_match {
+            Some(previous) if previous + 1 == index => score += 5,
+            Some(previous) => score -= (index - previous - 1).min(5) as i64,
+            None => score -= index.min(10) as i64,
+        }
+        let word_start = index == 0 || !candidate[index - 1].is_alphanumeric();
+        if word_start {
+            score += 3;
+        }
+
+        previous_match = Some(index);
+        position = index + 1;
+        matched.push(index);
+    }
+
+    Some((score, matched))
+}
+
+pub fn find_ignore_case(query: &str, candidate: &str) -> Option<Vec<usize>> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let candidate: Vec<char> = candidate.chars().collect();
+    if query.is_empty() {
+        return Some(Vec::new());
+    }
+    (0..candidate.len())
+        .find(|&start| {
+            candidate.len() - start >= query.len()
+                && query
+                    .iter()
+                    .zip(&candidate[start..])
+                    .all(|(&q, c)| c.to_lowercase().eq(std::iter::once(q)))
+        })
+        .map(|start| (start..start + query.len()).collect())
+}
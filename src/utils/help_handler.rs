@@ -17,16 +17,243 @@ impl HelpHandler {
         topics.insert(
             "commands".to_string(),
             r#"Available commands:
-:q - Quit (if no unsaved changes)
-:q! - Force quit
-:w - Save current file
-:w <filename> - Save as <filename>
+:q - Close the window, or quit (if no unsaved changes) in the last one
+:q! - Close the window, or force quit in the last one
+:qa - Quit; with unsaved changes, lists the modified buffers to save (s / S) or
+    discard (d / D) one by one, and quits once all are settled. Esc cancels
+:qa! - Quit discarding all unsaved changes
+:w - Save current file, unless it is read-only or changed on disk since it was read
+:w! - Save current file, overwriting a read-only file or changes made on disk
+:w <filename> - Write to <filename> (names a scratch buffer)
+:saveas <filename> - Save and rename the buffer to <filename>
+:w >> <filename> - Append to <filename>
+:<range>w <filename> - Write a line range (e.g. :10,20w, :'<,'>w) to <filename>
 :wq - Save and quit
-:e <filename> - Edit <filename>
+:e <filename> - Edit <filename> (~, $VARS and globs such as src/*.rs are expanded)
+:e <url> - Fetch an http(s) URL with curl into a read-only buffer, in the
+    background with progress in the status line
+:r <filename> - Insert <filename> below the current line
+:cd [dir] - Change the working directory (home if omitted)
+:lcd [dir] - Change the working directory of the current buffer
+:pwd - Show the working directory
+:earlier [N|Ns|Nm|Nh|Nd] - Restore the buffer N changes or N seconds/minutes/... ago
+:later [N|Ns|Nm|Nh|Nd] - Move forward again through the buffer's history
 :help - Show this help message
-:set <option> - Set editor option
-:split - Split view horizontally (not implemented)
-:vsplit - Split view vertically (not implemented)"#
+Commands can be shortened to any start that names only one, e.g. :norm, :vs,
+    :ter. While typing, the line above shows the command's usage; mistakes are
+    reported with it, and unknown names with the closest commands
+:set <option> - Set editor option (e.g. :set autochdir)
+:setlocal <option> - Set autosave, colorswatch or scrollbind for the current
+    buffer or window only, shadowing :set. :setlocal <option>< drops the local value
+:set expandtab / :set shiftwidth=N - Tab in insert mode inserts N spaces
+    (4 by default); :set noexpandtab inserts a tab character
+:set autosave - Write the current buffer when the terminal loses focus. On
+    regaining focus, files changed outside the editor are reloaded
+:autocmd <event> <pattern> <action> - Run <action> on BufReadPost, BufWritePre,
+    BufWritePost, ModeChanged, CursorHold, FocusGained or FocusLost for files
+    matching <pattern>.
+    <action> is a builtin (strip_whitespace), an ex command such as :w or a
+    shell command such as !ctags -R ({file} expands to the buffer path)
+:noautocmd - Remove all hooks
+:messages - Show the message history
+:split - Split the window horizontally
+:vsplit - Split the window vertically
+:align <delimiter> - Line up the delimiter (e.g. = or |) across a range, or
+    across the lines around the cursor that contain it
+:table [format|row|column] - Realign the markdown table under the cursor, or
+    add a row below / a column right of the cursor. In insert mode Tab and
+    Shift-Tab move between cells
+:[range]checkbox - Check or uncheck the - [ ] box of the list items on the
+    line or range, giving items without one an empty box
+:[range]renumber - Number the ordered list under the cursor (or in the range)
+    1. 2. 3. again at each level of nesting. In markdown and org files
+    Enter on a list item starts the next one; on an empty item it ends the list
+:set prose - Soft wrap lines at :set wrapwidth=80 (0 for the window's width),
+    with Up and Down moving by screen rows; ( and ) go to the previous / next
+    sentence in any file, and d( or c) work to them. :set typewriter keeps the
+    cursor line centered. :autocmd BufReadPost *.md :setlocal prose turns it
+    on for markdown files only
+:[range]reflow - Re-wrap the paragraph under the cursor, or the range, to
+    :set textwidth=80, repeating comment leaders such as // and lining wrapped
+    list items up under their text. gqq does the line, gqip the paragraph and
+    gq the visual selection. :set autowrap breaks lines at textwidth as you type
+:convert - Offer the number under the cursor in hex, decimal, binary and octal,
+    and unix timestamps as ISO-8601 dates or back. Enter replaces it
+:json pretty|minify / :xml pretty|minify - Reformat the buffer, or the lines of
+    a range such as '<,'> for the visual selection
+:export html|ansi <path> - Write the buffer with syntax highlighting as a
+    standalone HTML page or as text with ANSI colors
+:task [name] - Run a task from the [[tasks]] in config.toml, showing its
+    output in a window below; without a name, list the tasks
+:dirdiff <dir> <dir> - List the files added, removed or changed between two
+    trees. Enter on a changed file shows both versions side by side, scrolling
+    together, with - and + signs on the lines only one of them has
+:git branch / :git stash - Pick a branch to check out or a stash to apply.
+    Open files it changes are reloaded; ones with unsaved changes keep their
+    edits and are listed as conflicts
+:git log % - List the commits that changed the current file in a panel. Enter
+    opens the file as it was at a commit, read-only; d shows that beside the
+    working copy with the changed lines marked
+:note [encrypt|decrypt] - Open the notes for this project, kept under the
+    config directory rather than in the repository and saved when their window
+    closes or the editor quits. :note encrypt keeps them encrypted with a
+    passphrase, asked for on opening; :note decrypt stores them as plain text
+.pyne.toml - A project config in the working directory or above it, with
+    set = ["shiftwidth=2"], exclude = ["build"] (left out of the file finder),
+    [[tasks]] and [[save_hooks]] such as a formatter. It applies over config.toml
+    once trusted: on first sight, t trusts it and i ignores it. Editing the file
+    asks again
+:zen - Toggle zen mode: no gutter or status line, with the text centered between
+    :set zenpadding=20 blank columns on each side
+:terminal - Open a shell in a new window. Ctrl-\ leaves terminal mode;
+    in normal mode j/k/PageUp/PageDown scroll back and i returns to the shell
+Click - Move the cursor; double-click selects the word, triple-click the line.
+    Clicking a line number goes to the line and clicking beside it (the sign
+    column) toggles a bookmark (:set signclick=bookmark|breakpoint|none)
+    On the status line, clicking the file name lists the buffers (:buffers),
+    the git branch lists branches to check out (:git branch) and the position
+    opens the command line to go to a line, as :42 does
+:sign place <symbol> [group] [priority] / :sign unplace / :sign clear - Mark the
+    cursor line in the sign column. Groups (error, warning, bookmark, ...) take
+    their color from [sign_colors] in config.toml
+:set mousescroll=ver:3,hor:6 - Lines and columns per mouse wheel step; Shift
+    scrolls sideways and the wheel scrolls the file explorer preview too
+:set colorswatch=css,html - Show color literals such as #ff8800 or rgb() in
+    their color for these file extensions (* for all, empty to disable)
+:set scrolloff=6 / :set sidescrolloff=6 - Lines / columns kept in view around the
+    cursor; a value such as 999 keeps the cursor centered
+:set scrollbind - Scroll this window together with other scrollbound windows
+/pattern / ?pattern - Search forward / backward for the text; n and N repeat it
+* / # - Search forward / backward for the whole word under the cursor
+:[range]s/pattern/replacement/[g][i|I] - Replace the first (g: every) match on
+    each line of the range, or the current line. i ignores case, I matches it
+    \%V in the pattern only replaces inside the last visual selection, as in
+    :'<,'>s/\%Vfoo/bar/g; after Ctrl-v, only in the block's columns. Each
+    substitution is undone in one step
+Ctrl-v - Select a block of columns in visual mode; y and d copy and delete it
+:[range]normal <keys> - Type <keys> in normal mode on each line of the range, or
+    once at the cursor, e.g. :%normal A; appends ; to every line. Keys use the
+    -s script notation (<Esc>, <CR>, <C-w>); unfinished commands are cancelled
+:set ignorecase / :set smartcase - Search, * and :s ignore case; with smartcase
+    only while the pattern has no uppercase letter
+u / Ctrl-r - Undo / redo the last change; an insert session is one change
+p - Paste the clipboard at the cursor. Pastes over :set pastepreview=10000
+    characters (0 for none) are previewed first, with invisible characters drawn
+    as in :set pastechars=tab:→,space:·,eol:↵; Enter inserts, Esc cancels
+]p / [p - Paste the clipboard as lines below / above the cursor's line,
+    re-indented to match it; :set pasteindent makes p do this when the
+    clipboard holds whole lines
+f<c> / t<c> - Go to / just before the next <c> on the line; F and T search back
+; / , - Repeat the last f, t, F or T forward / backward
+d<motion> / c<motion> - Delete / change to a motion: f t F T ; , ^ _ ( ) (e.g. df, ct))
+:bd[!] - Close the current buffer (! discards its changes); :reopen or
+    Ctrl-Shift-T reopens the last closed file where its cursor was, and
+    :closed lists recently closed files to pick one
+:set title - Show the current file and whether it is modified in the
+    terminal's window title (on by default); the old title is put back on exit
+:set bell=beep|flash - Ring the terminal bell or flash the screen on errors,
+    failed motions and searches with no match (none by default)
+:history - List the snapshots kept each time the current file was saved
+    (:set localhistory=N keeps N per file, 0 none) in the config dir;
+    choosing one compares it with the file side by side, and
+    :history restore <n> puts snapshot n back in the buffer
+:calc - Open a scratch buffer where typing = at the end of a line appends
+    its value: + - * / % ^, parentheses, pi, e, sqrt(), abs(), round(),
+    floor(), ceil(), ln(), log(), exp(), sin(), cos(), tan()
+Ctrl-r = - In insert mode, evaluate an expression and insert its value
+:[range]move up|down - Move lines past their neighbour, re-indenting them for
+    the block they land in; Alt-Up and Alt-Down do the same for the
+    cursor's line or the selection, which stays selected
+:[range]duplicate [up] - Copy lines below themselves, or above; Alt-Shift-Down
+    and Alt-Shift-Up do the same for the cursor's line or the selection
+:todo [keyword] - List the lines of the project with a TODO, FIXME, HACK or
+    XXX, or only <keyword>, and jump to the chosen one; ]t / [t go to the
+    next / previous one in the buffer. The words are highlighted; change
+    them with :set todokeywords=TODO,FIXME (empty turns this off)
+:emoji [name] - Search characters and emoji by name (or U+XXXX) and insert
+    the chosen one at the cursor; Ctrl-k opens the same picker in insert mode
+% - Go to the bracket matching the next one on the line; d% deletes to it
+dif / daf - Delete inside / around a function; cif, vif and vaf work the same
+    way, and ia / aa select an argument of a call, parameter list or tuple
+:set autopairs - Close brackets and quotes as they are typed (on by default);
+    typing the closing half steps over it and Backspace removes an empty
+    pair. Rust and Markdown files are parsed with tree-sitter so strings and
+    comments are left alone
+[autopairs] - Choose the pairs per file extension (or * for the rest) in
+    config.toml, as two chars or halves split by a space, e.g.
+    html = ["()", "\"\"", "<>", "<!-- -->"] or md = ["``", "``` ```"]
+:rename <newname> - Rename the symbol under the cursor across the project with
+    the language server; files that are not open are opened in buffers, left
+    modified for :wa, and edits across files are listed to jump through
+:codeaction - Pick one of the language server's code actions for the cursor's
+    line. Servers are set per extension under [language_servers] in
+    config.toml (rs = "rust-analyzer" by default). A server starts by itself
+    for files it handles and marks the lines it finds problems on with
+    E, W, I or H signs
+:[range]format - Format the buffer or the range with the command for the
+    file's extension under [formatters] in config.toml (the lines are piped
+    through it; {file} is the file's path), or else the language server.
+    = in visual mode formats the selected lines. The cursor keeps its line
+:set virtualtext - Show the first diagnostic of a line dimmed after its text
+    (on by default)
+:diagnostics [error|warning|info] [file] - List the diagnostics of all files
+    in a panel below, only as severe as given and in paths containing file.
+    There Enter jumps to one, s sorts by severity or file, e/w/i/a filter and
+    q closes it. The status line counts them as E:n W:n I:n H:n
+:debug [setup] - Debug with a [[debug]] setup from config.toml or .pyne.toml
+    (an adapter command, launch or attach, and its arguments). F9 or :breakpoint
+    toggles a breakpoint on the cursor line, as does clicking the sign column
+    with :set signclick=breakpoint. F5 continues (or starts the first setup),
+    F6 pauses, F10 steps over, F11 into and Shift-F11 out; Shift-F5 or
+    :debugstop ends the session. The panel below lists the stack (Enter shows
+    a frame), its variables and the console; :debugeval <expr> evaluates in
+    the selected frame and :debugpanel shows the panel again
+:reveal - Open the file explorer with the current file selected; with
+    :set explorerfollow, - always does
+- - Open the file explorer. Its preview describes binary files (type, image
+    dimensions, size) and draws images in kitty, ghostty and WezTerm
+    The path above the listing is a row of breadcrumbs: press 1-9 or click
+    one to go straight to that directory
+    Space (Tab while searching) marks entries, here or across directories and
+    search results. With entries marked: d deletes them (after y to confirm),
+    m moves and c copies them into the directory shown, o opens the marked
+    files and u unmarks all
+    Each entry shows its rwx permissions; p opens a dialog on the selected
+    one: h/l pick a bit, Space toggles it, x sets executable where readable,
+    Enter applies
+    Symlinks are shown in blue italics (red when broken) with their target;
+    L goes to the directory the selected link points into. Global search
+    follows linked directories, walking each real directory once
+    G while searching cycles between names here, paths under here and the
+    contents of files under here. Global matches are grouped under a header
+    for each directory with its match count (Enter on a header folds it);
+    content matches show file:line and the line, and open at that line.
+    Names and paths can be globs (*.rs, src/**/mod.rs); Alt-c makes the
+    search case sensitive
+^ / _ - Go to the first non-blank character; g_ to the last
+A - Append at the end of the line
+mb - Toggle a bookmark on the cursor line. Bookmarks are kept per project in
+    bookmarks.toml in the config dir; :bookmarks lists them and Enter jumps
+PageUp / PageDown - Scroll a page, taking the cursor along
+Ctrl-e / Ctrl-y - Scroll the view a line down / up, moving the cursor only to keep
+    it in view
+Home - Go to the first non-blank character, or column 0 when already there
+Ctrl-w w / Ctrl-w W - Focus the next / previous window
+Ctrl-w s / Ctrl-w v / Ctrl-w c - Split horizontally / vertically, close window
+Ctrl-w o - Close all other windows; Ctrl-w = makes all windows the same size
+Ctrl-w + / Ctrl-w - / Ctrl-w > / Ctrl-w < - Make the window taller / shorter /
+    wider / narrower by one cell
+Ctrl-Shift-P - Search and run any command or key binding. A query starting with @,
+    such as @parse, searches the symbols in the ctags tags file (ctags -R) of the
+    working directory or a parent and jumps to the chosen definition
+    A query starting with /, such as /main, searches the files of the working
+    directory, indexed in the background and kept current as files change
+Ctrl-z - Suspend the editor (resume with fg)
+Ctrl-c - Stop background searches and running tasks; Esc stops the searches
+    alone. The statusline spinner shows what is still running
+Shift-D - Toggle the debug overlay (frame times, allocations, buffer stats)
+pyne -w session.keys [file] - Record every key typed into session.keys
+pyne -s session.keys [file] - Replay the keys in session.keys, then continue"#
                 .to_string(),
         );
 
@@ -73,17 +300,3 @@ pub fn get_help_text() -> Option<String> {
 pub fn add_help_topic(topic: String, content: String) {
     HELP_HANDLER.lock().unwrap().add_topic(topic, content);
 }
-
-use ratatui::{
-    layout::Rect,
-    style::{Color, Style},
-    widgets::Paragraph,
-    Frame,
-};
-
-pub fn render_help(f: &mut Frame, area: Rect) {
-    if let Some(help_text) = get_help_text() {
-        let help_paragraph = Paragraph::new(help_text).style(Style::default().fg(Color::Yellow));
-        f.render_widget(help_paragraph, area);
-    }
-}
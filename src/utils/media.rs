@@ -0,0 +1,145 @@
+use image::ImageReader;
+use std::{env, fmt::Write, path::Path};
+
+// Signatures of common binary formats, checked when the file is not an
+// image, with the offset each starts at
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"%PDF", "PDF document"),
+    (0, b"PK\x03\x04", "ZIP archive"),
+    (0, b"\x1f\x8b", "gzip archive"),
+    (0, b"\xfd7zXZ\x00", "xz archive"),
+    (0, b"\x28\xb5\x2f\xfd", "zstd archive"),
+    (0, b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (0, b"Rar!", "RAR archive"),
+    (0, b"\x7fELF", "ELF executable"),
+    (0, b"MZ", "Windows executable"),
+    (0, b"\xcf\xfa\xed\xfe", "Mach-O executable"),
+    (0, b"\x00asm", "WebAssembly module"),
+    (0, b"SQLite format 3\x00", "SQLite database"),
+    (0, b"ID3", "MP3 audio"),
+    (0, b"fLaC", "FLAC audio"),
+    (0, b"OggS", "Ogg media"),
+    (8, b"WAVE", "WAV audio"),
+    (8, b"AVI ", "AVI video"),
+    (4, b"ftyp", "MP4 video"),
+    (0, b"\x1a\x45\xdf\xa3", "Matroska video"),
+    (0, b"wOFF", "WOFF font"),
+    (0, b"wOF2", "WOFF2 font"),
+    (0, b"\x00\x01\x00\x00", "TrueType font"),
+    (0, b"OTTO", "OpenType font"),
+];
+
+// Largest side, in pixels, of the thumbnail sent to the terminal
+const THUMBNAIL_SIZE: u32 = 480;
+// Base64 characters per kitty graphics escape sequence
+const CHUNK_SIZE: usize = 4096;
+
+// Removes every image the kitty graphics protocol has drawn.
+pub const KITTY_CLEAR: &str = "\x1b_Ga=d,d=A,q=2\x1b\\";
+
+pub fn describe(path: &Path, head: &[u8], size: u64) -> String {
+    let image = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .ok()
+        .and_then(|reader| Some((reader.format()?, reader.into_dimensions().ok()?)));
+    let kind = match image {
+        Some((format, (width, height))) => {
+            let name = format.extensions_str().first().unwrap_or(&"unknown");
+            format!(
+                "{} image, {} × {} pixels",
+                name.to_uppercase(),
+                width,
+                height
+            )
+        }
+        None => SIGNATURES
+            .iter()
+            .find(|(offset, magic, _)| {
+                head.get(*offset..)
+                    .is_some_and(|rest| rest.starts_with(magic))
+            })
+            .map_or("Binary file", |(_, _, kind)| kind)
+            .to_string(),
+    };
+    format!("{}\nSize: {}", kind, human_size(size))
+}
+
+pub fn is_image(path: &Path) -> bool {
+    ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .is_ok_and(|reader| reader.format().is_some())
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {} ({} bytes)", size, UNITS[unit], bytes)
+}
+
+pub fn graphics_supported() -> bool {
+    env::var_os("KITTY_WINDOW_ID").is_some()
+        || env::var("TERM").is_ok_and(|term| term.contains("kitty") || term.contains("ghostty"))
+        || env::var("TERM_PROGRAM").is_ok_and(|program| program == "WezTerm")
+}
+
+pub fn kitty_image(path: &Path, columns: u16, rows: u16) -> Option<String> {
+    let image = image::open(path)
+        .ok()?
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 || columns == 0 || rows == 0 {
+        return None;
+    }
+    // Cells are about twice as tall as they are wide
+    let scale = (columns as f64 / width as f64).min(rows as f64 * 2.0 / height as f64);
+    let cell_columns = ((width as f64 * scale) as u16).clamp(1, columns);
+    let cell_rows = ((height as f64 * scale / 2.0) as u16).clamp(1, rows);
+
+    let data = base64(image.as_raw());
+    let chunks: Vec<&str> = data
+        .as_bytes()
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect();
+    let mut sequence = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = (index + 1 < chunks.len()) as u8;
+        if index == 0 {
+            let _ = write!(
+                sequence,
+                "\x1b_Ga=T,f=32,s={},v={},c={},r={},q=2,m={};{}\x1b\\",
+                width, height, cell_columns, cell_rows, more, chunk
+            );
+        } else {
+            let _ = write!(sequence, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    Some(sequence)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let value = group.iter().enumerate().fold(0u32, |value, (index, byte)| {
+            value | (*byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= group.len() {
+                encoded.push(ALPHABET[(value >> (18 - 6 * index)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -1,3 +1,12 @@
+pub mod alloc_counter;
+pub mod config;
 pub mod error_handler;
+#[cfg(feature = "tui")]
 pub mod file_explorer;
+pub mod fuzzy;
 pub mod help_handler;
+#[cfg(feature = "tui")]
+pub mod media;
+pub mod path_expander;
+pub mod progress;
+pub mod unicode_names;
@@ -0,0 +1,102 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+pub fn expand_path(input: &str, base: &Path) -> Result<Vec<PathBuf>, String> {
+    let expanded = expand_env(input);
+
+    if !is_glob(&expanded) {
+        return Ok(vec![PathBuf::from(expanded)]);
+    }
+
+    let pattern = if Path::new(&expanded).is_relative() {
+        base.join(&expanded)
+    } else {
+        PathBuf::from(&expanded)
+    };
+    let pattern = pattern.to_string_lossy();
+
+    let mut matches: Vec<PathBuf> = glob::glob(&pattern)
+        .map_err(|e| format!("Invalid pattern {}: {}", expanded, e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .collect();
+    matches.sort();
+
+    if matches.is_empty() {
+        Err(format!("No files match: {}", expanded))
+    } else {
+        Ok(matches)
+    }
+}
+
+pub fn expand_single_path(input: &str, base: &Path) -> Result<PathBuf, String> {
+    let mut paths = expand_path(input, base)?;
+    if paths.len() > 1 {
+        Err(format!(
+            "Too many file names: {} matches {} files",
+            input.trim(),
+            paths.len()
+        ))
+    } else {
+        Ok(paths.remove(0))
+    }
+}
+
+pub fn expand_env(input: &str) -> String {
+    expand_variables(&expand_home(input.trim()))
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+fn expand_home(path: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => env::var("HOME")
+            .map(|home| format!("{}{}", home, rest))
+            .unwrap_or_else(|_| path.to_string()),
+        _ => path.to_string(),
+    }
+}
+
+fn expand_variables(path: &str) -> String {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+
+        let (name, remaining, literal) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (
+                    &braced[..end],
+                    &braced[end + 1..],
+                    &rest[dollar..dollar + end + 3],
+                ),
+                None => ("", after, "$"),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (
+                &after[..end],
+                &after[end..],
+                &rest[dollar..dollar + end + 1],
+            )
+        };
+
+        // Unknown variables are left untouched rather than silently dropped
+        match env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => expanded.push_str(literal),
+        }
+        rest = remaining;
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
@@ -0,0 +1,196 @@
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Operation {
+    id: usize,
+    title: String,
+    done: usize,
+    // Amount of work in all, when known up front
+    total: Option<usize>,
+    started: Instant,
+    cancelled: bool,
+    // Stops work that cannot check `is_cancelled`, such as a blocking read
+    on_cancel: Option<Box<dyn FnOnce() + Send>>,
+}
+
+pub struct ProgressRegistry {
+    operations: Vec<Operation>,
+    next_id: usize,
+}
+
+impl ProgressRegistry {
+    pub fn new() -> Self {
+        ProgressRegistry {
+            operations: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    fn start(&mut self, title: String) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.operations.push(Operation {
+            id,
+            title,
+            done: 0,
+            total: None,
+            started: Instant::now(),
+            cancelled: false,
+            on_cancel: None,
+        });
+        id
+    }
+
+    fn operation(&mut self, id: usize) -> Option<&mut Operation> {
+        self.operations
+            .iter_mut()
+            .find(|operation| operation.id == id)
+    }
+
+    fn finish(&mut self, id: usize) {
+        self.operations.retain(|operation| operation.id != id);
+    }
+
+    pub fn cancel_all(&mut self) -> Vec<String> {
+        self.operations
+            .iter_mut()
+            .filter(|operation| !operation.cancelled)
+            .map(|operation| {
+                Self::cancel_operation(operation);
+                operation.title.clone()
+            })
+            .collect()
+    }
+
+    pub fn cancel(&mut self, id: usize) {
+        if let Some(operation) = self.operation(id) {
+            Self::cancel_operation(operation);
+        }
+    }
+
+    fn cancel_operation(operation: &mut Operation) {
+        operation.cancelled = true;
+        if let Some(on_cancel) = operation.on_cancel.take() {
+            on_cancel();
+        }
+    }
+
+    fn running(&self) -> impl Iterator<Item = &Operation> {
+        self.operations
+            .iter()
+            .filter(|operation| !operation.cancelled)
+    }
+
+    pub fn status(&self) -> Option<String> {
+        let operation = self.running().last()?;
+        let frame = (operation.started.elapsed().as_millis() / SPINNER_INTERVAL.as_millis())
+            as usize
+            % SPINNER.len();
+        let amount = match operation.total {
+            Some(0) => String::new(),
+            Some(total) => format!(" {}%", operation.done.min(total) * 100 / total),
+            None if operation.done > 0 => format!(" {}", operation.done),
+            None => String::new(),
+        };
+        let others = match self.running().count() - 1 {
+            0 => String::new(),
+            n => format!(" (+{})", n),
+        };
+        Some(format!(
+            "{} {}{}{}",
+            SPINNER[frame], operation.title, amount, others
+        ))
+    }
+}
+
+impl Default for ProgressRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub static PROGRESS: Lazy<Mutex<ProgressRegistry>> =
+    Lazy::new(|| Mutex::new(ProgressRegistry::new()));
+
+pub struct Progress {
+    id: usize,
+}
+
+impl Progress {
+    pub fn start(title: impl Into<String>) -> Self {
+        Progress {
+            id: PROGRESS.lock().unwrap().start(title.into()),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn set(&self, done: usize, total: usize) {
+        if let Some(operation) = PROGRESS.lock().unwrap().operation(self.id) {
+            operation.done = done;
+            operation.total = Some(total);
+        }
+    }
+
+    pub fn set_count(&self, done: usize) {
+        if let Some(operation) = PROGRESS.lock().unwrap().operation(self.id) {
+            operation.done = done;
+        }
+    }
+
+    pub fn set_title(&self, title: impl Into<String>) {
+        if let Some(operation) = PROGRESS.lock().unwrap().operation(self.id) {
+            operation.title = title.into();
+        }
+    }
+
+    pub fn on_cancel(&self, on_cancel: impl FnOnce() + Send + 'static) {
+        let mut registry = PROGRESS.lock().unwrap();
+        match registry.operation(self.id) {
+            Some(operation) if !operation.cancelled => {
+                operation.on_cancel = Some(Box::new(on_cancel));
+            }
+            _ => {
+                drop(registry);
+                on_cancel();
+            }
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        PROGRESS
+            .lock()
+            .unwrap()
+            .operation(self.id)
+            .is_none_or(|operation| operation.cancelled)
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        PROGRESS.lock().unwrap().finish(self.id);
+    }
+}
+
+pub fn is_active() -> bool {
+    PROGRESS.lock().unwrap().running().next().is_some()
+}
+
+pub fn status() -> Option<String> {
+    PROGRESS.lock().unwrap().status()
+}
+
+pub fn cancel_all() -> Vec<String> {
+    PROGRESS.lock().unwrap().cancel_all()
+}
+
+/// Cancels the operation with `id`, if it is still running.
+pub fn cancel(id: usize) {
+    PROGRESS.lock().unwrap().cancel(id)
+}
@@ -0,0 +1,424 @@
+// Characters offered by the character picker, with the names they are
+// searched by.
+pub const UNICODE_NAMES: &[(char, &str)] = &[
+    // Punctuation and typography
+    ('—', "em dash"),
+    ('–', "en dash"),
+    ('…', "horizontal ellipsis"),
+    ('‘', "left single quotation mark"),
+    ('’', "right single quotation mark, apostrophe"),
+    ('“', "left double quotation mark"),
+    ('”', "right double quotation mark"),
+    ('«', "left-pointing double angle quotation mark, guillemet"),
+    ('»', "right-pointing double angle quotation mark, guillemet"),
+    ('‹', "single left-pointing angle quotation mark"),
+    ('›', "single right-pointing angle quotation mark"),
+    ('„', "double low-9 quotation mark"),
+    ('•', "bullet"),
+    ('·', "middle dot"),
+    ('†', "dagger"),
+    ('‡', "double dagger"),
+    ('§', "section sign"),
+    ('¶', "pilcrow sign, paragraph"),
+    ('©', "copyright sign"),
+    ('®', "registered sign"),
+    ('™', "trade mark sign"),
+    ('°', "degree sign"),
+    ('′', "prime, minutes, feet"),
+    ('″', "double prime, seconds, inches"),
+    ('‰', "per mille sign"),
+    ('¡', "inverted exclamation mark"),
+    ('¿', "inverted question mark"),
+    ('‽', "interrobang"),
+    ('\u{a0}', "no-break space, nbsp"),
+    ('\u{2009}', "thin space"),
+    ('\u{200b}', "zero width space"),
+    ('\u{ad}', "soft hyphen"),
+    ('‐', "hyphen"),
+    ('‑', "non-breaking hyphen"),
+    ('№', "numero sign"),
+    ('℃', "degree celsius"),
+    ('℉', "degree fahrenheit"),
+    ('µ', "micro sign"),
+    // Currency
+    ('€', "euro sign"),
+    ('£', "pound sign"),
+    ('¥', "yen sign"),
+    ('¢', "cent sign"),
+    ('₹', "indian rupee sign"),
+    ('₽', "ruble sign"),
+    ('₩', "won sign"),
+    ('₺', "turkish lira sign"),
+    ('₿', "bitcoin sign"),
+    ('¤', "currency sign"),
+    // Arrows
+    ('←', "leftwards arrow"),
+    ('→', "rightwards arrow"),
+    ('↑', "upwards arrow"),
+    ('↓', "downwards arrow"),
+    ('↔', "left right arrow"),
+    ('↕', "up down arrow"),
+    ('↖', "north west arrow"),
+    ('↗', "north east arrow"),
+    ('↘', "south east arrow"),
+    ('↙', "south west arrow"),
+    ('⇐', "leftwards double arrow"),
+    ('⇒', "rightwards double arrow, implies"),
+    ('⇔', "left right double arrow, if and only if"),
+    ('↵', "downwards arrow with corner leftwards, return"),
+    ('↩', "leftwards arrow with hook"),
+    ('↪', "rightwards arrow with hook"),
+    ('⟶', "long rightwards arrow"),
+    ('↦', "rightwards arrow from bar, maps to"),
+    // Mathematics
+    ('×', "multiplication sign, times"),
+    ('÷', "division sign"),
+    ('±', "plus-minus sign"),
+    ('∓', "minus-or-plus sign"),
+    ('−', "minus sign"),
+    ('≠', "not equal to"),
+    ('≈', "almost equal to"),
+    ('≡', "identical to"),
+    ('≤', "less-than or equal to"),
+    ('≥', "greater-than or equal to"),
+    ('≪', "much less-than"),
+    ('≫', "much greater-than"),
+    ('∞', "infinity"),
+    ('√', "square root"),
+    ('∛', "cube root"),
+    ('∑', "n-ary summation, sum"),
+    ('∏', "n-ary product"),
+    ('∫', "integral"),
+    ('∂', "partial differential"),
+    ('∇', "nabla"),
+    ('∆', "increment"),
+    ('∝', "proportional to"),
+    ('∈', "element of"),
+    ('∉', "not an element of"),
+    ('∋', "contains as member"),
+    ('⊂', "subset of"),
+    ('⊃', "superset of"),
+    ('⊆', "subset of or equal to"),
+    ('⊇', "superset of or equal to"),
+    ('∪', "union"),
+    ('∩', "intersection"),
+    ('∅', "empty set"),
+    ('∀', "for all"),
+    ('∃', "there exists"),
+    ('∄', "there does not exist"),
+    ('¬', "not sign"),
+    ('∧', "logical and"),
+    ('∨', "logical or"),
+    ('⊕', "circled plus, xor"),
+    ('⊗', "circled times"),
+    ('⊤', "down tack, top"),
+    ('⊥', "up tack, bottom, perpendicular"),
+    ('∘', "ring operator, compose"),
+    ('∴', "therefore"),
+    ('∵', "because"),
+    ('ℕ', "double-struck capital n, natural numbers"),
+    ('ℤ', "double-struck capital z, integers"),
+    ('ℚ', "double-struck capital q, rationals"),
+    ('ℝ', "double-struck capital r, reals"),
+    ('ℂ', "double-struck capital c, complex numbers"),
+    ('⌊', "left floor"),
+    ('⌋', "right floor"),
+    ('⌈', "left ceiling"),
+    ('⌉', "right ceiling"),
+    ('⟨', "mathematical left angle bracket"),
+    ('⟩', "mathematical right angle bracket"),
+    ('½', "vulgar fraction one half"),
+    ('⅓', "vulgar fraction one third"),
+    ('¼', "vulgar fraction one quarter"),
+    ('¾', "vulgar fraction three quarters"),
+    ('²', "superscript two, squared"),
+    ('³', "superscript three, cubed"),
+    ('¹', "superscript one"),
+    ('⁰', "superscript zero"),
+    ('ⁿ', "superscript latin small letter n"),
+    ('₀', "subscript zero"),
+    ('₁', "subscript one"),
+    ('₂', "subscript two"),
+    // Greek
+    ('α', "greek small letter alpha"),
+    ('β', "greek small letter beta"),
+    ('γ', "greek small letter gamma"),
+    ('δ', "greek small letter delta"),
+    ('ε', "greek small letter epsilon"),
+    ('ζ', "greek small letter zeta"),
+    ('η', "greek small letter eta"),
+    ('θ', "greek small letter theta"),
+    ('ι', "greek small letter iota"),
+    ('κ', "greek small letter kappa"),
+    ('λ', "greek small letter lamda, lambda"),
+    ('μ', "greek small letter mu"),
+    ('ν', "greek small letter nu"),
+    ('ξ', "greek small letter xi"),
+    ('π', "greek small letter pi"),
+    ('ρ', "greek small letter rho"),
+    ('σ', "greek small letter sigma"),
+    ('τ', "greek small letter tau"),
+    ('υ', "greek small letter upsilon"),
+    ('φ', "greek small letter phi"),
+    ('χ', "greek small letter chi"),
+    ('ψ', "greek small letter psi"),
+    ('ω', "greek small letter omega"),
+    ('Γ', "greek capital letter gamma"),
+    ('Δ', "greek capital letter delta"),
+    ('Θ', "greek capital letter theta"),
+    ('Λ', "greek capital letter lamda, lambda"),
+    ('Π', "greek capital letter pi"),
+    ('Σ', "greek capital letter sigma"),
+    ('Φ', "greek capital letter phi"),
+    ('Ψ', "greek capital letter psi"),
+    ('Ω', "greek capital letter omega"),
+    // Latin letters with diacritics
+    ('é', "latin small letter e with acute"),
+    ('è', "latin small letter e with grave"),
+    ('ê', "latin small letter e with circumflex"),
+    ('ë', "latin small letter e with diaeresis"),
+    ('á', "latin small letter a with acute"),
+    ('à', "latin small letter a with grave"),
+    ('â', "latin small letter a with circumflex"),
+    ('ä', "latin small letter a with diaeresis, umlaut"),
+    ('å', "latin small letter a with ring above"),
+    ('ã', "latin small letter a with tilde"),
+    ('æ', "latin small letter ae"),
+    ('ç', "latin small letter c with cedilla"),
+    ('í', "latin small letter i with acute"),
+    ('ï', "latin small letter i with diaeresis"),
+    ('ñ', "latin small letter n with tilde"),
+    ('ó', "latin small letter o with acute"),
+    ('ô', "latin small letter o with circumflex"),
+    ('ö', "latin small letter o with diaeresis, umlaut"),
+    ('ø', "latin small letter o with stroke"),
+    ('œ', "latin small ligature oe"),
+    ('ß', "latin small letter sharp s, eszett"),
+    ('ú', "latin small letter u with acute"),
+    ('ü', "latin small letter u with diaeresis, umlaut"),
+    ('ý', "latin small letter y with acute"),
+    ('ł', "latin small letter l with stroke"),
+    ('š', "latin small letter s with caron"),
+    ('ž', "latin small letter z with caron"),
+    ('č', "latin small letter c with caron"),
+    ('ð', "latin small letter eth"),
+    ('þ', "latin small letter thorn"),
+    ('É', "latin capital letter e with acute"),
+    ('Ä', "latin capital letter a with diaeresis, umlaut"),
+    ('Ö', "latin capital letter o with diaeresis, umlaut"),
+    ('Ü', "latin capital letter u with diaeresis, umlaut"),
+    ('Ç', "latin capital letter c with cedilla"),
+    ('Ñ', "latin capital letter n with tilde"),
+    ('Å', "latin capital letter a with ring above"),
+    ('Ø', "latin capital letter o with stroke"),
+    // Symbols and shapes
+    ('✓', "check mark"),
+    ('✔', "heavy check mark"),
+    ('✗', "ballot x"),
+    ('✘', "heavy ballot x"),
+    ('☐', "ballot box"),
+    ('☑', "ballot box with check"),
+    ('☒', "ballot box with x"),
+    ('★', "black star"),
+    ('☆', "white star"),
+    ('♥', "black heart suit"),
+    ('♦', "black diamond suit"),
+    ('♣', "black club suit"),
+    ('♠', "black spade suit"),
+    ('♪', "eighth note, music"),
+    ('♫', "beamed eighth notes, music"),
+    ('☀', "black sun with rays"),
+    ('☁', "cloud"),
+    ('☂', "umbrella"),
+    ('☃', "snowman"),
+    ('☎', "black telephone"),
+    ('☕', "hot beverage, coffee"),
+    ('☠', "skull and crossbones"),
+    ('☢', "radioactive sign"),
+    ('☮', "peace symbol"),
+    ('☯', "yin yang"),
+    ('☺', "white smiling face"),
+    ('☹', "white frowning face"),
+    ('⚠', "warning sign"),
+    ('⚡', "high voltage sign, lightning"),
+    ('⚙', "gear"),
+    ('⌘', "place of interest sign, command key"),
+    ('⌥', "option key"),
+    ('⇧', "upwards white arrow, shift key"),
+    ('⌫', "erase to the left, backspace"),
+    ('⏎', "return symbol"),
+    ('⎋', "broken circle with northwest arrow, escape"),
+    ('●', "black circle"),
+    ('○', "white circle"),
+    ('■', "black square"),
+    ('□', "white square"),
+    ('▲', "black up-pointing triangle"),
+    ('▼', "black down-pointing triangle"),
+    ('◀', "black left-pointing triangle"),
+    ('▶', "black right-pointing triangle"),
+    ('◆', "black diamond"),
+    ('◇', "white diamond"),
+    ('░', "light shade"),
+    ('▒', "medium shade"),
+    ('▓', "dark shade"),
+    ('█', "full block"),
+    ('─', "box drawings light horizontal"),
+    ('│', "box drawings light vertical"),
+    ('┌', "box drawings light down and right"),
+    ('┐', "box drawings light down and left"),
+    ('└', "box drawings light up and right"),
+    ('┘', "box drawings light up and left"),
+    ('├', "box drawings light vertical and right"),
+    ('┤', "box drawings light vertical and left"),
+    ('┼', "box drawings light vertical and horizontal"),
+    ('═', "box drawings double horizontal"),
+    ('║', "box drawings double vertical"),
+    // Emoji
+    ('😀', "grinning face"),
+    ('😃', "smiling face with open mouth"),
+    ('😄', "smiling face with open mouth and smiling eyes"),
+    ('😁', "grinning face with smiling eyes"),
+    ('😂', "face with tears of joy"),
+    ('🤣', "rolling on the floor laughing"),
+    ('😊', "smiling face with smiling eyes, blush"),
+    ('😇', "smiling face with halo"),
+    ('🙂', "slightly smiling face"),
+    ('🙃', "upside-down face"),
+    ('😉', "winking face, wink"),
+    ('😍', "smiling face with heart-shaped eyes"),
+    ('😘', "face throwing a kiss"),
+    ('😋', "face savouring delicious food, yum"),
+    ('😎', "smiling face with sunglasses, cool"),
+    ('🤓', "nerd face"),
+    ('🤔', "thinking face"),
+    ('🤨', "face with raised eyebrow"),
+    ('😐', "neutral face"),
+    ('😑', "expressionless face"),
+    ('😶', "face without mouth"),
+    ('🙄', "face with rolling eyes"),
+    ('😏', "smirking face, smirk"),
+    ('😬', "grimacing face"),
+    ('😌', "relieved face"),
+    ('😴', "sleeping face"),
+    ('🤯', "shocked face with exploding head, mind blown"),
+    ('🥳', "face with party horn and party hat"),
+    ('😕', "confused face"),
+    ('😟', "worried face"),
+    ('😮', "face with open mouth"),
+    ('😲', "astonished face"),
+    ('😳', "flushed face"),
+    ('🥺', "face with pleading eyes"),
+    ('😢', "crying face, cry"),
+    ('😭', "loudly crying face, sob"),
+    ('😱', "face screaming in fear"),
+    ('😤', "face with look of triumph"),
+    ('😡', "pouting face, angry"),
+    ('🤬', "serious face with symbols covering mouth, swearing"),
+    ('😈', "smiling face with horns"),
+    ('💀', "skull"),
+    ('💩', "pile of poo"),
+    ('🤡', "clown face"),
+    ('👻', "ghost"),
+    ('👽', "extraterrestrial alien"),
+    ('🤖', "robot face"),
+    ('👋', "waving hand sign, wave"),
+    ('👌', "ok hand sign"),
+    ('✌', "victory hand"),
+    ('🤞', "hand with index and middle fingers crossed"),
+    ('👍', "thumbs up sign, +1"),
+    ('👎', "thumbs down sign, -1"),
+    ('👏', "clapping hands sign, clap"),
+    ('🙌', "person raising both hands in celebration"),
+    ('🙏', "person with folded hands, pray, thanks"),
+    ('💪', "flexed biceps, muscle"),
+    ('👀', "eyes"),
+    ('🧠', "brain"),
+    ('❤', "heavy black heart, red heart"),
+    ('🧡', "orange heart"),
+    ('💛', "yellow heart"),
+    ('💚', "green heart"),
+    ('💙', "blue heart"),
+    ('💜', "purple heart"),
+    ('🖤', "black heart"),
+    ('💔', "broken heart"),
+    ('💯', "hundred points symbol"),
+    ('💥', "collision symbol, boom"),
+    ('💡', "electric light bulb, idea"),
+    ('🔥', "fire"),
+    ('✨', "sparkles"),
+    ('⭐', "white medium star"),
+    ('🌟', "glowing star"),
+    ('🎉', "party popper, tada"),
+    ('🎊', "confetti ball"),
+    ('🎁', "wrapped present, gift"),
+    ('🏆', "trophy"),
+    ('🚀', "rocket"),
+    ('🐛', "bug"),
+    ('🐞', "lady beetle"),
+    ('🦀', "crab, rust"),
+    ('🐍', "snake, python"),
+    ('🐳', "spouting whale, docker"),
+    ('🐧', "penguin, linux"),
+    ('🐱', "cat face"),
+    ('🐶', "dog face"),
+    ('🦊', "fox face"),
+    ('🐢', "turtle"),
+    ('🌱', "seedling"),
+    ('🌲', "evergreen tree"),
+    ('🌈', "rainbow"),
+    ('🌙', "crescent moon"),
+    ('🌍', "earth globe europe-africa"),
+    ('☔', "umbrella with rain drops"),
+    ('❄', "snowflake"),
+    ('🍕', "slice of pizza"),
+    ('🍔', "hamburger"),
+    ('🍺', "beer mug"),
+    ('🍰', "shortcake"),
+    ('🍎', "red apple"),
+    ('🔒', "lock"),
+    ('🔓', "open lock"),
+    ('🔑', "key"),
+    ('🔧', "wrench"),
+    ('🔨', "hammer"),
+    ('🛠', "hammer and wrench"),
+    ('🔍', "left-pointing magnifying glass, search"),
+    ('🔗', "link symbol"),
+    ('📌', "pushpin"),
+    ('📎', "paperclip"),
+    ('📝', "memo"),
+    ('📄', "page facing up"),
+    ('📁', "file folder"),
+    ('📦', "package"),
+    ('📈', "chart with upwards trend"),
+    ('📉', "chart with downwards trend"),
+    ('📅', "calendar"),
+    ('📚', "books"),
+    ('📣', "cheering megaphone"),
+    ('🔔', "bell"),
+    ('💬', "speech balloon"),
+    ('💻', "personal computer, laptop"),
+    ('⌨', "keyboard"),
+    ('🖥', "desktop computer"),
+    ('📱', "mobile phone"),
+    ('⏰', "alarm clock"),
+    ('⏳', "hourglass with flowing sand"),
+    ('⌛', "hourglass"),
+    ('🚧', "construction sign"),
+    ('🚨', "police cars revolving light, siren"),
+    ('🛑', "octagonal sign, stop"),
+    ('✅', "white heavy check mark"),
+    ('❌', "cross mark"),
+    ('❎', "negative squared cross mark"),
+    ('❓', "black question mark ornament"),
+    ('❗', "heavy exclamation mark symbol"),
+    ('➕', "heavy plus sign"),
+    ('➖', "heavy minus sign"),
+    ('🆕', "squared new"),
+    ('🆗', "squared ok"),
+    ('🟢', "large green circle"),
+    ('🟡', "large yellow circle"),
+    ('🔴', "large red circle"),
+    ('🔵', "large blue circle"),
+    ('🏁', "chequered flag"),
+    ('🚩', "triangular flag on post"),
+];